@@ -0,0 +1,86 @@
+-- the schema the postgres migrations of src-cloud end up with, for the embedded database.
+-- timestamps are RFC 3339 text in UTC, which sorts like the instants it holds.
+CREATE TABLE lists
+(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    inbox BOOLEAN NOT NULL DEFAULT FALSE,
+    archived BOOLEAN NOT NULL DEFAULT FALSE,
+    CHECK (NOT (inbox AND archived))
+);
+
+-- there is exactly one inbox, todos created without a list end up there
+CREATE UNIQUE INDEX lists_inbox_idx ON lists (inbox) WHERE inbox;
+INSERT INTO lists (name, inbox) VALUES ('Inbox', TRUE);
+
+-- new occurrences of a recurring todo are created from its series
+CREATE TABLE todo_series
+(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    rrule TEXT NOT NULL,
+    text TEXT NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE todos
+(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    list_id INTEGER NOT NULL REFERENCES lists (id),
+    parent_id INTEGER REFERENCES todos (id) ON DELETE CASCADE CHECK (parent_id <> id),
+    text TEXT NOT NULL,
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+    starts_at TEXT,
+    due_at TEXT,
+    priority INTEGER NOT NULL DEFAULT 0 CHECK (priority BETWEEN 0 AND 4),
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    rrule TEXT,
+    series_id INTEGER REFERENCES todo_series (id) ON DELETE SET NULL,
+    version INTEGER NOT NULL DEFAULT 1,
+    CHECK (starts_at IS NULL OR due_at IS NULL OR starts_at <= due_at)
+);
+
+CREATE INDEX todos_due_at_idx ON todos (due_at) WHERE NOT completed;
+CREATE INDEX todos_position_idx ON todos (position, id);
+CREATE INDEX todos_list_id_idx ON todos (list_id);
+CREATE INDEX todos_parent_id_idx ON todos (parent_id);
+CREATE INDEX todos_series_id_idx ON todos (series_id);
+
+CREATE TABLE labels
+(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    color TEXT
+);
+
+CREATE TABLE todo_labels
+(
+    todo_id INTEGER NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+    label_id INTEGER NOT NULL REFERENCES labels (id) ON DELETE CASCADE,
+    PRIMARY KEY (todo_id, label_id)
+);
+
+CREATE INDEX todo_labels_label_id_idx ON todo_labels (label_id);
+
+-- full-text index over the text of todos, kept in sync by triggers. like the simple
+-- configuration of postgres, it folds case and doesn't stem.
+CREATE VIRTUAL TABLE todos_search USING fts5
+(
+    text,
+    content = 'todos',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 0'
+);
+
+CREATE TRIGGER todos_search_insert AFTER INSERT ON todos BEGIN
+    INSERT INTO todos_search (rowid, text) VALUES (new.id, new.text);
+END;
+
+CREATE TRIGGER todos_search_delete AFTER DELETE ON todos BEGIN
+    INSERT INTO todos_search (todos_search, rowid, text) VALUES ('delete', old.id, old.text);
+END;
+
+CREATE TRIGGER todos_search_update AFTER UPDATE OF text ON todos BEGIN
+    INSERT INTO todos_search (todos_search, rowid, text) VALUES ('delete', old.id, old.text);
+    INSERT INTO todos_search (rowid, text) VALUES (new.id, new.text);
+END;
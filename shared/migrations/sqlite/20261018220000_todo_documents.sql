@@ -0,0 +1,6 @@
+-- the text of todos as a CRDT, like the postgres table. documents are JSON text.
+CREATE TABLE todo_documents
+(
+    todo_id INTEGER PRIMARY KEY REFERENCES todos (id) ON DELETE CASCADE,
+    document TEXT NOT NULL
+);
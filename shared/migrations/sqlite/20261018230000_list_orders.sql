@@ -0,0 +1,6 @@
+-- the order of the todos of lists as a CRDT, like the postgres table. documents are JSON text.
+CREATE TABLE list_orders
+(
+    list_id INTEGER PRIMARY KEY REFERENCES lists (id) ON DELETE CASCADE,
+    document TEXT NOT NULL
+);
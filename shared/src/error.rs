@@ -0,0 +1,22 @@
+/// Implements the `{ "kind": ..., "message": ... }` payload and the axum response for a
+/// domain error that has `kind()` and `status_code()`.
+#[macro_export]
+macro_rules! impl_error_response {
+    ($error:ty) => {
+        impl serde::Serialize for $error {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use serde::ser::SerializeStruct;
+                let mut state = serializer.serialize_struct(stringify!($error), 2)?;
+                state.serialize_field("kind", self.kind())?;
+                state.serialize_field("message", &self.to_string())?;
+                state.end()
+            }
+        }
+
+        impl axum::response::IntoResponse for $error {
+            fn into_response(self) -> axum::response::Response {
+                (self.status_code(), axum::Json(self)).into_response()
+            }
+        }
+    };
+}
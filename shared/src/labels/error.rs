@@ -0,0 +1,96 @@
+use axum::http::StatusCode;
+use thiserror::Error;
+use validator::ValidationErrors;
+
+use crate::todos::{error::TodoError, repository::RepositoryError};
+
+/// Error returned by `LabelServiceTrait`, serialized like `TodoError`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum LabelError {
+    #[error("Label not found, id is {0}")]
+    NotFound(i32),
+    #[error("Todo not found, id is {0}")]
+    TodoNotFound(i32),
+    #[error("Validation error: [{0}]")]
+    Validation(String),
+    #[error("Forbidden: [{0}]")]
+    Forbidden(String),
+    #[error("Conflict: [{0}]")]
+    Conflict(String),
+    #[error("Storage error: [{0}]")]
+    Storage(String),
+    #[error("Unexpected Error: [{0}]")]
+    Unexpected(String),
+}
+
+impl LabelError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LabelError::NotFound(_) => "NotFound",
+            LabelError::TodoNotFound(_) => "TodoNotFound",
+            LabelError::Validation(_) => "Validation",
+            LabelError::Forbidden(_) => "Forbidden",
+            LabelError::Conflict(_) => "Conflict",
+            LabelError::Storage(_) => "Storage",
+            LabelError::Unexpected(_) => "Unexpected",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            LabelError::NotFound(_) | LabelError::TodoNotFound(_) => StatusCode::NOT_FOUND,
+            LabelError::Validation(_) => StatusCode::BAD_REQUEST,
+            LabelError::Forbidden(_) => StatusCode::FORBIDDEN,
+            LabelError::Conflict(_) => StatusCode::CONFLICT,
+            LabelError::Storage(_) => StatusCode::SERVICE_UNAVAILABLE,
+            LabelError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<RepositoryError> for LabelError {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::NotFound(id) => LabelError::NotFound(id),
+            RepositoryError::Invalid(message) => LabelError::Validation(message),
+            RepositoryError::Conflict(message) => LabelError::Conflict(message),
+            RepositoryError::Storage(message) => LabelError::Storage(message),
+            RepositoryError::Unexpected(message) => LabelError::Unexpected(message),
+        }
+    }
+}
+
+impl From<anyhow::Error> for LabelError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<RepositoryError>() {
+            Ok(e) => e.into(),
+            Err(e) => LabelError::Unexpected(e.to_string()),
+        }
+    }
+}
+
+/// Errors coming from the todo repository, where `NotFound` is about the todo.
+impl From<TodoError> for LabelError {
+    fn from(e: TodoError) -> Self {
+        match e {
+            TodoError::NotFound(id) => LabelError::TodoNotFound(id),
+            e @ (TodoError::SeriesNotFound(_) | TodoError::Cancelled) => {
+                LabelError::Unexpected(e.to_string())
+            }
+            TodoError::Validation(message) => LabelError::Validation(message),
+            TodoError::Forbidden(message) => LabelError::Forbidden(message),
+            TodoError::Conflict(message) => LabelError::Conflict(message),
+            TodoError::PreconditionFailed(message) => LabelError::Conflict(message),
+            TodoError::Storage(message) => LabelError::Storage(message),
+            TodoError::Unexpected(message) => LabelError::Unexpected(message),
+        }
+    }
+}
+
+impl From<ValidationErrors> for LabelError {
+    fn from(e: ValidationErrors) -> Self {
+        LabelError::Validation(e.to_string().replace('\n', ", "))
+    }
+}
+
+crate::impl_error_response!(LabelError);
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct Label {
+    pub id: i32,
+    pub name: String,
+    /// `#rrggbb`
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Validate, ToSchema)]
+pub struct CreateLabel {
+    #[validate(length(min = 1, max = 50, message = "Can not be empty and over name length"))]
+    pub name: String,
+    #[validate(custom(function = "validate_color"))]
+    pub color: Option<String>,
+}
+
+/// `color: null` removes the color, a missing `color` keeps it.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, Validate, ToSchema)]
+pub struct UpdateLabel {
+    #[validate(length(min = 1, max = 50, message = "Can not be empty and over name length"))]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "crate::todos::model::double_option")]
+    #[schema(value_type = Option<String>)]
+    #[validate(custom(function = "validate_color"))]
+    pub color: Option<Option<String>>,
+}
+
+fn validate_color(color: &str) -> Result<(), ValidationError> {
+    let hex = color.strip_prefix('#').unwrap_or_default();
+    if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(());
+    }
+    let mut error = ValidationError::new("color");
+    error.message = Some("color must be formatted as #rrggbb".into());
+    Err(error)
+}
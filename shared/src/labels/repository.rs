@@ -0,0 +1,620 @@
+use axum::async_trait;
+use sqlx::PgPool;
+
+use super::model::{CreateLabel, Label, UpdateLabel};
+use crate::todos::repository::RepositoryError;
+
+#[async_trait]
+pub trait LabelRepositoryTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    /// The same store, only seeing and creating the labels of user `owner`, whose names only
+    /// conflict with their own labels.
+    fn owned_by(&self, owner: i32) -> Self;
+    /// The same store, only seeing and creating the labels of workspace `workspace`, names only
+    /// conflicting within it.
+    fn in_workspace(&self, workspace: i32) -> Self;
+
+    async fn create(&self, payload: CreateLabel) -> anyhow::Result<Label>;
+    async fn find(&self, id: i32) -> anyhow::Result<Label>;
+    /// Unknown ids are skipped.
+    async fn find_many(&self, ids: &[i32]) -> anyhow::Result<Vec<Label>>;
+    async fn all(&self) -> anyhow::Result<Vec<Label>>;
+    async fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label>;
+    async fn delete(&self, id: i32) -> anyhow::Result<()>;
+}
+
+/// Every query is bound to the owner and the workspace. A `None` owner sees no label and can't
+/// create any, a `None` workspace sees those of every workspace, creating them in the personal
+/// workspace of the owner.
+#[derive(Debug, Clone)]
+pub struct LabelRepositoryForDb {
+    pool: PgPool,
+    owner: Option<i32>,
+    workspace: Option<i32>,
+}
+
+impl LabelRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        LabelRepositoryForDb {
+            pool,
+            owner: None,
+            workspace: None,
+        }
+    }
+}
+
+#[async_trait]
+impl LabelRepositoryTrait for LabelRepositoryForDb {
+    fn owned_by(&self, owner: i32) -> Self {
+        Self {
+            owner: Some(owner),
+            ..self.clone()
+        }
+    }
+
+    fn in_workspace(&self, workspace: i32) -> Self {
+        Self {
+            workspace: Some(workspace),
+            ..self.clone()
+        }
+    }
+
+    async fn create(&self, payload: CreateLabel) -> anyhow::Result<Label> {
+        let label = sqlx::query_as::<_, Label>(
+            r#"
+            insert into labels (name, color, user_id, workspace_id)
+            values ($1, $2, $3, coalesce($4, (select id from workspaces where personal_of=$3)))
+            returning *
+            "#,
+        )
+        .bind(payload.name)
+        .bind(payload.color)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(label)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Label> {
+        let label = sqlx::query_as::<_, Label>(
+            r#"
+            select * from labels where id=$1 and user_id=$2 and ($3::int is null or workspace_id=$3)
+            "#,
+        )
+        .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+
+        Ok(label)
+    }
+
+    async fn find_many(&self, ids: &[i32]) -> anyhow::Result<Vec<Label>> {
+        let labels = sqlx::query_as::<_, Label>(
+            r#"
+            select * from labels where id=any($1) and user_id=$2 and ($3::int is null or workspace_id=$3)
+            order by name, id
+            "#,
+        )
+        .bind(ids)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(labels)
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<Label>> {
+        let labels = sqlx::query_as::<_, Label>(
+            r#"
+            select * from labels where user_id=$1 and ($2::int is null or workspace_id=$2)
+            order by name, id
+            "#,
+        )
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(labels)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label> {
+        let label = sqlx::query_as::<_, Label>(
+            r#"
+            update labels set name=coalesce($1, name),
+                color=case when $2 then $3 else color end
+            where id=$4 and user_id=$5 and ($6::int is null or workspace_id=$6)
+            returning *
+            "#,
+        )
+        .bind(payload.name)
+        .bind(payload.color.is_some())
+        .bind(payload.color.flatten())
+        .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+
+        Ok(label)
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        let result = sqlx::query(
+            r#"
+            delete from labels where id=$1 and user_id=$2 and ($3::int is null or workspace_id=$3)
+            "#,
+        )
+        .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use dotenv::dotenv;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
+
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
+
+        let mut owners = vec![];
+        for name in ["alice", "bob"] {
+            let id = sqlx::query_scalar::<_, i32>(
+                "insert into users (email, password_hash) values ($1, '') returning id",
+            )
+            .bind(format!(
+                "label_crud_scenario.{name}.{}@example.com",
+                chrono::Utc::now().timestamp_micros()
+            ))
+            .fetch_one(&pool)
+            .await
+            .expect("[create user] returned Err");
+            owners.push(id);
+        }
+        let repository = LabelRepositoryForDb::new(pool.clone()).owned_by(owners[0]);
+        let other = LabelRepositoryForDb::new(pool.clone()).owned_by(owners[1]);
+
+        // create
+        let created = repository
+            .create(CreateLabel {
+                name: "[crud_scenario] label".to_string(),
+                color: Some("#ff0000".to_string()),
+            })
+            .await
+            .expect("[create] returned Err");
+
+        let duplicated = repository
+            .create(CreateLabel {
+                name: "[crud_scenario] label".to_string(),
+                color: None,
+            })
+            .await;
+        assert!(matches!(
+            duplicated.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::Conflict(_))
+        ));
+
+        // names are only unique among the labels of a user, who doesn't see the others
+        other
+            .create(CreateLabel {
+                name: "[crud_scenario] label".to_string(),
+                color: None,
+            })
+            .await
+            .expect("[create] returned Err");
+        assert!(other.find(created.id).await.is_err());
+        assert!(other.delete(created.id).await.is_err());
+        assert!(!other
+            .all()
+            .await
+            .expect("[all] returned Err")
+            .contains(&created));
+
+        // and among the labels of a workspace, which aren't seen from another one
+        let workspace = sqlx::query_scalar::<_, i32>(
+            "insert into workspaces (name) values ('[crud_scenario] workspace') returning id",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("[create workspace] returned Err");
+        let elsewhere = repository.in_workspace(workspace);
+        elsewhere
+            .create(CreateLabel {
+                name: "[crud_scenario] label".to_string(),
+                color: None,
+            })
+            .await
+            .expect("[create] returned Err");
+        assert!(elsewhere.find(created.id).await.is_err());
+        assert!(elsewhere.delete(created.id).await.is_err());
+        assert_eq!(elsewhere.all().await.expect("[all] returned Err").len(), 1);
+        assert!(repository
+            .all()
+            .await
+            .expect("[all] returned Err")
+            .contains(&created));
+        sqlx::query("delete from workspaces where id=$1")
+            .bind(workspace)
+            .execute(&pool)
+            .await
+            .expect("[delete workspace] returned Err");
+
+        // find
+        let label = repository
+            .find(created.id)
+            .await
+            .expect("[find] returned Err");
+        assert_eq!(created, label);
+
+        // update
+        let label = repository
+            .update(
+                created.id,
+                UpdateLabel {
+                    color: Some(None),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        assert_eq!(label.name, created.name);
+        assert_eq!(label.color, None);
+
+        // delete
+        repository
+            .delete(label.id)
+            .await
+            .expect("[delete] returned Err");
+        let res = repository.find(label.id).await;
+        assert!(res.is_err());
+
+        sqlx::query("delete from users where id=any($1)")
+            .bind(&owners)
+            .execute(&pool)
+            .await
+            .expect("[delete users] returned Err");
+    }
+}
+
+#[cfg(any(test, feature = "memory"))]
+pub mod memory {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, RwLock},
+    };
+
+    /// Labels by id, along with their owner and workspace.
+    type Store = HashMap<i32, (Option<i32>, Option<i32>, Label)>;
+
+    /// Labels of every owner in one map, a `None` owner being the single user of a local store. A
+    /// `None` workspace sees the labels of every workspace.
+    #[derive(Debug, Clone, Default)]
+    pub struct LabelRepositoryForMemory {
+        owner: Option<i32>,
+        workspace: Option<i32>,
+        store: Arc<RwLock<Store>>,
+    }
+
+    impl LabelRepositoryForMemory {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Whether a label of `owner` in `workspace` is seen.
+        fn sees(&self, owner: Option<i32>, workspace: Option<i32>) -> bool {
+            owner == self.owner && (self.workspace.is_none() || workspace == self.workspace)
+        }
+
+        /// The labels of the owner in the workspace.
+        fn owned<'a>(&'a self, store: &'a Store) -> impl Iterator<Item = &'a Label> {
+            store
+                .values()
+                .filter(|(owner, workspace, _)| self.sees(*owner, *workspace))
+                .map(|(_, _, label)| label)
+        }
+    }
+
+    #[async_trait]
+    impl LabelRepositoryTrait for LabelRepositoryForMemory {
+        fn owned_by(&self, owner: i32) -> Self {
+            Self {
+                owner: Some(owner),
+                ..self.clone()
+            }
+        }
+
+        fn in_workspace(&self, workspace: i32) -> Self {
+            Self {
+                workspace: Some(workspace),
+                ..self.clone()
+            }
+        }
+
+        async fn create(&self, payload: CreateLabel) -> anyhow::Result<Label> {
+            let mut store = self.store.write().unwrap();
+            if self.owned(&store).any(|label| label.name == payload.name) {
+                return Err(RepositoryError::Conflict(payload.name).into());
+            }
+            let id = store.keys().max().copied().unwrap_or(0) + 1;
+            let label = Label {
+                id,
+                name: payload.name,
+                color: payload.color,
+            };
+            store.insert(id, (self.owner, self.workspace, label.clone()));
+            Ok(label)
+        }
+
+        async fn find(&self, id: i32) -> anyhow::Result<Label> {
+            let store = self.store.read().unwrap();
+            let label = self
+                .owned(&store)
+                .find(|label| label.id == id)
+                .cloned()
+                .ok_or(RepositoryError::NotFound(id))?;
+            Ok(label)
+        }
+
+        async fn find_many(&self, ids: &[i32]) -> anyhow::Result<Vec<Label>> {
+            let mut labels = self.all().await?;
+            labels.retain(|label| ids.contains(&label.id));
+            Ok(labels)
+        }
+
+        async fn all(&self) -> anyhow::Result<Vec<Label>> {
+            let store = self.store.read().unwrap();
+            let mut labels = Vec::from_iter(self.owned(&store).cloned());
+            labels.sort_by(|a, b| (&a.name, a.id).cmp(&(&b.name, b.id)));
+            Ok(labels)
+        }
+
+        async fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label> {
+            let mut store = self.store.write().unwrap();
+            if let Some(name) = &payload.name {
+                if self
+                    .owned(&store)
+                    .any(|label| label.id != id && &label.name == name)
+                {
+                    return Err(RepositoryError::Conflict(name.clone()).into());
+                }
+            }
+            let label = match store.get_mut(&id) {
+                Some((owner, workspace, label)) if self.sees(*owner, *workspace) => label,
+                _ => return Err(RepositoryError::NotFound(id).into()),
+            };
+            if let Some(name) = payload.name {
+                label.name = name;
+            }
+            if let Some(color) = payload.color {
+                label.color = color;
+            }
+            Ok(label.clone())
+        }
+
+        async fn delete(&self, id: i32) -> anyhow::Result<()> {
+            let mut store = self.store.write().unwrap();
+            match store.get(&id) {
+                Some((owner, workspace, _)) if self.sees(*owner, *workspace) => store.remove(&id),
+                _ => return Err(RepositoryError::NotFound(id).into()),
+            };
+            Ok(())
+        }
+    }
+}
+
+pub mod sled {
+    use super::*;
+    use crate::todos::repository::sled::{decode, encode, generate_id, key, store_db};
+    use ::sled::{Db, Tree};
+    use std::sync::{Arc, Mutex};
+
+    /// A label along with its owner and workspace, as kept in the tree.
+    type Entry = (Option<i32>, Option<i32>, Label);
+
+    /// Labels as JSON in a tree of the sled database of the todos, for the desktop app. They are
+    /// scoped like those of `LabelRepositoryForMemory`.
+    #[derive(Debug, Clone)]
+    pub struct LabelRepositoryForSled {
+        db: Db,
+        tree: Tree,
+        owner: Option<i32>,
+        workspace: Option<i32>,
+        /// Held by every write, which checks the names of the other labels first.
+        write: Arc<Mutex<()>>,
+    }
+
+    impl LabelRepositoryForSled {
+        pub fn open(db: &Db) -> Result<Self, RepositoryError> {
+            Ok(Self {
+                db: db.clone(),
+                tree: db.open_tree("labels")?,
+                owner: None,
+                workspace: None,
+                write: Arc::default(),
+            })
+        }
+
+        /// Labels kept in the database of `crate::store`.
+        pub fn from_store() -> Result<Self, RepositoryError> {
+            Self::open(&store_db()?)
+        }
+
+        /// Whether a label of `owner` in `workspace` is seen.
+        fn sees(&self, owner: Option<i32>, workspace: Option<i32>) -> bool {
+            owner == self.owner && (self.workspace.is_none() || workspace == self.workspace)
+        }
+
+        /// The labels of the owner in the workspace, in id order.
+        fn owned(&self) -> Result<Vec<Label>, RepositoryError> {
+            let mut labels = Vec::new();
+            for bytes in self.tree.iter().values() {
+                let (owner, workspace, label): Entry = decode(&bytes?)?;
+                if self.sees(owner, workspace) {
+                    labels.push(label);
+                }
+            }
+            Ok(labels)
+        }
+
+        fn get(&self, id: i32) -> Result<Entry, RepositoryError> {
+            match self.tree.get(key(id))? {
+                Some(bytes) => match decode::<Entry>(&bytes)? {
+                    entry if self.sees(entry.0, entry.1) => Ok(entry),
+                    _ => Err(RepositoryError::NotFound(id)),
+                },
+                None => Err(RepositoryError::NotFound(id)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl LabelRepositoryTrait for LabelRepositoryForSled {
+        fn owned_by(&self, owner: i32) -> Self {
+            Self {
+                owner: Some(owner),
+                ..self.clone()
+            }
+        }
+
+        fn in_workspace(&self, workspace: i32) -> Self {
+            Self {
+                workspace: Some(workspace),
+                ..self.clone()
+            }
+        }
+
+        async fn create(&self, payload: CreateLabel) -> anyhow::Result<Label> {
+            let _write = self.write.lock().unwrap();
+            if self.owned()?.iter().any(|label| label.name == payload.name) {
+                return Err(RepositoryError::Conflict(payload.name).into());
+            }
+            let label = Label {
+                id: generate_id(&self.db, &self.tree)?,
+                name: payload.name,
+                color: payload.color,
+            };
+            self.tree
+                .insert(
+                    key(label.id),
+                    encode(&(self.owner, self.workspace, &label))?,
+                )
+                .map_err(RepositoryError::from)?;
+            Ok(label)
+        }
+
+        async fn find(&self, id: i32) -> anyhow::Result<Label> {
+            let (_, _, label) = self.get(id)?;
+            Ok(label)
+        }
+
+        async fn find_many(&self, ids: &[i32]) -> anyhow::Result<Vec<Label>> {
+            let mut labels = self.all().await?;
+            labels.retain(|label| ids.contains(&label.id));
+            Ok(labels)
+        }
+
+        async fn all(&self) -> anyhow::Result<Vec<Label>> {
+            let mut labels = self.owned()?;
+            labels.sort_by(|a, b| (&a.name, a.id).cmp(&(&b.name, b.id)));
+            Ok(labels)
+        }
+
+        async fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label> {
+            let _write = self.write.lock().unwrap();
+            let (owner, workspace, mut label) = self.get(id)?;
+            if let Some(name) = &payload.name {
+                if self
+                    .owned()?
+                    .iter()
+                    .any(|label| label.id != id && &label.name == name)
+                {
+                    return Err(RepositoryError::Conflict(name.clone()).into());
+                }
+                label.name = name.clone();
+            }
+            if let Some(color) = payload.color {
+                label.color = color;
+            }
+            self.tree
+                .insert(key(id), encode(&(owner, workspace, &label))?)
+                .map_err(RepositoryError::from)?;
+            Ok(label)
+        }
+
+        async fn delete(&self, id: i32) -> anyhow::Result<()> {
+            let _write = self.write.lock().unwrap();
+            self.get(id)?;
+            self.tree.remove(key(id)).map_err(RepositoryError::from)?;
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[tokio::test]
+        async fn sled_scenario() {
+            let db = ::sled::Config::new().temporary(true).open().unwrap();
+            let repository = LabelRepositoryForSled::open(&db).unwrap().in_workspace(1);
+            let label = repository
+                .create(CreateLabel {
+                    name: "work".to_string(),
+                    color: None,
+                })
+                .await
+                .unwrap();
+            let res = repository
+                .create(CreateLabel {
+                    name: "work".to_string(),
+                    color: None,
+                })
+                .await;
+            assert!(matches!(
+                res.unwrap_err().downcast_ref(),
+                Some(RepositoryError::Conflict(_))
+            ));
+
+            let reopened = LabelRepositoryForSled::open(&db).unwrap();
+            assert_eq!(
+                reopened.in_workspace(1).all().await.unwrap(),
+                vec![label.clone()]
+            );
+            assert!(reopened.in_workspace(2).all().await.unwrap().is_empty());
+            reopened.in_workspace(1).delete(label.id).await.unwrap();
+            assert!(repository.find(label.id).await.is_err());
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use axum::async_trait;
+use validator::Validate;
+
+use super::error::LabelError;
+use super::model::{CreateLabel, Label, UpdateLabel};
+use super::repository::LabelRepositoryTrait;
+use crate::todos::{error::TodoError, repository::TodoRepositoryTrait};
+
+/// Labels are stored by `LR`, which labels a todo carries is stored by `TR`.
+#[derive(Debug, Clone)]
+pub struct LabelService<LR, TR>
+where
+    LR: LabelRepositoryTrait,
+    TR: TodoRepositoryTrait,
+{
+    label_repository: LR,
+    todo_repository: TR,
+}
+
+#[async_trait]
+pub trait LabelServiceTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    async fn create(&self, payload: CreateLabel) -> Result<Label, LabelError>;
+    async fn find(&self, id: i32) -> Result<Label, LabelError>;
+    async fn find_all(&self) -> Result<Vec<Label>, LabelError>;
+    async fn update(&self, id: i32, payload: UpdateLabel) -> Result<Label, LabelError>;
+    /// Also detaches the label from every todo.
+    async fn delete(&self, id: i32) -> Result<(), LabelError>;
+    async fn find_by_todo(&self, todo_id: i32) -> Result<Vec<Label>, LabelError>;
+    async fn attach(&self, todo_id: i32, id: i32) -> Result<(), LabelError>;
+    async fn detach(&self, todo_id: i32, id: i32) -> Result<(), LabelError>;
+}
+
+impl<LR, TR> LabelService<LR, TR>
+where
+    LR: LabelRepositoryTrait,
+    TR: TodoRepositoryTrait,
+{
+    pub fn new(label_repository: LR, todo_repository: TR) -> Self {
+        Self {
+            label_repository,
+            todo_repository,
+        }
+    }
+
+    /// The labels of user `owner`, attached to the todos they see.
+    pub fn owned_by(&self, owner: i32) -> Self {
+        Self {
+            label_repository: self.label_repository.owned_by(owner),
+            todo_repository: self.todo_repository.owned_by(owner),
+        }
+    }
+
+    /// The labels of workspace `workspace`, attached to its todos.
+    pub fn in_workspace(&self, workspace: i32) -> Self {
+        Self {
+            label_repository: self.label_repository.in_workspace(workspace),
+            todo_repository: self.todo_repository.in_workspace(workspace),
+        }
+    }
+}
+
+#[async_trait]
+impl<LR, TR> LabelServiceTrait for LabelService<LR, TR>
+where
+    LR: LabelRepositoryTrait,
+    TR: TodoRepositoryTrait,
+{
+    async fn create(&self, payload: CreateLabel) -> Result<Label, LabelError> {
+        payload.validate()?;
+        let label = self.label_repository.create(payload).await?;
+        Ok(label)
+    }
+
+    async fn find(&self, id: i32) -> Result<Label, LabelError> {
+        let label = self.label_repository.find(id).await?;
+        Ok(label)
+    }
+
+    async fn find_all(&self) -> Result<Vec<Label>, LabelError> {
+        let labels = self.label_repository.all().await?;
+        Ok(labels)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateLabel) -> Result<Label, LabelError> {
+        payload.validate()?;
+        let label = self.label_repository.update(id, payload).await?;
+        Ok(label)
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), LabelError> {
+        self.label_repository.delete(id).await?;
+        self.todo_repository
+            .remove_label(id)
+            .await
+            .map_err(TodoError::from)?;
+        Ok(())
+    }
+
+    async fn find_by_todo(&self, todo_id: i32) -> Result<Vec<Label>, LabelError> {
+        self.todo_repository
+            .find(todo_id)
+            .await
+            .map_err(TodoError::from)?;
+        let ids = self
+            .todo_repository
+            .label_ids(todo_id)
+            .await
+            .map_err(TodoError::from)?;
+        let labels = self.label_repository.find_many(&ids).await?;
+        Ok(labels)
+    }
+
+    async fn attach(&self, todo_id: i32, id: i32) -> Result<(), LabelError> {
+        self.todo_repository
+            .find(todo_id)
+            .await
+            .map_err(TodoError::from)?;
+        self.label_repository.find(id).await?;
+        self.todo_repository
+            .attach_label(todo_id, id)
+            .await
+            .map_err(TodoError::from)?;
+        Ok(())
+    }
+
+    async fn detach(&self, todo_id: i32, id: i32) -> Result<(), LabelError> {
+        self.todo_repository
+            .find(todo_id)
+            .await
+            .map_err(TodoError::from)?;
+        self.todo_repository
+            .detach_label(todo_id, id)
+            .await
+            .map_err(TodoError::from)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::labels::repository::memory::LabelRepositoryForMemory;
+    use crate::todos::{
+        model::{CreateTodo, TodoQuery},
+        repository::memory::TodoRepositoryForMemory,
+    };
+
+    #[tokio::test]
+    async fn labels_scenario() {
+        let todo_repository = TodoRepositoryForMemory::new();
+        let service = LabelService::new(LabelRepositoryForMemory::new(), todo_repository.clone());
+        let label = |name: &str| CreateLabel {
+            name: name.to_string(),
+            color: None,
+        };
+
+        let work = service.create(label("work")).await.unwrap();
+        let home = service.create(label("home")).await.unwrap();
+        assert!(matches!(
+            service.create(label("work")).await,
+            Err(LabelError::Conflict(_))
+        ));
+        assert!(matches!(
+            service
+                .create(CreateLabel {
+                    color: Some("red".to_string()),
+                    ..label("red")
+                })
+                .await,
+            Err(LabelError::Validation(_))
+        ));
+        assert_eq!(
+            service.find_all().await.unwrap(),
+            vec![home.clone(), work.clone()]
+        );
+
+        let todo = todo_repository
+            .create(CreateTodo::new("labeled".to_string()))
+            .await
+            .unwrap();
+        let other = todo_repository
+            .create(CreateTodo::new("other".to_string()))
+            .await
+            .unwrap();
+        service.attach(todo.id, work.id).await.unwrap();
+        service.attach(todo.id, home.id).await.unwrap();
+        service.attach(other.id, home.id).await.unwrap();
+        assert_eq!(
+            service.attach(todo.id, 42).await,
+            Err(LabelError::NotFound(42))
+        );
+        assert_eq!(
+            service.attach(42, work.id).await,
+            Err(LabelError::TodoNotFound(42))
+        );
+        assert_eq!(
+            service.find_by_todo(todo.id).await.unwrap(),
+            vec![home.clone(), work.clone()]
+        );
+
+        let query = TodoQuery {
+            labels: vec![work.id, home.id],
+            ..Default::default()
+        };
+        let todos = todo_repository.all(&query).await.unwrap();
+        assert_eq!(todos, vec![todo.clone()]);
+
+        service.detach(todo.id, work.id).await.unwrap();
+        service.delete(home.id).await.unwrap();
+        assert!(service.find_by_todo(todo.id).await.unwrap().is_empty());
+        assert!(service.find_by_todo(other.id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_keep_labels_of_users_apart() {
+        let service = LabelService::new(
+            LabelRepositoryForMemory::new(),
+            TodoRepositoryForMemory::new(),
+        );
+        let (alice, bob) = (service.owned_by(1), service.owned_by(2));
+        let label = || CreateLabel {
+            name: "work".to_string(),
+            color: None,
+        };
+
+        let work = alice.create(label()).await.unwrap();
+        // the name is only taken among the labels of alice
+        let other = bob.create(label()).await.unwrap();
+        assert_eq!(bob.find_all().await.unwrap(), vec![other]);
+        assert_eq!(bob.find(work.id).await, Err(LabelError::NotFound(work.id)));
+        assert_eq!(
+            bob.delete(work.id).await,
+            Err(LabelError::NotFound(work.id))
+        );
+
+        let todo = bob
+            .todo_repository
+            .create(CreateTodo::new("todo of bob".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            bob.attach(todo.id, work.id).await,
+            Err(LabelError::NotFound(work.id))
+        );
+        assert_eq!(alice.find_all().await.unwrap(), vec![work]);
+    }
+}
@@ -1,7 +1,17 @@
+pub mod error;
+pub mod labels;
+pub mod lists;
+pub mod members;
+pub mod progress;
+pub mod sync;
 pub mod todos;
+pub mod tokens;
+pub mod users;
+pub mod workspaces;
 pub mod store;
 pub mod network;
 
+use todos::model::Todo;
 
 pub type Result<F, E = anyhow::Error> = anyhow::Result<F, E>;
 
@@ -27,22 +37,45 @@ pub fn init_env() {
                 .iter()
                 .for_each(|(k, v)| {
                     std::env::set_var(k.to_uppercase(), v);
-                    log::info!("{k}={v}");
+                    // values hold secrets like `JWT_SECRET`
+                    log::info!("{k} set from .env");
                 });
         }
     }
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, utoipa::ToSchema)]
+#[aliases(TodoPageData = RespData<Vec<Todo>>)]
 pub struct RespData<T: serde::Serialize> {
     data: T,
     status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<Meta>,
+}
+
+/// Paging metadata of a listing.
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct Meta {
+    /// Items matching the listing on every page.
+    pub total: i64,
+    /// Pass it as `cursor` to get the next page, absent on the last one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 pub fn resp_data<T: serde::Serialize>(data: T) -> RespData<T> {
     RespData {
         data,
         status: "Success",
+        meta: None,
+    }
+}
+
+pub fn resp_page<T: serde::Serialize>(data: T, meta: Meta) -> RespData<T> {
+    RespData {
+        data,
+        status: "Success",
+        meta: Some(meta),
     }
 }
 
@@ -0,0 +1,96 @@
+use axum::http::StatusCode;
+use thiserror::Error;
+use validator::ValidationErrors;
+
+use crate::todos::{error::TodoError, repository::RepositoryError};
+
+/// Error returned by `ListServiceTrait`, serialized like `TodoError`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ListError {
+    #[error("List not found, id is {0}")]
+    NotFound(i32),
+    #[error("Todo not found, id is {0}")]
+    TodoNotFound(i32),
+    #[error("Validation error: [{0}]")]
+    Validation(String),
+    #[error("Forbidden: [{0}]")]
+    Forbidden(String),
+    #[error("Conflict: [{0}]")]
+    Conflict(String),
+    #[error("Storage error: [{0}]")]
+    Storage(String),
+    #[error("Unexpected Error: [{0}]")]
+    Unexpected(String),
+}
+
+impl ListError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ListError::NotFound(_) => "NotFound",
+            ListError::TodoNotFound(_) => "TodoNotFound",
+            ListError::Validation(_) => "Validation",
+            ListError::Forbidden(_) => "Forbidden",
+            ListError::Conflict(_) => "Conflict",
+            ListError::Storage(_) => "Storage",
+            ListError::Unexpected(_) => "Unexpected",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ListError::NotFound(_) | ListError::TodoNotFound(_) => StatusCode::NOT_FOUND,
+            ListError::Validation(_) => StatusCode::BAD_REQUEST,
+            ListError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ListError::Conflict(_) => StatusCode::CONFLICT,
+            ListError::Storage(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ListError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<RepositoryError> for ListError {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::NotFound(id) => ListError::NotFound(id),
+            RepositoryError::Invalid(message) => ListError::Validation(message),
+            RepositoryError::Conflict(message) => ListError::Conflict(message),
+            RepositoryError::Storage(message) => ListError::Storage(message),
+            RepositoryError::Unexpected(message) => ListError::Unexpected(message),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ListError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<RepositoryError>() {
+            Ok(e) => e.into(),
+            Err(e) => ListError::Unexpected(e.to_string()),
+        }
+    }
+}
+
+/// Errors coming from the todo repository, where `NotFound` is about the todo.
+impl From<TodoError> for ListError {
+    fn from(e: TodoError) -> Self {
+        match e {
+            TodoError::NotFound(id) => ListError::TodoNotFound(id),
+            e @ (TodoError::SeriesNotFound(_) | TodoError::Cancelled) => {
+                ListError::Unexpected(e.to_string())
+            }
+            TodoError::Validation(message) => ListError::Validation(message),
+            TodoError::Forbidden(message) => ListError::Forbidden(message),
+            TodoError::Conflict(message) => ListError::Conflict(message),
+            TodoError::PreconditionFailed(message) => ListError::Conflict(message),
+            TodoError::Storage(message) => ListError::Storage(message),
+            TodoError::Unexpected(message) => ListError::Unexpected(message),
+        }
+    }
+}
+
+impl From<ValidationErrors> for ListError {
+    fn from(e: ValidationErrors) -> Self {
+        ListError::Validation(e.to_string().replace('\n', ", "))
+    }
+}
+
+crate::impl_error_response!(ListError);
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct List {
+    pub id: i32,
+    pub name: String,
+    /// The list todos go to when no list is given, it can't be archived nor deleted.
+    pub inbox: bool,
+    pub archived: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Validate, ToSchema)]
+pub struct CreateList {
+    #[validate(length(min = 1, max = 50, message = "Can not be empty and over name length"))]
+    pub name: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, Validate, ToSchema)]
+pub struct UpdateList {
+    #[validate(length(min = 1, max = 50, message = "Can not be empty and over name length"))]
+    pub name: Option<String>,
+}
+
+/// What happens to the todos of a list that is archived or deleted.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ListCascade {
+    #[default]
+    MoveToInbox,
+    Delete,
+}
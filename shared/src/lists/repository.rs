@@ -0,0 +1,672 @@
+use axum::async_trait;
+use sqlx::PgPool;
+
+use super::model::{CreateList, List, UpdateList};
+use crate::todos::repository::RepositoryError;
+
+#[async_trait]
+pub trait ListRepositoryTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    /// The same store, only seeing the inbox of user `owner` and the lists they are a member of,
+    /// and creating lists owned by them. Memory and local stores keep a single inbox and return
+    /// themselves.
+    fn owned_by(&self, owner: i32) -> Self;
+    /// The same store, only seeing and creating the lists of workspace `workspace`, where each
+    /// user has an inbox of their own. Memory stores keep their single inbox in every workspace.
+    fn in_workspace(&self, workspace: i32) -> Self;
+
+    async fn create(&self, payload: CreateList) -> anyhow::Result<List>;
+    async fn find(&self, id: i32) -> anyhow::Result<List>;
+    /// Created along the first todo without a list, or here if there is none yet.
+    async fn inbox(&self) -> anyhow::Result<List>;
+    /// The inbox first, then oldest first.
+    async fn all(&self, include_archived: bool) -> anyhow::Result<Vec<List>>;
+    async fn update(&self, id: i32, payload: UpdateList) -> anyhow::Result<List>;
+    async fn archive(&self, id: i32) -> anyhow::Result<List>;
+    /// The list has to be emptied first.
+    async fn delete(&self, id: i32) -> anyhow::Result<()>;
+}
+
+/// Id of the inbox in memory and local stores, where it's the inbox of every user in every
+/// workspace. Their todos in it are kept apart by the todo repository.
+pub const INBOX_ID: i32 = 1;
+
+/// Every query is bound to the owner and the workspace. A `None` owner sees no list and can't
+/// create any, a `None` workspace sees those of every workspace, creating them in the personal
+/// workspace of the owner.
+#[derive(Debug, Clone)]
+pub struct ListRepositoryForDb {
+    pool: PgPool,
+    owner: Option<i32>,
+    workspace: Option<i32>,
+}
+
+impl ListRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        ListRepositoryForDb {
+            pool,
+            owner: None,
+            workspace: None,
+        }
+    }
+}
+
+/// Creates the inbox of user `owner` in `workspace`, their personal one when `None`, unless they
+/// have one there.
+pub(crate) async fn create_inbox<'e, E>(
+    executor: E,
+    owner: Option<i32>,
+    workspace: Option<i32>,
+) -> sqlx::Result<()>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query(
+        r#"
+        insert into lists (name, inbox, user_id, workspace_id)
+        values ('Inbox', true, $1, coalesce($2, (select id from workspaces where personal_of=$1)))
+        on conflict (workspace_id, user_id) where inbox do nothing
+        "#,
+    )
+    .bind(owner)
+    .bind(workspace)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[async_trait]
+impl ListRepositoryTrait for ListRepositoryForDb {
+    fn owned_by(&self, owner: i32) -> Self {
+        Self {
+            owner: Some(owner),
+            ..self.clone()
+        }
+    }
+
+    fn in_workspace(&self, workspace: i32) -> Self {
+        Self {
+            workspace: Some(workspace),
+            ..self.clone()
+        }
+    }
+
+    async fn create(&self, payload: CreateList) -> anyhow::Result<List> {
+        let list = sqlx::query_as::<_, List>(
+            r#"
+            insert into lists (name, user_id, workspace_id)
+            values ($1, $2, coalesce($3, (select id from workspaces where personal_of=$2)))
+            returning *
+            "#,
+        )
+        .bind(payload.name)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(list)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<List> {
+        let list = sqlx::query_as::<_, List>(
+            r#"
+            select * from lists where id=$1 and list_visible(id, inbox, user_id, workspace_id, $2, $3)
+            "#,
+        )
+        .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+
+        Ok(list)
+    }
+
+    async fn inbox(&self) -> anyhow::Result<List> {
+        create_inbox(&self.pool, self.owner, self.workspace)
+            .await
+            .map_err(RepositoryError::from)?;
+        let list = sqlx::query_as::<_, List>(
+            r#"
+            select * from lists where inbox and user_id=$1
+                and workspace_id=coalesce($2, (select id from workspaces where personal_of=$1))
+            "#,
+        )
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(list)
+    }
+
+    async fn all(&self, include_archived: bool) -> anyhow::Result<Vec<List>> {
+        let lists = sqlx::query_as::<_, List>(
+            r#"
+            select * from lists
+            where ($1 or not archived) and list_visible(id, inbox, user_id, workspace_id, $2, $3)
+            order by inbox desc, id
+            "#,
+        )
+        .bind(include_archived)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(lists)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateList) -> anyhow::Result<List> {
+        let list = sqlx::query_as::<_, List>(
+            r#"
+            update lists set name=coalesce($1, name)
+            where id=$2 and list_visible(id, inbox, user_id, workspace_id, $3, $4)
+            returning *
+            "#,
+        )
+        .bind(payload.name)
+        .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+
+        Ok(list)
+    }
+
+    async fn archive(&self, id: i32) -> anyhow::Result<List> {
+        let list = sqlx::query_as::<_, List>(
+            r#"
+            update lists set archived=true
+            where id=$1 and list_visible(id, inbox, user_id, workspace_id, $2, $3)
+            returning *
+            "#,
+        )
+        .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+
+        Ok(list)
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        let result = sqlx::query(
+            r#"
+            delete from lists where id=$1 and list_visible(id, inbox, user_id, workspace_id, $2, $3)
+            "#,
+        )
+        .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use dotenv::dotenv;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
+
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
+
+        let mut owners = vec![];
+        for name in ["alice", "bob"] {
+            let id = sqlx::query_scalar::<_, i32>(
+                "insert into users (email, password_hash) values ($1, '') returning id",
+            )
+            .bind(format!(
+                "list_crud_scenario.{name}.{}@example.com",
+                chrono::Utc::now().timestamp_micros()
+            ))
+            .fetch_one(&pool)
+            .await
+            .expect("[create user] returned Err");
+            owners.push(id);
+        }
+        let repository = ListRepositoryForDb::new(pool.clone()).owned_by(owners[0]);
+        let other = ListRepositoryForDb::new(pool.clone()).owned_by(owners[1]);
+
+        // inbox, one per user
+        let inbox = repository.inbox().await.expect("[inbox] returned Err");
+        assert!(inbox.inbox);
+        assert!(!inbox.archived);
+        assert_eq!(
+            repository.inbox().await.expect("[inbox] returned Err"),
+            inbox
+        );
+        let other_inbox = other.inbox().await.expect("[inbox] returned Err");
+        assert_ne!(other_inbox.id, inbox.id);
+        assert!(other.find(inbox.id).await.is_err());
+
+        // and one per workspace, lists aren't seen from another one
+        let workspace = sqlx::query_scalar::<_, i32>(
+            "insert into workspaces (name) values ('[crud_scenario] workspace') returning id",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("[create workspace] returned Err");
+        let elsewhere = repository.in_workspace(workspace);
+        let elsewhere_inbox = elsewhere.inbox().await.expect("[inbox] returned Err");
+        assert_ne!(elsewhere_inbox.id, inbox.id);
+        assert!(elsewhere.find(inbox.id).await.is_err());
+        assert_eq!(
+            elsewhere.all(true).await.expect("[all] returned Err"),
+            vec![elsewhere_inbox]
+        );
+        sqlx::query("delete from workspaces where id=$1")
+            .bind(workspace)
+            .execute(&pool)
+            .await
+            .expect("[delete workspace] returned Err");
+
+        // create, seen once its owner is a member
+        let created = repository
+            .create(CreateList {
+                name: "[crud_scenario] list".to_string(),
+            })
+            .await
+            .expect("[create] returned Err");
+        assert!(!created.inbox);
+        assert!(repository.find(created.id).await.is_err());
+        sqlx::query("insert into list_members (list_id, user_id, role) values ($1, $2, 2)")
+            .bind(created.id)
+            .bind(owners[0])
+            .execute(&pool)
+            .await
+            .expect("[add member] returned Err");
+
+        // find
+        let list = repository
+            .find(created.id)
+            .await
+            .expect("[find] returned Err");
+        assert_eq!(created, list);
+        assert!(other.find(created.id).await.is_err());
+        assert!(other.delete(created.id).await.is_err());
+
+        // all
+        let lists = repository.all(false).await.expect("[all] returned Err");
+        assert_eq!(lists.first(), Some(&inbox));
+        assert!(lists.contains(&created));
+
+        // update
+        let list = repository
+            .update(
+                created.id,
+                UpdateList {
+                    name: Some("[crud_scenario] updated list".to_string()),
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        assert_eq!(list.name, "[crud_scenario] updated list");
+
+        // archive
+        let list = repository
+            .archive(list.id)
+            .await
+            .expect("[archive] returned Err");
+        assert!(list.archived);
+        let lists = repository.all(false).await.expect("[all] returned Err");
+        assert!(!lists.iter().any(|l| l.id == list.id));
+        let lists = repository.all(true).await.expect("[all] returned Err");
+        assert!(lists.contains(&list));
+
+        let res = repository.archive(inbox.id).await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::Invalid(_))
+        ));
+
+        // delete
+        repository
+            .delete(list.id)
+            .await
+            .expect("[delete] returned Err");
+        let res = repository.find(list.id).await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::NotFound(id)) if id == list.id
+        ));
+
+        sqlx::query("delete from users where id=any($1)")
+            .bind(&owners)
+            .execute(&pool)
+            .await
+            .expect("[delete users] returned Err");
+    }
+}
+
+#[cfg(any(test, feature = "memory"))]
+pub mod memory {
+    use super::*;
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, RwLock},
+    };
+
+    /// Lists by id, along with the workspace they were created in.
+    type Store = BTreeMap<i32, (Option<i32>, List)>;
+
+    /// Starts with the inbox. Which lists a user sees is left to their roles, a `None` workspace
+    /// sees the lists of every workspace.
+    #[derive(Debug, Clone)]
+    pub struct ListRepositoryForMemory {
+        store: Arc<RwLock<Store>>,
+        workspace: Option<i32>,
+    }
+
+    impl ListRepositoryForMemory {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Whether `list`, created in `workspace`, is seen from the workspace of the store.
+        fn sees(&self, workspace: Option<i32>, list: &List) -> bool {
+            list.inbox || self.workspace.is_none() || workspace == self.workspace
+        }
+
+        fn seen<'a>(&self, store: &'a mut Store, id: i32) -> Result<&'a mut List, RepositoryError> {
+            match store.get_mut(&id) {
+                Some((workspace, list)) if self.sees(*workspace, list) => Ok(list),
+                _ => Err(RepositoryError::NotFound(id)),
+            }
+        }
+    }
+
+    impl Default for ListRepositoryForMemory {
+        fn default() -> Self {
+            let inbox = List {
+                id: INBOX_ID,
+                name: "Inbox".to_string(),
+                inbox: true,
+                archived: false,
+            };
+            Self {
+                store: Arc::new(RwLock::new(BTreeMap::from([(inbox.id, (None, inbox))]))),
+                workspace: None,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ListRepositoryTrait for ListRepositoryForMemory {
+        fn owned_by(&self, _owner: i32) -> Self {
+            self.clone()
+        }
+
+        fn in_workspace(&self, workspace: i32) -> Self {
+            Self {
+                workspace: Some(workspace),
+                ..self.clone()
+            }
+        }
+
+        async fn create(&self, payload: CreateList) -> anyhow::Result<List> {
+            let mut store = self.store.write().unwrap();
+            let id = store.keys().max().copied().unwrap_or(0) + 1;
+            let list = List {
+                id,
+                name: payload.name,
+                inbox: false,
+                archived: false,
+            };
+            store.insert(id, (self.workspace, list.clone()));
+            Ok(list)
+        }
+
+        async fn find(&self, id: i32) -> anyhow::Result<List> {
+            let store = self.store.read().unwrap();
+            let list = store
+                .get(&id)
+                .filter(|(workspace, list)| self.sees(*workspace, list))
+                .map(|(_, list)| list.clone())
+                .ok_or(RepositoryError::NotFound(id))?;
+            Ok(list)
+        }
+
+        async fn inbox(&self) -> anyhow::Result<List> {
+            self.find(INBOX_ID).await
+        }
+
+        async fn all(&self, include_archived: bool) -> anyhow::Result<Vec<List>> {
+            let store = self.store.read().unwrap();
+            Ok(Vec::from_iter(
+                store
+                    .values()
+                    .filter(|(workspace, list)| self.sees(*workspace, list))
+                    .map(|(_, list)| list)
+                    .filter(|list| include_archived || !list.archived)
+                    .cloned(),
+            ))
+        }
+
+        async fn update(&self, id: i32, payload: UpdateList) -> anyhow::Result<List> {
+            let mut store = self.store.write().unwrap();
+            let list = self.seen(&mut store, id)?;
+            if let Some(name) = payload.name {
+                list.name = name;
+            }
+            Ok(list.clone())
+        }
+
+        async fn archive(&self, id: i32) -> anyhow::Result<List> {
+            let mut store = self.store.write().unwrap();
+            let list = self.seen(&mut store, id)?;
+            if list.inbox {
+                return Err(RepositoryError::Invalid("the inbox can't be archived".into()).into());
+            }
+            list.archived = true;
+            Ok(list.clone())
+        }
+
+        async fn delete(&self, id: i32) -> anyhow::Result<()> {
+            let mut store = self.store.write().unwrap();
+            self.seen(&mut store, id)?;
+            store.remove(&id);
+            Ok(())
+        }
+    }
+}
+
+pub mod sled {
+    use super::*;
+    use crate::todos::repository::sled::{decode, encode, generate_id, key, store_db};
+    use ::sled::{Db, Tree};
+
+    /// A list along with the workspace it was created in, as kept in the tree.
+    type Entry = (Option<i32>, List);
+
+    /// Lists as JSON in a tree of the sled database of the todos, for the desktop app. They are
+    /// seen like those of `ListRepositoryForMemory`, starting with the inbox.
+    #[derive(Debug, Clone)]
+    pub struct ListRepositoryForSled {
+        db: Db,
+        tree: Tree,
+        workspace: Option<i32>,
+    }
+
+    impl ListRepositoryForSled {
+        pub fn open(db: &Db) -> Result<Self, RepositoryError> {
+            let tree = db.open_tree("lists")?;
+            let inbox = List {
+                id: INBOX_ID,
+                name: "Inbox".to_string(),
+                inbox: true,
+                archived: false,
+            };
+            // an inbox from before is kept
+            tree.compare_and_swap(
+                key(INBOX_ID),
+                None as Option<&[u8]>,
+                Some(encode(&(None::<i32>, inbox))?),
+            )?
+            .ok();
+            Ok(Self {
+                db: db.clone(),
+                tree,
+                workspace: None,
+            })
+        }
+
+        /// Lists kept in the database of `crate::store`.
+        pub fn from_store() -> Result<Self, RepositoryError> {
+            Self::open(&store_db()?)
+        }
+
+        /// Whether `list`, created in `workspace`, is seen from the workspace of the store.
+        fn sees(&self, workspace: Option<i32>, list: &List) -> bool {
+            list.inbox || self.workspace.is_none() || workspace == self.workspace
+        }
+
+        fn get(&self, id: i32) -> Result<Entry, RepositoryError> {
+            match self.tree.get(key(id))? {
+                Some(bytes) => match decode::<Entry>(&bytes)? {
+                    (workspace, list) if self.sees(workspace, &list) => Ok((workspace, list)),
+                    _ => Err(RepositoryError::NotFound(id)),
+                },
+                None => Err(RepositoryError::NotFound(id)),
+            }
+        }
+
+        fn save(&self, workspace: Option<i32>, list: &List) -> Result<(), RepositoryError> {
+            self.tree
+                .insert(key(list.id), encode(&(workspace, list))?)?;
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl ListRepositoryTrait for ListRepositoryForSled {
+        fn owned_by(&self, _owner: i32) -> Self {
+            self.clone()
+        }
+
+        fn in_workspace(&self, workspace: i32) -> Self {
+            Self {
+                workspace: Some(workspace),
+                ..self.clone()
+            }
+        }
+
+        async fn create(&self, payload: CreateList) -> anyhow::Result<List> {
+            let mut id = generate_id(&self.db, &self.tree)?;
+            if id == INBOX_ID {
+                id = generate_id(&self.db, &self.tree)?;
+            }
+            let list = List {
+                id,
+                name: payload.name,
+                inbox: false,
+                archived: false,
+            };
+            self.save(self.workspace, &list)?;
+            Ok(list)
+        }
+
+        async fn find(&self, id: i32) -> anyhow::Result<List> {
+            let (_, list) = self.get(id)?;
+            Ok(list)
+        }
+
+        async fn inbox(&self) -> anyhow::Result<List> {
+            self.find(INBOX_ID).await
+        }
+
+        async fn all(&self, include_archived: bool) -> anyhow::Result<Vec<List>> {
+            let mut lists = Vec::new();
+            for bytes in self.tree.iter().values() {
+                let (workspace, list): Entry = decode(&bytes.map_err(RepositoryError::from)?)?;
+                if self.sees(workspace, &list) && (include_archived || !list.archived) {
+                    lists.push(list);
+                }
+            }
+            Ok(lists)
+        }
+
+        async fn update(&self, id: i32, payload: UpdateList) -> anyhow::Result<List> {
+            let (workspace, mut list) = self.get(id)?;
+            if let Some(name) = payload.name {
+                list.name = name;
+            }
+            self.save(workspace, &list)?;
+            Ok(list)
+        }
+
+        async fn archive(&self, id: i32) -> anyhow::Result<List> {
+            let (workspace, mut list) = self.get(id)?;
+            if list.inbox {
+                return Err(RepositoryError::Invalid("the inbox can't be archived".into()).into());
+            }
+            list.archived = true;
+            self.save(workspace, &list)?;
+            Ok(list)
+        }
+
+        async fn delete(&self, id: i32) -> anyhow::Result<()> {
+            self.get(id)?;
+            self.tree.remove(key(id)).map_err(RepositoryError::from)?;
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[tokio::test]
+        async fn sled_scenario() {
+            let db = ::sled::Config::new().temporary(true).open().unwrap();
+            let repository = ListRepositoryForSled::open(&db).unwrap().in_workspace(1);
+            let list = repository
+                .create(CreateList {
+                    name: "groceries".to_string(),
+                })
+                .await
+                .unwrap();
+            assert_ne!(list.id, INBOX_ID);
+            repository.archive(list.id).await.unwrap();
+            assert!(repository.archive(INBOX_ID).await.is_err());
+
+            // the inbox is only made once, and seen from every workspace
+            let reopened = ListRepositoryForSled::open(&db).unwrap();
+            let lists = reopened.in_workspace(1).all(true).await.unwrap();
+            assert_eq!(
+                Vec::from_iter(lists.iter().map(|l| (l.id, l.archived))),
+                vec![(INBOX_ID, false), (list.id, true)]
+            );
+            let lists = reopened.in_workspace(2).all(true).await.unwrap();
+            assert_eq!(Vec::from_iter(lists.iter().map(|l| l.id)), vec![INBOX_ID]);
+            assert!(reopened.in_workspace(2).find(list.id).await.is_err());
+        }
+    }
+}
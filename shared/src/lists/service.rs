@@ -0,0 +1,374 @@
+use axum::async_trait;
+use validator::Validate;
+
+use super::error::ListError;
+use super::model::{CreateList, List, ListCascade, UpdateList};
+use super::repository::ListRepositoryTrait;
+use crate::members::{model::Role, repository::MemberRepositoryTrait, service::role_of};
+use crate::todos::{
+    error::TodoError,
+    model::{check_limit, CreateTodo, Todo, TodoPage, TodoQuery},
+    repository::TodoRepositoryTrait,
+};
+
+/// Lists are stored by `LR`, which list a todo belongs to is stored by `TR` and the roles of the
+/// members of a list by `MR`. Lists the user isn't a member of aren't found, except their inbox.
+#[derive(Debug, Clone)]
+pub struct ListService<LR, TR, MR>
+where
+    LR: ListRepositoryTrait,
+    TR: TodoRepositoryTrait,
+    MR: MemberRepositoryTrait,
+{
+    list_repository: LR,
+    todo_repository: TR,
+    member_repository: MR,
+    /// Whose roles are checked, `None` is allowed everything.
+    user: Option<i32>,
+}
+
+#[async_trait]
+pub trait ListServiceTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    /// The user creating the list becomes its owner.
+    async fn create(&self, payload: CreateList) -> Result<List, ListError>;
+    async fn find(&self, id: i32) -> Result<List, ListError>;
+    async fn find_all(&self, include_archived: bool) -> Result<Vec<List>, ListError>;
+    async fn update(&self, id: i32, payload: UpdateList) -> Result<List, ListError>;
+    /// The todos of the list are moved to the inbox or deleted depending on `cascade`.
+    async fn archive(&self, id: i32, cascade: ListCascade) -> Result<List, ListError>;
+    /// The todos of the list are moved to the inbox or deleted depending on `cascade`.
+    async fn delete(&self, id: i32, cascade: ListCascade) -> Result<(), ListError>;
+    /// A page of the todos of the list, `query.list_id` is ignored.
+    async fn find_todos(&self, id: i32, query: TodoQuery) -> Result<TodoPage, ListError>;
+    /// Archived lists don't take new todos.
+    async fn create_todo(&self, id: i32, payload: CreateTodo) -> Result<Todo, ListError>;
+}
+
+impl<LR, TR, MR> ListService<LR, TR, MR>
+where
+    LR: ListRepositoryTrait,
+    TR: TodoRepositoryTrait,
+    MR: MemberRepositoryTrait,
+{
+    pub fn new(list_repository: LR, todo_repository: TR, member_repository: MR) -> Self {
+        Self {
+            list_repository,
+            todo_repository,
+            member_repository,
+            user: None,
+        }
+    }
+
+    /// The inbox of user `owner` and the lists they are a member of, holding the todos they see.
+    pub fn owned_by(&self, owner: i32) -> Self {
+        Self {
+            list_repository: self.list_repository.owned_by(owner),
+            todo_repository: self.todo_repository.owned_by(owner),
+            member_repository: self.member_repository.clone(),
+            user: Some(owner),
+        }
+    }
+
+    /// The lists of workspace `workspace`, holding its todos.
+    pub fn in_workspace(&self, workspace: i32) -> Self {
+        Self {
+            list_repository: self.list_repository.in_workspace(workspace),
+            todo_repository: self.todo_repository.in_workspace(workspace),
+            ..self.clone()
+        }
+    }
+
+    /// The list, unless the user isn't a member or their role is lower than `needed`.
+    async fn check_role(&self, id: i32, needed: Role) -> Result<List, ListError> {
+        let list = self.list_repository.find(id).await?;
+        match role_of(&self.member_repository, self.user, id).await? {
+            Some(role) if role >= needed => Ok(list),
+            Some(_) => Err(ListError::Forbidden(format!(
+                "needs to be {needed:?} of list {id}"
+            ))),
+            None => Err(ListError::NotFound(id)),
+        }
+    }
+
+    async fn empty(&self, id: i32, cascade: ListCascade) -> Result<List, ListError> {
+        let list = self.check_role(id, Role::Owner).await?;
+        if list.inbox {
+            return Err(ListError::Validation(
+                "the inbox can't be archived nor deleted".to_string(),
+            ));
+        }
+        match cascade {
+            ListCascade::MoveToInbox => {
+                let inbox = self.list_repository.inbox().await?;
+                self.todo_repository
+                    .move_list(id, inbox.id)
+                    .await
+                    .map_err(TodoError::from)?;
+            }
+            ListCascade::Delete => {
+                self.todo_repository
+                    .delete_list(id)
+                    .await
+                    .map_err(TodoError::from)?;
+            }
+        }
+        Ok(list)
+    }
+}
+
+#[async_trait]
+impl<LR, TR, MR> ListServiceTrait for ListService<LR, TR, MR>
+where
+    LR: ListRepositoryTrait,
+    TR: TodoRepositoryTrait,
+    MR: MemberRepositoryTrait,
+{
+    async fn create(&self, payload: CreateList) -> Result<List, ListError> {
+        payload.validate()?;
+        let list = self.list_repository.create(payload).await?;
+        if let Some(user) = self.user {
+            self.member_repository
+                .add(list.id, user, Role::Owner)
+                .await?;
+        }
+        Ok(list)
+    }
+
+    async fn find(&self, id: i32) -> Result<List, ListError> {
+        self.check_role(id, Role::Viewer).await
+    }
+
+    async fn find_all(&self, include_archived: bool) -> Result<Vec<List>, ListError> {
+        let mut lists = self.list_repository.all(include_archived).await?;
+        if let Some(user) = self.user {
+            let roles = self.member_repository.roles(user).await?;
+            lists.retain(|list| list.inbox || roles.contains_key(&list.id));
+        }
+        Ok(lists)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateList) -> Result<List, ListError> {
+        payload.validate()?;
+        self.check_role(id, Role::Owner).await?;
+        let list = self.list_repository.update(id, payload).await?;
+        Ok(list)
+    }
+
+    async fn archive(&self, id: i32, cascade: ListCascade) -> Result<List, ListError> {
+        self.empty(id, cascade).await?;
+        let list = self.list_repository.archive(id).await?;
+        Ok(list)
+    }
+
+    async fn delete(&self, id: i32, cascade: ListCascade) -> Result<(), ListError> {
+        self.empty(id, cascade).await?;
+        self.list_repository.delete(id).await?;
+        Ok(())
+    }
+
+    async fn find_todos(&self, id: i32, query: TodoQuery) -> Result<TodoPage, ListError> {
+        check_limit(query.limit).map_err(ListError::Validation)?;
+        self.check_role(id, Role::Viewer).await?;
+        let page = self
+            .todo_repository
+            .page(&TodoQuery {
+                list_id: Some(id),
+                ..query
+            })
+            .await
+            .map_err(TodoError::from)?;
+        Ok(page)
+    }
+
+    async fn create_todo(&self, id: i32, payload: CreateTodo) -> Result<Todo, ListError> {
+        payload.validate()?;
+        let list = self.check_role(id, Role::Editor).await?;
+        if list.archived {
+            return Err(ListError::Validation(format!("list {id} is archived")));
+        }
+        let todo = self
+            .todo_repository
+            .create(CreateTodo {
+                list_id: Some(id),
+                ..payload
+            })
+            .await
+            .map_err(TodoError::from)?;
+        Ok(todo)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lists::repository::memory::ListRepositoryForMemory;
+    use crate::members::repository::memory::MemberRepositoryForMemory;
+    use crate::todos::{
+        model::{TodoSort, UpdateTodo},
+        repository::memory::TodoRepositoryForMemory,
+    };
+
+    #[tokio::test]
+    async fn lists_scenario() {
+        let todo_repository = TodoRepositoryForMemory::new();
+        let service = ListService::new(
+            ListRepositoryForMemory::new(),
+            todo_repository.clone(),
+            MemberRepositoryForMemory::new(),
+        );
+        let list = |name: &str| CreateList {
+            name: name.to_string(),
+        };
+        let by_position = TodoQuery {
+            sort: TodoSort::Position,
+            ..Default::default()
+        };
+        let ids = |todos: Vec<Todo>| todos.into_iter().map(|t| t.id).collect::<Vec<_>>();
+
+        let inbox = service.find_all(false).await.unwrap().remove(0);
+        assert!(inbox.inbox);
+        let work = service.create(list("work")).await.unwrap();
+        let home = service.create(list("home")).await.unwrap();
+        assert!(matches!(
+            service.create(list("")).await,
+            Err(ListError::Validation(_))
+        ));
+
+        let loose = todo_repository
+            .create(CreateTodo::new("loose".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(loose.list_id, inbox.id);
+        let report = service
+            .create_todo(work.id, CreateTodo::new("report".to_string()))
+            .await
+            .unwrap();
+        let dishes = service
+            .create_todo(home.id, CreateTodo::new("dishes".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            service
+                .create_todo(42, CreateTodo::new("lost".to_string()))
+                .await,
+            Err(ListError::NotFound(42))
+        );
+        assert_eq!(
+            ids(service
+                .find_todos(work.id, by_position.clone())
+                .await
+                .unwrap()
+                .todos),
+            vec![report.id]
+        );
+
+        // moving through UpdateTodo
+        todo_repository
+            .update(
+                loose.id,
+                UpdateTodo {
+                    list_id: Some(work.id),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            ids(service
+                .find_todos(work.id, by_position.clone())
+                .await
+                .unwrap()
+                .todos),
+            vec![loose.id, report.id]
+        );
+
+        // archiving moves the todos to the inbox
+        let archived = service
+            .archive(work.id, ListCascade::MoveToInbox)
+            .await
+            .unwrap();
+        assert!(archived.archived);
+        assert_eq!(
+            service.find_all(false).await.unwrap(),
+            vec![inbox.clone(), home.clone()]
+        );
+        assert!(matches!(
+            service
+                .create_todo(work.id, CreateTodo::new("late".to_string()))
+                .await,
+            Err(ListError::Validation(_))
+        ));
+        assert_eq!(
+            ids(service
+                .find_todos(inbox.id, by_position.clone())
+                .await
+                .unwrap()
+                .todos),
+            vec![loose.id, report.id]
+        );
+
+        // deleting can take the todos along
+        service.delete(home.id, ListCascade::Delete).await.unwrap();
+        assert_eq!(
+            service.find(home.id).await,
+            Err(ListError::NotFound(home.id))
+        );
+        assert!(todo_repository.find(dishes.id).await.is_err());
+
+        assert!(matches!(
+            service.delete(inbox.id, ListCascade::Delete).await,
+            Err(ListError::Validation(_))
+        ));
+        assert!(matches!(
+            service.archive(inbox.id, ListCascade::MoveToInbox).await,
+            Err(ListError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_hide_lists_of_others() {
+        let member_repository = MemberRepositoryForMemory::new();
+        let service = ListService::new(
+            ListRepositoryForMemory::new(),
+            TodoRepositoryForMemory::new(),
+            member_repository.clone(),
+        );
+        let (alice, bob) = (service.owned_by(1), service.owned_by(2));
+        let names = |lists: Vec<List>| lists.into_iter().map(|l| l.name).collect::<Vec<_>>();
+
+        let work = alice
+            .create(CreateList {
+                name: "work".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            names(alice.find_all(false).await.unwrap()),
+            ["Inbox", "work"]
+        );
+        assert_eq!(names(bob.find_all(false).await.unwrap()), ["Inbox"]);
+        assert_eq!(bob.find(work.id).await, Err(ListError::NotFound(work.id)));
+
+        member_repository
+            .add(work.id, 2, Role::Viewer)
+            .await
+            .unwrap();
+        assert_eq!(bob.find(work.id).await, Ok(work.clone()));
+        assert!(matches!(
+            bob.create_todo(work.id, CreateTodo::new("report".to_string()))
+                .await,
+            Err(ListError::Forbidden(_))
+        ));
+        assert!(matches!(
+            bob.delete(work.id, ListCascade::Delete).await,
+            Err(ListError::Forbidden(_))
+        ));
+        alice
+            .create_todo(work.id, CreateTodo::new("report".to_string()))
+            .await
+            .unwrap();
+    }
+}
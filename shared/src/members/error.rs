@@ -0,0 +1,77 @@
+use axum::http::StatusCode;
+use thiserror::Error;
+
+use crate::todos::repository::RepositoryError;
+
+/// Error returned by `MemberServiceTrait`, serialized like `TodoError`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum MemberError {
+    /// Also returned for the lists the user can't see.
+    #[error("List not found, id is {0}")]
+    ListNotFound(i32),
+    #[error("Member not found, user id is {0}")]
+    NotFound(i32),
+    /// The token is unknown, expired or already taken, which isn't told apart.
+    #[error("Invalid invitation")]
+    InvalidInvitation,
+    #[error("Forbidden: [{0}]")]
+    Forbidden(String),
+    #[error("Validation error: [{0}]")]
+    Validation(String),
+    #[error("Conflict: [{0}]")]
+    Conflict(String),
+    #[error("Storage error: [{0}]")]
+    Storage(String),
+    #[error("Unexpected Error: [{0}]")]
+    Unexpected(String),
+}
+
+impl MemberError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MemberError::ListNotFound(_) => "ListNotFound",
+            MemberError::NotFound(_) => "NotFound",
+            MemberError::InvalidInvitation => "InvalidInvitation",
+            MemberError::Forbidden(_) => "Forbidden",
+            MemberError::Validation(_) => "Validation",
+            MemberError::Conflict(_) => "Conflict",
+            MemberError::Storage(_) => "Storage",
+            MemberError::Unexpected(_) => "Unexpected",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            MemberError::ListNotFound(_) | MemberError::NotFound(_) => StatusCode::NOT_FOUND,
+            MemberError::InvalidInvitation => StatusCode::BAD_REQUEST,
+            MemberError::Forbidden(_) => StatusCode::FORBIDDEN,
+            MemberError::Validation(_) => StatusCode::BAD_REQUEST,
+            MemberError::Conflict(_) => StatusCode::CONFLICT,
+            MemberError::Storage(_) => StatusCode::SERVICE_UNAVAILABLE,
+            MemberError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<RepositoryError> for MemberError {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::NotFound(id) => MemberError::NotFound(id),
+            RepositoryError::Invalid(message) => MemberError::Validation(message),
+            RepositoryError::Conflict(message) => MemberError::Conflict(message),
+            RepositoryError::Storage(message) => MemberError::Storage(message),
+            RepositoryError::Unexpected(message) => MemberError::Unexpected(message),
+        }
+    }
+}
+
+impl From<anyhow::Error> for MemberError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<RepositoryError>() {
+            Ok(e) => e.into(),
+            Err(e) => MemberError::Unexpected(e.to_string()),
+        }
+    }
+}
+
+crate::impl_error_response!(MemberError);
@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+/// What a member may do with a list and its todos, each role being allowed what the ones before
+/// it are.
+#[derive(
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    sqlx::Type,
+    ToSchema,
+)]
+#[repr(i16)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Sees the todos.
+    #[default]
+    Viewer = 0,
+    /// Creates, changes and deletes the todos.
+    Editor = 1,
+    /// Changes the list itself, invites members and changes their roles.
+    Owner = 2,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct Member {
+    pub list_id: i32,
+    pub user_id: i32,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct UpdateMember {
+    pub role: Role,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct CreateInvitation {
+    /// Given to whoever accepts the invitation.
+    #[serde(default)]
+    pub role: Role,
+}
+
+/// Joins a list once accepted with its token. Its token is only known when it's created, then
+/// only its hash is kept.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct Invitation {
+    pub id: i32,
+    pub list_id: i32,
+    /// Joined along with the list, the workspace it was shared from.
+    pub workspace_id: Option<i32>,
+    pub role: Role,
+    pub created_by: i32,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_by: Option<i32>,
+    pub accepted_at: Option<DateTime<Utc>>,
+}
+
+/// An invitation as created, along with the token to hand to the invited user.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct NewInvitation {
+    #[serde(flatten)]
+    pub invitation: Invitation,
+    /// Shown this once, accepting the invitation takes it.
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct AcceptInvitation {
+    pub token: String,
+}
@@ -0,0 +1,707 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+use super::model::{Invitation, Member, Role};
+use crate::todos::repository::RepositoryError;
+
+#[async_trait]
+pub trait MemberRepositoryTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    /// The role of the user in the list, `None` when they aren't a member. Users are `Owner` of
+    /// their inbox without being listed among its members.
+    async fn role(&self, list_id: i32, user_id: i32) -> anyhow::Result<Option<Role>>;
+    /// The lists the user is a member of, mapped to their role in it.
+    async fn roles(&self, user_id: i32) -> anyhow::Result<HashMap<i32, Role>>;
+    /// Owners first, then oldest first.
+    async fn members(&self, list_id: i32) -> anyhow::Result<Vec<Member>>;
+    /// A user who is already a member keeps the highest of both roles.
+    async fn add(&self, list_id: i32, user_id: i32, role: Role) -> anyhow::Result<Member>;
+    /// Fails with `NotFound` when the user isn't a member.
+    async fn update_role(&self, list_id: i32, user_id: i32, role: Role) -> anyhow::Result<Member>;
+    /// Fails with `NotFound` when the user isn't a member.
+    async fn remove(&self, list_id: i32, user_id: i32) -> anyhow::Result<()>;
+    /// `workspace_id` defaults to the workspace of the list in stores keeping workspaces.
+    #[allow(clippy::too_many_arguments)]
+    async fn create_invitation(
+        &self,
+        list_id: i32,
+        workspace_id: Option<i32>,
+        role: Role,
+        created_by: i32,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> anyhow::Result<Invitation>;
+    /// Takes the invitation with `token_hash` for the user unless it expired or was taken before.
+    async fn accept_invitation(
+        &self,
+        token_hash: &str,
+        user_id: i32,
+    ) -> anyhow::Result<Option<Invitation>>;
+}
+
+#[derive(Debug, Clone)]
+pub struct MemberRepositoryForDb {
+    pool: PgPool,
+}
+
+impl MemberRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        MemberRepositoryForDb { pool }
+    }
+}
+
+#[async_trait]
+impl MemberRepositoryTrait for MemberRepositoryForDb {
+    async fn role(&self, list_id: i32, user_id: i32) -> anyhow::Result<Option<Role>> {
+        let role = sqlx::query_scalar::<_, Role>(
+            r#"
+            select role from list_members where list_id=$1 and user_id=$2
+            union all
+            select 2::smallint from lists where id=$1 and inbox and user_id=$2
+            "#,
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(role)
+    }
+
+    async fn roles(&self, user_id: i32) -> anyhow::Result<HashMap<i32, Role>> {
+        let roles = sqlx::query_as::<_, (i32, Role)>(
+            r#"
+            select list_id, role from list_members where user_id=$1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(roles.into_iter().collect())
+    }
+
+    async fn members(&self, list_id: i32) -> anyhow::Result<Vec<Member>> {
+        let members = sqlx::query_as::<_, Member>(
+            r#"
+            select * from list_members where list_id=$1
+            order by role desc, created_at, user_id
+            "#,
+        )
+        .bind(list_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(members)
+    }
+
+    async fn add(&self, list_id: i32, user_id: i32, role: Role) -> anyhow::Result<Member> {
+        let member = sqlx::query_as::<_, Member>(
+            r#"
+            insert into list_members (list_id, user_id, role)
+            values ($1, $2, $3)
+            on conflict (list_id, user_id) do update set role=greatest(list_members.role, excluded.role)
+            returning *
+            "#,
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .bind(role)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(member)
+    }
+
+    async fn update_role(&self, list_id: i32, user_id: i32, role: Role) -> anyhow::Result<Member> {
+        let member = sqlx::query_as::<_, Member>(
+            r#"
+            update list_members set role=$3
+            where list_id=$1 and user_id=$2
+            returning *
+            "#,
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .bind(role)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(user_id))?;
+
+        Ok(member)
+    }
+
+    async fn remove(&self, list_id: i32, user_id: i32) -> anyhow::Result<()> {
+        let res = sqlx::query("delete from list_members where list_id=$1 and user_id=$2")
+            .bind(list_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepositoryError::from)?;
+        if res.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(user_id).into());
+        }
+
+        Ok(())
+    }
+
+    async fn create_invitation(
+        &self,
+        list_id: i32,
+        workspace_id: Option<i32>,
+        role: Role,
+        created_by: i32,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> anyhow::Result<Invitation> {
+        let invitation = sqlx::query_as::<_, Invitation>(
+            r#"
+            insert into list_invitations (list_id, workspace_id, role, created_by, token_hash, expires_at)
+            values ($1, coalesce($2, (select workspace_id from lists where id=$1)), $3, $4, $5, $6)
+            returning id, list_id, workspace_id, role, created_by, created_at, expires_at, accepted_by, accepted_at
+            "#,
+        )
+        .bind(list_id)
+        .bind(workspace_id)
+        .bind(role)
+        .bind(created_by)
+        .bind(token_hash)
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(invitation)
+    }
+
+    async fn accept_invitation(
+        &self,
+        token_hash: &str,
+        user_id: i32,
+    ) -> anyhow::Result<Option<Invitation>> {
+        let invitation = sqlx::query_as::<_, Invitation>(
+            r#"
+            update list_invitations set accepted_by=$2, accepted_at=now()
+            where token_hash=$1 and accepted_at is null and expires_at > now()
+            returning id, list_id, workspace_id, role, created_by, created_at, expires_at, accepted_by, accepted_at
+            "#,
+        )
+        .bind(token_hash)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(invitation)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use crate::lists::model::CreateList;
+    use crate::lists::repository::{ListRepositoryForDb, ListRepositoryTrait};
+    use crate::users::repository::{UserRepositoryForDb, UserRepositoryTrait};
+    use crate::workspaces::repository::{WorkspaceRepositoryForDb, WorkspaceRepositoryTrait};
+    use chrono::Duration;
+    use dotenv::dotenv;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
+
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
+
+        let stamp = chrono::Utc::now().timestamp_micros();
+        let users = UserRepositoryForDb::new(pool.clone());
+        let alice = users
+            .create(&format!("members.alice.{stamp}@example.com"), "hash")
+            .await
+            .expect("failed to create a user");
+        let bob = users
+            .create(&format!("members.bob.{stamp}@example.com"), "hash")
+            .await
+            .expect("failed to create a user");
+        let lists = ListRepositoryForDb::new(pool.clone()).owned_by(alice.id);
+        let list = lists
+            .create(CreateList {
+                name: format!("members.{stamp}"),
+            })
+            .await
+            .expect("failed to create a list");
+        let inbox = lists.inbox().await.expect("failed to create an inbox");
+        let repository = MemberRepositoryForDb::new(pool.clone());
+
+        // nobody has a role in a list without members, users own their inbox
+        assert_eq!(repository.role(list.id, alice.id).await.unwrap(), None);
+        assert_eq!(
+            repository.role(inbox.id, alice.id).await.unwrap(),
+            Some(Role::Owner)
+        );
+        assert_eq!(repository.role(inbox.id, bob.id).await.unwrap(), None);
+
+        // add
+        let owner = repository
+            .add(list.id, alice.id, Role::Owner)
+            .await
+            .expect("[add] returned Err");
+        assert_eq!(owner.role, Role::Owner);
+        assert_eq!(repository.role(list.id, bob.id).await.unwrap(), None);
+        assert_eq!(
+            repository.roles(alice.id).await.unwrap().get(&list.id),
+            Some(&Role::Owner)
+        );
+        assert_eq!(repository.roles(bob.id).await.unwrap().get(&list.id), None);
+
+        // invitation
+        let hash = format!("crud_scenario.{stamp}");
+        let expires_at = Utc::now() + Duration::days(1);
+        repository
+            .create_invitation(list.id, None, Role::Editor, alice.id, &hash, expires_at)
+            .await
+            .expect("[create_invitation] returned Err");
+        let invitation = repository
+            .accept_invitation(&hash, bob.id)
+            .await
+            .expect("[accept_invitation] returned Err")
+            .expect("[accept_invitation] found no invitation");
+        assert_eq!(invitation.accepted_by, Some(bob.id));
+        assert_eq!(
+            invitation.workspace_id,
+            Some(
+                WorkspaceRepositoryForDb::new(pool.clone())
+                    .personal(alice.id)
+                    .await
+                    .unwrap()
+                    .id
+            )
+        );
+        assert_eq!(
+            repository.accept_invitation(&hash, bob.id).await.unwrap(),
+            None
+        );
+
+        // add keeps the highest role
+        repository.add(list.id, bob.id, Role::Editor).await.unwrap();
+        let member = repository.add(list.id, bob.id, Role::Viewer).await.unwrap();
+        assert_eq!(member.role, Role::Editor);
+
+        // members
+        let members = repository.members(list.id).await.unwrap();
+        assert_eq!(
+            members.iter().map(|m| m.user_id).collect::<Vec<_>>(),
+            vec![alice.id, bob.id]
+        );
+
+        // update_role
+        let member = repository
+            .update_role(list.id, bob.id, Role::Viewer)
+            .await
+            .expect("[update_role] returned Err");
+        assert_eq!(member.role, Role::Viewer);
+        assert_eq!(
+            repository.role(list.id, bob.id).await.unwrap(),
+            Some(Role::Viewer)
+        );
+
+        // remove
+        repository
+            .remove(list.id, bob.id)
+            .await
+            .expect("[remove] returned Err");
+        let res = repository.remove(list.id, bob.id).await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::NotFound(_))
+        ));
+
+        sqlx::query("delete from lists where id=$1")
+            .bind(list.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("delete from users where id=any($1)")
+            .bind(vec![alice.id, bob.id])
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
+
+#[cfg(any(test, feature = "memory"))]
+pub mod memory {
+    use super::*;
+    use crate::lists::repository::INBOX_ID;
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, RwLock},
+    };
+
+    /// Every user owns `INBOX_ID`, their inbox in memory.
+    #[derive(Debug, Clone, Default)]
+    pub struct MemberRepositoryForMemory {
+        members: Arc<RwLock<BTreeMap<(i32, i32), Member>>>,
+        invitations: Arc<RwLock<BTreeMap<i32, (Invitation, String)>>>,
+    }
+
+    impl MemberRepositoryForMemory {
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+
+    #[async_trait]
+    impl MemberRepositoryTrait for MemberRepositoryForMemory {
+        async fn role(&self, list_id: i32, user_id: i32) -> anyhow::Result<Option<Role>> {
+            if list_id == INBOX_ID {
+                return Ok(Some(Role::Owner));
+            }
+            let members = self.members.read().unwrap();
+            Ok(members.get(&(list_id, user_id)).map(|member| member.role))
+        }
+
+        async fn roles(&self, user_id: i32) -> anyhow::Result<HashMap<i32, Role>> {
+            let members = self.members.read().unwrap();
+            Ok(members
+                .values()
+                .filter(|member| member.user_id == user_id)
+                .map(|member| (member.list_id, member.role))
+                .collect())
+        }
+
+        async fn members(&self, list_id: i32) -> anyhow::Result<Vec<Member>> {
+            let members = self.members.read().unwrap();
+            let mut members = members
+                .values()
+                .filter(|member| member.list_id == list_id)
+                .cloned()
+                .collect::<Vec<_>>();
+            members.sort_by_key(|member| {
+                (
+                    std::cmp::Reverse(member.role),
+                    member.created_at,
+                    member.user_id,
+                )
+            });
+            Ok(members)
+        }
+
+        async fn add(&self, list_id: i32, user_id: i32, role: Role) -> anyhow::Result<Member> {
+            let mut members = self.members.write().unwrap();
+            let member = members.entry((list_id, user_id)).or_insert(Member {
+                list_id,
+                user_id,
+                role,
+                created_at: Utc::now(),
+            });
+            member.role = member.role.max(role);
+            Ok(member.clone())
+        }
+
+        async fn update_role(
+            &self,
+            list_id: i32,
+            user_id: i32,
+            role: Role,
+        ) -> anyhow::Result<Member> {
+            let mut members = self.members.write().unwrap();
+            let member = members
+                .get_mut(&(list_id, user_id))
+                .ok_or(RepositoryError::NotFound(user_id))?;
+            member.role = role;
+            Ok(member.clone())
+        }
+
+        async fn remove(&self, list_id: i32, user_id: i32) -> anyhow::Result<()> {
+            let mut members = self.members.write().unwrap();
+            members
+                .remove(&(list_id, user_id))
+                .ok_or(RepositoryError::NotFound(user_id))?;
+            Ok(())
+        }
+
+        async fn create_invitation(
+            &self,
+            list_id: i32,
+            workspace_id: Option<i32>,
+            role: Role,
+            created_by: i32,
+            token_hash: &str,
+            expires_at: DateTime<Utc>,
+        ) -> anyhow::Result<Invitation> {
+            let mut invitations = self.invitations.write().unwrap();
+            let id = invitations.keys().max().copied().unwrap_or(0) + 1;
+            let invitation = Invitation {
+                id,
+                list_id,
+                workspace_id,
+                role,
+                created_by,
+                created_at: Utc::now(),
+                expires_at,
+                accepted_by: None,
+                accepted_at: None,
+            };
+            invitations.insert(id, (invitation.clone(), token_hash.to_string()));
+            Ok(invitation)
+        }
+
+        async fn accept_invitation(
+            &self,
+            token_hash: &str,
+            user_id: i32,
+        ) -> anyhow::Result<Option<Invitation>> {
+            let mut invitations = self.invitations.write().unwrap();
+            let now = Utc::now();
+            Ok(invitations
+                .values_mut()
+                .find(|(invitation, hash)| {
+                    hash == token_hash
+                        && invitation.accepted_at.is_none()
+                        && invitation.expires_at > now
+                })
+                .map(|(invitation, _)| {
+                    invitation.accepted_by = Some(user_id);
+                    invitation.accepted_at = Some(now);
+                    invitation.clone()
+                }))
+        }
+    }
+}
+
+pub mod sled {
+    use super::*;
+    use crate::lists::repository::INBOX_ID;
+    use crate::todos::repository::sled::{decode, encode, generate_id, key, pair_key, store_db};
+    use ::sled::{Db, Tree};
+    use std::sync::{Arc, Mutex};
+
+    /// Members and invitations as JSON in trees of the sled database of the todos, for the
+    /// desktop app. Every user owns `INBOX_ID`, like in memory.
+    #[derive(Debug, Clone)]
+    pub struct MemberRepositoryForSled {
+        db: Db,
+        /// Members under the `pair_key` of their list and user.
+        members: Tree,
+        /// Invitations along with the hash of their token.
+        invitations: Tree,
+        /// Held by every write, which reads a row and writes it back.
+        write: Arc<Mutex<()>>,
+    }
+
+    impl MemberRepositoryForSled {
+        pub fn open(db: &Db) -> Result<Self, RepositoryError> {
+            Ok(Self {
+                db: db.clone(),
+                members: db.open_tree("list_members")?,
+                invitations: db.open_tree("list_invitations")?,
+                write: Arc::default(),
+            })
+        }
+
+        /// Members kept in the database of `crate::store`.
+        pub fn from_store() -> Result<Self, RepositoryError> {
+            Self::open(&store_db()?)
+        }
+
+        fn member(&self, list_id: i32, user_id: i32) -> Result<Option<Member>, RepositoryError> {
+            match self.members.get(pair_key(list_id, user_id))? {
+                Some(bytes) => Ok(Some(decode(&bytes)?)),
+                None => Ok(None),
+            }
+        }
+
+        fn all(&self) -> Result<Vec<Member>, RepositoryError> {
+            self.members
+                .iter()
+                .values()
+                .map(|bytes| decode(&bytes?))
+                .collect()
+        }
+
+        fn save(&self, member: &Member) -> Result<(), RepositoryError> {
+            self.members
+                .insert(pair_key(member.list_id, member.user_id), encode(member)?)?;
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl MemberRepositoryTrait for MemberRepositoryForSled {
+        async fn role(&self, list_id: i32, user_id: i32) -> anyhow::Result<Option<Role>> {
+            if list_id == INBOX_ID {
+                return Ok(Some(Role::Owner));
+            }
+            Ok(self.member(list_id, user_id)?.map(|member| member.role))
+        }
+
+        async fn roles(&self, user_id: i32) -> anyhow::Result<HashMap<i32, Role>> {
+            Ok(self
+                .all()?
+                .into_iter()
+                .filter(|member| member.user_id == user_id)
+                .map(|member| (member.list_id, member.role))
+                .collect())
+        }
+
+        async fn members(&self, list_id: i32) -> anyhow::Result<Vec<Member>> {
+            let mut members = Vec::from_iter(
+                self.all()?
+                    .into_iter()
+                    .filter(|member| member.list_id == list_id),
+            );
+            members.sort_by_key(|member| {
+                (
+                    std::cmp::Reverse(member.role),
+                    member.created_at,
+                    member.user_id,
+                )
+            });
+            Ok(members)
+        }
+
+        async fn add(&self, list_id: i32, user_id: i32, role: Role) -> anyhow::Result<Member> {
+            let _write = self.write.lock().unwrap();
+            let mut member = self.member(list_id, user_id)?.unwrap_or(Member {
+                list_id,
+                user_id,
+                role,
+                created_at: Utc::now(),
+            });
+            member.role = member.role.max(role);
+            self.save(&member)?;
+            Ok(member)
+        }
+
+        async fn update_role(
+            &self,
+            list_id: i32,
+            user_id: i32,
+            role: Role,
+        ) -> anyhow::Result<Member> {
+            let _write = self.write.lock().unwrap();
+            let mut member = self
+                .member(list_id, user_id)?
+                .ok_or(RepositoryError::NotFound(user_id))?;
+            member.role = role;
+            self.save(&member)?;
+            Ok(member)
+        }
+
+        async fn remove(&self, list_id: i32, user_id: i32) -> anyhow::Result<()> {
+            self.members
+                .remove(pair_key(list_id, user_id))
+                .map_err(RepositoryError::from)?
+                .ok_or(RepositoryError::NotFound(user_id))?;
+            Ok(())
+        }
+
+        async fn create_invitation(
+            &self,
+            list_id: i32,
+            workspace_id: Option<i32>,
+            role: Role,
+            created_by: i32,
+            token_hash: &str,
+            expires_at: DateTime<Utc>,
+        ) -> anyhow::Result<Invitation> {
+            let invitation = Invitation {
+                id: generate_id(&self.db, &self.invitations)?,
+                list_id,
+                workspace_id,
+                role,
+                created_by,
+                created_at: Utc::now(),
+                expires_at,
+                accepted_by: None,
+                accepted_at: None,
+            };
+            self.invitations
+                .insert(key(invitation.id), encode(&(&invitation, token_hash))?)
+                .map_err(RepositoryError::from)?;
+            Ok(invitation)
+        }
+
+        async fn accept_invitation(
+            &self,
+            token_hash: &str,
+            user_id: i32,
+        ) -> anyhow::Result<Option<Invitation>> {
+            let _write = self.write.lock().unwrap();
+            let now = Utc::now();
+            for bytes in self.invitations.iter().values() {
+                let (mut invitation, hash): (Invitation, String) =
+                    decode(&bytes.map_err(RepositoryError::from)?)?;
+                if hash == token_hash
+                    && invitation.accepted_at.is_none()
+                    && invitation.expires_at > now
+                {
+                    invitation.accepted_by = Some(user_id);
+                    invitation.accepted_at = Some(now);
+                    self.invitations
+                        .insert(key(invitation.id), encode(&(&invitation, hash))?)
+                        .map_err(RepositoryError::from)?;
+                    return Ok(Some(invitation));
+                }
+            }
+            Ok(None)
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[tokio::test]
+        async fn sled_scenario() {
+            let db = ::sled::Config::new().temporary(true).open().unwrap();
+            let repository = MemberRepositoryForSled::open(&db).unwrap();
+            repository.add(2, 1, Role::Owner).await.unwrap();
+            repository.add(2, 3, Role::Editor).await.unwrap();
+            assert_eq!(
+                repository.add(2, 3, Role::Viewer).await.unwrap().role,
+                Role::Editor
+            );
+            let invitation = repository
+                .create_invitation(
+                    2,
+                    None,
+                    Role::Viewer,
+                    1,
+                    "hash",
+                    Utc::now() + chrono::Days::new(1),
+                )
+                .await
+                .unwrap();
+
+            let reopened = MemberRepositoryForSled::open(&db).unwrap();
+            assert_eq!(reopened.role(INBOX_ID, 4).await.unwrap(), Some(Role::Owner));
+            let members = reopened.members(2).await.unwrap();
+            assert_eq!(
+                Vec::from_iter(members.iter().map(|m| (m.user_id, m.role))),
+                vec![(1, Role::Owner), (3, Role::Editor)]
+            );
+            let accepted = reopened.accept_invitation("hash", 4).await.unwrap();
+            assert_eq!(
+                accepted.map(|i| (i.id, i.accepted_by)),
+                Some((invitation.id, Some(4)))
+            );
+            assert_eq!(repository.accept_invitation("hash", 5).await.unwrap(), None);
+            reopened.remove(2, 3).await.unwrap();
+            assert_eq!(repository.role(2, 3).await.unwrap(), None);
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use axum::async_trait;
+use chrono::{Duration, Utc};
+
+use super::error::MemberError;
+use super::model::{AcceptInvitation, CreateInvitation, Member, NewInvitation, Role, UpdateMember};
+use super::repository::MemberRepositoryTrait;
+use crate::lists::{model::List, repository::ListRepositoryTrait};
+use crate::tokens::service::{generate_secret, hash_secret};
+use crate::workspaces::{model::WorkspaceRole, repository::WorkspaceRepositoryTrait};
+
+/// How long an invitation can be accepted for.
+pub const INVITATION_TTL: Duration = Duration::days(7);
+
+/// Starts every invitation token.
+pub const INVITATION_PREFIX: &str = "inv_";
+
+/// The role `user` has in list `list_id`. `None` is a user who sees every todo, like the stores of
+/// a single user, and owns every list.
+pub async fn role_of<MR>(
+    member_repository: &MR,
+    user: Option<i32>,
+    list_id: i32,
+) -> anyhow::Result<Option<Role>>
+where
+    MR: MemberRepositoryTrait,
+{
+    match user {
+        Some(user) => member_repository.role(list_id, user).await,
+        None => Ok(Some(Role::Owner)),
+    }
+}
+
+/// Members are stored by `MR`, `LR` tells which lists exist and `WR` keeps the workspaces that
+/// invited users join along with a list. The creator of a list is its first owner, see
+/// `ListServiceTrait::create`.
+#[derive(Debug, Clone)]
+pub struct MemberService<MR, LR, WR>
+where
+    MR: MemberRepositoryTrait,
+    LR: ListRepositoryTrait,
+    WR: WorkspaceRepositoryTrait,
+{
+    member_repository: MR,
+    list_repository: LR,
+    workspace_repository: WR,
+    /// Where the lists are shared from, see `in_workspace`.
+    workspace: Option<i32>,
+}
+
+#[async_trait]
+pub trait MemberServiceTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    /// Any member sees the others. The inbox has none.
+    async fn members(&self, user_id: i32, list_id: i32) -> Result<Vec<Member>, MemberError>;
+    /// Only owners invite, and the inbox isn't shared.
+    async fn invite(
+        &self,
+        user_id: i32,
+        list_id: i32,
+        payload: CreateInvitation,
+    ) -> Result<NewInvitation, MemberError>;
+    /// Joins the list of the invitation and the workspace it was shared from, an invitation is
+    /// only accepted once.
+    async fn accept(&self, user_id: i32, payload: AcceptInvitation) -> Result<Member, MemberError>;
+    /// Only owners change roles, and a list keeps at least one owner.
+    async fn update_role(
+        &self,
+        user_id: i32,
+        list_id: i32,
+        member_id: i32,
+        payload: UpdateMember,
+    ) -> Result<Member, MemberError>;
+    /// Owners remove anyone and members remove themselves, a list keeps at least one owner.
+    async fn remove(&self, user_id: i32, list_id: i32, member_id: i32) -> Result<(), MemberError>;
+}
+
+impl<MR, LR, WR> MemberService<MR, LR, WR>
+where
+    MR: MemberRepositoryTrait,
+    LR: ListRepositoryTrait,
+    WR: WorkspaceRepositoryTrait,
+{
+    pub fn new(member_repository: MR, list_repository: LR, workspace_repository: WR) -> Self {
+        Self {
+            member_repository,
+            list_repository,
+            workspace_repository,
+            workspace: None,
+        }
+    }
+
+    /// The members of the lists of workspace `workspace`, which invited users join too.
+    pub fn in_workspace(&self, workspace: i32) -> Self {
+        Self {
+            list_repository: self.list_repository.in_workspace(workspace),
+            workspace: Some(workspace),
+            ..self.clone()
+        }
+    }
+
+    /// Lists the user can't see are not found, those where the role is too low are forbidden.
+    async fn check_role(
+        &self,
+        user_id: i32,
+        list_id: i32,
+        needed: Role,
+    ) -> Result<List, MemberError> {
+        let list = self
+            .list_repository
+            .owned_by(user_id)
+            .find(list_id)
+            .await
+            .map_err(|e| match MemberError::from(e) {
+                MemberError::NotFound(id) => MemberError::ListNotFound(id),
+                e => e,
+            })?;
+        match self.member_repository.role(list_id, user_id).await? {
+            Some(role) if role >= needed => Ok(list),
+            Some(role) => Err(MemberError::Forbidden(format!(
+                "{role:?} of list {list_id}, not {needed:?}"
+            ))),
+            None => Err(MemberError::ListNotFound(list_id)),
+        }
+    }
+
+    /// Fails when `member_id` is the last owner of the list.
+    async fn keep_an_owner(&self, list_id: i32, member_id: i32) -> Result<(), MemberError> {
+        let members = self.member_repository.members(list_id).await?;
+        let owners = Vec::from_iter(members.iter().filter(|m| m.role == Role::Owner));
+        if owners.len() == 1 && owners[0].user_id == member_id {
+            return Err(MemberError::Conflict(format!(
+                "user {member_id} is the last owner of list {list_id}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<MR, LR, WR> MemberServiceTrait for MemberService<MR, LR, WR>
+where
+    MR: MemberRepositoryTrait,
+    LR: ListRepositoryTrait,
+    WR: WorkspaceRepositoryTrait,
+{
+    async fn members(&self, user_id: i32, list_id: i32) -> Result<Vec<Member>, MemberError> {
+        self.check_role(user_id, list_id, Role::Viewer).await?;
+        let members = self.member_repository.members(list_id).await?;
+        Ok(members)
+    }
+
+    async fn invite(
+        &self,
+        user_id: i32,
+        list_id: i32,
+        payload: CreateInvitation,
+    ) -> Result<NewInvitation, MemberError> {
+        let list = self.check_role(user_id, list_id, Role::Owner).await?;
+        if list.inbox {
+            return Err(MemberError::Validation(
+                "the inbox can't be shared".to_string(),
+            ));
+        }
+        let token = generate_secret(INVITATION_PREFIX);
+        let invitation = self
+            .member_repository
+            .create_invitation(
+                list_id,
+                self.workspace,
+                payload.role,
+                user_id,
+                &hash_secret(&token),
+                Utc::now() + INVITATION_TTL,
+            )
+            .await?;
+        Ok(NewInvitation { invitation, token })
+    }
+
+    async fn accept(&self, user_id: i32, payload: AcceptInvitation) -> Result<Member, MemberError> {
+        let invitation = self
+            .member_repository
+            .accept_invitation(&hash_secret(payload.token.trim()), user_id)
+            .await?
+            .ok_or(MemberError::InvalidInvitation)?;
+        if let Some(workspace) = invitation.workspace_id {
+            self.workspace_repository
+                .add_member(workspace, user_id, WorkspaceRole::Member)
+                .await?;
+        }
+        let member = self
+            .member_repository
+            .add(invitation.list_id, user_id, invitation.role)
+            .await?;
+        Ok(member)
+    }
+
+    async fn update_role(
+        &self,
+        user_id: i32,
+        list_id: i32,
+        member_id: i32,
+        payload: UpdateMember,
+    ) -> Result<Member, MemberError> {
+        self.check_role(user_id, list_id, Role::Owner).await?;
+        if payload.role != Role::Owner {
+            self.keep_an_owner(list_id, member_id).await?;
+        }
+        let member = self
+            .member_repository
+            .update_role(list_id, member_id, payload.role)
+            .await?;
+        Ok(member)
+    }
+
+    async fn remove(&self, user_id: i32, list_id: i32, member_id: i32) -> Result<(), MemberError> {
+        let needed = if member_id == user_id {
+            Role::Viewer
+        } else {
+            Role::Owner
+        };
+        self.check_role(user_id, list_id, needed).await?;
+        self.keep_an_owner(list_id, member_id).await?;
+        self.member_repository.remove(list_id, member_id).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lists::model::CreateList;
+    use crate::lists::repository::memory::ListRepositoryForMemory;
+    use crate::members::repository::memory::MemberRepositoryForMemory;
+    use crate::workspaces::repository::memory::WorkspaceRepositoryForMemory;
+
+    const ALICE: i32 = 1;
+    const BOB: i32 = 2;
+    const CAROL: i32 = 3;
+
+    #[tokio::test]
+    async fn members_scenario() {
+        let workspace_repository = WorkspaceRepositoryForMemory::new();
+        let workspace = workspace_repository.personal(ALICE).await.unwrap().id;
+        let list_repository = ListRepositoryForMemory::new().in_workspace(workspace);
+        let member_repository = MemberRepositoryForMemory::new();
+        let service = MemberService::new(
+            member_repository.clone(),
+            list_repository.clone(),
+            workspace_repository.clone(),
+        )
+        .in_workspace(workspace);
+        let list = list_repository
+            .create(CreateList {
+                name: "work".to_string(),
+            })
+            .await
+            .unwrap();
+        let inbox = list_repository.inbox().await.unwrap();
+        let invite = |user_id, role| service.invite(user_id, list.id, CreateInvitation { role });
+        let accept = |user_id, token: &str| {
+            service.accept(
+                user_id,
+                AcceptInvitation {
+                    token: token.to_string(),
+                },
+            )
+        };
+
+        // nobody sees a list without members, let alone invites themselves to it
+        assert_eq!(
+            service.members(BOB, list.id).await,
+            Err(MemberError::ListNotFound(list.id))
+        );
+        assert_eq!(
+            invite(BOB, Role::Owner).await,
+            Err(MemberError::ListNotFound(list.id))
+        );
+        assert!(matches!(
+            service
+                .invite(ALICE, inbox.id, CreateInvitation::default())
+                .await,
+            Err(MemberError::Validation(_))
+        ));
+        member_repository
+            .add(list.id, ALICE, Role::Owner)
+            .await
+            .unwrap();
+        let invitation = invite(ALICE, Role::Editor).await.unwrap();
+        assert!(invitation.token.starts_with(INVITATION_PREFIX));
+        assert_eq!(
+            service.members(BOB, list.id).await,
+            Err(MemberError::ListNotFound(list.id))
+        );
+
+        // invitations are taken once, along with the workspace of the list
+        let bob = accept(BOB, &invitation.token).await.unwrap();
+        assert_eq!(bob.role, Role::Editor);
+        assert_eq!(
+            workspace_repository.role(workspace, BOB).await.unwrap(),
+            Some(WorkspaceRole::Member)
+        );
+        assert_eq!(
+            accept(CAROL, &invitation.token).await,
+            Err(MemberError::InvalidInvitation)
+        );
+        let members = service.members(BOB, list.id).await.unwrap();
+        assert_eq!(
+            members
+                .iter()
+                .map(|m| (m.user_id, m.role))
+                .collect::<Vec<_>>(),
+            vec![(ALICE, Role::Owner), (BOB, Role::Editor)]
+        );
+
+        // only owners invite and change roles
+        assert!(matches!(
+            invite(BOB, Role::Viewer).await,
+            Err(MemberError::Forbidden(_))
+        ));
+        let viewer = UpdateMember { role: Role::Viewer };
+        assert!(matches!(
+            service
+                .update_role(BOB, list.id, ALICE, viewer.clone())
+                .await,
+            Err(MemberError::Forbidden(_))
+        ));
+        assert!(matches!(
+            service
+                .update_role(ALICE, list.id, ALICE, viewer.clone())
+                .await,
+            Err(MemberError::Conflict(_))
+        ));
+        let bob = service
+            .update_role(ALICE, list.id, BOB, viewer)
+            .await
+            .unwrap();
+        assert_eq!(bob.role, Role::Viewer);
+        assert_eq!(
+            member_repository.role(list.id, BOB).await.unwrap(),
+            Some(Role::Viewer)
+        );
+
+        // members leave, the last owner can't
+        assert!(matches!(
+            service.remove(ALICE, list.id, ALICE).await,
+            Err(MemberError::Conflict(_))
+        ));
+        service.remove(BOB, list.id, BOB).await.unwrap();
+        assert_eq!(
+            service.remove(ALICE, list.id, BOB).await,
+            Err(MemberError::NotFound(BOB))
+        );
+    }
+}
@@ -60,7 +60,7 @@ pub fn remove_auth<T: AsRef<str>>(url: T) -> String {
 
 fn get_auth(url: &str) -> Option<(String, String)> {
     if let Some(pos) = url.find('@') {
-        let (schema, url) = split_schema(&url);
+        let (schema, url) = split_schema(url);
         let auth = url[..pos - schema.len() - 3].to_string();
         let mut auth = auth.split(':');
         let username = auth.next().unwrap_or_default().to_string();
@@ -72,7 +72,7 @@ fn get_auth(url: &str) -> Option<(String, String)> {
 
 pub async fn fetch(url: &str) -> crate::Result<String> {
     Ok(build_proxy_client()
-        .unwrap_or(reqwest::Client::new())
+        .unwrap_or_default()
         .get(url)
         .send()
         .await?
@@ -0,0 +1,58 @@
+use serde::Serialize;
+
+/// What a long operation is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// A push and pull round of a sync.
+    Round,
+    /// A page of local changes was pushed.
+    Push,
+    /// A page of remote changes was pulled.
+    Pull,
+    /// The text documents were exchanged.
+    Exchange,
+    /// A page of todos was exported.
+    Export,
+    /// A page of todos was imported.
+    Import,
+}
+
+/// How far a long operation got in `stage`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Step {
+    pub stage: Stage,
+    /// Items done so far in the stage, those of this step included.
+    pub done: u64,
+    /// `None` when it isn't known upfront, like the changes of a pull.
+    pub total: Option<u64>,
+}
+
+impl Step {
+    pub fn new(stage: Stage, done: usize, total: Option<usize>) -> Self {
+        Self {
+            stage,
+            done: done as u64,
+            total: total.map(|total| total as u64),
+        }
+    }
+}
+
+/// Where long operations, like syncs, imports and exports, report their steps. They check
+/// `is_cancelled` between steps, and stop with what they did so far kept once it is.
+pub trait ProgressSink: Send + Sync {
+    fn report(&self, step: Step);
+    fn is_cancelled(&self) -> bool;
+}
+
+/// Reports nowhere and is never cancelled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn report(&self, _step: Step) {}
+
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
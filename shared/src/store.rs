@@ -1,5 +1,6 @@
-use once_cell::sync::Lazy;
 use log;
+use once_cell::sync::Lazy;
+use rand_core::{OsRng, RngCore};
 use sled::Db;
 use std::{collections::HashMap, ops::DerefMut, sync::Mutex};
 
@@ -64,7 +65,7 @@ where
     let mut guard = STORE.lock().unwrap();
     match guard.deref_mut() {
         Store::DB(db) => Ok(db.get(k)?.map(|x| x.to_vec())),
-        Store::Map(m) => Ok(m.get(k.as_ref()).map(|v| v.clone())),
+        Store::Map(m) => Ok(m.get(k.as_ref()).cloned()),
     }
 }
 
@@ -83,3 +84,42 @@ where
     }
     Ok(())
 }
+
+/// The sled database behind the store, `None` when it couldn't be opened and values only live
+/// in memory.
+pub fn db() -> Option<Db> {
+    let guard = STORE.lock().unwrap();
+    match &*guard {
+        Store::DB(db) => Some(db.clone()),
+        Store::Map(_) => None,
+    }
+}
+
+/// 32 random bytes kept in `db` under `key`, made the first time they are asked for, so that
+/// each install has its own.
+pub fn secret(db: &Db, key: &str) -> crate::Result<Vec<u8>> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    // the secret from before wins
+    let secret = match db.compare_and_swap(key, None as Option<&[u8]>, Some(&bytes[..]))? {
+        Ok(()) => bytes.to_vec(),
+        Err(e) => e.current.map(|x| x.to_vec()).unwrap_or(bytes.to_vec()),
+    };
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_keep_the_secret_of_the_install() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let first = secret(&db, "secret").unwrap();
+        assert_eq!(first.len(), 32);
+        assert_eq!(secret(&db, "secret").unwrap(), first);
+
+        let other = sled::Config::new().temporary(true).open().unwrap();
+        assert_ne!(secret(&other, "secret").unwrap(), first);
+    }
+}
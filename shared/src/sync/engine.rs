@@ -0,0 +1,1620 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde_json::{Map, Value};
+use tokio::sync::Mutex;
+
+use super::error::SyncError;
+use super::model::{
+    diff, synced_fields, DocumentRequest, DocumentResult, DocumentSync, FieldChange, FieldChanges,
+    OrderResult, OrderSync, Pending, PendingChange, PushOp, PushRequest, PushResult, SyncReport,
+    SyncState, SyncStatus, SYNCED_FIELDS,
+};
+use super::outbox::Outbox;
+use super::repository::SyncStateRepositoryTrait;
+use super::transport::SyncTransportTrait;
+use crate::lists::model::CreateList;
+use crate::lists::repository::ListRepositoryTrait;
+use crate::progress::{ProgressSink, Stage, Step};
+use crate::todos::crdt::{new_replica, ReplicaId, TextDelta};
+use crate::todos::document::{write_order, TodoDocumentRepositoryTrait};
+use crate::todos::model::{CreateTodo, Todo, TodoChange, UpdateTodo, MAX_PAGE_SIZE};
+use crate::todos::repository::{RepositoryError, TodoRepositoryTrait};
+
+/// Push and pull rounds of a sync. A round pushes what the previous one merged or unblocked,
+/// like the subtasks of todos that were just created.
+const MAX_ROUNDS: usize = 4;
+
+/// Ops pushed per request, a page like those pulled.
+const PUSH_PAGE_SIZE: usize = MAX_PAGE_SIZE as usize;
+
+/// Stands in the orders of the lists for the todos the other side doesn't know of, no todo has
+/// this id.
+const UNKNOWN_TODO: i32 = 0;
+
+/// Syncs the local store `TR`, with the lists in `LR` and the text documents in `DR`, with a
+/// server reached through `T`.
+///
+/// The local changes are recorded into the outbox by a `TodoRepositoryWithOutbox` sharing the
+/// same store and `Outbox`. A sync pushes them, then pulls the changes made on the server since
+/// the last one. A field changed on both sides keeps the value the server got last, its change
+/// sequence orders them rather than the clocks of the clients: the local change, which is
+/// pushed after the remote one it meets, wins. The text of todos with a document is merged
+/// instead: the ops the server lacks are exchanged for those it has, so that concurrent edits
+/// all make it. The lists of both sides are matched before each round, see `sync_lists`, and
+/// the todos are placed in the list matching theirs. The series are matched through the first
+/// occurrence of them pushed or pulled, the others join the series matching theirs. The orders
+/// of the lists are exchanged along with the documents, the ids of their todos mapped from one
+/// side to the other, and the todos moved into the order they read.
+///
+/// The state is saved after every page pushed or pulled, so a sync cancelled through its
+/// `ProgressSink`, which is checked before each of them, picks up from there next time.
+#[derive(Debug, Clone)]
+pub struct SyncEngine<TR, LR, SR, DR, T> {
+    todo_repository: TR,
+    list_repository: LR,
+    document_repository: DR,
+    outbox: Outbox<SR>,
+    transport: T,
+    /// Of the text edits made before the todo had a document.
+    replica: ReplicaId,
+    syncing: Arc<Mutex<()>>,
+}
+
+impl<TR, LR, SR, DR, T> SyncEngine<TR, LR, SR, DR, T>
+where
+    TR: TodoRepositoryTrait,
+    LR: ListRepositoryTrait,
+    SR: SyncStateRepositoryTrait,
+    DR: TodoDocumentRepositoryTrait,
+    T: SyncTransportTrait,
+{
+    pub fn new(
+        todo_repository: TR,
+        list_repository: LR,
+        document_repository: DR,
+        outbox: Outbox<SR>,
+        transport: T,
+    ) -> Self {
+        Self {
+            todo_repository,
+            list_repository,
+            document_repository,
+            outbox,
+            transport,
+            replica: new_replica(),
+            syncing: Arc::default(),
+        }
+    }
+
+    pub async fn status(&self) -> Result<SyncStatus, SyncError> {
+        let state = self.outbox.state().await?;
+        Ok(SyncStatus {
+            pending: state.outbox.len(),
+            cursor: state.cursor,
+            syncing: self.syncing.try_lock().is_err(),
+            last_synced_at: state.last_synced_at,
+            last_error: state.last_error,
+        })
+    }
+
+    /// Waits for the sync already running, if any, before starting this one. Each round and
+    /// page is reported to `progress`, a cancelled sync leaves the last error as it was.
+    pub async fn sync(&self, progress: &dyn ProgressSink) -> Result<SyncReport, SyncError> {
+        let _syncing = self.syncing.lock().await;
+        let mut report = SyncReport::default();
+        let res = self.rounds(&mut report, progress).await;
+        if res == Err(SyncError::Cancelled) {
+            return Err(SyncError::Cancelled);
+        }
+        let error = res.as_ref().err().map(ToString::to_string);
+        self.outbox
+            .update(|state| {
+                if error.is_none() {
+                    state.last_synced_at = Some(Utc::now());
+                }
+                state.last_error = error;
+            })
+            .await?;
+        res.map(|()| report)
+    }
+
+    async fn rounds(
+        &self,
+        report: &mut SyncReport,
+        progress: &dyn ProgressSink,
+    ) -> Result<(), SyncError> {
+        for round in 1..=MAX_ROUNDS {
+            check(progress)?;
+            progress.report(Step::new(Stage::Round, round, Some(MAX_ROUNDS)));
+            self.sync_lists().await?;
+            let pushed = self.push(report, progress).await?;
+            self.pull(report, progress).await?;
+            let exchanged = self.exchange(report, progress).await?;
+            if pushed == 0 && exchanged == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Pushes the outbox but what has to wait, page by page, returns how many ops were sent.
+    async fn push(
+        &self,
+        report: &mut SyncReport,
+        progress: &dyn ProgressSink,
+    ) -> Result<usize, SyncError> {
+        let state = self.merge_text_edits().await?;
+        let mut sent = Vec::new();
+        // local series started by an occurrence pushed
+        let mut starting = BTreeSet::new();
+        for pending in &state.outbox {
+            let op = match &pending.change {
+                PendingChange::Created => {
+                    let Some(todo) = self.find(pending.id).await? else {
+                        continue;
+                    };
+                    let Some(parent_id) = remote_parent(&state, todo.parent_id) else {
+                        continue;
+                    };
+                    // lists created during the sync wait for the next one
+                    let Some(&list_id) = state.remote_lists.get(&todo.list_id) else {
+                        continue;
+                    };
+                    let series_id = match todo.series_id {
+                        Some(series_id) => match state.remote_series.get(&series_id) {
+                            Some(&remote_id) => Some(remote_id),
+                            // an ended series isn't started again
+                            None if todo.rrule.is_none() => None,
+                            // the other occurrences wait for the series to be started
+                            None if starting.insert(series_id) => None,
+                            None => continue,
+                        },
+                        None => None,
+                    };
+                    // the server makes the same document from the same text
+                    let text = match self.document_repository.find(pending.id).await? {
+                        Some(document) => document.genesis_text(),
+                        None => todo.text,
+                    };
+                    PushOp::Create {
+                        todo: CreateTodo {
+                            text,
+                            list_id: Some(list_id),
+                            parent_id,
+                            starts_at: todo.starts_at,
+                            due_at: todo.due_at,
+                            priority: todo.priority,
+                            rrule: todo.rrule,
+                        },
+                        series_id,
+                    }
+                }
+                PendingChange::Updated { fields } => {
+                    let (Some(&id), Some(shadow)) = (
+                        state.remote_ids.get(&pending.id),
+                        state.shadows.get(&pending.id),
+                    ) else {
+                        continue;
+                    };
+                    let Some(patch) = remote_patch(&state, fields)? else {
+                        continue;
+                    };
+                    PushOp::Update {
+                        id,
+                        version: shadow.version,
+                        patch,
+                    }
+                }
+                PendingChange::Deleted => match state.remote_ids.get(&pending.id) {
+                    Some(&id) => PushOp::Delete { id },
+                    None => continue,
+                },
+            };
+            sent.push((pending.clone(), op));
+        }
+        let total = sent.len();
+        let mut done = 0;
+        for page in sent.chunks(PUSH_PAGE_SIZE) {
+            check(progress)?;
+            self.push_page(page.to_vec(), report).await?;
+            done += page.len();
+            progress.report(Step::new(Stage::Push, done, Some(total)));
+        }
+        Ok(total)
+    }
+
+    /// Pushes the ops of `sent`, then records what the server made of them.
+    async fn push_page(
+        &self,
+        sent: Vec<(Pending, PushOp)>,
+        report: &mut SyncReport,
+    ) -> Result<(), SyncError> {
+        let res = self
+            .transport
+            .push(&PushRequest {
+                ops: sent.iter().map(|(_, op)| op.clone()).collect(),
+            })
+            .await?;
+        if res.results.len() != sent.len() {
+            return Err(SyncError::Remote(format!(
+                "expected {} results, got {}",
+                sent.len(),
+                res.results.len()
+            )));
+        }
+        // what the todos applied are now, to push the fields a creation doesn't carry and match
+        // the series they started
+        let mut applied = HashMap::new();
+        for ((pending, _), result) in sent.iter().zip(&res.results) {
+            if let PushResult::Applied { .. } = result {
+                applied.insert(pending.id, self.find(pending.id).await?);
+            }
+        }
+
+        self.outbox
+            .update(|state| {
+                for ((pending, _), result) in sent.into_iter().zip(res.results) {
+                    let id = pending.id;
+                    match (pending.change, result) {
+                        (PendingChange::Created, PushResult::Applied { todo }) => {
+                            report.pushed += 1;
+                            state.map(id, todo.id);
+                            state
+                                .outbox
+                                .retain(|p| !(p.id == id && p.change == PendingChange::Created));
+                            match applied.remove(&id).flatten() {
+                                Some(local) => {
+                                    match_series(state, &local, &todo);
+                                    // the server puts it into the order of the list
+                                    state.stale_orders.insert(local.list_id);
+                                    let fields = diff(&localized(state, &todo), &local);
+                                    if !fields.is_empty() {
+                                        state.record(id, PendingChange::Updated { fields });
+                                    }
+                                }
+                                // deleted while it was pushed
+                                None => state.record(id, PendingChange::Deleted),
+                            }
+                            state.shadows.insert(id, todo);
+                        }
+                        (PendingChange::Updated { fields: sent }, PushResult::Applied { todo }) => {
+                            report.pushed += 1;
+                            // fields changed again while they were pushed wait for the next round
+                            unpend(state, id, |name, change| sent.get(name) == Some(change));
+                            if let Some(Some(local)) = applied.remove(&id) {
+                                match_series(state, &local, &todo);
+                                if sent.contains_key("list_id") || sent.contains_key("position") {
+                                    state.stale_orders.insert(local.list_id);
+                                }
+                            }
+                            state.shadows.insert(id, todo);
+                        }
+                        (PendingChange::Deleted, PushResult::Deleted) => {
+                            report.pushed += 1;
+                            state.forget(id);
+                        }
+                        (PendingChange::Deleted, PushResult::Missing) => state.forget(id),
+                        // the pull brings the deletion, or the changes to merge with
+                        (_, PushResult::Missing | PushResult::Conflict { .. }) => {}
+                        (_, PushResult::Retry { message }) => {
+                            log::warn!("Todo {id} will be pushed again: {message}")
+                        }
+                        (_, PushResult::Rejected { message, .. }) => {
+                            log::warn!("Todo {id} was rejected: {message}");
+                            report.rejected += 1;
+                            state.outbox.retain(|p| p.id != id);
+                        }
+                        (change, result) => {
+                            log::warn!("Unexpected result of {change:?} of todo {id}: {result:?}")
+                        }
+                    }
+                }
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Pulls and applies the pages of changes made on the server since the cursor.
+    async fn pull(
+        &self,
+        report: &mut SyncReport,
+        progress: &dyn ProgressSink,
+    ) -> Result<(), SyncError> {
+        let mut done = 0;
+        loop {
+            check(progress)?;
+            let cursor = self.outbox.state().await?.cursor;
+            let page = self.transport.pull(cursor, MAX_PAGE_SIZE).await?;
+            done += page.changes.len();
+            let mut upserted = Vec::new();
+            let mut deleted = Vec::new();
+            for change in page.changes {
+                match change {
+                    TodoChange::Upserted { todo, .. } => upserted.push(todo),
+                    TodoChange::Deleted { id, .. } => deleted.push(id),
+                }
+            }
+            self.match_lists(&upserted).await?;
+            // parents first, so subtasks are created under them
+            while !upserted.is_empty() {
+                let waiting: BTreeSet<i32> = upserted.iter().map(|todo| todo.id).collect();
+                let (mut ready, mut later): (Vec<_>, Vec<_>) =
+                    upserted.into_iter().partition(|todo| {
+                        todo.parent_id
+                            .is_none_or(|parent_id| !waiting.contains(&parent_id))
+                    });
+                if ready.is_empty() {
+                    std::mem::swap(&mut ready, &mut later);
+                }
+                for todo in ready {
+                    self.apply_upserted(todo, report).await?;
+                }
+                upserted = later;
+            }
+            for id in deleted {
+                self.apply_deleted(id, report).await?;
+            }
+            self.outbox
+                .update(|state| state.cursor = page.cursor)
+                .await?;
+            progress.report(Step::new(Stage::Pull, done, None));
+            if !page.has_more {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn apply_upserted(&self, remote: Todo, report: &mut SyncReport) -> Result<(), SyncError> {
+        let state = self.outbox.state().await?;
+        let Some(&id) = state.local_ids.get(&remote.id) else {
+            let payload = CreateTodo {
+                text: remote.text.clone(),
+                list_id: state.local_lists.get(&remote.list_id).copied(),
+                parent_id: remote
+                    .parent_id
+                    .and_then(|parent_id| state.local_ids.get(&parent_id).copied()),
+                starts_at: remote.starts_at,
+                due_at: remote.due_at,
+                priority: remote.priority,
+                rrule: remote.rrule.clone(),
+            };
+            // occurrences of a series known here join it, the others start it
+            let todo = match remote
+                .series_id
+                .and_then(|series_id| state.local_series.get(&series_id))
+            {
+                Some(&series_id) => {
+                    self.todo_repository
+                        .add_occurrence(series_id, payload)
+                        .await?
+                }
+                None => self.todo_repository.create(payload).await?,
+            };
+            if remote.completed {
+                self.todo_repository
+                    .update(
+                        todo.id,
+                        UpdateTodo {
+                            completed: Some(true),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+            }
+            report.pulled += 1;
+            return Ok(self
+                .outbox
+                .update(|state| {
+                    state.map(todo.id, remote.id);
+                    match_series(state, &todo, &remote);
+                    state.stale_documents.insert(todo.id);
+                    state.stale_orders.insert(todo.list_id);
+                    state.shadows.insert(todo.id, remote);
+                })
+                .await?);
+        };
+        let local = match state.pending(id) {
+            // a local deletion wins, it is pushed next
+            Some(PendingChange::Deleted) => None,
+            _ => self.find(id).await?,
+        };
+        let Some(local) = local else {
+            return Ok(self
+                .outbox
+                .update(|state| {
+                    state.shadows.insert(id, remote);
+                })
+                .await?);
+        };
+
+        let pending = match state.pending(id) {
+            Some(PendingChange::Updated { fields }) => fields.clone(),
+            _ => FieldChanges::new(),
+        };
+        let base = state
+            .shadows
+            .get(&id)
+            .map(synced_fields)
+            .unwrap_or_default();
+        let text_changed = base.get("text") != Some(&remote.text.clone().into());
+        let has_document = self.document_repository.find(id).await?.is_some();
+        let theirs = synced_fields(&localized(&state, &remote));
+        let remote_fields = synced_fields(&remote);
+        let mine = synced_fields(&local);
+        let mut patch = Map::new();
+        let mut rrule = None;
+        for name in SYNCED_FIELDS {
+            if name == "text" && has_document {
+                continue;
+            }
+            let their = theirs.get(name).cloned().unwrap_or_default();
+            match pending.get(name) {
+                // changed here only, it is pushed next
+                Some(_) if base.get(name) == remote_fields.get(name) => continue,
+                // the server has the remote change already, the local one comes after it
+                Some(_) => {
+                    report.conflicts += 1;
+                    continue;
+                }
+                None => {}
+            }
+            match mine.get(name) == Some(&their) {
+                true => {}
+                false if name == "rrule" => rrule = Some(their),
+                false => {
+                    patch.insert(name.to_string(), their);
+                }
+            }
+        }
+        if !patch.is_empty() || rrule.is_some() {
+            report.pulled += 1;
+        }
+        if !patch.is_empty() {
+            let patch: UpdateTodo = serde_json::from_value(Value::Object(patch))
+                .map_err(|e| SyncError::Unexpected(e.to_string()))?;
+            self.todo_repository.update(id, patch).await?;
+        }
+        let local = match rrule {
+            Some(rrule) => {
+                self.write_rrule(&local, rrule.as_str().map(ToString::to_string))
+                    .await?
+            }
+            None => local,
+        };
+        let list_id = localized(&state, &remote).list_id;
+        Ok(self
+            .outbox
+            .update(|state| {
+                if text_changed {
+                    state.stale_documents.insert(id);
+                }
+                // moved on the server, whose order has the move
+                if state
+                    .shadows
+                    .get(&id)
+                    .is_none_or(|shadow| shadow.position != remote.position)
+                {
+                    state.stale_orders.insert(list_id);
+                }
+                match_series(state, &local, &remote);
+                state.shadows.insert(id, remote);
+            })
+            .await?)
+    }
+
+    async fn apply_deleted(
+        &self,
+        remote_id: i32,
+        report: &mut SyncReport,
+    ) -> Result<(), SyncError> {
+        let Some(&id) = self.outbox.state().await?.local_ids.get(&remote_id) else {
+            return Ok(());
+        };
+        // the subtasks go along, with their local changes
+        let ids = match self.todo_repository.subtree(id).await {
+            Ok(todos) => todos.into_iter().map(|todo| todo.id).collect(),
+            Err(e) if is_not_found(&e) => vec![id],
+            Err(e) => return Err(e.into()),
+        };
+        match self.todo_repository.delete(id, None).await {
+            Ok(()) => report.pulled += 1,
+            Err(e) if is_not_found(&e) => {}
+            Err(e) => return Err(e.into()),
+        }
+        self.document_repository.delete(&ids).await?;
+        Ok(self
+            .outbox
+            .update(|state| {
+                for id in ids {
+                    state.forget(id);
+                }
+            })
+            .await?)
+    }
+
+    /// Matches the lists of both sides, by name the first time, and creates on each side those
+    /// the other one lacks. A list deleted on one side since the last sync is deleted on the
+    /// other one, where its todos left go to the inbox. Names and archiving stay with each side.
+    async fn sync_lists(&self) -> Result<(), SyncError> {
+        let state = self.outbox.state().await?;
+        let remote = self.transport.lists().await?;
+        let local = self.list_repository.all(true).await?;
+        let inbox = self.list_repository.inbox().await?;
+
+        let mut forgotten = Vec::new();
+        for (&id, &remote_id) in &state.remote_lists {
+            let here = local.iter().any(|list| list.id == id);
+            let there = remote.iter().any(|list| list.id == remote_id);
+            match (here, there) {
+                (true, true) => continue,
+                // an inbox is never deleted, another one is matched below
+                _ if id == inbox.id => {}
+                (true, false) => {
+                    self.todo_repository.move_list(id, inbox.id).await?;
+                    self.list_repository.delete(id).await?;
+                    self.document_repository.delete_orders(&[id]).await?;
+                }
+                (false, true) => self.transport.delete_list(remote_id).await?,
+                (false, false) => {}
+            }
+            forgotten.push(id);
+        }
+        let known = |id: i32| state.remote_lists.contains_key(&id) && !forgotten.contains(&id);
+        let mut unmatched = Vec::from_iter(
+            remote
+                .iter()
+                .filter(|list| !state.local_lists.get(&list.id).is_some_and(|&id| known(id))),
+        );
+
+        let mut matched = Vec::new();
+        for list in local.iter().filter(|list| !known(list.id)) {
+            let remote_id = match unmatched.iter().position(|remote| {
+                remote.inbox == list.inbox && (list.inbox || remote.name == list.name)
+            }) {
+                Some(index) => unmatched.remove(index).id,
+                // the server makes its inbox along the first todo in it
+                None if list.inbox => continue,
+                None => {
+                    let payload = CreateList {
+                        name: list.name.clone(),
+                    };
+                    self.transport.create_list(&payload).await?.id
+                }
+            };
+            matched.push((list.id, remote_id));
+        }
+        for list in unmatched.into_iter().filter(|list| !list.inbox) {
+            let payload = CreateList {
+                name: list.name.clone(),
+            };
+            matched.push((self.list_repository.create(payload).await?.id, list.id));
+        }
+
+        Ok(self
+            .outbox
+            .update(|state| {
+                for id in forgotten {
+                    state.forget_list(id);
+                }
+                for (id, remote_id) in matched {
+                    state.map_list(id, remote_id);
+                }
+            })
+            .await?)
+    }
+
+    /// Matches the lists again when a todo of `upserted` is in a list made since, so that every
+    /// one of them has a local list.
+    async fn match_lists(&self, upserted: &[Todo]) -> Result<(), SyncError> {
+        let unknown = |state: &SyncState| {
+            upserted
+                .iter()
+                .find(|todo| !state.local_lists.contains_key(&todo.list_id))
+                .map(|todo| (todo.id, todo.list_id))
+        };
+        if unknown(&self.outbox.state().await?).is_none() {
+            return Ok(());
+        }
+        self.sync_lists().await?;
+        match unknown(&self.outbox.state().await?) {
+            Some((id, list_id)) => Err(SyncError::Remote(format!(
+                "todo {id} is in list {list_id}, which isn't listed"
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Moves the pending text edits of todos with a document, which are in it already, to the
+    /// documents to exchange.
+    async fn merge_text_edits(&self) -> Result<SyncState, SyncError> {
+        let state = self.outbox.state().await?;
+        let mut merged = Vec::new();
+        for pending in &state.outbox {
+            if let PendingChange::Updated { fields } = &pending.change {
+                if fields.contains_key("text")
+                    && state.remote_ids.contains_key(&pending.id)
+                    && self.document_repository.find(pending.id).await?.is_some()
+                {
+                    merged.push(pending.id);
+                }
+            }
+        }
+        if merged.is_empty() {
+            return Ok(state);
+        }
+        Ok(self
+            .outbox
+            .update(|state| {
+                for id in merged {
+                    unpend(state, id, |name, _| name == "text");
+                    state.stale_documents.insert(id);
+                }
+                state.clone()
+            })
+            .await?)
+    }
+
+    /// Exchanges the stale documents and orders with the server, returns how many were sent.
+    async fn exchange(
+        &self,
+        report: &mut SyncReport,
+        progress: &dyn ProgressSink,
+    ) -> Result<usize, SyncError> {
+        let state = self.outbox.state().await?;
+        let mut sent = Vec::new();
+        for &id in &state.stale_documents {
+            // created todos wait for their creation
+            let Some(&remote_id) = state.remote_ids.get(&id) else {
+                continue;
+            };
+            let document = self.document_repository.find(id).await?;
+            let acked = state.documents.get(&id).cloned().unwrap_or_default();
+            let sync = match &document {
+                Some(document) => DocumentSync {
+                    id: remote_id,
+                    version: document.version(),
+                    delta: document.delta(&acked),
+                },
+                None => DocumentSync {
+                    id: remote_id,
+                    version: Default::default(),
+                    delta: TextDelta::default(),
+                },
+            };
+            sent.push((id, document.is_some(), sync));
+        }
+        let orders = self.stale_orders(&state).await?;
+        if sent.is_empty() && orders.is_empty() {
+            return Ok(0);
+        }
+
+        check(progress)?;
+        let res = self
+            .transport
+            .exchange(&DocumentRequest {
+                documents: sent.iter().map(|(_, _, sync)| sync.clone()).collect(),
+                orders: orders.iter().map(|(_, sync)| sync.clone()).collect(),
+            })
+            .await?;
+        if res.results.len() != sent.len() || res.orders.len() != orders.len() {
+            return Err(SyncError::Remote(format!(
+                "expected {} results and {} orders, got {} and {}",
+                sent.len(),
+                orders.len(),
+                res.results.len(),
+                res.orders.len()
+            )));
+        }
+        // acknowledged versions by local id, and whether the todo has ops left to send
+        let mut done = Vec::new();
+        for ((id, had_document, sent), result) in sent.iter().zip(res.results) {
+            let id = *id;
+            match result {
+                DocumentResult::Merged { document } => {
+                    if !sent.delta.is_empty() {
+                        report.pushed += 1;
+                    }
+                    let mut merged = self.document_repository.merge(id, &document.delta).await?;
+                    // an edit made before the todo had a document is made again on it
+                    let edited = match state.pending(id) {
+                        Some(PendingChange::Updated { fields })
+                            if !had_document && fields.contains_key("text") =>
+                        {
+                            self.find(id).await?
+                        }
+                        _ => None,
+                    };
+                    if let Some(todo) = &edited {
+                        let mut edit = merged.clone();
+                        edit.set_text(self.replica, &todo.text);
+                        merged = self
+                            .document_repository
+                            .merge(id, &edit.delta(&merged.version()))
+                            .await?;
+                    }
+                    self.write_text(id, merged.text(), report).await?;
+                    done.push((id, Some(document.version), edited.is_some()));
+                }
+                DocumentResult::Rejected {
+                    message, document, ..
+                } => {
+                    log::warn!("Text of todo {id} was rejected: {message}");
+                    report.rejected += 1;
+                    // what the server has is all there is
+                    self.document_repository.delete(&[id]).await?;
+                    let merged = self.document_repository.merge(id, &document.delta).await?;
+                    self.write_text(id, merged.text(), report).await?;
+                    done.push((id, Some(document.version), false));
+                }
+                // the pull brings the deletion
+                DocumentResult::Missing => done.push((id, None, false)),
+                DocumentResult::Retry { message } => {
+                    log::warn!("Text of todo {id} will be sent again: {message}")
+                }
+            }
+        }
+
+        // acknowledged versions of the orders by local list id
+        let mut ordered = Vec::new();
+        for ((list_id, sent), result) in orders.iter().zip(res.orders) {
+            let list_id = *list_id;
+            match result {
+                OrderResult::Merged { order } => {
+                    if !sent.delta.is_empty() {
+                        report.pushed += 1;
+                    }
+                    self.merge_order(&state, list_id, &order, report).await?;
+                    ordered.push((list_id, order.version));
+                }
+                OrderResult::Rejected { message, order, .. } => {
+                    log::warn!("Order of list {list_id} was rejected: {message}");
+                    report.rejected += 1;
+                    self.document_repository.delete_orders(&[list_id]).await?;
+                    self.merge_order(&state, list_id, &order, report).await?;
+                    ordered.push((list_id, order.version));
+                }
+                // nothing is sent again, the lists are matched again next round
+                OrderResult::Missing => ordered.push((list_id, sent.version.clone())),
+                OrderResult::Retry { message } => {
+                    log::warn!("Order of list {list_id} will be sent again: {message}")
+                }
+            }
+        }
+
+        let count = sent.len() + orders.len();
+        self.outbox
+            .update(|state| {
+                for (id, acked, edited) in done {
+                    if let Some(version) = acked {
+                        state.documents.insert(id, version);
+                    }
+                    if edited {
+                        unpend(state, id, |name, _| name == "text");
+                    } else {
+                        state.stale_documents.remove(&id);
+                    }
+                }
+                for (list_id, version) in ordered {
+                    state.orders.insert(list_id, version);
+                    state.stale_orders.remove(&list_id);
+                }
+            })
+            .await?;
+        progress.report(Step::new(Stage::Exchange, count, Some(count)));
+        Ok(count)
+    }
+
+    /// Changes the rule of the series of `todo`, or starts one when it has none, as the server
+    /// does for a change of `rrule`. Returns the todo after it.
+    async fn write_rrule(&self, todo: &Todo, rrule: Option<String>) -> Result<Todo, SyncError> {
+        match (todo.series_id, rrule) {
+            (Some(series_id), rrule) => {
+                let payload = UpdateTodo {
+                    rrule: Some(rrule),
+                    ..Default::default()
+                };
+                self.todo_repository
+                    .update_series(series_id, &payload)
+                    .await?;
+            }
+            (None, Some(rrule)) => {
+                self.todo_repository.start_series(todo.id, rrule).await?;
+            }
+            (None, None) => {}
+        }
+        Ok(self.todo_repository.find(todo.id).await?)
+    }
+
+    /// The orders to send of the lists the server has: those changed here since it acknowledged
+    /// them, and those it changed. An order holding todos still to be created waits for them.
+    async fn stale_orders(&self, state: &SyncState) -> Result<Vec<(i32, OrderSync)>, SyncError> {
+        let mut sent = Vec::new();
+        for (&list_id, &remote_id) in &state.remote_lists {
+            let order = self
+                .document_repository
+                .find_order(list_id)
+                .await?
+                .unwrap_or_default();
+            let acked = state.orders.get(&list_id).cloned().unwrap_or_default();
+            if order.version() == acked && !state.stale_orders.contains(&list_id) {
+                continue;
+            }
+            let mut waiting = false;
+            let delta = order
+                .delta(&acked)
+                .map(|id| match state.remote_ids.get(id) {
+                    Some(&remote_id) => remote_id,
+                    None => {
+                        waiting |= state.pending(*id) == Some(&PendingChange::Created);
+                        UNKNOWN_TODO
+                    }
+                });
+            if !waiting {
+                let sync = OrderSync {
+                    list_id: remote_id,
+                    version: order.version(),
+                    delta,
+                };
+                sent.push((list_id, sync));
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Merges `order` of the server into the order of local list `list_id`, then moves the
+    /// todos of the list into the order it reads.
+    async fn merge_order(
+        &self,
+        state: &SyncState,
+        list_id: i32,
+        order: &OrderSync,
+        report: &mut SyncReport,
+    ) -> Result<(), SyncError> {
+        // the todos made since the pull are pulled next round, and moved again then
+        let delta = order
+            .delta
+            .map(|id| state.local_ids.get(id).copied().unwrap_or(UNKNOWN_TODO));
+        let merged = self
+            .document_repository
+            .merge_order(list_id, &delta)
+            .await?;
+        if write_order(&self.todo_repository, list_id, &merged).await? > 0 {
+            report.pulled += 1;
+        }
+        Ok(())
+    }
+
+    /// Writes the text a document reads to the local store, unless it is there already.
+    async fn write_text(
+        &self,
+        id: i32,
+        text: String,
+        report: &mut SyncReport,
+    ) -> Result<(), SyncError> {
+        match self.find(id).await? {
+            Some(todo) if todo.text != text => {
+                let patch = UpdateTodo {
+                    text: Some(text),
+                    ..Default::default()
+                };
+                self.todo_repository.update(id, patch).await?;
+                report.pulled += 1;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// `None` when the todo isn't in the local store anymore.
+    async fn find(&self, id: i32) -> Result<Option<Todo>, SyncError> {
+        match self.todo_repository.find(id).await {
+            Ok(todo) => Ok(Some(todo)),
+            Err(e) if is_not_found(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn check(progress: &dyn ProgressSink) -> Result<(), SyncError> {
+    match progress.is_cancelled() {
+        true => Err(SyncError::Cancelled),
+        false => Ok(()),
+    }
+}
+
+fn is_not_found(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref(), Some(RepositoryError::NotFound(_)))
+}
+
+/// The remote id of local parent `parent_id`, `None` while the parent waits to be created.
+/// Parents the server will never know of are dropped.
+fn remote_parent(state: &SyncState, parent_id: Option<i32>) -> Option<Option<i32>> {
+    let Some(parent_id) = parent_id else {
+        return Some(None);
+    };
+    match state.remote_ids.get(&parent_id) {
+        Some(&remote_id) => Some(Some(remote_id)),
+        None if state.pending(parent_id) == Some(&PendingChange::Created) => None,
+        None => Some(None),
+    }
+}
+
+/// The update of the server for the local changes `fields`, `None` while it has to wait.
+fn remote_patch(state: &SyncState, fields: &FieldChanges) -> Result<Option<UpdateTodo>, SyncError> {
+    let mut patch = Map::new();
+    for (name, change) in fields {
+        let value = match name.as_str() {
+            "parent_id" => {
+                let parent_id = change.value.as_i64().map(|id| id as i32);
+                match remote_parent(state, parent_id) {
+                    Some(parent_id) => parent_id.into(),
+                    None => return Ok(None),
+                }
+            }
+            "list_id" => {
+                let list_id = change.value.as_i64().map(|id| id as i32);
+                match list_id.and_then(|list_id| state.remote_lists.get(&list_id)) {
+                    Some(&list_id) => list_id.into(),
+                    None => return Ok(None),
+                }
+            }
+            _ => change.value.clone(),
+        };
+        patch.insert(name.clone(), value);
+    }
+    serde_json::from_value(Value::Object(patch))
+        .map(Some)
+        .map_err(|e| SyncError::Unexpected(e.to_string()))
+}
+
+/// `todo` of the server with its parent and list as local ids. The list is matched before the
+/// todo is pulled, see `SyncEngine::match_lists`.
+fn localized(state: &SyncState, todo: &Todo) -> Todo {
+    Todo {
+        list_id: state
+            .local_lists
+            .get(&todo.list_id)
+            .copied()
+            .unwrap_or(todo.list_id),
+        parent_id: todo
+            .parent_id
+            .and_then(|parent_id| state.local_ids.get(&parent_id).copied()),
+        ..todo.clone()
+    }
+}
+
+/// Matches the series of `local` with that of `remote`, its todo on the server, unless either
+/// one is matched already.
+fn match_series(state: &mut SyncState, local: &Todo, remote: &Todo) {
+    if let (Some(id), Some(remote_id)) = (local.series_id, remote.series_id) {
+        if !state.remote_series.contains_key(&id) && !state.local_series.contains_key(&remote_id) {
+            state.map_series(id, remote_id);
+        }
+    }
+}
+
+/// Removes from the pending update of todo `id` the fields matching `done`, and the update
+/// itself once it has no field left.
+fn unpend(state: &mut SyncState, id: i32, done: impl Fn(&String, &FieldChange) -> bool) {
+    state
+        .outbox
+        .retain_mut(|pending| match &mut pending.change {
+            PendingChange::Updated { fields } if pending.id == id => {
+                fields.retain(|name, change| !done(name, change));
+                !fields.is_empty()
+            }
+            _ => true,
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lists::model::{List, ListCascade};
+    use crate::lists::repository::memory::ListRepositoryForMemory;
+    use crate::lists::service::{ListService, ListServiceTrait};
+    use crate::members::repository::memory::MemberRepositoryForMemory;
+    use crate::progress::NoProgress;
+    use crate::sync::outbox::TodoRepositoryWithOutbox;
+    use crate::sync::repository::memory::SyncStateRepositoryForMemory;
+    use crate::sync::service::SyncService;
+    use crate::sync::transport::memory::SyncTransportForService;
+    use crate::todos::document::memory::TodoDocumentRepositoryForMemory;
+    use crate::todos::document::TodoRepositoryWithDocuments;
+    use crate::todos::model::{Priority, TodoQuery, TodoSort};
+    use crate::todos::repository::memory::TodoRepositoryForMemory;
+    use crate::todos::service::{TodoService, TodoServiceTrait};
+    use chrono::Duration;
+
+    type Documents = TodoDocumentRepositoryForMemory;
+    type ServerTodos = TodoRepositoryWithDocuments<TodoRepositoryForMemory, Documents>;
+    type Server = SyncTransportForService<
+        ServerTodos,
+        MemberRepositoryForMemory,
+        Documents,
+        ListRepositoryForMemory,
+    >;
+    type LocalTodos = TodoRepositoryWithDocuments<
+        TodoRepositoryWithOutbox<TodoRepositoryForMemory, SyncStateRepositoryForMemory>,
+        Documents,
+    >;
+
+    struct Client {
+        todos: LocalTodos,
+        lists: ListService<ListRepositoryForMemory, LocalTodos, MemberRepositoryForMemory>,
+        engine: SyncEngine<
+            TodoRepositoryForMemory,
+            ListRepositoryForMemory,
+            SyncStateRepositoryForMemory,
+            Documents,
+            Server,
+        >,
+    }
+
+    fn server() -> Server {
+        let documents = Documents::new();
+        let todos: ServerTodos =
+            TodoRepositoryWithDocuments::new(TodoRepositoryForMemory::new(), documents.clone());
+        let members = MemberRepositoryForMemory::new();
+        SyncTransportForService::new(
+            SyncService::new(todos.clone(), members.clone(), documents),
+            ListService::new(ListRepositoryForMemory::new(), todos, members),
+        )
+    }
+
+    fn client(server: &Server) -> Client {
+        let store = TodoRepositoryForMemory::new();
+        let lists = ListRepositoryForMemory::new();
+        let documents = Documents::new();
+        let outbox = Outbox::new(SyncStateRepositoryForMemory::new());
+        let todos = TodoRepositoryWithDocuments::new(
+            TodoRepositoryWithOutbox::new(store.clone(), outbox.clone()),
+            documents.clone(),
+        );
+        Client {
+            lists: ListService::new(
+                lists.clone(),
+                todos.clone(),
+                MemberRepositoryForMemory::new(),
+            ),
+            todos,
+            engine: SyncEngine::new(store, lists, documents, outbox, server.clone()),
+        }
+    }
+
+    impl Client {
+        /// Text, completion and parent text of every todo, sorted.
+        async fn todos(&self) -> Vec<(String, bool, Option<String>)> {
+            let todos = self.todos.all(&TodoQuery::default()).await.unwrap();
+            let text = |id| {
+                todos
+                    .iter()
+                    .find(|todo: &&Todo| todo.id == id)
+                    .map(|todo| todo.text.clone())
+            };
+            let mut todos = Vec::from_iter(todos.iter().map(|todo| {
+                (
+                    todo.text.clone(),
+                    todo.completed,
+                    todo.parent_id.and_then(text),
+                )
+            }));
+            todos.sort();
+            todos
+        }
+
+        async fn find(&self, text: &str) -> Todo {
+            let todos = self.todos.all(&TodoQuery::default()).await.unwrap();
+            todos.into_iter().find(|todo| todo.text == text).unwrap()
+        }
+
+        async fn edit(&self, text: &str, patch: UpdateTodo) {
+            let id = self.find(text).await.id;
+            self.todos.update(id, patch).await.unwrap();
+        }
+
+        /// Names of the lists, sorted.
+        async fn lists(&self) -> Vec<String> {
+            let lists = self.lists.find_all(true).await.unwrap();
+            let mut names = Vec::from_iter(lists.into_iter().map(|list| list.name));
+            names.sort();
+            names
+        }
+
+        async fn list(&self, name: &str) -> List {
+            let lists = self.lists.find_all(true).await.unwrap();
+            lists.into_iter().find(|list| list.name == name).unwrap()
+        }
+
+        /// Text of every todo, by position.
+        async fn order(&self) -> Vec<String> {
+            let query = TodoQuery {
+                sort: TodoSort::Position,
+                ..Default::default()
+            };
+            let todos = self.todos.all(&query).await.unwrap();
+            Vec::from_iter(todos.into_iter().map(|todo| todo.text))
+        }
+
+        async fn move_before(&self, text: &str, before: Option<&str>) {
+            let id = self.find(text).await.id;
+            let before = match before {
+                Some(text) => Some(self.find(text).await.id),
+                None => None,
+            };
+            self.todos.move_before(id, before).await.unwrap();
+        }
+
+        /// Todos with `text`, earliest due first.
+        async fn occurrences(&self, text: &str) -> Vec<Todo> {
+            let todos = self.todos.all(&TodoQuery::default()).await.unwrap();
+            let mut todos = Vec::from_iter(todos.into_iter().filter(|todo| todo.text == text));
+            todos.sort_by_key(|todo| todo.due_at);
+            todos
+        }
+
+        /// The service of the app, completing an occurrence creates the next one.
+        fn service(&self) -> TodoService<LocalTodos, MemberRepositoryForMemory> {
+            TodoService::new(self.todos.clone(), MemberRepositoryForMemory::new())
+        }
+
+        /// Name of the list of the todo with `text`.
+        async fn list_of(&self, text: &str) -> String {
+            let list_id = self.find(text).await.list_id;
+            self.lists.find(list_id).await.unwrap().name
+        }
+    }
+
+    /// Records the steps reported, and cancels once a step of `cancel_after` is.
+    #[derive(Default)]
+    struct Recorder {
+        steps: std::sync::Mutex<Vec<Step>>,
+        cancel_after: Option<Stage>,
+    }
+
+    impl Recorder {
+        fn steps(&self) -> Vec<Step> {
+            self.steps.lock().unwrap().clone()
+        }
+    }
+
+    impl ProgressSink for Recorder {
+        fn report(&self, step: Step) {
+            self.steps.lock().unwrap().push(step);
+        }
+
+        fn is_cancelled(&self) -> bool {
+            self.steps()
+                .iter()
+                .any(|step| Some(step.stage) == self.cancel_after)
+        }
+    }
+
+    fn text(text: &str) -> UpdateTodo {
+        UpdateTodo {
+            text: Some(text.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn syncs_two_clients() {
+        let server = server();
+        let (alice, bob) = (client(&server), client(&server));
+
+        let parent = alice
+            .todos
+            .create(CreateTodo::new("parent".to_string()))
+            .await
+            .unwrap();
+        let child = alice
+            .todos
+            .create(CreateTodo {
+                parent_id: Some(parent.id),
+                ..CreateTodo::new("child".to_string())
+            })
+            .await
+            .unwrap();
+        alice
+            .todos
+            .update(
+                child.id,
+                UpdateTodo {
+                    completed: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(alice.engine.status().await.unwrap().pending, 2);
+
+        let report = alice.engine.sync(&NoProgress).await.unwrap();
+        // the child waits for its parent, then its completion for its creation
+        assert_eq!(report.pushed, 3);
+        let status = alice.engine.status().await.unwrap();
+        assert_eq!(status.pending, 0);
+        assert!(status.last_synced_at.is_some());
+        assert!(!status.syncing);
+
+        bob.engine.sync(&NoProgress).await.unwrap();
+        let expected = vec![
+            ("child".to_string(), true, Some("parent".to_string())),
+            ("parent".to_string(), false, None),
+        ];
+        assert_eq!(bob.todos().await, expected);
+
+        bob.edit("parent", text("renamed")).await;
+        bob.engine.sync(&NoProgress).await.unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(alice.todos().await, bob.todos().await);
+        assert_eq!(alice.find("renamed").await.id, parent.id);
+
+        alice.todos.delete(parent.id, None).await.unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        bob.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(bob.todos().await, vec![]);
+        assert_eq!(bob.engine.status().await.unwrap().pending, 0);
+    }
+
+    #[tokio::test]
+    async fn syncs_lists_with_their_todos() {
+        let server = server();
+        let (alice, bob) = (client(&server), client(&server));
+        let groceries = alice
+            .lists
+            .create(CreateList {
+                name: "Groceries".to_string(),
+            })
+            .await
+            .unwrap();
+        for (text, list_id) in [("milk", Some(groceries.id)), ("call mom", None)] {
+            alice
+                .todos
+                .create(CreateTodo {
+                    list_id,
+                    ..CreateTodo::new(text.to_string())
+                })
+                .await
+                .unwrap();
+        }
+        alice.engine.sync(&NoProgress).await.unwrap();
+        bob.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(bob.lists().await, ["Groceries", "Inbox"]);
+        assert_eq!(bob.list_of("milk").await, "Groceries");
+        assert_eq!(bob.list_of("call mom").await, "Inbox");
+
+        // moves go both ways, lists made offline on both sides are matched by name
+        let inbox = bob.list("Inbox").await;
+        bob.edit(
+            "milk",
+            UpdateTodo {
+                list_id: Some(inbox.id),
+                ..Default::default()
+            },
+        )
+        .await;
+        for client in [&alice, &bob] {
+            let work = client
+                .lists
+                .create(CreateList {
+                    name: "Work".to_string(),
+                })
+                .await
+                .unwrap();
+            client
+                .edit(
+                    "call mom",
+                    UpdateTodo {
+                        list_id: Some(work.id),
+                        ..Default::default()
+                    },
+                )
+                .await;
+        }
+        for client in [&bob, &alice, &bob] {
+            client.engine.sync(&NoProgress).await.unwrap();
+        }
+        assert_eq!(alice.lists().await, ["Groceries", "Inbox", "Work"]);
+        assert_eq!(bob.lists().await, alice.lists().await);
+        assert_eq!(alice.list_of("milk").await, "Inbox");
+        assert_eq!(alice.list_of("call mom").await, "Work");
+
+        // a list deleted on one side is deleted on the other one
+        let work = alice.list("Work").await;
+        alice
+            .lists
+            .delete(work.id, ListCascade::MoveToInbox)
+            .await
+            .unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        bob.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(bob.lists().await, ["Groceries", "Inbox"]);
+        assert_eq!(bob.list_of("call mom").await, "Inbox");
+        assert_eq!(bob.engine.status().await.unwrap().pending, 0);
+    }
+
+    #[tokio::test]
+    async fn syncs_series_with_their_occurrences() {
+        let server = server();
+        let (alice, bob) = (client(&server), client(&server));
+        let due_at = Utc::now();
+        alice
+            .todos
+            .create(CreateTodo {
+                due_at: Some(due_at),
+                rrule: Some("FREQ=DAILY".to_string()),
+                ..CreateTodo::new("water plants".to_string())
+            })
+            .await
+            .unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        bob.engine.sync(&NoProgress).await.unwrap();
+
+        // both create the next occurrence offline, the server keeps one
+        for client in [&alice, &bob] {
+            let id = client.find("water plants").await.id;
+            let completed = UpdateTodo {
+                completed: Some(true),
+                ..Default::default()
+            };
+            client.service().update(id, completed).await.unwrap();
+        }
+        for client in [&alice, &bob, &alice] {
+            client.engine.sync(&NoProgress).await.unwrap();
+        }
+        let carol = client(&server);
+        carol.engine.sync(&NoProgress).await.unwrap();
+        let tomorrow = due_at + Duration::days(1);
+        for client in [&alice, &bob, &carol] {
+            let occurrences = client.occurrences("water plants").await;
+            let due = Vec::from_iter(occurrences.iter().map(|todo| (todo.due_at, todo.completed)));
+            assert_eq!(due, [(Some(due_at), true), (Some(tomorrow), false)]);
+            assert!(occurrences[0].series_id.is_some());
+            assert_eq!(occurrences[0].series_id, occurrences[1].series_id);
+        }
+
+        // a new rule changes the series on the other side
+        let next = alice.occurrences("water plants").await.pop().unwrap();
+        let weekly = UpdateTodo {
+            rrule: Some(Some("FREQ=WEEKLY".to_string())),
+            ..Default::default()
+        };
+        alice
+            .service()
+            .update_all_future(next.id, weekly)
+            .await
+            .unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        bob.engine.sync(&NoProgress).await.unwrap();
+        let next = bob.occurrences("water plants").await.pop().unwrap();
+        assert_eq!(next.rrule.as_deref(), Some("FREQ=WEEKLY"));
+        let completed = UpdateTodo {
+            completed: Some(true),
+            ..Default::default()
+        };
+        bob.service().update(next.id, completed).await.unwrap();
+        bob.engine.sync(&NoProgress).await.unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        let due = Vec::from_iter(
+            alice
+                .occurrences("water plants")
+                .await
+                .into_iter()
+                .map(|todo| todo.due_at),
+        );
+        assert_eq!(
+            due,
+            [
+                Some(due_at),
+                Some(tomorrow),
+                Some(tomorrow + Duration::weeks(1))
+            ]
+        );
+        assert_eq!(alice.engine.status().await.unwrap().pending, 0);
+    }
+
+    #[tokio::test]
+    async fn merges_concurrent_reorders() {
+        let server = server();
+        let (alice, bob) = (client(&server), client(&server));
+        for text in ["a", "b", "c", "d"] {
+            alice
+                .todos
+                .create(CreateTodo::new(text.to_string()))
+                .await
+                .unwrap();
+        }
+        alice.engine.sync(&NoProgress).await.unwrap();
+        bob.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(bob.order().await, ["a", "b", "c", "d"]);
+
+        // both move a different todo offline, neither move is lost
+        alice.move_before("d", Some("a")).await;
+        bob.move_before("a", None).await;
+        for client in [&alice, &bob, &alice] {
+            client.engine.sync(&NoProgress).await.unwrap();
+        }
+        assert_eq!(alice.order().await, ["d", "b", "c", "a"]);
+        assert_eq!(bob.order().await, alice.order().await);
+        let carol = client(&server);
+        carol.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(carol.order().await, alice.order().await);
+
+        // a moved todo stays moved after another sync
+        bob.move_before("c", Some("b")).await;
+        bob.engine.sync(&NoProgress).await.unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(alice.order().await, ["d", "c", "b", "a"]);
+        for client in [&alice, &bob] {
+            assert_eq!(client.engine.status().await.unwrap().pending, 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_progress_and_stops_when_cancelled() {
+        let server = server();
+        let (alice, bob) = (client(&server), client(&server));
+        for text in ["first", "second"] {
+            alice
+                .todos
+                .create(CreateTodo::new(text.to_string()))
+                .await
+                .unwrap();
+        }
+
+        // what was pushed before the cancellation stays pushed
+        let progress = Recorder {
+            cancel_after: Some(Stage::Push),
+            ..Default::default()
+        };
+        assert_eq!(
+            alice.engine.sync(&progress).await,
+            Err(SyncError::Cancelled)
+        );
+        assert_eq!(
+            progress.steps(),
+            vec![
+                Step::new(Stage::Round, 1, Some(MAX_ROUNDS)),
+                Step::new(Stage::Push, 2, Some(2)),
+            ]
+        );
+        let status = alice.engine.status().await.unwrap();
+        assert_eq!((status.pending, status.cursor), (0, 0));
+        assert_eq!((status.last_synced_at, status.last_error), (None, None));
+
+        let progress = Recorder::default();
+        bob.engine.sync(&progress).await.unwrap();
+        assert_eq!(bob.todos().await.len(), 2);
+        assert!(progress.steps().contains(&Step::new(Stage::Pull, 2, None)));
+    }
+
+    #[tokio::test]
+    async fn merges_concurrent_edits_per_field() {
+        let server = server();
+        let (alice, bob) = (client(&server), client(&server));
+        alice
+            .todos
+            .create(CreateTodo::new("todo".to_string()))
+            .await
+            .unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        bob.engine.sync(&NoProgress).await.unwrap();
+
+        // different fields both make it
+        alice.edit("todo", text("edited")).await;
+        bob.edit(
+            "todo",
+            UpdateTodo {
+                completed: Some(true),
+                ..Default::default()
+            },
+        )
+        .await;
+        alice.engine.sync(&NoProgress).await.unwrap();
+        let report = bob.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(report.conflicts, 0);
+        alice.engine.sync(&NoProgress).await.unwrap();
+        let expected = vec![("edited".to_string(), true, None)];
+        assert_eq!(alice.todos().await, expected);
+        assert_eq!(bob.todos().await, expected);
+
+        // the same field keeps the value the server got last
+        let priority = |priority| UpdateTodo {
+            priority: Some(priority),
+            ..Default::default()
+        };
+        alice.edit("edited", priority(Priority::Low)).await;
+        alice.engine.sync(&NoProgress).await.unwrap();
+        bob.edit("edited", priority(Priority::High)).await;
+        let report = bob.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(report.conflicts, 1);
+        alice.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(alice.find("edited").await.priority, Priority::High);
+        assert_eq!(bob.find("edited").await.priority, Priority::High);
+
+        // whenever it was made, bob's edit reaches the server after alice's
+        alice.edit("edited", priority(Priority::Urgent)).await;
+        bob.edit("edited", priority(Priority::Medium)).await;
+        alice.engine.sync(&NoProgress).await.unwrap();
+        bob.engine.sync(&NoProgress).await.unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(alice.find("edited").await.priority, Priority::Medium);
+        assert_eq!(bob.find("edited").await.priority, Priority::Medium);
+        assert_eq!(bob.engine.status().await.unwrap().pending, 0);
+    }
+
+    #[tokio::test]
+    async fn merges_concurrent_text_edits() {
+        let server = server();
+        let (alice, bob) = (client(&server), client(&server));
+        let todo = alice
+            .todos
+            .create(CreateTodo::new("buy milk".to_string()))
+            .await
+            .unwrap();
+        // edited before it reached the server
+        alice
+            .todos
+            .update(todo.id, text("buy oat milk"))
+            .await
+            .unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        bob.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(bob.todos().await, alice.todos().await);
+
+        alice
+            .edit("buy oat milk", text("buy oat milk and eggs"))
+            .await;
+        bob.edit("buy oat milk", text("buy cold oat milk")).await;
+        alice.engine.sync(&NoProgress).await.unwrap();
+        let report = bob.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(report.conflicts, 0);
+        alice.engine.sync(&NoProgress).await.unwrap();
+        let expected = vec![("buy cold oat milk and eggs".to_string(), false, None)];
+        assert_eq!(alice.todos().await, expected);
+        assert_eq!(bob.todos().await, expected);
+        assert_eq!(alice.engine.status().await.unwrap().pending, 0);
+
+        // an empty text never reaches the server, the server's comes back
+        bob.edit("buy cold oat milk and eggs", text("")).await;
+        let report = bob.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(report.rejected, 1);
+        assert_eq!(bob.todos().await, expected);
+        bob.edit("buy cold oat milk and eggs", text("milk")).await;
+        bob.engine.sync(&NoProgress).await.unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(alice.todos().await, vec![("milk".to_string(), false, None)]);
+    }
+
+    #[tokio::test]
+    async fn deletions_win_over_edits() {
+        let server = server();
+        let (alice, bob) = (client(&server), client(&server));
+        let todo = alice
+            .todos
+            .create(CreateTodo::new("todo".to_string()))
+            .await
+            .unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        bob.engine.sync(&NoProgress).await.unwrap();
+
+        bob.edit("todo", text("edited")).await;
+        alice.todos.delete(todo.id, None).await.unwrap();
+        alice.engine.sync(&NoProgress).await.unwrap();
+        bob.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(bob.todos().await, vec![]);
+        assert_eq!(bob.engine.status().await.unwrap().pending, 0);
+
+        // a todo created and deleted offline never reaches the server
+        let todo = bob
+            .todos
+            .create(CreateTodo::new("offline".to_string()))
+            .await
+            .unwrap();
+        bob.todos.delete(todo.id, None).await.unwrap();
+        let report = bob.engine.sync(&NoProgress).await.unwrap();
+        assert_eq!(report.pushed, 0);
+    }
+}
@@ -0,0 +1,67 @@
+use axum::http::StatusCode;
+use thiserror::Error;
+
+use crate::todos::repository::RepositoryError;
+
+/// Error returned by `SyncEngine`, serialized like `TodoError`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum SyncError {
+    /// There is no server to sync with, see `SyncTransportForHttp::from_env`.
+    #[error("Sync not configured: [{0}]")]
+    NotConfigured(String),
+    /// The server couldn't be reached, or answered with an error.
+    #[error("Remote error: [{0}]")]
+    Remote(String),
+    #[error("Storage error: [{0}]")]
+    Storage(String),
+    /// Stopped by its `ProgressSink`, keeping what was synced so far.
+    #[error("Sync cancelled")]
+    Cancelled,
+    #[error("Unexpected Error: [{0}]")]
+    Unexpected(String),
+}
+
+impl SyncError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SyncError::NotConfigured(_) => "NotConfigured",
+            SyncError::Remote(_) => "Remote",
+            SyncError::Storage(_) => "Storage",
+            SyncError::Cancelled => "Cancelled",
+            SyncError::Unexpected(_) => "Unexpected",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            SyncError::NotConfigured(_) => StatusCode::BAD_REQUEST,
+            SyncError::Remote(_) => StatusCode::BAD_GATEWAY,
+            SyncError::Storage(_) => StatusCode::SERVICE_UNAVAILABLE,
+            SyncError::Cancelled => StatusCode::BAD_REQUEST,
+            SyncError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<RepositoryError> for SyncError {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::Storage(message) => SyncError::Storage(message),
+            e => SyncError::Unexpected(e.to_string()),
+        }
+    }
+}
+
+impl From<anyhow::Error> for SyncError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<SyncError>() {
+            Ok(e) => e,
+            Err(e) => match e.downcast::<RepositoryError>() {
+                Ok(e) => e.into(),
+                Err(e) => SyncError::Unexpected(e.to_string()),
+            },
+        }
+    }
+}
+
+crate::impl_error_response!(SyncError);
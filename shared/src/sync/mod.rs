@@ -0,0 +1,7 @@
+pub mod engine;
+pub mod error;
+pub mod model;
+pub mod outbox;
+pub mod repository;
+pub mod service;
+pub mod transport;
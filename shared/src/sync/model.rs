@@ -0,0 +1,360 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::ToSchema;
+
+use crate::todos::crdt::{ListOrderDelta, TextDelta, VersionVector};
+use crate::todos::model::{CreateTodo, Todo, TodoChange, UpdateTodo};
+
+/// Fields of a todo kept in sync, by their JSON name. Labels and ordering stay with each
+/// store, and a change of `rrule` changes the series of the todo. The text of todos with a
+/// document is merged through it instead.
+pub const SYNCED_FIELDS: [&str; 8] = [
+    "text",
+    "completed",
+    "starts_at",
+    "due_at",
+    "priority",
+    "list_id",
+    "parent_id",
+    "rrule",
+];
+
+/// Changes after a cursor, what `GET /sync` returns.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ChangePage {
+    pub changes: Vec<TodoChange>,
+    /// To pull the changes made after these ones.
+    pub cursor: i64,
+    /// Set when there are more changes after this page.
+    pub has_more: bool,
+}
+
+/// Local changes sent with `POST /sync`, applied in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PushRequest {
+    pub ops: Vec<PushOp>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PushOp {
+    /// Adds the todo to series `series_id` when set, unless the series has an occurrence due at
+    /// the same time already, which the todo is then. Otherwise `todo.rrule` starts a series.
+    Create {
+        todo: CreateTodo,
+        #[serde(default)]
+        series_id: Option<i32>,
+    },
+    /// Fails with a conflict when the todo isn't at `version` anymore.
+    Update {
+        id: i32,
+        version: i32,
+        patch: UpdateTodo,
+    },
+    /// Unconditional, a deletion wins over the updates made meanwhile.
+    Delete { id: i32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PushResponse {
+    /// One for each op, in the same order.
+    pub results: Vec<PushResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PushResult {
+    /// The todo as it is after the op.
+    Applied {
+        todo: Todo,
+    },
+    Deleted,
+    /// The todo is at `version` now, the op has to be merged with the changes made since.
+    Conflict {
+        version: i32,
+    },
+    /// The todo was deleted.
+    Missing,
+    /// The op will never succeed, like an invalid text or a list the user can't edit.
+    Rejected {
+        kind: String,
+        message: String,
+    },
+    /// The op wasn't applied this time, it can be pushed again.
+    Retry {
+        message: String,
+    },
+}
+
+/// The text document of a todo as one side has it: its version and the ops the other side
+/// lacks, by the id of the todo on the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DocumentSync {
+    pub id: i32,
+    /// The latest counter of each replica, as `[replica, counter]` pairs.
+    #[schema(value_type = Object)]
+    pub version: VersionVector,
+    /// Ops inserting and deleting characters.
+    #[schema(value_type = Object)]
+    pub delta: TextDelta,
+}
+
+/// The order of the todos of a list as one side has it, like `DocumentSync`, by the ids of the
+/// list and its todos on the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct OrderSync {
+    pub list_id: i32,
+    /// The latest counter of each replica, as `[replica, counter]` pairs.
+    #[schema(value_type = Object)]
+    pub version: VersionVector,
+    /// Ops inserting and deleting the ids of todos.
+    #[schema(value_type = Object)]
+    pub delta: ListOrderDelta,
+}
+
+/// The documents a client sends with `POST /sync/documents`, with the ops the server hasn't
+/// acknowledged yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DocumentRequest {
+    pub documents: Vec<DocumentSync>,
+    /// The orders of the lists, merged as the documents are.
+    #[serde(default)]
+    pub orders: Vec<OrderSync>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DocumentResponse {
+    /// One for each document, in the same order.
+    pub results: Vec<DocumentResult>,
+    /// One for each order.
+    #[serde(default)]
+    pub orders: Vec<OrderResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DocumentResult {
+    /// The ops were merged and the text of the todo is what the document reads. `document`
+    /// holds what the client lacks of it.
+    Merged { document: DocumentSync },
+    /// The ops will never be merged, like those emptying the text or from a user who can't
+    /// edit the todo. `document` holds all of the server's, to start over from.
+    Rejected {
+        kind: String,
+        message: String,
+        document: DocumentSync,
+    },
+    /// The todo was deleted, or the user can't see it anymore.
+    Missing,
+    /// The ops weren't merged this time, they can be sent again.
+    Retry { message: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum OrderResult {
+    /// The ops were merged and the todos of the list are in the order of the document. `order`
+    /// holds what the client lacks of it.
+    Merged { order: OrderSync },
+    /// The ops will never be merged, like those from a user who can't edit the list. `order`
+    /// holds all of the server's, to start over from.
+    Rejected {
+        kind: String,
+        message: String,
+        order: OrderSync,
+    },
+    /// The list was deleted, or the user can't see it anymore.
+    Missing,
+    /// The ops weren't merged this time, they can be sent again.
+    Retry { message: String },
+}
+
+/// A local change of a synced field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub value: Value,
+}
+
+/// The synced fields changed locally, by name.
+pub type FieldChanges = BTreeMap<String, FieldChange>;
+
+/// What happened locally to a todo since it was last pushed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PendingChange {
+    /// Pushed with the fields the todo has then.
+    Created,
+    Updated {
+        fields: FieldChanges,
+    },
+    Deleted,
+}
+
+/// A local change waiting in the outbox, there is at most one for each todo.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pending {
+    /// Local id of the todo.
+    pub id: i32,
+    pub change: PendingChange,
+}
+
+/// Everything the sync engine keeps next to the local store.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncState {
+    /// Oldest first.
+    pub outbox: Vec<Pending>,
+    /// `cursor` of the last page pulled.
+    pub cursor: i64,
+    /// Remote ids by local id, `local_ids` the other way round.
+    pub remote_ids: BTreeMap<i32, i32>,
+    pub local_ids: BTreeMap<i32, i32>,
+    /// Remote ids of the lists by local id, `local_lists` the other way round.
+    #[serde(default)]
+    pub remote_lists: BTreeMap<i32, i32>,
+    #[serde(default)]
+    pub local_lists: BTreeMap<i32, i32>,
+    /// Remote ids of the series by local id, `local_series` the other way round.
+    #[serde(default)]
+    pub remote_series: BTreeMap<i32, i32>,
+    #[serde(default)]
+    pub local_series: BTreeMap<i32, i32>,
+    /// The todos as the server last sent them, by local id. They are the base the local and
+    /// remote changes are merged from.
+    pub shadows: BTreeMap<i32, Todo>,
+    /// Versions of the text documents the server is known to have, by local id.
+    #[serde(default)]
+    pub documents: BTreeMap<i32, VersionVector>,
+    /// Todos whose text document has to be exchanged with the server, by local id.
+    #[serde(default)]
+    pub stale_documents: BTreeSet<i32>,
+    /// Versions of the orders of the lists the server is known to have, by local list id.
+    #[serde(default)]
+    pub orders: BTreeMap<i32, VersionVector>,
+    /// Lists whose order has to be exchanged with the server, by local id.
+    #[serde(default)]
+    pub stale_orders: BTreeSet<i32>,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+impl SyncState {
+    pub fn pending(&self, id: i32) -> Option<&PendingChange> {
+        self.outbox
+            .iter()
+            .find(|pending| pending.id == id)
+            .map(|pending| &pending.change)
+    }
+
+    /// Adds `change` of todo `id` to the outbox, folded into the one already waiting.
+    pub fn record(&mut self, id: i32, change: PendingChange) {
+        let known = self.remote_ids.contains_key(&id);
+        let Some(index) = self.outbox.iter().position(|pending| pending.id == id) else {
+            match change {
+                PendingChange::Deleted if !known => {}
+                // todos created before the first sync are pushed as new ones
+                PendingChange::Updated { .. } if !known => self.outbox.push(Pending {
+                    id,
+                    change: PendingChange::Created,
+                }),
+                change => self.outbox.push(Pending { id, change }),
+            }
+            return;
+        };
+        match (&mut self.outbox[index].change, change) {
+            // the server never saw it
+            (PendingChange::Created, PendingChange::Deleted) => {
+                self.outbox.remove(index);
+            }
+            (PendingChange::Updated { fields }, PendingChange::Updated { fields: newer }) => {
+                fields.extend(newer)
+            }
+            (waiting @ PendingChange::Updated { .. }, PendingChange::Deleted) => {
+                *waiting = PendingChange::Deleted
+            }
+            // created todos are pushed with the fields they have then
+            _ => {}
+        }
+    }
+
+    pub fn map(&mut self, id: i32, remote_id: i32) {
+        self.remote_ids.insert(id, remote_id);
+        self.local_ids.insert(remote_id, id);
+    }
+
+    pub fn map_list(&mut self, id: i32, remote_id: i32) {
+        self.remote_lists.insert(id, remote_id);
+        self.local_lists.insert(remote_id, id);
+    }
+
+    pub fn map_series(&mut self, id: i32, remote_id: i32) {
+        self.remote_series.insert(id, remote_id);
+        self.local_series.insert(remote_id, id);
+    }
+
+    /// Forgets list `id`, once it is gone on both sides.
+    pub fn forget_list(&mut self, id: i32) {
+        if let Some(remote_id) = self.remote_lists.remove(&id) {
+            self.local_lists.remove(&remote_id);
+        }
+        self.orders.remove(&id);
+        self.stale_orders.remove(&id);
+    }
+
+    /// Forgets everything about todo `id`, once it is gone on both sides.
+    pub fn forget(&mut self, id: i32) {
+        if let Some(remote_id) = self.remote_ids.remove(&id) {
+            self.local_ids.remove(&remote_id);
+        }
+        self.shadows.remove(&id);
+        self.documents.remove(&id);
+        self.stale_documents.remove(&id);
+        self.outbox.retain(|pending| pending.id != id);
+    }
+}
+
+/// What `SyncEngine::status` reports to the app.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncStatus {
+    /// Local changes waiting to be pushed.
+    pub pending: usize,
+    pub cursor: i64,
+    pub syncing: bool,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    /// Why the last sync failed, cleared by the next one that succeeds.
+    pub last_error: Option<String>,
+}
+
+/// What a sync did.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncReport {
+    /// Ops the server applied.
+    pub pushed: usize,
+    /// Remote changes applied to the local store.
+    pub pulled: usize,
+    /// Fields changed on both sides, the local change was kept as it reaches the server last.
+    pub conflicts: usize,
+    /// Ops the server will never apply, dropped from the outbox.
+    pub rejected: usize,
+}
+
+/// The synced fields of `todo` as JSON, by name.
+pub fn synced_fields(todo: &Todo) -> Map<String, Value> {
+    let Ok(Value::Object(mut fields)) = serde_json::to_value(todo) else {
+        unreachable!("a todo serializes to an object");
+    };
+    fields.retain(|name, _| SYNCED_FIELDS.contains(&name.as_str()));
+    fields
+}
+
+/// The synced fields that differ between `before` and `after`.
+pub fn diff(before: &Todo, after: &Todo) -> FieldChanges {
+    let before = synced_fields(before);
+    synced_fields(after)
+        .into_iter()
+        .filter(|(name, value)| before.get(name) != Some(value))
+        .map(|(name, value)| (name, FieldChange { value }))
+        .collect()
+}
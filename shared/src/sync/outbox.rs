@@ -0,0 +1,254 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
+
+use super::model::{diff, PendingChange, SyncState};
+use super::repository::SyncStateRepositoryTrait;
+use crate::todos::model::{
+    CreateTodo, Todo, TodoChange, TodoQuery, TodoSearchHit, TodoSeries, UpdateTodo,
+};
+use crate::todos::repository::TodoRepositoryTrait;
+
+/// The sync state shared by the store recording the local changes and the engine pushing them.
+/// It is loaded on first use and saved after every change.
+#[derive(Debug, Clone)]
+pub struct Outbox<SR> {
+    repository: SR,
+    state: Arc<Mutex<Option<SyncState>>>,
+}
+
+impl<SR: SyncStateRepositoryTrait> Outbox<SR> {
+    pub fn new(repository: SR) -> Self {
+        Self {
+            repository,
+            state: Arc::default(),
+        }
+    }
+
+    pub async fn state(&self) -> anyhow::Result<SyncState> {
+        self.update(|state| state.clone()).await
+    }
+
+    /// Changes the state with `f` and saves it.
+    pub async fn update<R>(&self, f: impl FnOnce(&mut SyncState) -> R) -> anyhow::Result<R> {
+        let mut state = self.state.lock().await;
+        let state = match &mut *state {
+            Some(state) => state,
+            state => state.insert(self.repository.load().await?),
+        };
+        let result = f(state);
+        self.repository.save(state).await?;
+        Ok(result)
+    }
+
+    pub async fn record(&self, id: i32, change: PendingChange) -> anyhow::Result<()> {
+        self.update(|state| state.record(id, change)).await
+    }
+
+    /// Records the synced fields changed between `before` and `after`, if any.
+    async fn record_diff(&self, before: &Todo, after: &Todo) -> anyhow::Result<()> {
+        let fields = diff(before, after);
+        if fields.is_empty() {
+            return Ok(());
+        }
+        self.record(after.id, PendingChange::Updated { fields })
+            .await
+    }
+
+    async fn record_deleted(&self, ids: impl IntoIterator<Item = i32>) -> anyhow::Result<()> {
+        self.update(|state| {
+            for id in ids {
+                state.record(id, PendingChange::Deleted)
+            }
+        })
+        .await
+    }
+}
+
+/// The local store `TR`, recording into `outbox` the changes to push. The engine writes the
+/// changes it pulls to `TR` itself, so they aren't pushed back.
+#[derive(Debug, Clone)]
+pub struct TodoRepositoryWithOutbox<TR, SR> {
+    todo_repository: TR,
+    outbox: Outbox<SR>,
+}
+
+impl<TR, SR> TodoRepositoryWithOutbox<TR, SR> {
+    pub fn new(todo_repository: TR, outbox: Outbox<SR>) -> Self {
+        Self {
+            todo_repository,
+            outbox,
+        }
+    }
+}
+
+impl<TR: TodoRepositoryTrait, SR> TodoRepositoryWithOutbox<TR, SR> {
+    async fn all_ids(&self) -> anyhow::Result<BTreeSet<i32>> {
+        let todos = self.todo_repository.all(&TodoQuery::default()).await?;
+        Ok(todos.into_iter().map(|todo| todo.id).collect())
+    }
+}
+
+#[async_trait]
+impl<TR, SR> TodoRepositoryTrait for TodoRepositoryWithOutbox<TR, SR>
+where
+    TR: TodoRepositoryTrait,
+    SR: SyncStateRepositoryTrait,
+{
+    fn owned_by(&self, owner: i32) -> Self {
+        Self::new(self.todo_repository.owned_by(owner), self.outbox.clone())
+    }
+
+    fn in_workspace(&self, workspace: i32) -> Self {
+        Self::new(
+            self.todo_repository.in_workspace(workspace),
+            self.outbox.clone(),
+        )
+    }
+
+    async fn create(&self, payload: CreateTodo) -> anyhow::Result<Todo> {
+        let todo = self.todo_repository.create(payload).await?;
+        self.outbox.record(todo.id, PendingChange::Created).await?;
+        Ok(todo)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Todo> {
+        self.todo_repository.find(id).await
+    }
+
+    async fn all(&self, query: &TodoQuery) -> anyhow::Result<Vec<Todo>> {
+        self.todo_repository.all(query).await
+    }
+
+    async fn count(&self, query: &TodoQuery) -> anyhow::Result<i64> {
+        self.todo_repository.count(query).await
+    }
+
+    async fn search(&self, query: &str, limit: u32) -> anyhow::Result<Vec<TodoSearchHit>> {
+        self.todo_repository.search(query, limit).await
+    }
+
+    async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<Todo> {
+        let before = self.todo_repository.find(id).await?;
+        let after = self.todo_repository.update(id, payload).await?;
+        self.outbox.record_diff(&before, &after).await?;
+        Ok(after)
+    }
+
+    async fn delete(&self, id: i32, version: Option<i32>) -> anyhow::Result<()> {
+        let subtree = self.todo_repository.subtree(id).await?;
+        self.todo_repository.delete(id, version).await?;
+        self.outbox
+            .record_deleted(subtree.into_iter().map(|todo| todo.id))
+            .await
+    }
+
+    async fn subtree(&self, id: i32) -> anyhow::Result<Vec<Todo>> {
+        self.todo_repository.subtree(id).await
+    }
+
+    async fn ancestor_ids(&self, id: i32) -> anyhow::Result<Vec<i32>> {
+        self.todo_repository.ancestor_ids(id).await
+    }
+
+    async fn move_before(&self, id: i32, before: Option<i32>) -> anyhow::Result<Todo> {
+        self.todo_repository.move_before(id, before).await
+    }
+
+    async fn label_ids(&self, id: i32) -> anyhow::Result<Vec<i32>> {
+        self.todo_repository.label_ids(id).await
+    }
+
+    async fn attach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()> {
+        self.todo_repository.attach_label(id, label_id).await
+    }
+
+    async fn detach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()> {
+        self.todo_repository.detach_label(id, label_id).await
+    }
+
+    async fn remove_label(&self, label_id: i32) -> anyhow::Result<()> {
+        self.todo_repository.remove_label(label_id).await
+    }
+
+    async fn move_list(&self, from: i32, to: i32) -> anyhow::Result<()> {
+        let query = TodoQuery {
+            list_id: Some(from),
+            ..Default::default()
+        };
+        let before = self.todo_repository.all(&query).await?;
+        self.todo_repository.move_list(from, to).await?;
+        for before in before {
+            let after = self.todo_repository.find(before.id).await?;
+            self.outbox.record_diff(&before, &after).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_list(&self, list_id: i32) -> anyhow::Result<()> {
+        // subtasks of the list's todos go along, wherever they are
+        let before = self.all_ids().await?;
+        self.todo_repository.delete_list(list_id).await?;
+        let after = self.all_ids().await?;
+        self.outbox
+            .record_deleted(before.difference(&after).copied())
+            .await
+    }
+
+    async fn add_occurrence(&self, series_id: i32, payload: CreateTodo) -> anyhow::Result<Todo> {
+        let todo = self
+            .todo_repository
+            .add_occurrence(series_id, payload)
+            .await?;
+        self.outbox.record(todo.id, PendingChange::Created).await?;
+        Ok(todo)
+    }
+
+    async fn find_series(&self, series_id: i32) -> anyhow::Result<TodoSeries> {
+        self.todo_repository.find_series(series_id).await
+    }
+
+    async fn series_occurrences(&self, series_id: i32) -> anyhow::Result<Vec<Todo>> {
+        self.todo_repository.series_occurrences(series_id).await
+    }
+
+    async fn start_series(&self, id: i32, rrule: String) -> anyhow::Result<Todo> {
+        let before = self.todo_repository.find(id).await?;
+        let after = self.todo_repository.start_series(id, rrule).await?;
+        self.outbox.record_diff(&before, &after).await?;
+        Ok(after)
+    }
+
+    async fn update_series(
+        &self,
+        series_id: i32,
+        payload: &UpdateTodo,
+    ) -> anyhow::Result<TodoSeries> {
+        let before = self.todo_repository.series_occurrences(series_id).await?;
+        let series = self
+            .todo_repository
+            .update_series(series_id, payload)
+            .await?;
+        for after in self.todo_repository.series_occurrences(series_id).await? {
+            if let Some(before) = before.iter().find(|todo| todo.id == after.id) {
+                self.outbox.record_diff(before, &after).await?;
+            }
+        }
+        Ok(series)
+    }
+
+    async fn find_due(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Todo>> {
+        self.todo_repository.find_due(from, to).await
+    }
+
+    async fn changes(&self, cursor: i64, limit: u32) -> anyhow::Result<Vec<TodoChange>> {
+        self.todo_repository.changes(cursor, limit).await
+    }
+}
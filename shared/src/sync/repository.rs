@@ -0,0 +1,116 @@
+use axum::async_trait;
+use sled::{Db, Tree};
+
+use super::model::SyncState;
+use crate::todos::repository::RepositoryError;
+
+/// Where the sync engine keeps its state, next to the local store of the todos.
+#[async_trait]
+pub trait SyncStateRepositoryTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    /// The default state before the first save.
+    async fn load(&self) -> anyhow::Result<SyncState>;
+    async fn save(&self, state: &SyncState) -> anyhow::Result<()>;
+}
+
+const STATE_KEY: &[u8] = b"state";
+
+/// The state as JSON in a tree of the sled database of the todos, for the desktop app.
+#[derive(Debug, Clone)]
+pub struct SyncStateRepositoryForSled {
+    tree: Tree,
+}
+
+impl SyncStateRepositoryForSled {
+    pub fn open(db: &Db) -> Result<Self, RepositoryError> {
+        Ok(Self {
+            tree: db.open_tree("sync")?,
+        })
+    }
+
+    /// State kept in the database of `crate::store`.
+    pub fn from_store() -> Result<Self, RepositoryError> {
+        let db = crate::store::db()
+            .ok_or_else(|| RepositoryError::Storage("the store has no database".to_string()))?;
+        Self::open(&db)
+    }
+}
+
+#[async_trait]
+impl SyncStateRepositoryTrait for SyncStateRepositoryForSled {
+    async fn load(&self) -> anyhow::Result<SyncState> {
+        match self.tree.get(STATE_KEY).map_err(RepositoryError::from)? {
+            Some(bytes) => Ok(serde_json::from_slice(&bytes)
+                .map_err(|e| RepositoryError::Storage(e.to_string()))?),
+            None => Ok(SyncState::default()),
+        }
+    }
+
+    async fn save(&self, state: &SyncState) -> anyhow::Result<()> {
+        let bytes =
+            serde_json::to_vec(state).map_err(|e| RepositoryError::Unexpected(e.to_string()))?;
+        self.tree
+            .insert(STATE_KEY, bytes)
+            .map_err(RepositoryError::from)?;
+        self.tree
+            .flush_async()
+            .await
+            .map_err(RepositoryError::from)?;
+        Ok(())
+    }
+}
+
+#[cfg(any(test, feature = "memory"))]
+pub mod memory {
+    use super::*;
+    use std::sync::{Arc, RwLock};
+
+    #[derive(Debug, Clone, Default)]
+    pub struct SyncStateRepositoryForMemory {
+        state: Arc<RwLock<SyncState>>,
+    }
+
+    impl SyncStateRepositoryForMemory {
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+
+    #[async_trait]
+    impl SyncStateRepositoryTrait for SyncStateRepositoryForMemory {
+        async fn load(&self) -> anyhow::Result<SyncState> {
+            Ok(self.state.read().unwrap().clone())
+        }
+
+        async fn save(&self, state: &SyncState) -> anyhow::Result<()> {
+            *self.state.write().unwrap() = state.clone();
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sync::model::{PendingChange, SyncState};
+
+    #[tokio::test]
+    async fn sled_scenario() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let repository = SyncStateRepositoryForSled::open(&db).unwrap();
+        assert_eq!(repository.load().await.unwrap(), SyncState::default());
+
+        let mut state = SyncState {
+            cursor: 42,
+            ..Default::default()
+        };
+        state.map(1, 10);
+        state.record(2, PendingChange::Created);
+        repository.save(&state).await.unwrap();
+
+        let reopened = SyncStateRepositoryForSled::open(&db).unwrap();
+        assert_eq!(reopened.load().await.unwrap(), state);
+    }
+}
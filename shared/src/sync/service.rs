@@ -0,0 +1,523 @@
+use axum::async_trait;
+use validator::Validate;
+
+use super::model::{
+    ChangePage, DocumentRequest, DocumentResponse, DocumentResult, DocumentSync, OrderResult,
+    OrderSync, PushOp, PushRequest, PushResponse, PushResult,
+};
+use crate::members::model::Role;
+use crate::members::repository::MemberRepositoryTrait;
+use crate::todos::crdt::{
+    new_replica, ListOrder, ListOrderDelta, ReplicaId, TextDelta, TextDocument, VersionVector,
+};
+use crate::todos::document::{genesis, list_order, write_order, TodoDocumentRepositoryTrait};
+use crate::todos::error::TodoError;
+use crate::todos::model::{check_limit, CreateTodo, Todo, UpdateTodo, DEFAULT_PAGE_SIZE};
+use crate::todos::repository::TodoRepositoryTrait;
+use crate::todos::service::{TodoService, TodoServiceTrait};
+
+/// The server side of the sync: the change log of the todos the user can see, and the local
+/// changes of clients applied through `TodoService`, so their roles are checked as for any
+/// other change. Text documents are merged into `DR`, the documents `TR` keeps, and so are the
+/// orders of the lists, which the positions of their todos follow. The todos that were never
+/// moved are put into the orders as ops of the replica of the service.
+#[derive(Debug, Clone)]
+pub struct SyncService<TR, MR, DR>
+where
+    TR: TodoRepositoryTrait,
+    MR: MemberRepositoryTrait,
+    DR: TodoDocumentRepositoryTrait,
+{
+    todo_repository: TR,
+    todo_service: TodoService<TR, MR>,
+    document_repository: DR,
+    replica: ReplicaId,
+}
+
+#[async_trait]
+pub trait SyncServiceTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    /// At most `limit` changes made after `cursor`, 0 pulling them all from the start.
+    async fn pull(&self, cursor: i64, limit: Option<u32>) -> Result<ChangePage, TodoError>;
+    /// Applies the ops in order, each one failing on its own.
+    async fn push(&self, request: PushRequest) -> Result<PushResponse, TodoError>;
+    /// Merges the ops of each document and sends back those the client lacks. A todo that has
+    /// no document yet gets one made of its text. The orders of the lists are merged the same
+    /// way, and the todos moved into the order they read.
+    async fn exchange(&self, request: DocumentRequest) -> Result<DocumentResponse, TodoError>;
+}
+
+impl<TR, MR, DR> SyncService<TR, MR, DR>
+where
+    TR: TodoRepositoryTrait,
+    MR: MemberRepositoryTrait,
+    DR: TodoDocumentRepositoryTrait,
+{
+    pub fn new(todo_repository: TR, member_repository: MR, document_repository: DR) -> Self {
+        Self {
+            todo_service: TodoService::new(todo_repository.clone(), member_repository),
+            todo_repository,
+            document_repository,
+            replica: new_replica(),
+        }
+    }
+
+    /// The same service over the todos of user `owner`, checking their roles.
+    pub fn owned_by(&self, owner: i32) -> Self {
+        Self {
+            todo_repository: self.todo_repository.owned_by(owner),
+            todo_service: self.todo_service.owned_by(owner),
+            document_repository: self.document_repository.clone(),
+            replica: self.replica,
+        }
+    }
+
+    /// The same service over the todos of workspace `workspace`.
+    pub fn in_workspace(&self, workspace: i32) -> Self {
+        Self {
+            todo_repository: self.todo_repository.in_workspace(workspace),
+            todo_service: self.todo_service.in_workspace(workspace),
+            document_repository: self.document_repository.clone(),
+            replica: self.replica,
+        }
+    }
+
+    async fn apply(&self, op: PushOp) -> PushResult {
+        match op {
+            PushOp::Create {
+                todo,
+                series_id: None,
+            } => applied(self.todo_service.create(todo).await),
+            PushOp::Create {
+                todo,
+                series_id: Some(series_id),
+            } => applied(self.add_occurrence(series_id, todo).await),
+            PushOp::Update {
+                id,
+                version,
+                mut patch,
+            } => {
+                patch.version = Some(version);
+                match self.update(id, patch).await {
+                    Err(TodoError::Conflict(_)) => match self.todo_service.find(id).await {
+                        Ok(todo) => PushResult::Conflict {
+                            version: todo.version,
+                        },
+                        Err(e) => failed(e),
+                    },
+                    res => applied(res),
+                }
+            }
+            PushOp::Delete { id } => match self.todo_service.delete(id, None).await {
+                Ok(()) => PushResult::Deleted,
+                Err(e) => failed(e),
+            },
+        }
+    }
+
+    /// Adds `todo` to series `series_id`, unless the series has an occurrence due at the same
+    /// time already, like the one completing the previous occurrence made. A series the user
+    /// can't see any occurrence of anymore is started again.
+    async fn add_occurrence(&self, series_id: i32, todo: CreateTodo) -> Result<Todo, TodoError> {
+        let occurrences = match self.todo_service.find_series(series_id).await {
+            Ok(series) => series.occurrences,
+            Err(TodoError::SeriesNotFound(_)) => vec![],
+            Err(e) => return Err(e),
+        };
+        if occurrences.is_empty() {
+            return self.todo_service.create(todo).await;
+        }
+        match occurrences
+            .into_iter()
+            .find(|occurrence| occurrence.due_at == todo.due_at)
+        {
+            Some(occurrence) => Ok(occurrence),
+            None => self.todo_service.add_occurrence(series_id, todo).await,
+        }
+    }
+
+    /// A change of `rrule` changes the series of the todo, the other fields of `patch` only
+    /// this occurrence.
+    async fn update(&self, id: i32, mut patch: UpdateTodo) -> Result<Todo, TodoError> {
+        let Some(rrule) = patch.rrule.take() else {
+            return self.todo_service.update(id, patch).await;
+        };
+        self.todo_service.update(id, patch).await?;
+        let series = UpdateTodo {
+            rrule: Some(rrule),
+            ..Default::default()
+        };
+        self.todo_service.update_all_future(id, series).await
+    }
+
+    async fn exchange_document(&self, sent: DocumentSync) -> DocumentResult {
+        let (todo, document) = match self.document(sent.id).await {
+            Ok(found) => found,
+            Err(e) => return failed_document(e, None),
+        };
+        match self.merge(&todo, document.clone(), &sent.delta).await {
+            Ok(document) => DocumentResult::Merged {
+                document: DocumentSync {
+                    id: todo.id,
+                    version: document.version(),
+                    delta: document.delta(&sent.version),
+                },
+            },
+            Err(e) => failed_document(
+                e,
+                Some(DocumentSync {
+                    id: todo.id,
+                    version: document.version(),
+                    delta: document.delta(&VersionVector::default()),
+                }),
+            ),
+        }
+    }
+
+    /// Todo `id` with its document.
+    async fn document(&self, id: i32) -> Result<(Todo, TextDocument), TodoError> {
+        let todo = self.todo_service.find(id).await?;
+        let document = match self.document_repository.find(id).await? {
+            Some(document) => document,
+            None => {
+                self.document_repository
+                    .merge(id, &genesis(&todo.text))
+                    .await?
+            }
+        };
+        Ok((todo, document))
+    }
+
+    /// Merges `delta` into `document` of `todo` and writes the text it reads then, once the
+    /// user is known to be allowed to.
+    async fn merge(
+        &self,
+        todo: &Todo,
+        mut document: TextDocument,
+        delta: &TextDelta,
+    ) -> Result<TextDocument, TodoError> {
+        if delta.is_empty() {
+            return Ok(document);
+        }
+        document.apply(delta).map_err(TodoError::Validation)?;
+        let text = document.text();
+        if text != todo.text {
+            self.todo_service.check_edit(todo, None).await?;
+            UpdateTodo {
+                text: Some(text),
+                ..Default::default()
+            }
+            .validate()?;
+        }
+        // merged first, so that writing the text it reads makes no op of the server
+        let document = self.document_repository.merge(todo.id, delta).await?;
+        if document.text() != todo.text {
+            let patch = UpdateTodo {
+                text: Some(document.text()),
+                ..Default::default()
+            };
+            self.todo_service.update(todo.id, patch).await?;
+        }
+        Ok(document)
+    }
+
+    async fn exchange_order(&self, sent: OrderSync) -> OrderResult {
+        let list_id = sent.list_id;
+        let order = match self.order(list_id).await {
+            Ok(order) => order,
+            Err(e) => return failed_order(e, None),
+        };
+        match self.merge_order(list_id, order.clone(), &sent.delta).await {
+            Ok(order) => OrderResult::Merged {
+                order: OrderSync {
+                    list_id,
+                    version: order.version(),
+                    delta: order.delta(&sent.version),
+                },
+            },
+            Err(e) => failed_order(
+                e,
+                Some(OrderSync {
+                    list_id,
+                    version: order.version(),
+                    delta: order.delta(&VersionVector::default()),
+                }),
+            ),
+        }
+    }
+
+    /// The order of list `list_id`, once the user is known to see it.
+    async fn order(&self, list_id: i32) -> Result<ListOrder, TodoError> {
+        self.todo_service.check_role(list_id, Role::Viewer).await?;
+        let order = self.document_repository.find_order(list_id).await?;
+        Ok(order.unwrap_or_default())
+    }
+
+    /// Merges `delta` into `order` of list `list_id` and moves its todos into the order it
+    /// reads then, once the user is known to be allowed to. The todos missing from the order go
+    /// in where they are, after those of the client so that none goes in twice.
+    async fn merge_order(
+        &self,
+        list_id: i32,
+        mut order: ListOrder,
+        delta: &ListOrderDelta,
+    ) -> Result<ListOrder, TodoError> {
+        if !delta.is_empty() {
+            order.apply(delta).map_err(TodoError::Validation)?;
+            self.todo_service.check_role(list_id, Role::Editor).await?;
+            order = self.document_repository.merge_order(list_id, delta).await?;
+            write_order(&self.todo_repository, list_id, &order).await?;
+        }
+        let mut placed = order.clone();
+        placed.insert_missing(
+            self.replica,
+            &list_order(&self.todo_repository, list_id).await?,
+        );
+        let delta = placed.delta(&order.version());
+        if delta.is_empty() {
+            return Ok(order);
+        }
+        Ok(self
+            .document_repository
+            .merge_order(list_id, &delta)
+            .await?)
+    }
+}
+
+fn applied(res: Result<Todo, TodoError>) -> PushResult {
+    match res {
+        Ok(todo) => PushResult::Applied { todo },
+        Err(e) => failed(e),
+    }
+}
+
+fn failed(e: TodoError) -> PushResult {
+    match e {
+        TodoError::NotFound(_) => PushResult::Missing,
+        TodoError::Storage(_) | TodoError::Unexpected(_) => PushResult::Retry {
+            message: e.to_string(),
+        },
+        e => PushResult::Rejected {
+            kind: e.kind().to_string(),
+            message: e.to_string(),
+        },
+    }
+}
+
+/// `document` is sent back along with the rejections, there is none when the todo can't be
+/// read.
+fn failed_document(e: TodoError, document: Option<DocumentSync>) -> DocumentResult {
+    match e {
+        TodoError::NotFound(_) => DocumentResult::Missing,
+        TodoError::Storage(_) | TodoError::Unexpected(_) => DocumentResult::Retry {
+            message: e.to_string(),
+        },
+        e => match document {
+            Some(document) => DocumentResult::Rejected {
+                kind: e.kind().to_string(),
+                message: e.to_string(),
+                document,
+            },
+            None => DocumentResult::Missing,
+        },
+    }
+}
+
+/// `order` is sent back along with the rejections, like the document of `failed_document`.
+fn failed_order(e: TodoError, order: Option<OrderSync>) -> OrderResult {
+    match e {
+        TodoError::NotFound(_) => OrderResult::Missing,
+        TodoError::Storage(_) | TodoError::Unexpected(_) => OrderResult::Retry {
+            message: e.to_string(),
+        },
+        e => match order {
+            Some(order) => OrderResult::Rejected {
+                kind: e.kind().to_string(),
+                message: e.to_string(),
+                order,
+            },
+            None => OrderResult::Missing,
+        },
+    }
+}
+
+#[async_trait]
+impl<TR, MR, DR> SyncServiceTrait for SyncService<TR, MR, DR>
+where
+    TR: TodoRepositoryTrait,
+    MR: MemberRepositoryTrait,
+    DR: TodoDocumentRepositoryTrait,
+{
+    async fn pull(&self, cursor: i64, limit: Option<u32>) -> Result<ChangePage, TodoError> {
+        check_limit(limit).map_err(TodoError::Validation)?;
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        // one more change than asked tells whether there is a next page
+        let mut changes = self.todo_repository.changes(cursor, limit + 1).await?;
+        let has_more = changes.len() > limit as usize;
+        changes.truncate(limit as usize);
+        Ok(ChangePage {
+            cursor: changes.last().map_or(cursor, |change| change.seq()),
+            changes,
+            has_more,
+        })
+    }
+
+    async fn push(&self, request: PushRequest) -> Result<PushResponse, TodoError> {
+        let mut results = Vec::with_capacity(request.ops.len());
+        for op in request.ops {
+            results.push(self.apply(op).await);
+        }
+        Ok(PushResponse { results })
+    }
+
+    async fn exchange(&self, request: DocumentRequest) -> Result<DocumentResponse, TodoError> {
+        let mut results = Vec::with_capacity(request.documents.len());
+        for document in request.documents {
+            results.push(self.exchange_document(document).await);
+        }
+        let mut orders = Vec::with_capacity(request.orders.len());
+        for order in request.orders {
+            orders.push(self.exchange_order(order).await);
+        }
+        Ok(DocumentResponse { results, orders })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::members::repository::memory::MemberRepositoryForMemory;
+    use crate::todos::document::memory::TodoDocumentRepositoryForMemory;
+    use crate::todos::document::TodoRepositoryWithDocuments;
+    use crate::todos::model::{CreateTodo, TodoChange};
+    use crate::todos::repository::memory::TodoRepositoryForMemory;
+
+    #[tokio::test]
+    async fn push_reports_each_op() {
+        let repository = TodoRepositoryForMemory::new();
+        let service = SyncService::new(
+            repository.clone(),
+            MemberRepositoryForMemory::new(),
+            TodoDocumentRepositoryForMemory::new(),
+        );
+        let todo = repository
+            .create(CreateTodo::new("kept".to_string()))
+            .await
+            .unwrap();
+        repository
+            .create(CreateTodo::new("other".to_string()))
+            .await
+            .unwrap();
+        let edit = |version| PushOp::Update {
+            id: todo.id,
+            version,
+            patch: UpdateTodo {
+                text: Some("edited".to_string()),
+                ..Default::default()
+            },
+        };
+
+        let res = service
+            .push(PushRequest {
+                ops: vec![
+                    PushOp::Create {
+                        todo: CreateTodo::new("".to_string()),
+                        series_id: None,
+                    },
+                    edit(todo.version),
+                    edit(todo.version),
+                    PushOp::Delete { id: 42 },
+                ],
+            })
+            .await
+            .unwrap();
+        let [rejected, applied, conflict, missing] = &res.results[..] else {
+            panic!("one result for each op, got {res:?}");
+        };
+        assert!(matches!(rejected, PushResult::Rejected { kind, .. } if kind == "Validation"));
+        let PushResult::Applied { todo: edited } = applied else {
+            panic!("expected the update to apply, got {applied:?}");
+        };
+        assert_eq!(edited.text, "edited");
+        assert_eq!(
+            conflict,
+            &PushResult::Conflict {
+                version: edited.version
+            }
+        );
+        assert_eq!(missing, &PushResult::Missing);
+
+        let page = service.pull(0, Some(1)).await.unwrap();
+        assert!(page.has_more);
+        assert!(matches!(&page.changes[..], [TodoChange::Upserted { .. }]));
+        // the edited todo changed last
+        let page = service.pull(page.cursor, None).await.unwrap();
+        assert!(!page.has_more);
+        assert!(
+            matches!(&page.changes[..], [TodoChange::Upserted { todo, .. }] if todo.text == "edited")
+        );
+
+        assert!(matches!(
+            service.pull(0, Some(0)).await,
+            Err(TodoError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn exchange_merges_documents() {
+        let repository = TodoRepositoryForMemory::new();
+        let documents = TodoDocumentRepositoryForMemory::new();
+        let service = SyncService::new(
+            TodoRepositoryWithDocuments::new(repository.clone(), documents.clone()),
+            MemberRepositoryForMemory::new(),
+            documents,
+        );
+        // from before todos had documents
+        let todo = repository
+            .create(CreateTodo::new("buy milk".to_string()))
+            .await
+            .unwrap();
+        let exchange = |id, local: &TextDocument, acked: &VersionVector| {
+            service.exchange(DocumentRequest {
+                documents: vec![DocumentSync {
+                    id,
+                    version: local.version(),
+                    delta: local.delta(acked),
+                }],
+                orders: vec![],
+            })
+        };
+
+        let mut local = TextDocument::new();
+        let res = exchange(todo.id, &local, &local.version()).await.unwrap();
+        let [DocumentResult::Merged { document }] = &res.results[..] else {
+            panic!("expected the document to merge, got {res:?}");
+        };
+        local.apply(&document.delta).unwrap();
+        assert_eq!(local.text(), "buy milk");
+
+        let acked = local.version();
+        local.set_text(1, "buy oat milk");
+        let res = exchange(todo.id, &local, &acked).await.unwrap();
+        assert!(
+            matches!(&res.results[..], [DocumentResult::Merged { document }] if document.delta.is_empty())
+        );
+        assert_eq!(repository.find(todo.id).await.unwrap().text, "buy oat milk");
+
+        let acked = local.version();
+        local.set_text(1, "");
+        let res = exchange(todo.id, &local, &acked).await.unwrap();
+        let [DocumentResult::Rejected { kind, document, .. }] = &res.results[..] else {
+            panic!("expected the document to be rejected, got {res:?}");
+        };
+        assert_eq!(kind, "Validation");
+        let mut server = TextDocument::new();
+        server.apply(&document.delta).unwrap();
+        assert_eq!(server.text(), "buy oat milk");
+
+        let res = exchange(42, &local, &acked).await.unwrap();
+        assert_eq!(res.results, [DocumentResult::Missing]);
+    }
+}
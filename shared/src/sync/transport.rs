@@ -0,0 +1,234 @@
+use axum::async_trait;
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+
+use super::error::SyncError;
+use super::model::{ChangePage, DocumentRequest, DocumentResponse, PushRequest, PushResponse};
+use crate::get_env;
+use crate::lists::model::{CreateList, List, ListCascade};
+
+/// How the engine reaches the `/sync` endpoints of the server, and the `/lists` ones the todos
+/// are placed in.
+#[async_trait]
+pub trait SyncTransportTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    async fn pull(&self, cursor: i64, limit: u32) -> Result<ChangePage, SyncError>;
+    async fn push(&self, request: &PushRequest) -> Result<PushResponse, SyncError>;
+    async fn exchange(&self, request: &DocumentRequest) -> Result<DocumentResponse, SyncError>;
+    /// Every list the todos pulled can be in, the archived ones included.
+    async fn lists(&self) -> Result<Vec<List>, SyncError>;
+    async fn create_list(&self, payload: &CreateList) -> Result<List, SyncError>;
+    /// Moves the todos left in the list to the inbox first.
+    async fn delete_list(&self, id: i32) -> Result<(), SyncError>;
+}
+
+/// The server at `url`, authenticated with a personal API token.
+#[derive(Debug, Clone)]
+pub struct SyncTransportForHttp {
+    client: reqwest::Client,
+    url: String,
+    token: String,
+    workspace: Option<i32>,
+}
+
+impl SyncTransportForHttp {
+    /// `url` is where the server is mounted, the paths of the endpoints are added to it.
+    pub fn new(url: &str, token: String) -> Self {
+        Self {
+            client: crate::network::build_proxy_client().unwrap_or_default(),
+            url: url.trim_end_matches('/').to_string(),
+            token,
+            workspace: None,
+        }
+    }
+
+    /// Syncs with workspace `workspace` instead of the first one of the user.
+    pub fn in_workspace(self, workspace: i32) -> Self {
+        Self {
+            workspace: Some(workspace),
+            ..self
+        }
+    }
+
+    /// The server at `SYNC_URL`, with `SYNC_TOKEN`, a token of the `read_write` scope, and the
+    /// workspace `SYNC_WORKSPACE` when set.
+    pub fn from_env() -> Result<Self, SyncError> {
+        let (url, token) = (get_env("SYNC_URL"), get_env("SYNC_TOKEN"));
+        if url.is_empty() || token.is_empty() {
+            return Err(SyncError::NotConfigured(
+                "SYNC_URL and SYNC_TOKEN must be set".to_string(),
+            ));
+        }
+        let transport = Self::new(&url, token);
+        match get_env("SYNC_WORKSPACE") {
+            workspace if workspace.is_empty() => Ok(transport),
+            workspace => Ok(transport.in_workspace(workspace.parse().map_err(|_| {
+                SyncError::NotConfigured(format!("invalid SYNC_WORKSPACE [{workspace}]"))
+            })?)),
+        }
+    }
+
+    /// A request to `path` of the server.
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .client
+            .request(method, format!("{}{path}", self.url))
+            .bearer_auth(&self.token);
+        match self.workspace {
+            Some(workspace) => request.header("x-workspace-id", workspace),
+            None => request,
+        }
+    }
+
+    /// Fails unless the server answers `request` with a success.
+    async fn send_ok(request: RequestBuilder) -> Result<Response, SyncError> {
+        let res = request.send().await.map_err(remote)?;
+        let status = res.status();
+        if !status.is_success() {
+            let body = res.text().await.unwrap_or_default();
+            return Err(SyncError::Remote(format!("{status}: {body}")));
+        }
+        Ok(res)
+    }
+
+    async fn send<R: DeserializeOwned>(request: RequestBuilder) -> Result<R, SyncError> {
+        Self::send_ok(request).await?.json().await.map_err(remote)
+    }
+}
+
+fn remote(e: reqwest::Error) -> SyncError {
+    SyncError::Remote(e.to_string())
+}
+
+#[async_trait]
+impl SyncTransportTrait for SyncTransportForHttp {
+    async fn pull(&self, cursor: i64, limit: u32) -> Result<ChangePage, SyncError> {
+        Self::send(
+            self.request(Method::GET, "/sync")
+                .query(&[("cursor", cursor), ("limit", limit.into())]),
+        )
+        .await
+    }
+
+    async fn push(&self, request: &PushRequest) -> Result<PushResponse, SyncError> {
+        Self::send(self.request(Method::POST, "/sync").json(request)).await
+    }
+
+    async fn exchange(&self, request: &DocumentRequest) -> Result<DocumentResponse, SyncError> {
+        Self::send(self.request(Method::POST, "/sync/documents").json(request)).await
+    }
+
+    async fn lists(&self) -> Result<Vec<List>, SyncError> {
+        Self::send(
+            self.request(Method::GET, "/lists")
+                .query(&[("archived", true)]),
+        )
+        .await
+    }
+
+    async fn create_list(&self, payload: &CreateList) -> Result<List, SyncError> {
+        Self::send(self.request(Method::POST, "/lists").json(payload)).await
+    }
+
+    async fn delete_list(&self, id: i32) -> Result<(), SyncError> {
+        let request = self
+            .request(Method::DELETE, &format!("/lists/{id}"))
+            .query(&[("todos", ListCascade::MoveToInbox)]);
+        Self::send_ok(request).await.map(drop)
+    }
+}
+
+#[cfg(any(test, feature = "memory"))]
+pub mod memory {
+    use super::*;
+    use crate::lists::repository::ListRepositoryTrait;
+    use crate::lists::service::{ListService, ListServiceTrait};
+    use crate::members::repository::MemberRepositoryTrait;
+    use crate::sync::service::{SyncService, SyncServiceTrait};
+    use crate::todos::document::TodoDocumentRepositoryTrait;
+    use crate::todos::repository::TodoRepositoryTrait;
+
+    /// A `SyncService` and a `ListService` in the same process, the server of the tests.
+    #[derive(Debug, Clone)]
+    pub struct SyncTransportForService<TR, MR, DR, LR>
+    where
+        TR: TodoRepositoryTrait,
+        MR: MemberRepositoryTrait,
+        DR: TodoDocumentRepositoryTrait,
+        LR: ListRepositoryTrait,
+    {
+        service: SyncService<TR, MR, DR>,
+        list_service: ListService<LR, TR, MR>,
+    }
+
+    impl<TR, MR, DR, LR> SyncTransportForService<TR, MR, DR, LR>
+    where
+        TR: TodoRepositoryTrait,
+        MR: MemberRepositoryTrait,
+        DR: TodoDocumentRepositoryTrait,
+        LR: ListRepositoryTrait,
+    {
+        pub fn new(
+            service: SyncService<TR, MR, DR>,
+            list_service: ListService<LR, TR, MR>,
+        ) -> Self {
+            Self {
+                service,
+                list_service,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl<TR, MR, DR, LR> SyncTransportTrait for SyncTransportForService<TR, MR, DR, LR>
+    where
+        TR: TodoRepositoryTrait,
+        MR: MemberRepositoryTrait,
+        DR: TodoDocumentRepositoryTrait,
+        LR: ListRepositoryTrait,
+    {
+        async fn pull(&self, cursor: i64, limit: u32) -> Result<ChangePage, SyncError> {
+            self.service
+                .pull(cursor, Some(limit))
+                .await
+                .map_err(|e| SyncError::Remote(e.to_string()))
+        }
+
+        async fn push(&self, request: &PushRequest) -> Result<PushResponse, SyncError> {
+            self.service
+                .push(request.clone())
+                .await
+                .map_err(|e| SyncError::Remote(e.to_string()))
+        }
+
+        async fn exchange(&self, request: &DocumentRequest) -> Result<DocumentResponse, SyncError> {
+            self.service
+                .exchange(request.clone())
+                .await
+                .map_err(|e| SyncError::Remote(e.to_string()))
+        }
+
+        async fn lists(&self) -> Result<Vec<List>, SyncError> {
+            self.list_service
+                .find_all(true)
+                .await
+                .map_err(|e| SyncError::Remote(e.to_string()))
+        }
+
+        async fn create_list(&self, payload: &CreateList) -> Result<List, SyncError> {
+            self.list_service
+                .create(payload.clone())
+                .await
+                .map_err(|e| SyncError::Remote(e.to_string()))
+        }
+
+        async fn delete_list(&self, id: i32) -> Result<(), SyncError> {
+            self.list_service
+                .delete(id, ListCascade::MoveToInbox)
+                .await
+                .map_err(|e| SyncError::Remote(e.to_string()))
+        }
+    }
+}
@@ -0,0 +1,847 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// Identifies a replica editing documents, a device or the server.
+pub type ReplicaId = u64;
+
+/// The replica of the content documents start with, see `Rga::genesis`.
+pub const GENESIS: ReplicaId = 0;
+
+/// A random replica id, for a store that starts editing documents.
+pub fn new_replica() -> ReplicaId {
+    loop {
+        let replica = OsRng.next_u64();
+        if replica != GENESIS {
+            return replica;
+        }
+    }
+}
+
+/// Identifies an op: a Lamport timestamp, made unique by the replica that made the op. Dots
+/// are ordered by counter, then replica, which is the same order on every replica.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "(u64, ReplicaId)", into = "(u64, ReplicaId)")]
+pub struct Dot {
+    pub counter: u64,
+    pub replica: ReplicaId,
+}
+
+impl From<(u64, ReplicaId)> for Dot {
+    fn from((counter, replica): (u64, ReplicaId)) -> Self {
+        Self { counter, replica }
+    }
+}
+
+impl From<Dot> for (u64, ReplicaId) {
+    fn from(dot: Dot) -> Self {
+        (dot.counter, dot.replica)
+    }
+}
+
+/// The latest counter seen from each replica. A replica's ops always travel together with its
+/// older ones, so this tells every op a document has.
+///
+/// It serializes as `[replica, counter]` pairs, the keys of JSON objects being strings.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<(ReplicaId, u64)>", into = "Vec<(ReplicaId, u64)>")]
+pub struct VersionVector(BTreeMap<ReplicaId, u64>);
+
+impl From<Vec<(ReplicaId, u64)>> for VersionVector {
+    fn from(counters: Vec<(ReplicaId, u64)>) -> Self {
+        Self(counters.into_iter().collect())
+    }
+}
+
+impl From<VersionVector> for Vec<(ReplicaId, u64)> {
+    fn from(version: VersionVector) -> Self {
+        version.0.into_iter().collect()
+    }
+}
+
+impl VersionVector {
+    pub fn contains(&self, dot: Dot) -> bool {
+        self.0
+            .get(&dot.replica)
+            .is_some_and(|&counter| dot.counter <= counter)
+    }
+
+    fn observe(&mut self, dot: Dot) {
+        let counter = self.0.entry(dot.replica).or_default();
+        *counter = (*counter).max(dot.counter);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Node<T> {
+    id: Dot,
+    /// The node it was inserted after, `None` at the start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    origin: Option<Dot>,
+    value: T,
+    /// The latest op that deleted it. Deleted nodes stay, later inserts may come after them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deleted: Option<Dot>,
+}
+
+/// A replicated growable array: a sequence that replicas edit on their own and merge in any
+/// order to the same result.
+///
+/// A node inserted after `origin` goes right after it, behind the nodes inserted after the same
+/// origin with a greater dot, and theirs. Concurrent inserts at the same place hence keep their
+/// runs together, the one made last first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rga<T> {
+    /// In document order, deleted ones included.
+    nodes: Vec<Node<T>>,
+}
+
+/// The ops of an `Rga` missing from a version, see `Rga::delta`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RgaDelta<T> {
+    /// New nodes in document order, so that origins come before the nodes inserted after them.
+    nodes: Vec<Node<T>>,
+    /// Deletions of nodes the version has, as node and deleting op.
+    deletes: Vec<(Dot, Dot)>,
+}
+
+impl<T> Default for Rga<T> {
+    fn default() -> Self {
+        Self { nodes: Vec::new() }
+    }
+}
+
+impl<T> Default for RgaDelta<T> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            deletes: Vec::new(),
+        }
+    }
+}
+
+impl<T> RgaDelta<T> {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.deletes.is_empty()
+    }
+
+    /// The values of the nodes it inserts.
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.nodes.iter().map(|node| &node.value)
+    }
+
+    /// The same ops with their values mapped by `f`, like the ids of items from one store to
+    /// another.
+    pub fn map<U>(&self, mut f: impl FnMut(&T) -> U) -> RgaDelta<U> {
+        RgaDelta {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|node| Node {
+                    id: node.id,
+                    origin: node.origin,
+                    value: f(&node.value),
+                    deleted: node.deleted,
+                })
+                .collect(),
+            deletes: self.deletes.clone(),
+        }
+    }
+}
+
+impl<T: Clone> Rga<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `values` inserted in order by `GENESIS` with counters from 1, so that replicas creating a
+    /// document from the same values independently have the very same ops.
+    pub fn genesis(values: impl IntoIterator<Item = T>) -> Self {
+        let mut rga = Self::new();
+        rga.splice(GENESIS, 0, 0, values);
+        rga
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// The values that aren't deleted, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.nodes
+            .iter()
+            .filter(|node| node.deleted.is_none())
+            .map(|node| &node.value)
+    }
+
+    /// The values inserted by `GENESIS`, deleted ones included, in order.
+    pub fn genesis_values(&self) -> impl Iterator<Item = &T> {
+        self.nodes
+            .iter()
+            .filter(|node| node.id.replica == GENESIS)
+            .map(|node| &node.value)
+    }
+
+    pub fn insert(&mut self, replica: ReplicaId, index: usize, value: T) {
+        self.splice(replica, index, 0, [value]);
+    }
+
+    pub fn remove(&mut self, replica: ReplicaId, index: usize) {
+        self.splice(replica, index, 1, []);
+    }
+
+    /// Deletes the `remove` values from `index` and inserts `values` there, as ops of
+    /// `replica`.
+    ///
+    /// # Panics
+    ///
+    /// When `index + remove` is greater than the length.
+    pub fn splice(
+        &mut self,
+        replica: ReplicaId,
+        index: usize,
+        remove: usize,
+        values: impl IntoIterator<Item = T>,
+    ) {
+        let visible: Vec<usize> = (0..self.nodes.len())
+            .filter(|&at| self.nodes[at].deleted.is_none())
+            .collect();
+        assert!(
+            index + remove <= visible.len(),
+            "splice of {remove} values at {index} is out of {} values",
+            visible.len()
+        );
+        let mut counter = self.clock();
+        let mut next = || {
+            counter += 1;
+            Dot { counter, replica }
+        };
+        for &at in &visible[index..index + remove] {
+            self.nodes[at].deleted = Some(next());
+        }
+        // the newest dots go right after their origin
+        let (mut origin, mut at) = match index.checked_sub(1) {
+            Some(before) => (Some(self.nodes[visible[before]].id), visible[before] + 1),
+            None => (None, 0),
+        };
+        for value in values {
+            let id = next();
+            self.nodes.insert(
+                at,
+                Node {
+                    id,
+                    origin,
+                    value,
+                    deleted: None,
+                },
+            );
+            origin = Some(id);
+            at += 1;
+        }
+    }
+
+    /// The greatest counter of the ops applied, new ops get a greater one.
+    fn clock(&self) -> u64 {
+        self.nodes
+            .iter()
+            .flat_map(|node| [Some(node.id), node.deleted])
+            .flatten()
+            .map(|dot| dot.counter)
+            .max()
+            .unwrap_or_default()
+    }
+
+    pub fn version(&self) -> VersionVector {
+        let mut version = VersionVector::default();
+        for node in &self.nodes {
+            version.observe(node.id);
+            if let Some(deleted) = node.deleted {
+                version.observe(deleted);
+            }
+        }
+        version
+    }
+
+    /// The ops a replica at `version` is missing.
+    pub fn delta(&self, version: &VersionVector) -> RgaDelta<T> {
+        let mut delta = RgaDelta::default();
+        for node in &self.nodes {
+            if !version.contains(node.id) {
+                delta.nodes.push(node.clone());
+            } else if let Some(deleted) = node.deleted.filter(|&dot| !version.contains(dot)) {
+                delta.deletes.push((node.id, deleted));
+            }
+        }
+        delta
+    }
+
+    /// Applies the ops of `delta`, those already applied are ignored. Fails without any change
+    /// when `delta` refers to a node that neither has.
+    pub fn apply(&mut self, delta: &RgaDelta<T>) -> Result<(), String> {
+        let mut known: BTreeSet<Dot> = self.nodes.iter().map(|node| node.id).collect();
+        for node in &delta.nodes {
+            if let Some(origin) = node.origin.filter(|origin| !known.contains(origin)) {
+                return Err(format!("unknown origin {origin:?} of {:?}", node.id));
+            }
+            known.insert(node.id);
+        }
+        if let Some((id, _)) = delta.deletes.iter().find(|(id, _)| !known.contains(id)) {
+            return Err(format!("deletion of unknown {id:?}"));
+        }
+
+        for node in &delta.nodes {
+            self.integrate(node.clone());
+        }
+        for &(id, deleted) in &delta.deletes {
+            if let Some(node) = self.nodes.iter_mut().find(|node| node.id == id) {
+                node.deleted = node.deleted.max(Some(deleted));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies all the ops of `other`.
+    pub fn merge(&mut self, other: &Self) -> Result<(), String> {
+        self.apply(&other.delta(&self.version()))
+    }
+
+    fn integrate(&mut self, node: Node<T>) {
+        if let Some(existing) = self.nodes.iter_mut().find(|n| n.id == node.id) {
+            existing.deleted = existing.deleted.max(node.deleted);
+            return;
+        }
+        let mut at = match node.origin {
+            Some(origin) => self.nodes.iter().position(|n| n.id == origin).unwrap() + 1,
+            None => 0,
+        };
+        // whatever was inserted after the origin with a greater dot comes first: siblings made
+        // later, and the nodes inserted after them, which are greater still
+        while at < self.nodes.len() && self.nodes[at].id > node.id {
+            at += 1;
+        }
+        self.nodes.insert(at, node);
+    }
+}
+
+/// The text of a todo, edited character by character.
+pub type TextDocument = Rga<char>;
+
+/// Ops missing from a version of a `TextDocument`.
+pub type TextDelta = RgaDelta<char>;
+
+impl Rga<char> {
+    pub fn text(&self) -> String {
+        self.iter().collect()
+    }
+
+    /// The text the document was created with, see `Rga::genesis`.
+    pub fn genesis_text(&self) -> String {
+        self.genesis_values().collect()
+    }
+
+    /// Edits the text into `text` as ops of `replica`, replacing what lies between the prefix
+    /// and the suffix both texts have in common.
+    pub fn set_text(&mut self, replica: ReplicaId, text: &str) {
+        let old: Vec<char> = self.iter().copied().collect();
+        let new: Vec<char> = text.chars().collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        if prefix + suffix == old.len() && old.len() == new.len() {
+            return;
+        }
+        self.splice(
+            replica,
+            prefix,
+            old.len() - prefix - suffix,
+            new[prefix..new.len() - suffix].iter().copied(),
+        );
+    }
+}
+
+/// The order of the todos of a list, by id.
+pub type ListOrder = OrderDocument<i32>;
+
+/// The ops of a `ListOrder`.
+pub type ListOrderDelta = RgaDelta<i32>;
+
+/// The order of items, like the todos of a list, as a list CRDT. Moving an item deletes where
+/// it was and inserts it at its new place, so an item moved concurrently on two replicas shows
+/// up twice after a merge: it stays at the first place and the next move or removal clears the
+/// other one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderDocument<T> {
+    order: Rga<T>,
+}
+
+impl<T> Default for OrderDocument<T> {
+    fn default() -> Self {
+        Self {
+            order: Rga::default(),
+        }
+    }
+}
+
+impl<T: Clone + Ord> OrderDocument<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The items in order, each once.
+    pub fn items(&self) -> Vec<&T> {
+        let mut seen = BTreeSet::new();
+        self.order
+            .iter()
+            .filter(|item| seen.insert(*item))
+            .collect()
+    }
+
+    /// Puts `item` right before `before`, or last when `before` is `None` or isn't there.
+    pub fn move_before(&mut self, replica: ReplicaId, item: T, before: Option<&T>) {
+        self.remove(replica, &item);
+        let index = before
+            .and_then(|before| self.order.iter().position(|other| other == before))
+            .unwrap_or_else(|| self.order.len());
+        self.order.insert(replica, index, item);
+    }
+
+    /// Moves the items so that they are in the order of `order`, as `moves` does. Those it
+    /// lacks stay where they are, those missing here are inserted.
+    pub fn set_order(&mut self, replica: ReplicaId, order: &[T]) {
+        let items = Vec::from_iter(self.items().into_iter().cloned());
+        for (item, before) in moves(&items, order) {
+            self.move_before(replica, item, before.as_ref());
+        }
+    }
+
+    /// Inserts the items of `order` missing here, each right before the item following it
+    /// there, so that the others don't move.
+    pub fn insert_missing(&mut self, replica: ReplicaId, order: &[T]) {
+        let here = BTreeSet::from_iter(self.items().into_iter().cloned());
+        for (at, item) in order.iter().enumerate().rev() {
+            if !here.contains(item) {
+                self.move_before(replica, item.clone(), order.get(at + 1));
+            }
+        }
+    }
+
+    /// Whether `item` is followed by the same item here as in `order`, among the items both
+    /// have, like after `set_order`.
+    pub fn is_placed(&self, order: &[T], item: &T) -> bool {
+        let there = BTreeSet::from_iter(order);
+        let here = Vec::from_iter(self.items().into_iter().filter(|i| there.contains(i)));
+        let both = BTreeSet::from_iter(here.iter().copied());
+        let there = Vec::from_iter(order.iter().filter(|i| both.contains(i)));
+        let next = |items: &[&T]| {
+            let at = items.iter().position(|i| *i == item)?;
+            Some(items.get(at + 1).map(|next| (*next).clone()))
+        };
+        matches!((next(&here), next(&there)), (Some(a), Some(b)) if a == b)
+    }
+
+    pub fn remove(&mut self, replica: ReplicaId, item: &T) {
+        loop {
+            let Some(index) = self.order.iter().position(|other| other == item) else {
+                return;
+            };
+            self.order.remove(replica, index);
+        }
+    }
+
+    pub fn version(&self) -> VersionVector {
+        self.order.version()
+    }
+
+    pub fn delta(&self, version: &VersionVector) -> RgaDelta<T> {
+        self.order.delta(version)
+    }
+
+    pub fn apply(&mut self, delta: &RgaDelta<T>) -> Result<(), String> {
+        self.order.apply(delta)
+    }
+
+    pub fn merge(&mut self, other: &Self) -> Result<(), String> {
+        self.order.merge(&other.order)
+    }
+}
+
+/// The moves turning `from` into `to`, each putting an item right before another one or last.
+/// The longest run of items already in order stays, the others are moved from the last, so
+/// that each one goes before an item that is at its place already. Items of `to` that `from`
+/// lacks are moved in too, those `to` lacks aren't moved.
+pub fn moves<T: Clone + Ord>(from: &[T], to: &[T]) -> Vec<(T, Option<T>)> {
+    let rank = BTreeMap::from_iter(to.iter().enumerate().map(|(rank, item)| (item, rank)));
+    let ranks = Vec::from_iter(from.iter().filter_map(|item| rank.get(item).copied()));
+    let staying = BTreeSet::from_iter(longest_run(&ranks).into_iter().map(|i| ranks[i]));
+    let mut moves = Vec::new();
+    for (rank, item) in to.iter().enumerate().rev() {
+        if !staying.contains(&rank) {
+            moves.push((item.clone(), to.get(rank + 1).cloned()));
+        }
+    }
+    moves
+}
+
+/// Indexes of a longest strictly increasing subsequence of `values`.
+fn longest_run(values: &[usize]) -> Vec<usize> {
+    // the index of the least last value of the runs of each length, and what comes before
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; values.len()];
+    for (i, &value) in values.iter().enumerate() {
+        let length = tails.partition_point(|&tail| values[tail] < value);
+        if length > 0 {
+            previous[i] = Some(tails[length - 1]);
+        }
+        match tails.get_mut(length) {
+            Some(tail) => *tail = i,
+            None => tails.push(i),
+        }
+    }
+    let mut run = Vec::new();
+    let mut next = tails.last().copied();
+    while let Some(i) = next {
+        run.push(i);
+        next = previous[i];
+    }
+    run.reverse();
+    run
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+
+    const REPLICAS: usize = 3;
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Insert {
+            at: usize,
+            text: String,
+        },
+        Remove {
+            at: usize,
+            len: usize,
+        },
+        SetText(String),
+        /// Sends the delta of replica `from` to replica `to`.
+        Sync {
+            from: usize,
+            to: usize,
+        },
+    }
+
+    fn op() -> impl Strategy<Value = (usize, Op)> {
+        let op = prop_oneof![
+            (any::<usize>(), "[a-d]{1,3}").prop_map(|(at, text)| Op::Insert { at, text }),
+            (any::<usize>(), 1..3usize).prop_map(|(at, len)| Op::Remove { at, len }),
+            "[a-d ]{0,6}".prop_map(Op::SetText),
+            (0..REPLICAS, 0..REPLICAS).prop_map(|(from, to)| Op::Sync { from, to }),
+        ];
+        (0..REPLICAS, op)
+    }
+
+    fn replica(index: usize) -> ReplicaId {
+        index as ReplicaId + 1
+    }
+
+    /// Runs `ops` on replicas of a document created from `base`.
+    fn run(base: &str, ops: &[(usize, Op)]) -> Vec<TextDocument> {
+        let mut docs = vec![TextDocument::genesis(base.chars()); REPLICAS];
+        for (index, op) in ops {
+            let doc = &mut docs[*index];
+            match op {
+                Op::Insert { at, text } => {
+                    let at = at % (doc.len() + 1);
+                    doc.splice(replica(*index), at, 0, text.chars());
+                }
+                Op::Remove { at, len } if !doc.is_empty() => {
+                    let at = at % doc.len();
+                    let len = (*len).min(doc.len() - at);
+                    doc.splice(replica(*index), at, len, []);
+                }
+                Op::Remove { .. } => {}
+                Op::SetText(text) => doc.set_text(replica(*index), text),
+                Op::Sync { from, to } => {
+                    let delta = docs[*from].delta(&docs[*to].version());
+                    docs[*to].apply(&delta).unwrap();
+                }
+            }
+        }
+        docs
+    }
+
+    fn merged(a: &TextDocument, b: &TextDocument) -> TextDocument {
+        let mut merged = a.clone();
+        merged.merge(b).unwrap();
+        merged
+    }
+
+    #[derive(Debug, Clone)]
+    enum OrderOp {
+        Move {
+            item: u8,
+            before: Option<u8>,
+        },
+        Remove(u8),
+        /// Sends the delta of replica `from` to replica `to`.
+        Sync {
+            from: usize,
+            to: usize,
+        },
+    }
+
+    fn order_op() -> impl Strategy<Value = (usize, OrderOp)> {
+        let op = prop_oneof![
+            (0..6u8, prop::option::of(0..6u8))
+                .prop_map(|(item, before)| OrderOp::Move { item, before }),
+            (0..6u8).prop_map(OrderOp::Remove),
+            (0..REPLICAS, 0..REPLICAS).prop_map(|(from, to)| OrderOp::Sync { from, to }),
+        ];
+        (0..REPLICAS, op)
+    }
+
+    /// Some of the items, in any order.
+    fn order() -> impl Strategy<Value = Vec<u8>> {
+        prop::sample::subsequence(Vec::from_iter(0..8u8), 0..8).prop_shuffle()
+    }
+
+    /// Runs `ops` on replicas of an empty order.
+    fn run_orders(ops: &[(usize, OrderOp)]) -> Vec<OrderDocument<u8>> {
+        let mut orders = vec![OrderDocument::new(); REPLICAS];
+        for (index, op) in ops {
+            let order = &mut orders[*index];
+            match op {
+                OrderOp::Move { item, before } => {
+                    order.move_before(replica(*index), *item, before.as_ref())
+                }
+                OrderOp::Remove(item) => order.remove(replica(*index), item),
+                OrderOp::Sync { from, to } => {
+                    let delta = orders[*from].delta(&orders[*to].version());
+                    orders[*to].apply(&delta).unwrap();
+                }
+            }
+        }
+        orders
+    }
+
+    fn merged_order(a: &OrderDocument<u8>, b: &OrderDocument<u8>) -> OrderDocument<u8> {
+        let mut merged = a.clone();
+        merged.merge(b).unwrap();
+        merged
+    }
+
+    #[test]
+    fn edits_text() {
+        let mut doc = TextDocument::genesis("buy milk".chars());
+        doc.set_text(1, "buy oat milk");
+        assert_eq!(doc.text(), "buy oat milk");
+        doc.set_text(1, "milk");
+        assert_eq!(doc.text(), "milk");
+        doc.set_text(1, "");
+        assert_eq!(doc.text(), "");
+        assert_eq!(doc.genesis_text(), "buy milk");
+
+        let version = doc.version();
+        doc.set_text(1, "");
+        assert!(doc.delta(&version).is_empty());
+    }
+
+    #[test]
+    fn keeps_concurrent_inserts_together() {
+        let base = TextDocument::genesis("todo".chars());
+        let (mut alice, mut bob) = (base.clone(), base.clone());
+        alice.set_text(1, "todo: milk");
+        bob.set_text(2, "todo: eggs");
+        let (a, b) = (merged(&alice, &bob), merged(&bob, &alice));
+        assert_eq!(a, b);
+        assert!(["todo: milk: eggs", "todo: eggs: milk"].contains(&a.text().as_str()));
+    }
+
+    #[test]
+    fn rejects_deltas_missing_their_origin() {
+        let mut doc = TextDocument::genesis("ab".chars());
+        let version = doc.version();
+        doc.set_text(1, "abc");
+        let middle = doc.clone();
+        doc.set_text(1, "abcd");
+        let delta = doc.delta(&middle.version());
+
+        let mut stale = TextDocument::genesis("ab".chars());
+        assert_eq!(stale.version(), version);
+        assert!(stale.apply(&delta).is_err());
+        assert_eq!(stale.text(), "ab");
+    }
+
+    #[test]
+    fn orders_items() {
+        let mut order = OrderDocument::new();
+        for item in [1, 2, 3] {
+            order.move_before(1, item, None);
+        }
+        order.move_before(1, 3, Some(&1));
+        assert_eq!(order.items(), [&3, &1, &2]);
+
+        // both move 2, the first place wins until it moves again
+        let (mut alice, mut bob) = (order.clone(), order.clone());
+        alice.move_before(1, 2, Some(&3));
+        bob.move_before(2, 2, Some(&1));
+        alice.merge(&bob).unwrap();
+        bob.merge(&alice).unwrap();
+        assert_eq!(alice, bob);
+        assert_eq!(alice.items().len(), 3);
+        alice.move_before(1, 2, None);
+        assert_eq!(alice.items(), [&3, &1, &2]);
+        alice.remove(1, &1);
+        assert_eq!(alice.items(), [&3, &2]);
+
+        // the item that moved, and only it
+        let version = alice.version();
+        alice.set_order(1, &[2, 3, 4]);
+        assert_eq!(alice.items(), [&2, &3, &4]);
+        assert!(alice.is_placed(&[2, 3, 4], &2));
+        assert!(!alice.is_placed(&[3, 2, 4], &2));
+        assert_eq!(alice.delta(&version).values().count(), 2);
+
+        // the missing items, and only them
+        let version = alice.version();
+        alice.insert_missing(1, &[5, 4, 6, 3, 2, 7]);
+        assert_eq!(alice.items(), [&2, &6, &3, &5, &4, &7]);
+        assert_eq!(alice.delta(&version).values().count(), 3);
+    }
+
+    proptest! {
+        #[test]
+        fn edits_match_a_string(base in "[a-d]{0,6}", texts in prop::collection::vec("[a-d ]{0,8}", 1..8)) {
+            let mut doc = TextDocument::genesis(base.chars());
+            for text in &texts {
+                doc.set_text(1, text);
+                prop_assert_eq!(doc.text(), text.as_str());
+            }
+        }
+
+        #[test]
+        fn replicas_converge(base in "[a-d]{0,6}", ops in prop::collection::vec(op(), 0..40)) {
+            let mut docs = run(&base, &ops);
+            // every replica sends its delta to every other one, twice so that what one got
+            // from another is passed on
+            for _ in 0..2 {
+                for from in 0..REPLICAS {
+                    for to in 0..REPLICAS {
+                        let delta = docs[from].delta(&docs[to].version());
+                        docs[to].apply(&delta).unwrap();
+                    }
+                }
+            }
+            for doc in &docs[1..] {
+                prop_assert_eq!(doc, &docs[0]);
+                prop_assert_eq!(doc.version(), docs[0].version());
+            }
+        }
+
+        #[test]
+        fn merges_commute(base in "[a-d]{0,6}", ops in prop::collection::vec(op(), 0..40)) {
+            let docs = run(&base, &ops);
+            let (a, b, c) = (&docs[0], &docs[1], &docs[2]);
+            prop_assert_eq!(merged(a, b), merged(b, a));
+            prop_assert_eq!(merged(&merged(a, b), c), merged(a, &merged(b, c)));
+            prop_assert_eq!(&merged(a, a), a);
+            prop_assert_eq!(&merged(&merged(a, b), b), &merged(a, b));
+        }
+
+        #[test]
+        fn deltas_match_merges(base in "[a-d]{0,6}", ops in prop::collection::vec(op(), 0..40)) {
+            let docs = run(&base, &ops);
+            let (a, b) = (&docs[0], &docs[1]);
+            let mut synced = b.clone();
+            synced.apply(&a.delta(&b.version())).unwrap();
+            prop_assert_eq!(&synced, &merged(b, a));
+            // a delta survives the trip through JSON
+            let json = serde_json::to_string(&a.delta(&VersionVector::default())).unwrap();
+            let mut copy = TextDocument::new();
+            copy.apply(&serde_json::from_str(&json).unwrap()).unwrap();
+            prop_assert_eq!(&copy, a);
+        }
+
+        #[test]
+        fn orders_converge(ops in prop::collection::vec(order_op(), 0..40)) {
+            let mut orders = run_orders(&ops);
+            for _ in 0..2 {
+                for from in 0..REPLICAS {
+                    for to in 0..REPLICAS {
+                        let delta = orders[from].delta(&orders[to].version());
+                        orders[to].apply(&delta).unwrap();
+                    }
+                }
+            }
+            for order in &orders[1..] {
+                prop_assert_eq!(order, &orders[0]);
+                prop_assert_eq!(order.version(), orders[0].version());
+            }
+            let items = orders[0].items();
+            prop_assert_eq!(BTreeSet::from_iter(&items).len(), items.len());
+        }
+
+        #[test]
+        fn order_merges_commute(ops in prop::collection::vec(order_op(), 0..40)) {
+            let orders = run_orders(&ops);
+            let (a, b, c) = (&orders[0], &orders[1], &orders[2]);
+            prop_assert_eq!(merged_order(a, b), merged_order(b, a));
+            prop_assert_eq!(
+                merged_order(&merged_order(a, b), c),
+                merged_order(a, &merged_order(b, c))
+            );
+            prop_assert_eq!(&merged_order(a, a), a);
+            prop_assert_eq!(&merged_order(&merged_order(a, b), b), &merged_order(a, b));
+        }
+
+        #[test]
+        fn order_deltas_match_merges(ops in prop::collection::vec(order_op(), 0..40)) {
+            let orders = run_orders(&ops);
+            let (a, b) = (&orders[0], &orders[1]);
+            let mut synced = b.clone();
+            synced.apply(&a.delta(&b.version())).unwrap();
+            prop_assert_eq!(&synced, &merged_order(b, a));
+            let json = serde_json::to_string(&a.delta(&VersionVector::default())).unwrap();
+            let mut copy = OrderDocument::new();
+            copy.apply(&serde_json::from_str(&json).unwrap()).unwrap();
+            prop_assert_eq!(&copy, a);
+        }
+
+        #[test]
+        fn orders_match_an_order(orders in prop::collection::vec(order(), 1..8)) {
+            let mut document = OrderDocument::new();
+            for order in &orders {
+                document.set_order(1, order);
+                let items = Vec::from_iter(
+                    document.items().into_iter().copied().filter(|item| order.contains(item)),
+                );
+                prop_assert_eq!(&items, order);
+            }
+        }
+
+        #[test]
+        fn moves_turn_an_order_into_another(from in order(), to in order()) {
+            let mut moved = from.clone();
+            for (item, before) in moves(&from, &to) {
+                moved.retain(|other| *other != item);
+                let at = before
+                    .and_then(|before| moved.iter().position(|other| *other == before))
+                    .unwrap_or(moved.len());
+                moved.insert(at, item);
+            }
+            moved.retain(|item| to.contains(item));
+            prop_assert_eq!(moved, to);
+        }
+    }
+}
@@ -0,0 +1,559 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+
+use super::crdt::{
+    moves, new_replica, ListOrder, ListOrderDelta, ReplicaId, TextDelta, TextDocument,
+    VersionVector,
+};
+use super::model::{
+    CreateTodo, Todo, TodoChange, TodoQuery, TodoSearchHit, TodoSeries, TodoSort, UpdateTodo,
+};
+use super::repository::{RepositoryError, TodoRepositoryTrait};
+
+#[cfg(any(test, feature = "memory"))]
+pub mod memory;
+pub mod sled;
+pub mod sqlite;
+
+/// Where the text documents of todos are kept, next to the todos, along with the order documents
+/// of lists. A document is created by the first merge into it and goes away with its todo or
+/// list.
+#[async_trait]
+pub trait TodoDocumentRepositoryTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    async fn find(&self, id: i32) -> anyhow::Result<Option<TextDocument>>;
+    /// Applies `delta` to the document of todo `id` and returns it. Concurrent merges into the
+    /// same document are applied one after the other. Fails with `Invalid` when `delta` doesn't
+    /// apply to the document.
+    async fn merge(&self, id: i32, delta: &TextDelta) -> anyhow::Result<TextDocument>;
+    /// Ids without a document are skipped.
+    async fn delete(&self, ids: &[i32]) -> anyhow::Result<()>;
+    /// The order of the todos of list `list_id`, which only holds those moved once.
+    async fn find_order(&self, list_id: i32) -> anyhow::Result<Option<ListOrder>>;
+    /// Applies `delta` to the order of list `list_id` and returns it, as `merge` does.
+    async fn merge_order(&self, list_id: i32, delta: &ListOrderDelta) -> anyhow::Result<ListOrder>;
+    /// Lists without an order are skipped.
+    async fn delete_orders(&self, list_ids: &[i32]) -> anyhow::Result<()>;
+}
+
+/// The ops of a document created from `text`, the same on every replica.
+pub fn genesis(text: &str) -> TextDelta {
+    TextDocument::genesis(text.chars()).delta(&VersionVector::default())
+}
+
+/// Ids of the todos of list `list_id`, in their manual order.
+pub async fn list_order(
+    todos: &impl TodoRepositoryTrait,
+    list_id: i32,
+) -> anyhow::Result<Vec<i32>> {
+    let query = TodoQuery {
+        sort: TodoSort::Position,
+        list_id: Some(list_id),
+        ..Default::default()
+    };
+    let todos = todos.all(&query).await?;
+    Ok(todos.into_iter().map(|todo| todo.id).collect())
+}
+
+/// Moves the todos of list `list_id` into the order of `order`, those it lacks stay where they
+/// are. Returns how many were moved.
+pub async fn write_order(
+    todos: &impl TodoRepositoryTrait,
+    list_id: i32,
+    order: &ListOrder,
+) -> anyhow::Result<usize> {
+    let from = list_order(todos, list_id).await?;
+    let to = Vec::from_iter(
+        order
+            .items()
+            .into_iter()
+            .copied()
+            .filter(|id| from.contains(id)),
+    );
+    let moves = moves(&from, &to);
+    for &(id, before) in &moves {
+        todos.move_before(id, before).await?;
+    }
+    Ok(moves.len())
+}
+
+/// Documents as JSONB, in the database of the todos.
+#[derive(Debug, Clone)]
+pub struct TodoDocumentRepositoryForDb {
+    pool: PgPool,
+}
+
+impl TodoDocumentRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        TodoDocumentRepositoryForDb { pool }
+    }
+}
+
+#[async_trait]
+impl TodoDocumentRepositoryTrait for TodoDocumentRepositoryForDb {
+    async fn find(&self, id: i32) -> anyhow::Result<Option<TextDocument>> {
+        let document = sqlx::query_scalar::<_, Json<TextDocument>>(
+            "select document from todo_documents where todo_id=$1",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+        Ok(document.map(|Json(document)| document))
+    }
+
+    async fn merge(&self, id: i32, delta: &TextDelta) -> anyhow::Result<TextDocument> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        // the row to lock, for the merges of a document that doesn't exist yet too
+        sqlx::query(
+            "insert into todo_documents (todo_id, document) values ($1, $2) on conflict (todo_id) do nothing",
+        )
+        .bind(id)
+        .bind(Json(TextDocument::new()))
+        .execute(&mut *tx)
+        .await
+        .map_err(RepositoryError::from)?;
+        let Json(mut document) = sqlx::query_scalar::<_, Json<TextDocument>>(
+            "select document from todo_documents where todo_id=$1 for update",
+        )
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(RepositoryError::from)?;
+        document.apply(delta).map_err(RepositoryError::Invalid)?;
+        sqlx::query("update todo_documents set document=$2 where todo_id=$1")
+            .bind(id)
+            .bind(Json(&document))
+            .execute(&mut *tx)
+            .await
+            .map_err(RepositoryError::from)?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+        Ok(document)
+    }
+
+    async fn delete(&self, ids: &[i32]) -> anyhow::Result<()> {
+        sqlx::query("delete from todo_documents where todo_id = any($1)")
+            .bind(ids)
+            .execute(&self.pool)
+            .await
+            .map_err(RepositoryError::from)?;
+        Ok(())
+    }
+
+    async fn find_order(&self, list_id: i32) -> anyhow::Result<Option<ListOrder>> {
+        let order = sqlx::query_scalar::<_, Json<ListOrder>>(
+            "select document from list_orders where list_id=$1",
+        )
+        .bind(list_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+        Ok(order.map(|Json(order)| order))
+    }
+
+    async fn merge_order(&self, list_id: i32, delta: &ListOrderDelta) -> anyhow::Result<ListOrder> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        sqlx::query(
+            "insert into list_orders (list_id, document) values ($1, $2) on conflict (list_id) do nothing",
+        )
+        .bind(list_id)
+        .bind(Json(ListOrder::new()))
+        .execute(&mut *tx)
+        .await
+        .map_err(RepositoryError::from)?;
+        let Json(mut order) = sqlx::query_scalar::<_, Json<ListOrder>>(
+            "select document from list_orders where list_id=$1 for update",
+        )
+        .bind(list_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(RepositoryError::from)?;
+        order.apply(delta).map_err(RepositoryError::Invalid)?;
+        sqlx::query("update list_orders set document=$2 where list_id=$1")
+            .bind(list_id)
+            .bind(Json(&order))
+            .execute(&mut *tx)
+            .await
+            .map_err(RepositoryError::from)?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+        Ok(order)
+    }
+
+    async fn delete_orders(&self, list_ids: &[i32]) -> anyhow::Result<()> {
+        sqlx::query("delete from list_orders where list_id = any($1)")
+            .bind(list_ids)
+            .execute(&self.pool)
+            .await
+            .map_err(RepositoryError::from)?;
+        Ok(())
+    }
+}
+
+/// The todos of `TR` with their text documents in `DR`. A todo gets its document when it is
+/// created, and the text edits made through here are merged into it as ops of a replica of its
+/// own. Todos without a document, created before there were any, are left without one.
+///
+/// Moving a todo merges the order of its list into the order document of the list, which gets
+/// the todos it lacks then. A move the document has already, like those writing a merged order
+/// back with `write_order`, leaves it alone.
+#[derive(Debug, Clone)]
+pub struct TodoRepositoryWithDocuments<TR, DR> {
+    todo_repository: TR,
+    document_repository: DR,
+    replica: ReplicaId,
+    /// Held by every edit, two edits must not make ops with the same dot.
+    editing: Arc<Mutex<()>>,
+}
+
+impl<TR, DR> TodoRepositoryWithDocuments<TR, DR> {
+    pub fn new(todo_repository: TR, document_repository: DR) -> Self {
+        Self {
+            todo_repository,
+            document_repository,
+            replica: new_replica(),
+            editing: Arc::default(),
+        }
+    }
+}
+
+impl<TR, DR> TodoRepositoryWithDocuments<TR, DR>
+where
+    TR: TodoRepositoryTrait,
+    DR: TodoDocumentRepositoryTrait,
+{
+    fn with(&self, todo_repository: TR) -> Self {
+        Self {
+            todo_repository,
+            document_repository: self.document_repository.clone(),
+            replica: self.replica,
+            editing: self.editing.clone(),
+        }
+    }
+
+    async fn created(&self, todo: &Todo) -> anyhow::Result<()> {
+        self.document_repository
+            .merge(todo.id, &genesis(&todo.text))
+            .await?;
+        Ok(())
+    }
+
+    /// Merges into the document of `after` the ops editing the text of `before` into its own.
+    async fn edited(&self, before: &Todo, after: &Todo) -> anyhow::Result<()> {
+        if before.text == after.text {
+            return Ok(());
+        }
+        let _editing = self.editing.lock().await;
+        let Some(document) = self.document_repository.find(after.id).await? else {
+            return Ok(());
+        };
+        let mut edited = document.clone();
+        edited.set_text(self.replica, &after.text);
+        let delta = edited.delta(&document.version());
+        if !delta.is_empty() {
+            self.document_repository.merge(after.id, &delta).await?;
+        }
+        Ok(())
+    }
+
+    /// Merges into the order of the list of `todo` the order the list has after `todo` moved.
+    async fn moved(&self, todo: &Todo) -> anyhow::Result<()> {
+        let order = list_order(&self.todo_repository, todo.list_id).await?;
+        let _editing = self.editing.lock().await;
+        let document = self
+            .document_repository
+            .find_order(todo.list_id)
+            .await?
+            .unwrap_or_default();
+        if document.is_placed(&order, &todo.id) {
+            return Ok(());
+        }
+        let mut edited = document.clone();
+        edited.set_order(self.replica, &order);
+        let delta = edited.delta(&document.version());
+        if !delta.is_empty() {
+            self.document_repository
+                .merge_order(todo.list_id, &delta)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn all_ids(&self) -> anyhow::Result<BTreeSet<i32>> {
+        let todos = self.todo_repository.all(&TodoQuery::default()).await?;
+        Ok(todos.into_iter().map(|todo| todo.id).collect())
+    }
+}
+
+#[async_trait]
+impl<TR, DR> TodoRepositoryTrait for TodoRepositoryWithDocuments<TR, DR>
+where
+    TR: TodoRepositoryTrait,
+    DR: TodoDocumentRepositoryTrait,
+{
+    fn owned_by(&self, owner: i32) -> Self {
+        self.with(self.todo_repository.owned_by(owner))
+    }
+
+    fn in_workspace(&self, workspace: i32) -> Self {
+        self.with(self.todo_repository.in_workspace(workspace))
+    }
+
+    async fn create(&self, payload: CreateTodo) -> anyhow::Result<Todo> {
+        let todo = self.todo_repository.create(payload).await?;
+        self.created(&todo).await?;
+        Ok(todo)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Todo> {
+        self.todo_repository.find(id).await
+    }
+
+    async fn all(&self, query: &TodoQuery) -> anyhow::Result<Vec<Todo>> {
+        self.todo_repository.all(query).await
+    }
+
+    async fn count(&self, query: &TodoQuery) -> anyhow::Result<i64> {
+        self.todo_repository.count(query).await
+    }
+
+    async fn search(&self, query: &str, limit: u32) -> anyhow::Result<Vec<TodoSearchHit>> {
+        self.todo_repository.search(query, limit).await
+    }
+
+    async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<Todo> {
+        let before = self.todo_repository.find(id).await?;
+        let after = self.todo_repository.update(id, payload).await?;
+        self.edited(&before, &after).await?;
+        Ok(after)
+    }
+
+    async fn delete(&self, id: i32, version: Option<i32>) -> anyhow::Result<()> {
+        let subtree = self.todo_repository.subtree(id).await?;
+        self.todo_repository.delete(id, version).await?;
+        let ids: Vec<i32> = subtree.into_iter().map(|todo| todo.id).collect();
+        self.document_repository.delete(&ids).await
+    }
+
+    async fn subtree(&self, id: i32) -> anyhow::Result<Vec<Todo>> {
+        self.todo_repository.subtree(id).await
+    }
+
+    async fn ancestor_ids(&self, id: i32) -> anyhow::Result<Vec<i32>> {
+        self.todo_repository.ancestor_ids(id).await
+    }
+
+    async fn move_before(&self, id: i32, before: Option<i32>) -> anyhow::Result<Todo> {
+        let todo = self.todo_repository.move_before(id, before).await?;
+        self.moved(&todo).await?;
+        Ok(todo)
+    }
+
+    async fn label_ids(&self, id: i32) -> anyhow::Result<Vec<i32>> {
+        self.todo_repository.label_ids(id).await
+    }
+
+    async fn attach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()> {
+        self.todo_repository.attach_label(id, label_id).await
+    }
+
+    async fn detach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()> {
+        self.todo_repository.detach_label(id, label_id).await
+    }
+
+    async fn remove_label(&self, label_id: i32) -> anyhow::Result<()> {
+        self.todo_repository.remove_label(label_id).await
+    }
+
+    async fn move_list(&self, from: i32, to: i32) -> anyhow::Result<()> {
+        self.todo_repository.move_list(from, to).await
+    }
+
+    async fn delete_list(&self, list_id: i32) -> anyhow::Result<()> {
+        let before = self.all_ids().await?;
+        self.todo_repository.delete_list(list_id).await?;
+        let after = self.all_ids().await?;
+        let ids: Vec<i32> = before.difference(&after).copied().collect();
+        self.document_repository.delete(&ids).await?;
+        self.document_repository.delete_orders(&[list_id]).await
+    }
+
+    async fn add_occurrence(&self, series_id: i32, payload: CreateTodo) -> anyhow::Result<Todo> {
+        let todo = self
+            .todo_repository
+            .add_occurrence(series_id, payload)
+            .await?;
+        self.created(&todo).await?;
+        Ok(todo)
+    }
+
+    async fn find_series(&self, series_id: i32) -> anyhow::Result<TodoSeries> {
+        self.todo_repository.find_series(series_id).await
+    }
+
+    async fn series_occurrences(&self, series_id: i32) -> anyhow::Result<Vec<Todo>> {
+        self.todo_repository.series_occurrences(series_id).await
+    }
+
+    async fn start_series(&self, id: i32, rrule: String) -> anyhow::Result<Todo> {
+        self.todo_repository.start_series(id, rrule).await
+    }
+
+    async fn update_series(
+        &self,
+        series_id: i32,
+        payload: &UpdateTodo,
+    ) -> anyhow::Result<TodoSeries> {
+        let before = self.todo_repository.series_occurrences(series_id).await?;
+        let series = self
+            .todo_repository
+            .update_series(series_id, payload)
+            .await?;
+        for after in self.todo_repository.series_occurrences(series_id).await? {
+            if let Some(before) = before.iter().find(|todo| todo.id == after.id) {
+                self.edited(before, &after).await?;
+            }
+        }
+        Ok(series)
+    }
+
+    async fn find_due(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Todo>> {
+        self.todo_repository.find_due(from, to).await
+    }
+
+    async fn changes(&self, cursor: i64, limit: u32) -> anyhow::Result<Vec<TodoChange>> {
+        self.todo_repository.changes(cursor, limit).await
+    }
+}
+
+/// The scenario every document repository has to pass.
+#[cfg(test)]
+pub(crate) mod scenario {
+    use super::*;
+
+    /// Runs against a store holding no todo yet.
+    pub async fn documents_scenario(
+        todos: &impl TodoRepositoryTrait,
+        documents: &impl TodoDocumentRepositoryTrait,
+    ) {
+        let todo = todos
+            .create(CreateTodo::new("buy milk".to_string()))
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(documents.find(todo.id).await.unwrap(), None);
+
+        let base = TextDocument::genesis(todo.text.chars());
+        let document = documents
+            .merge(todo.id, &genesis(&todo.text))
+            .await
+            .expect("[merge] returned Err");
+        assert_eq!(document, base);
+
+        // concurrent edits both make it
+        let (mut alice, mut bob) = (base.clone(), base.clone());
+        alice.set_text(1, "buy oat milk");
+        bob.set_text(2, "buy milk and eggs");
+        let (alice_delta, bob_delta) = (alice.delta(&base.version()), bob.delta(&base.version()));
+        let (a, b) = tokio::join!(
+            documents.merge(todo.id, &alice_delta),
+            documents.merge(todo.id, &bob_delta),
+        );
+        a.unwrap();
+        b.unwrap();
+        let document = documents.find(todo.id).await.unwrap().unwrap();
+        assert_eq!(document.text(), "buy oat milk and eggs");
+        assert_eq!(
+            documents.merge(todo.id, &alice_delta).await.unwrap(),
+            document
+        );
+
+        // a delta from a document it doesn't have
+        let other = TextDocument::genesis("walk the dog".chars());
+        let mut edited = other.clone();
+        edited.set_text(1, "walk the cat");
+        let res = documents
+            .merge(todo.id, &edited.delta(&other.version()))
+            .await;
+        assert!(matches!(
+            res.map_err(|e| e.downcast::<RepositoryError>()),
+            Err(Ok(RepositoryError::Invalid(_)))
+        ));
+        assert_eq!(documents.find(todo.id).await.unwrap().unwrap(), document);
+
+        documents.delete(&[todo.id, todo.id + 1]).await.unwrap();
+        assert_eq!(documents.find(todo.id).await.unwrap(), None);
+
+        // orders are kept by list, in the same way
+        let list_id = todo.list_id;
+        assert_eq!(documents.find_order(list_id).await.unwrap(), None);
+        let base = ListOrder::new();
+        let (mut alice, mut bob) = (base.clone(), base.clone());
+        alice.move_before(1, todo.id, None);
+        bob.move_before(2, todo.id + 1, None);
+        for delta in [alice.delta(&base.version()), bob.delta(&base.version())] {
+            documents
+                .merge_order(list_id, &delta)
+                .await
+                .expect("[merge_order] returned Err");
+        }
+        let order = documents.find_order(list_id).await.unwrap().unwrap();
+        alice.merge(&bob).unwrap();
+        assert_eq!(order, alice);
+        assert_eq!(order.items().len(), 2);
+        documents.delete_orders(&[list_id]).await.unwrap();
+        assert_eq!(documents.find_order(list_id).await.unwrap(), None);
+        todos.delete(todo.id, None).await.unwrap();
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use crate::todos::repository::TodoRepositoryForDb;
+    use dotenv::dotenv;
+    use std::env;
+
+    #[tokio::test]
+    async fn documents_scenario() {
+        dotenv().ok();
+
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
+
+        let user = sqlx::query_scalar::<_, i32>(
+            "insert into users (email, password_hash) values ($1, '') returning id",
+        )
+        .bind(format!(
+            "documents_scenario.{}@example.com",
+            Utc::now().timestamp_micros()
+        ))
+        .fetch_one(&pool)
+        .await
+        .expect("[create user] returned Err");
+        scenario::documents_scenario(
+            &TodoRepositoryForDb::new(pool.clone()).owned_by(user),
+            &TodoDocumentRepositoryForDb::new(pool.clone()),
+        )
+        .await;
+
+        sqlx::query("delete from users where id=$1")
+            .bind(user)
+            .execute(&pool)
+            .await
+            .expect("[delete users] returned Err");
+    }
+}
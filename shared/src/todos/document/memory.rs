@@ -0,0 +1,161 @@
+use axum::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use super::TodoDocumentRepositoryTrait;
+use crate::todos::crdt::{ListOrder, ListOrderDelta, TextDelta, TextDocument};
+use crate::todos::repository::RepositoryError;
+
+/// Documents held in memory, by todo id, and the orders by list id.
+#[derive(Debug, Clone, Default)]
+pub struct TodoDocumentRepositoryForMemory {
+    documents: Arc<RwLock<HashMap<i32, TextDocument>>>,
+    orders: Arc<RwLock<HashMap<i32, ListOrder>>>,
+}
+
+impl TodoDocumentRepositoryForMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TodoDocumentRepositoryTrait for TodoDocumentRepositoryForMemory {
+    async fn find(&self, id: i32) -> anyhow::Result<Option<TextDocument>> {
+        Ok(self.documents.read().unwrap().get(&id).cloned())
+    }
+
+    async fn merge(&self, id: i32, delta: &TextDelta) -> anyhow::Result<TextDocument> {
+        let mut documents = self.documents.write().unwrap();
+        let mut document = documents.get(&id).cloned().unwrap_or_default();
+        document.apply(delta).map_err(RepositoryError::Invalid)?;
+        documents.insert(id, document.clone());
+        Ok(document)
+    }
+
+    async fn delete(&self, ids: &[i32]) -> anyhow::Result<()> {
+        let mut documents = self.documents.write().unwrap();
+        for id in ids {
+            documents.remove(id);
+        }
+        Ok(())
+    }
+
+    async fn find_order(&self, list_id: i32) -> anyhow::Result<Option<ListOrder>> {
+        Ok(self.orders.read().unwrap().get(&list_id).cloned())
+    }
+
+    async fn merge_order(&self, list_id: i32, delta: &ListOrderDelta) -> anyhow::Result<ListOrder> {
+        let mut orders = self.orders.write().unwrap();
+        let mut order = orders.get(&list_id).cloned().unwrap_or_default();
+        order.apply(delta).map_err(RepositoryError::Invalid)?;
+        orders.insert(list_id, order.clone());
+        Ok(order)
+    }
+
+    async fn delete_orders(&self, list_ids: &[i32]) -> anyhow::Result<()> {
+        let mut orders = self.orders.write().unwrap();
+        for list_id in list_ids {
+            orders.remove(list_id);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::todos::document::{list_order, scenario, write_order, TodoRepositoryWithDocuments};
+    use crate::todos::model::{CreateTodo, UpdateTodo};
+    use crate::todos::repository::memory::TodoRepositoryForMemory;
+    use crate::todos::repository::TodoRepositoryTrait;
+
+    #[tokio::test]
+    async fn documents_scenario() {
+        scenario::documents_scenario(
+            &TodoRepositoryForMemory::new(),
+            &TodoDocumentRepositoryForMemory::new(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn todos_keep_their_document() {
+        let documents = TodoDocumentRepositoryForMemory::new();
+        let todos =
+            TodoRepositoryWithDocuments::new(TodoRepositoryForMemory::new(), documents.clone());
+        let todo = todos
+            .create(CreateTodo::new("buy milk".to_string()))
+            .await
+            .unwrap();
+        let text = |text: &str| UpdateTodo {
+            text: Some(text.to_string()),
+            ..Default::default()
+        };
+        todos.update(todo.id, text("buy oat milk")).await.unwrap();
+        let document = documents.find(todo.id).await.unwrap().unwrap();
+        assert_eq!(document.text(), "buy oat milk");
+        assert_eq!(document.genesis_text(), "buy milk");
+
+        // other fields leave it alone
+        todos
+            .update(
+                todo.id,
+                UpdateTodo {
+                    completed: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(documents.find(todo.id).await.unwrap().unwrap(), document);
+
+        let child = todos
+            .create(CreateTodo {
+                parent_id: Some(todo.id),
+                ..CreateTodo::new("child".to_string())
+            })
+            .await
+            .unwrap();
+        todos.delete(todo.id, None).await.unwrap();
+        assert_eq!(documents.find(todo.id).await.unwrap(), None);
+        assert_eq!(documents.find(child.id).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn moves_go_to_the_order_of_the_list() {
+        let documents = TodoDocumentRepositoryForMemory::new();
+        let todos =
+            TodoRepositoryWithDocuments::new(TodoRepositoryForMemory::new(), documents.clone());
+        let mut ids = Vec::new();
+        for text in ["a", "b", "c"] {
+            let todo = todos
+                .create(CreateTodo::new(text.to_string()))
+                .await
+                .unwrap();
+            ids.push(todo.id);
+        }
+        let (a, b, c) = (ids[0], ids[1], ids[2]);
+        let list_id = todos.find(a).await.unwrap().list_id;
+        assert_eq!(documents.find_order(list_id).await.unwrap(), None);
+
+        todos.move_before(c, Some(a)).await.unwrap();
+        let order = documents.find_order(list_id).await.unwrap().unwrap();
+        assert_eq!(order.items(), [&c, &a, &b]);
+
+        // writing the order back moves nothing, and leaves it alone
+        let other = TodoRepositoryForMemory::new();
+        for text in ["a", "b", "c"] {
+            other
+                .create(CreateTodo::new(text.to_string()))
+                .await
+                .unwrap();
+        }
+        let others =
+            TodoRepositoryWithDocuments::new(other.clone(), TodoDocumentRepositoryForMemory::new());
+        assert_eq!(write_order(&others, list_id, &order).await.unwrap(), 1);
+        assert_eq!(list_order(&other, list_id).await.unwrap(), [c, a, b]);
+        assert_eq!(write_order(&todos, list_id, &order).await.unwrap(), 0);
+        assert_eq!(documents.find_order(list_id).await.unwrap().unwrap(), order);
+    }
+}
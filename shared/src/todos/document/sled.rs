@@ -0,0 +1,124 @@
+use ::sled::{Db, Tree};
+use axum::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::TodoDocumentRepositoryTrait;
+use crate::todos::crdt::{ListOrder, ListOrderDelta, TextDelta, TextDocument};
+use crate::todos::repository::RepositoryError;
+
+fn decode<D: DeserializeOwned>(bytes: &[u8]) -> Result<D, RepositoryError> {
+    serde_json::from_slice(bytes).map_err(|e| RepositoryError::Storage(e.to_string()))
+}
+
+fn find<D: DeserializeOwned>(tree: &Tree, id: i32) -> Result<Option<D>, RepositoryError> {
+    match tree.get(id.to_be_bytes())? {
+        Some(bytes) => Ok(Some(decode(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
+/// Changes the document `id` of `tree` with `apply`, again when another merge swapped the
+/// document meanwhile.
+fn merge<D>(
+    tree: &Tree,
+    id: i32,
+    apply: impl Fn(&mut D) -> Result<(), String>,
+) -> Result<D, RepositoryError>
+where
+    D: Default + Serialize + DeserializeOwned,
+{
+    let key = id.to_be_bytes();
+    loop {
+        let old = tree.get(key)?;
+        let mut document = match &old {
+            Some(bytes) => decode(bytes)?,
+            None => D::default(),
+        };
+        apply(&mut document).map_err(RepositoryError::Invalid)?;
+        let new = serde_json::to_vec(&document)
+            .map_err(|e| RepositoryError::Unexpected(e.to_string()))?;
+        if tree.compare_and_swap(key, old, Some(new))?.is_ok() {
+            return Ok(document);
+        }
+    }
+}
+
+fn delete(tree: &Tree, ids: &[i32]) -> Result<(), RepositoryError> {
+    for id in ids {
+        tree.remove(id.to_be_bytes())?;
+    }
+    Ok(())
+}
+
+/// Documents as JSON in a tree of the sled database of the todos, for the desktop app, and the
+/// orders of the lists in another one.
+#[derive(Debug, Clone)]
+pub struct TodoDocumentRepositoryForSled {
+    tree: Tree,
+    orders: Tree,
+}
+
+impl TodoDocumentRepositoryForSled {
+    pub fn open(db: &Db) -> Result<Self, RepositoryError> {
+        Ok(Self {
+            tree: db.open_tree("todo_documents")?,
+            orders: db.open_tree("list_orders")?,
+        })
+    }
+
+    /// Documents kept in the database of `crate::store`.
+    pub fn from_store() -> Result<Self, RepositoryError> {
+        let db = crate::store::db()
+            .ok_or_else(|| RepositoryError::Storage("the store has no database".to_string()))?;
+        Self::open(&db)
+    }
+}
+
+#[async_trait]
+impl TodoDocumentRepositoryTrait for TodoDocumentRepositoryForSled {
+    async fn find(&self, id: i32) -> anyhow::Result<Option<TextDocument>> {
+        Ok(find(&self.tree, id)?)
+    }
+
+    async fn merge(&self, id: i32, delta: &TextDelta) -> anyhow::Result<TextDocument> {
+        Ok(merge(&self.tree, id, |document: &mut TextDocument| {
+            document.apply(delta)
+        })?)
+    }
+
+    async fn delete(&self, ids: &[i32]) -> anyhow::Result<()> {
+        Ok(delete(&self.tree, ids)?)
+    }
+
+    async fn find_order(&self, list_id: i32) -> anyhow::Result<Option<ListOrder>> {
+        Ok(find(&self.orders, list_id)?)
+    }
+
+    async fn merge_order(&self, list_id: i32, delta: &ListOrderDelta) -> anyhow::Result<ListOrder> {
+        Ok(merge(&self.orders, list_id, |order: &mut ListOrder| {
+            order.apply(delta)
+        })?)
+    }
+
+    async fn delete_orders(&self, list_ids: &[i32]) -> anyhow::Result<()> {
+        Ok(delete(&self.orders, list_ids)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::todos::document::scenario;
+    use crate::todos::repository::sled::TodoRepositoryForSled;
+
+    #[tokio::test]
+    async fn documents_scenario() {
+        let db = ::sled::Config::new().temporary(true).open().unwrap();
+        scenario::documents_scenario(
+            &TodoRepositoryForSled::open(&db).unwrap(),
+            &TodoDocumentRepositoryForSled::open(&db).unwrap(),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,144 @@
+use axum::async_trait;
+use sqlx::types::Json;
+use sqlx::SqlitePool;
+
+use super::TodoDocumentRepositoryTrait;
+use crate::todos::crdt::{ListOrder, ListOrderDelta, TextDelta, TextDocument};
+use crate::todos::repository::RepositoryError;
+
+/// Documents as JSON text, in the local SQLite database of the todos. The pool must have been
+/// migrated with `MIGRATOR` of the todos.
+#[derive(Debug, Clone)]
+pub struct TodoDocumentRepositoryForSqlite {
+    pool: SqlitePool,
+}
+
+impl TodoDocumentRepositoryForSqlite {
+    pub fn new(pool: SqlitePool) -> Self {
+        TodoDocumentRepositoryForSqlite { pool }
+    }
+}
+
+#[async_trait]
+impl TodoDocumentRepositoryTrait for TodoDocumentRepositoryForSqlite {
+    async fn find(&self, id: i32) -> anyhow::Result<Option<TextDocument>> {
+        let document = sqlx::query_scalar::<_, Json<TextDocument>>(
+            "select document from todo_documents where todo_id=?1",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+        Ok(document.map(|Json(document)| document))
+    }
+
+    async fn merge(&self, id: i32, delta: &TextDelta) -> anyhow::Result<TextDocument> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        let document = sqlx::query_scalar::<_, Json<TextDocument>>(
+            "select document from todo_documents where todo_id=?1",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(RepositoryError::from)?;
+        let mut document = document.map(|Json(document)| document).unwrap_or_default();
+        document.apply(delta).map_err(RepositoryError::Invalid)?;
+        sqlx::query(
+            r#"
+            insert into todo_documents (todo_id, document) values (?1, ?2)
+            on conflict (todo_id) do update set document=excluded.document
+            "#,
+        )
+        .bind(id)
+        .bind(Json(&document))
+        .execute(&mut *tx)
+        .await
+        .map_err(RepositoryError::from)?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+        Ok(document)
+    }
+
+    async fn delete(&self, ids: &[i32]) -> anyhow::Result<()> {
+        sqlx::query(
+            "delete from todo_documents where todo_id in (select value from json_each(?1))",
+        )
+        .bind(Json(ids))
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+        Ok(())
+    }
+
+    async fn find_order(&self, list_id: i32) -> anyhow::Result<Option<ListOrder>> {
+        let order = sqlx::query_scalar::<_, Json<ListOrder>>(
+            "select document from list_orders where list_id=?1",
+        )
+        .bind(list_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+        Ok(order.map(|Json(order)| order))
+    }
+
+    async fn merge_order(&self, list_id: i32, delta: &ListOrderDelta) -> anyhow::Result<ListOrder> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        let order = sqlx::query_scalar::<_, Json<ListOrder>>(
+            "select document from list_orders where list_id=?1",
+        )
+        .bind(list_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(RepositoryError::from)?;
+        let mut order = order.map(|Json(order)| order).unwrap_or_default();
+        order.apply(delta).map_err(RepositoryError::Invalid)?;
+        sqlx::query(
+            r#"
+            insert into list_orders (list_id, document) values (?1, ?2)
+            on conflict (list_id) do update set document=excluded.document
+            "#,
+        )
+        .bind(list_id)
+        .bind(Json(&order))
+        .execute(&mut *tx)
+        .await
+        .map_err(RepositoryError::from)?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+        Ok(order)
+    }
+
+    async fn delete_orders(&self, list_ids: &[i32]) -> anyhow::Result<()> {
+        sqlx::query("delete from list_orders where list_id in (select value from json_each(?1))")
+            .bind(Json(list_ids))
+            .execute(&self.pool)
+            .await
+            .map_err(RepositoryError::from)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::todos::document::scenario;
+    use crate::todos::repository::sqlite::{TodoRepositoryForSqlite, MIGRATOR};
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn documents_scenario() {
+        // every connection to `sqlite::memory:` opens a database of its own, keep the one
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .expect("failed to open an in-memory database");
+        MIGRATOR.run(&pool).await.expect("failed to migrate");
+
+        scenario::documents_scenario(
+            &TodoRepositoryForSqlite::new(pool.clone()),
+            &TodoDocumentRepositoryForSqlite::new(pool),
+        )
+        .await;
+    }
+}
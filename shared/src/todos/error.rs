@@ -0,0 +1,93 @@
+use axum::http::StatusCode;
+use thiserror::Error;
+use validator::ValidationErrors;
+
+use super::repository::RepositoryError;
+
+/// Error returned by `TodoServiceTrait`.
+///
+/// It is serialized as `{ "kind": ..., "message": ... }`, which is the payload
+/// both the HTTP api and the tauri commands send back to clients.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum TodoError {
+    #[error("Todo not found, id is {0}")]
+    NotFound(i32),
+    #[error("Series not found, id is {0}")]
+    SeriesNotFound(i32),
+    #[error("Validation error: [{0}]")]
+    Validation(String),
+    #[error("Conflict: [{0}]")]
+    Conflict(String),
+    /// The role of the user in the list of the todo doesn't allow it.
+    #[error("Forbidden: [{0}]")]
+    Forbidden(String),
+    /// A conflict on a version the client asked for with a precondition, like `If-Match`.
+    #[error("Precondition failed: [{0}]")]
+    PreconditionFailed(String),
+    #[error("Storage error: [{0}]")]
+    Storage(String),
+    /// A long operation was stopped by its `ProgressSink`, keeping what it did so far.
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("Unexpected Error: [{0}]")]
+    Unexpected(String),
+}
+
+impl TodoError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TodoError::NotFound(_) => "NotFound",
+            TodoError::SeriesNotFound(_) => "SeriesNotFound",
+            TodoError::Validation(_) => "Validation",
+            TodoError::Forbidden(_) => "Forbidden",
+            TodoError::Conflict(_) => "Conflict",
+            TodoError::PreconditionFailed(_) => "PreconditionFailed",
+            TodoError::Storage(_) => "Storage",
+            TodoError::Cancelled => "Cancelled",
+            TodoError::Unexpected(_) => "Unexpected",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            TodoError::NotFound(_) | TodoError::SeriesNotFound(_) => StatusCode::NOT_FOUND,
+            TodoError::Validation(_) => StatusCode::BAD_REQUEST,
+            TodoError::Forbidden(_) => StatusCode::FORBIDDEN,
+            TodoError::Conflict(_) => StatusCode::CONFLICT,
+            TodoError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            TodoError::Storage(_) => StatusCode::SERVICE_UNAVAILABLE,
+            TodoError::Cancelled => StatusCode::BAD_REQUEST,
+            TodoError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<RepositoryError> for TodoError {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::NotFound(id) => TodoError::NotFound(id),
+            RepositoryError::Invalid(message) => TodoError::Validation(message),
+            RepositoryError::Conflict(message) => TodoError::Conflict(message),
+            RepositoryError::Storage(message) => TodoError::Storage(message),
+            RepositoryError::Unexpected(message) => TodoError::Unexpected(message),
+        }
+    }
+}
+
+// repositories return anyhow::Result, so the typed cause has to be recovered here
+impl From<anyhow::Error> for TodoError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<RepositoryError>() {
+            Ok(e) => e.into(),
+            Err(e) => TodoError::Unexpected(e.to_string()),
+        }
+    }
+}
+
+impl From<ValidationErrors> for TodoError {
+    fn from(e: ValidationErrors) -> Self {
+        TodoError::Validation(e.to_string().replace('\n', ", "))
+    }
+}
+
+crate::impl_error_response!(TodoError);
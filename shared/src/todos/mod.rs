@@ -1,3 +1,9 @@
+pub mod crdt;
+pub mod document;
+pub mod error;
 pub mod model;
+pub mod recurrence;
 pub mod repository;
+pub mod search;
 pub mod service;
+pub mod transfer;
@@ -1,26 +1,438 @@
-use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+use super::recurrence::Recurrence;
 
 // TODO: split models and dtos. remove ToSchema
 // consider to include validate
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
 pub struct Todo {
     pub id: i32,
+    pub list_id: i32,
+    pub parent_id: Option<i32>,
     pub text: String,
     pub completed: bool,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Priority,
+    /// Manual ordering key, only meaningful relative to other todos.
+    pub position: i64,
+    pub created_at: DateTime<Utc>,
+    /// RFC 5545 `RRULE` the todo recurs by, see `Recurrence` for the supported subset.
+    pub rrule: Option<String>,
+    /// Occurrences of the same recurring todo share their series.
+    pub series_id: Option<i32>,
+    /// Bumped by every change to the todo.
+    pub version: i32,
+}
+
+#[derive(
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    sqlx::Type,
+    ToSchema,
+)]
+#[repr(i16)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    #[default]
+    None = 0,
+    Low = 1,
+    Medium = 2,
+    High = 3,
+    Urgent = 4,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoSort {
+    /// manual order
+    Position,
+    /// most urgent first
+    Priority,
+    /// earliest due first, todos without due date last
+    DueDate,
+    /// newest first
+    #[default]
+    CreatedAt,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Validate, ToSchema)]
+#[validate(schema(function = "validate_create_schedule"))]
 pub struct CreateTodo {
     #[validate(length(min = 1, max = 100, message = "Can not be empty and over text length"))]
     pub text: String,
+    /// The parent's list, or the inbox, when omitted.
+    pub list_id: Option<i32>,
+    /// Makes the todo a subtask.
+    pub parent_id: Option<i32>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Needs `due_at`, the next occurrence is created when this one is completed.
+    #[validate(custom(function = "validate_rrule"))]
+    pub rrule: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Validate, ToSchema)]
+/// `starts_at` and `due_at` distinguish a missing field (keep the current value)
+/// from an explicit `null` (clear it).
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, Validate, ToSchema)]
+#[validate(schema(function = "validate_update_schedule"))]
 pub struct UpdateTodo {
     #[validate(length(min = 1, max = 100, message = "Can not be empty and over text length"))]
     pub text: Option<String>,
     pub completed: Option<bool>,
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<DateTime<Utc>>)]
+    pub starts_at: Option<Option<DateTime<Utc>>>,
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<DateTime<Utc>>)]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+    pub priority: Option<Priority>,
+    /// Moves the todo to another list.
+    pub list_id: Option<i32>,
+    /// Moves the todo, with its subtasks, under another todo. `null` makes it a root todo.
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<i32>)]
+    pub parent_id: Option<Option<i32>>,
+    /// Only editable for all future occurrences, `null` ends the series.
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<String>)]
+    #[validate(custom(function = "validate_rrule"))]
+    pub rrule: Option<Option<String>>,
+    /// The version the update is based on, it fails with a conflict when the todo has changed
+    /// since. Unconditional when missing.
+    pub version: Option<i32>,
+}
+
+/// Which occurrences of a recurring todo an update applies to.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EditScope {
+    #[default]
+    This,
+    /// this occurrence and the ones that will be created after it
+    AllFuture,
+}
+
+/// What new occurrences of a recurring todo are created from.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct TodoSeries {
+    pub id: i32,
+    pub rrule: String,
+    pub text: String,
+    pub priority: Priority,
+}
+
+/// A series with its occurrences, earliest due first.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct SeriesOccurrences {
+    #[serde(flatten)]
+    pub series: TodoSeries,
+    pub occurrences: Vec<Todo>,
+}
+
+/// How many levels a todo tree can have, the root included.
+pub const MAX_DEPTH: usize = 3;
+
+/// Page size when a listing doesn't ask for one.
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+/// Largest page a listing can ask for.
+pub const MAX_PAGE_SIZE: u32 = 200;
+
+/// A todo matching a full-text search.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, FromRow, ToSchema)]
+pub struct TodoSearchHit {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub todo: Todo,
+    /// relevance, only comparable between hits of the same search
+    pub rank: f32,
+    /// the text with matched words wrapped in `<mark>`, it isn't HTML escaped
+    pub snippet: String,
+}
+
+/// A todo with its subtasks, in manual order.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct TodoTree {
+    #[serde(flatten)]
+    pub todo: Todo,
+    pub children: Vec<TodoTree>,
+}
+
+impl TodoTree {
+    /// Builds the tree rooted at `id` out of a subtree in any order, `None` when `id` is missing.
+    pub fn build(id: i32, mut todos: Vec<Todo>) -> Option<TodoTree> {
+        todos.sort_by_key(|todo| (todo.position, todo.id));
+        let index = todos.iter().position(|todo| todo.id == id)?;
+        let root = todos.remove(index);
+        Some(Self::grow(root, &todos))
+    }
+
+    fn grow(todo: Todo, todos: &[Todo]) -> TodoTree {
+        let children = todos
+            .iter()
+            .filter(|child| child.parent_id == Some(todo.id))
+            .map(|child| Self::grow(child.clone(), todos))
+            .collect();
+        TodoTree { todo, children }
+    }
+
+    /// Levels in this tree, 1 for a todo without subtasks.
+    pub fn height(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .map(TodoTree::height)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Filters, ordering and paging for listing todos. Date ranges are `[from, to)`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TodoQuery {
+    pub sort: TodoSort,
+    pub list_id: Option<i32>,
+    /// Only todos carrying every one of these labels.
+    pub labels: Vec<i32>,
+    pub completed: Option<bool>,
+    /// Case insensitive substring of the text.
+    pub text: Option<String>,
+    pub due_from: Option<DateTime<Utc>>,
+    pub due_to: Option<DateTime<Utc>>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    /// Only todos sorted after this one.
+    pub cursor: Option<TodoCursor>,
+    /// `None` is unlimited for repositories, and a default page size for services.
+    pub limit: Option<u32>,
+}
+
+/// Pages can hold from 1 to `MAX_PAGE_SIZE` todos.
+pub fn check_limit(limit: Option<u32>) -> Result<(), String> {
+    match limit {
+        Some(limit) if !(1..=MAX_PAGE_SIZE).contains(&limit) => Err(format!(
+            "limit must be between 1 and {MAX_PAGE_SIZE}, got {limit}"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Where a page ends, made of the sort keys of its last todo.
+///
+/// It is handed to clients as an opaque string, and stays valid when that todo goes away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoCursor {
+    pub id: i32,
+    pub position: i64,
+    pub priority: Priority,
+    pub due_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&Todo> for TodoCursor {
+    fn from(todo: &Todo) -> Self {
+        TodoCursor {
+            id: todo.id,
+            position: todo.position,
+            priority: todo.priority,
+            due_at: todo.due_at,
+            created_at: todo.created_at,
+        }
+    }
+}
+
+impl fmt::Display for TodoCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nanos = |at: DateTime<Utc>| at.timestamp_nanos_opt().unwrap_or(i64::MAX);
+        write!(
+            f,
+            "{}.{}.{}.{}.{}",
+            self.id,
+            self.position,
+            self.priority as i16,
+            self.due_at
+                .map(nanos)
+                .map(|n| n.to_string())
+                .unwrap_or_default(),
+            nanos(self.created_at)
+        )
+    }
+}
+
+impl FromStr for TodoCursor {
+    type Err = String;
+
+    fn from_str(cursor: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid cursor: {cursor}");
+        let parts = Vec::from_iter(cursor.split('.'));
+        let [id, position, priority, due_at, created_at] = parts[..] else {
+            return Err(invalid());
+        };
+        let priority = match priority.parse::<i16>().map_err(|_| invalid())? {
+            0 => Priority::None,
+            1 => Priority::Low,
+            2 => Priority::Medium,
+            3 => Priority::High,
+            4 => Priority::Urgent,
+            _ => return Err(invalid()),
+        };
+        let at = |nanos: &str| nanos.parse().map(DateTime::from_timestamp_nanos);
+        Ok(TodoCursor {
+            id: id.parse().map_err(|_| invalid())?,
+            position: position.parse().map_err(|_| invalid())?,
+            priority,
+            due_at: match due_at {
+                "" => None,
+                due_at => Some(at(due_at).map_err(|_| invalid())?),
+            },
+            created_at: at(created_at).map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl TodoSort {
+    /// Todos in ascending order of this key are in this sort order.
+    pub fn key(self, cursor: &TodoCursor) -> (i64, i64, i64) {
+        let nanos = |at: DateTime<Utc>| at.timestamp_nanos_opt().unwrap_or(i64::MAX);
+        let id = cursor.id as i64;
+        match self {
+            TodoSort::Position => (cursor.position, id, 0),
+            TodoSort::Priority => (-(cursor.priority as i64), cursor.position, id),
+            TodoSort::DueDate => (cursor.due_at.map_or(i64::MAX, nanos), cursor.position, id),
+            TodoSort::CreatedAt => (-nanos(cursor.created_at), -id, 0),
+        }
+    }
+}
+
+/// One page of todos.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoPage {
+    pub todos: Vec<Todo>,
+    /// Todos matching the filters on every page.
+    pub total: i64,
+    /// Set when there are more todos after this page.
+    pub next_cursor: Option<String>,
+}
+
+impl From<TodoPage> for crate::RespData<Vec<Todo>> {
+    fn from(page: TodoPage) -> Self {
+        crate::resp_page(
+            page.todos,
+            crate::Meta {
+                total: page.total,
+                next_cursor: page.next_cursor,
+            },
+        )
+    }
+}
+
+/// A change to a todo in the change log of the server, which sync clients pull.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TodoChange {
+    /// The todo was created or updated, `todo` is how it is now.
+    Upserted {
+        seq: i64,
+        updated_at: DateTime<Utc>,
+        todo: Todo,
+    },
+    Deleted {
+        seq: i64,
+        deleted_at: DateTime<Utc>,
+        id: i32,
+    },
+}
+
+impl TodoChange {
+    /// Position of the change in the log, later changes have greater ones.
+    pub fn seq(&self) -> i64 {
+        match self {
+            TodoChange::Upserted { seq, .. } | TodoChange::Deleted { seq, .. } => *seq,
+        }
+    }
+}
+
+/// Moves a todo right before `before`, or to the end when `before` is `None`.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct MoveTodo {
+    pub before: Option<i32>,
+}
+
+pub(crate) fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+fn validate_schedule(
+    starts_at: Option<&DateTime<Utc>>,
+    due_at: Option<&DateTime<Utc>>,
+) -> Result<(), ValidationError> {
+    match (starts_at, due_at) {
+        (Some(starts_at), Some(due_at)) if starts_at > due_at => {
+            let mut error = ValidationError::new("schedule");
+            error.message = Some("starts_at must not be after due_at".into());
+            Err(error)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_rrule(rrule: &str) -> Result<(), ValidationError> {
+    rrule.parse::<Recurrence>().map(|_| ()).map_err(|message| {
+        let mut error = ValidationError::new("rrule");
+        error.message = Some(message.into());
+        error
+    })
+}
+
+fn validate_create_schedule(payload: &CreateTodo) -> Result<(), ValidationError> {
+    if payload.rrule.is_some() && payload.due_at.is_none() {
+        let mut error = ValidationError::new("rrule");
+        error.message = Some("a recurring todo needs due_at".into());
+        return Err(error);
+    }
+    validate_schedule(payload.starts_at.as_ref(), payload.due_at.as_ref())
+}
+
+fn validate_update_schedule(payload: &UpdateTodo) -> Result<(), ValidationError> {
+    validate_schedule(
+        payload.starts_at.as_ref().and_then(Option::as_ref),
+        payload.due_at.as_ref().and_then(Option::as_ref),
+    )
 }
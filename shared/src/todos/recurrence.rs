@@ -0,0 +1,255 @@
+use std::str::FromStr;
+
+use chrono::{
+    DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday,
+};
+
+/// The subset of an RFC 5545 `RRULE` that todos can recur by: `FREQ`, `INTERVAL`, `COUNT`,
+/// `UNTIL` and, for weekly rules, `BYDAY` without ordinals. Rules are evaluated in UTC and weeks
+/// start on monday.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub count: Option<usize>,
+    pub until: Option<DateTime<Utc>>,
+    pub by_day: Vec<Weekday>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+        let mut freq = None;
+        let mut recurrence = Recurrence {
+            freq: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+        };
+
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("malformed RRULE part: {part}"))?;
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("unsupported FREQ: {value}")),
+                    })
+                }
+                "INTERVAL" => {
+                    recurrence.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|interval| *interval > 0)
+                        .ok_or_else(|| format!("invalid INTERVAL: {value}"))?
+                }
+                "COUNT" => {
+                    recurrence.count = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|count| *count > 0)
+                            .ok_or_else(|| format!("invalid COUNT: {value}"))?,
+                    )
+                }
+                "UNTIL" => recurrence.until = Some(parse_until(value)?),
+                "BYDAY" => {
+                    recurrence.by_day = value
+                        .split(',')
+                        .map(parse_weekday)
+                        .collect::<Result<_, _>>()?
+                }
+                "WKST" if value.eq_ignore_ascii_case("MO") => {}
+                _ => return Err(format!("unsupported RRULE part: {part}")),
+            }
+        }
+
+        recurrence.freq = freq.ok_or("FREQ is required")?;
+        if recurrence.count.is_some() && recurrence.until.is_some() {
+            return Err("COUNT and UNTIL can't be used together".to_string());
+        }
+        if !recurrence.by_day.is_empty() && recurrence.freq != Frequency::Weekly {
+            return Err("BYDAY is only supported with FREQ=WEEKLY".to_string());
+        }
+        recurrence.by_day.sort_by_key(Weekday::num_days_from_monday);
+        recurrence.by_day.dedup();
+        Ok(recurrence)
+    }
+}
+
+impl Recurrence {
+    /// The occurrence following `current`, given that the series already has `occurrences` of
+    /// them. `None` once the series is over.
+    pub fn next(&self, current: DateTime<Utc>, occurrences: usize) -> Option<DateTime<Utc>> {
+        if self.count.is_some_and(|count| occurrences >= count) {
+            return None;
+        }
+        let next = match self.freq {
+            Frequency::Daily => current + Duration::days(self.interval as i64),
+            Frequency::Weekly => self.next_weekly(current),
+            Frequency::Monthly => add_months(current, self.interval)?,
+            Frequency::Yearly => add_months(current, self.interval.checked_mul(12)?)?,
+        };
+        match self.until {
+            Some(until) if next > until => None,
+            _ => Some(next),
+        }
+    }
+
+    fn next_weekly(&self, current: DateTime<Utc>) -> DateTime<Utc> {
+        let weekday = current.weekday().num_days_from_monday();
+        let later_this_week = self
+            .by_day
+            .iter()
+            .map(Weekday::num_days_from_monday)
+            .find(|day| *day > weekday);
+        match (later_this_week, self.by_day.first()) {
+            (Some(day), _) => current + Duration::days((day - weekday) as i64),
+            (None, Some(first)) => {
+                let monday = current - Duration::days(weekday as i64);
+                monday
+                    + Duration::weeks(self.interval as i64)
+                    + Duration::days(first.num_days_from_monday() as i64)
+            }
+            (None, None) => current + Duration::weeks(self.interval as i64),
+        }
+    }
+}
+
+/// Months without the day of `current` (the 31st, february 29th) are skipped like RFC 5545 does.
+fn add_months(current: DateTime<Utc>, months: u32) -> Option<DateTime<Utc>> {
+    (1..=12)
+        .filter_map(|step| months.checked_mul(step))
+        .find_map(|months| {
+            let date = current
+                .date_naive()
+                .checked_add_months(Months::new(months))?;
+            (date.day() == current.day()).then(|| date.and_time(current.time()).and_utc())
+        })
+}
+
+fn parse_until(value: &str) -> Result<DateTime<Utc>, String> {
+    let invalid = || format!("invalid UNTIL: {value}");
+    if let Ok(until) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ") {
+        return Ok(until.and_utc());
+    }
+    // a date only UNTIL includes the whole day
+    let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| invalid())?;
+    let end_of_day = NaiveTime::from_hms_opt(23, 59, 59).ok_or_else(invalid)?;
+    Ok(date.and_time(end_of_day).and_utc())
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday, String> {
+    match value.to_ascii_uppercase().as_str() {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => Err(format!("unsupported BYDAY: {value}")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().to_utc()
+    }
+
+    fn occurrences(rule: &str, start: &str, n: usize) -> Vec<DateTime<Utc>> {
+        let recurrence: Recurrence = rule.parse().unwrap();
+        let mut occurrences = vec![at(start)];
+        while occurrences.len() < n {
+            let last = *occurrences.last().unwrap();
+            match recurrence.next(last, occurrences.len()) {
+                Some(next) => occurrences.push(next),
+                None => break,
+            }
+        }
+        occurrences
+    }
+
+    #[test]
+    fn parses_the_supported_subset() {
+        let recurrence: Recurrence = "RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=FR,MO;COUNT=4"
+            .parse()
+            .unwrap();
+        assert_eq!(recurrence.freq, Frequency::Weekly);
+        assert_eq!(recurrence.interval, 2);
+        assert_eq!(recurrence.count, Some(4));
+        assert_eq!(recurrence.by_day, vec![Weekday::Mon, Weekday::Fri]);
+
+        for rule in [
+            "",
+            "INTERVAL=2",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;COUNT=2;UNTIL=20240101",
+            "FREQ=MONTHLY;BYDAY=MO",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=DAILY;BYHOUR=9",
+        ] {
+            assert!(rule.parse::<Recurrence>().is_err(), "{rule}");
+        }
+    }
+
+    #[test]
+    fn steps_through_occurrences() {
+        assert_eq!(
+            occurrences("FREQ=DAILY;INTERVAL=2;COUNT=3", "2024-05-30T09:00:00Z", 10),
+            vec![
+                at("2024-05-30T09:00:00Z"),
+                at("2024-06-01T09:00:00Z"),
+                at("2024-06-03T09:00:00Z"),
+            ]
+        );
+        // wednesday, then monday and friday every other week
+        assert_eq!(
+            occurrences(
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR",
+                "2024-05-15T09:00:00Z",
+                4
+            ),
+            vec![
+                at("2024-05-15T09:00:00Z"),
+                at("2024-05-17T09:00:00Z"),
+                at("2024-05-27T09:00:00Z"),
+                at("2024-05-31T09:00:00Z"),
+            ]
+        );
+        assert_eq!(
+            occurrences("FREQ=MONTHLY;UNTIL=20240731", "2024-01-31T09:00:00Z", 10),
+            vec![
+                at("2024-01-31T09:00:00Z"),
+                at("2024-03-31T09:00:00Z"),
+                at("2024-05-31T09:00:00Z"),
+                at("2024-07-31T09:00:00Z"),
+            ]
+        );
+        assert_eq!(
+            occurrences("FREQ=YEARLY", "2024-02-29T09:00:00Z", 2),
+            vec![at("2024-02-29T09:00:00Z"), at("2028-02-29T09:00:00Z")]
+        );
+    }
+}
@@ -1,15 +1,75 @@
 use axum::async_trait;
-use sqlx::PgPool;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use std::collections::BTreeSet;
 use thiserror::Error;
 
-use super::model::{CreateTodo, Todo, UpdateTodo};
+use super::model::{
+    CreateTodo, Todo, TodoChange, TodoCursor, TodoPage, TodoQuery, TodoSearchHit, TodoSeries,
+    TodoSort, UpdateTodo, DEFAULT_PAGE_SIZE,
+};
+use super::search::HIGHLIGHT;
+use crate::lists::repository::create_inbox;
+
+#[cfg(any(test, feature = "memory"))]
+pub mod memory;
+pub mod sled;
+pub mod sqlite;
+
+/// Gap left between neighbouring positions, so most moves only rewrite one row.
+const POSITION_GAP: i64 = 1 << 16;
+
+/// A position strictly between `prev` and `next`, `None` when they are too close.
+fn position_between(prev: Option<i64>, next: i64) -> Option<i64> {
+    match prev {
+        None => Some(next - POSITION_GAP),
+        Some(prev) if next - prev > 1 => Some(prev + (next - prev) / 2),
+        Some(_) => None,
+    }
+}
 
 #[derive(Debug, Error)]
-enum RepositoryError {
+pub enum RepositoryError {
     #[error("Unexpected Error: [{0}]")]
     Unexpected(String),
     #[error("NotFound, id is {0}")]
     NotFound(i32),
+    #[error("Invalid: [{0}]")]
+    Invalid(String),
+    #[error("Conflict: [{0}]")]
+    Conflict(String),
+    #[error("Storage Error: [{0}]")]
+    Storage(String),
+}
+
+impl From<sqlx::Error> for RepositoryError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                RepositoryError::Conflict(db.message().to_string())
+            }
+            sqlx::Error::Database(db)
+                if db.is_foreign_key_violation() || db.is_check_violation() =>
+            {
+                RepositoryError::Invalid(db.message().to_string())
+            }
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => RepositoryError::Storage(e.to_string()),
+            _ => RepositoryError::Unexpected(e.to_string()),
+        }
+    }
+}
+
+impl RepositoryError {
+    pub(crate) fn from_sqlx(id: i32) -> impl FnOnce(sqlx::Error) -> Self {
+        move |e| match e {
+            sqlx::Error::RowNotFound => RepositoryError::NotFound(id),
+            _ => e.into(),
+        }
+    }
 }
 
 #[async_trait]
@@ -17,326 +77,2061 @@ pub trait TodoRepositoryTrait
 where
     Self: Clone + std::marker::Send + std::marker::Sync + 'static,
 {
+    /// The same store, only seeing the todos of user `owner` and those of the lists shared with
+    /// them, and creating todos of `owner`. Local stores hold the todos of a single user and
+    /// return themselves.
+    fn owned_by(&self, owner: i32) -> Self;
+    /// The same store, only seeing and creating the todos of workspace `workspace`. Local stores
+    /// hold a single workspace and return themselves.
+    fn in_workspace(&self, workspace: i32) -> Self;
+    /// A todo with a `rrule` starts a new series.
     async fn create(&self, payload: CreateTodo) -> anyhow::Result<Todo>;
     async fn find(&self, id: i32) -> anyhow::Result<Todo>;
-    async fn all(&self) -> anyhow::Result<Vec<Todo>>;
+    /// Todos matching `query`, sorted and paged by it.
+    async fn all(&self, query: &TodoQuery) -> anyhow::Result<Vec<Todo>>;
+    /// How many todos match the filters of `query`, ignoring its cursor and limit.
+    async fn count(&self, query: &TodoQuery) -> anyhow::Result<i64>;
+    /// A page of `query.limit` todos, `DEFAULT_PAGE_SIZE` when unset.
+    async fn page(&self, query: &TodoQuery) -> anyhow::Result<TodoPage> {
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        // one more todo than asked tells whether there is a next page
+        let mut todos = self
+            .all(&TodoQuery {
+                limit: Some(limit + 1),
+                ..query.clone()
+            })
+            .await?;
+        let next_cursor = if todos.len() > limit as usize {
+            todos.truncate(limit as usize);
+            todos.last().map(|todo| TodoCursor::from(todo).to_string())
+        } else {
+            None
+        };
+        let total = self.count(query).await?;
+        Ok(TodoPage {
+            todos,
+            total,
+            next_cursor,
+        })
+    }
+    /// At most `limit` todos holding every word of `query`, most relevant first.
+    async fn search(&self, query: &str, limit: u32) -> anyhow::Result<Vec<TodoSearchHit>>;
+    /// Fails with `Conflict` when `payload.version` is set and the todo is at another version.
     async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<Todo>;
-    async fn delete(&self, id: i32) -> anyhow::Result<()>;
+    /// Subtasks are deleted along with their parent. Fails with `Conflict` when `version` is set
+    /// and the todo is at another version.
+    async fn delete(&self, id: i32, version: Option<i32>) -> anyhow::Result<()>;
+    /// `id` and all of its descendants, in no particular order.
+    async fn subtree(&self, id: i32) -> anyhow::Result<Vec<Todo>>;
+    /// Ids of the ancestors of `id`, its parent first.
+    async fn ancestor_ids(&self, id: i32) -> anyhow::Result<Vec<i32>>;
+    /// Moves `id` right before `before`, or after every other todo when `before` is `None`.
+    /// Only the moved todo gets a new position unless its neighbours have run out of room.
+    async fn move_before(&self, id: i32, before: Option<i32>) -> anyhow::Result<Todo>;
+    /// Labels are owned by the labels domain, a todo only keeps the ids attached to it.
+    async fn label_ids(&self, id: i32) -> anyhow::Result<Vec<i32>>;
+    /// Attaching an already attached label is a no-op.
+    async fn attach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()>;
+    /// Detaching a label that isn't attached is a no-op.
+    async fn detach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()>;
+    /// Detaches `label_id` from every todo, used when the label itself goes away.
+    async fn remove_label(&self, label_id: i32) -> anyhow::Result<()>;
+    /// Moves every todo of list `from` to list `to`, keeping their positions.
+    async fn move_list(&self, from: i32, to: i32) -> anyhow::Result<()>;
+    /// Deletes every todo of `list_id`.
+    async fn delete_list(&self, list_id: i32) -> anyhow::Result<()>;
+    /// Adds a todo to an existing series, `payload.rrule` is stored as is.
+    async fn add_occurrence(&self, series_id: i32, payload: CreateTodo) -> anyhow::Result<Todo>;
+    async fn find_series(&self, series_id: i32) -> anyhow::Result<TodoSeries>;
+    /// Earliest due first.
+    async fn series_occurrences(&self, series_id: i32) -> anyhow::Result<Vec<Todo>>;
+    /// Turns `id` into the first occurrence of a new series recurring by `rrule`.
+    async fn start_series(&self, id: i32, rrule: String) -> anyhow::Result<Todo>;
+    /// Applies `text`, `priority` and `rrule` of `payload` to the series and to its uncompleted
+    /// occurrences. Clearing `rrule` only clears it on the occurrences, which ends the series.
+    async fn update_series(
+        &self,
+        series_id: i32,
+        payload: &UpdateTodo,
+    ) -> anyhow::Result<TodoSeries>;
+    /// Uncompleted todos due in `[from, to)`, earliest first. `from: None` has no lower bound.
+    async fn find_due(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Todo>>;
+    /// At most `limit` changes made after change `cursor`, in the order they were made. Only the
+    /// last change of a todo is kept. Local stores keep no change log, they are the clients.
+    async fn changes(&self, cursor: i64, limit: u32) -> anyhow::Result<Vec<TodoChange>> {
+        let _ = (cursor, limit);
+        Err(RepositoryError::Unexpected("this store keeps no change log".to_string()).into())
+    }
+}
+
+/// Filters of a `TodoQuery`, bound to `$1..$8` in field order, the owner bound to `$9` and the
+/// workspace to `$10`.
+const FILTER: &str = r#"
+    where todo_visible(user_id, list_id, workspace_id, $9, $10)
+    and ($2::int is null or list_id=$2)
+    and ($3::bool is null or completed=$3)
+    and ($4::text is null or strpos(lower(text), lower($4)) > 0)
+    and ($5::timestamptz is null or due_at >= $5)
+    and ($6::timestamptz is null or due_at < $6)
+    and ($7::timestamptz is null or created_at >= $7)
+    and ($8::timestamptz is null or created_at < $8)
+    and not exists (
+        select 1 from unnest($1::int[]) as wanted(label_id)
+        where not exists (
+            select 1 from todo_labels
+            where todo_labels.todo_id=todos.id and todo_labels.label_id=wanted.label_id
+        )
+    )
+"#;
+
+/// The filters of a `TodoQuery`, for the repositories filtering todos themselves.
+struct Filter<'a> {
+    query: &'a TodoQuery,
+    text: Option<String>,
+}
+
+impl<'a> Filter<'a> {
+    fn new(query: &'a TodoQuery) -> Self {
+        let text = query.text.as_ref().map(|text| text.to_lowercase());
+        Filter { query, text }
+    }
+
+    /// Whether `todo` with `label_ids` attached passes every filter.
+    fn matches(&self, todo: &Todo, label_ids: Option<&BTreeSet<i32>>) -> bool {
+        let query = self.query;
+        let within =
+            |at: Option<DateTime<Utc>>, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>| {
+                from.is_none_or(|from| at.is_some_and(|at| at >= from))
+                    && to.is_none_or(|to| at.is_some_and(|at| at < to))
+            };
+        query.list_id.is_none_or(|list_id| todo.list_id == list_id)
+            && query
+                .completed
+                .is_none_or(|completed| todo.completed == completed)
+            && self
+                .text
+                .as_ref()
+                .is_none_or(|text| todo.text.to_lowercase().contains(text))
+            && within(todo.due_at, query.due_from, query.due_to)
+            && within(Some(todo.created_at), query.created_from, query.created_to)
+            && query
+                .labels
+                .iter()
+                .all(|label_id| label_ids.is_some_and(|l| l.contains(label_id)))
+    }
+}
+
+/// `todos` matching `query` sorted and paged by it.
+fn sort_and_page(query: &TodoQuery, todos: Vec<Todo>) -> Vec<Todo> {
+    let after = query.cursor.as_ref().map(|cursor| query.sort.key(cursor));
+    let key = |t: &Todo| query.sort.key(&TodoCursor::from(t));
+    let mut todos = Vec::from_iter(
+        todos
+            .into_iter()
+            .filter(|t| after.is_none_or(|after| key(t) > after)),
+    );
+    todos.sort_by_key(key);
+    if let Some(limit) = query.limit {
+        todos.truncate(limit as usize);
+    }
+    todos
+}
+
+fn check_version(todo: &Todo, version: Option<i32>) -> Result<(), RepositoryError> {
+    match version {
+        Some(expected) if expected != todo.version => Err(RepositoryError::Conflict(format!(
+            "todo {} is at version {}, not {expected}",
+            todo.id, todo.version
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Serializes the inserts and moves of todos until the transaction of `conn` ends, they all
+/// read the positions of the others to place theirs.
+async fn lock_positions(conn: &mut PgConnection) -> Result<(), RepositoryError> {
+    sqlx::query("select pg_advisory_xact_lock(hashtext('todos.position'))")
+        .execute(conn)
+        .await?;
+    Ok(())
 }
 
 // TODO: Arc
+/// Every query is bound to the owner, who also sees every todo of the lists they are a member of,
+/// and to the workspace, whose todos are never seen from another one. A `None` owner sees no
+/// todo and can't create any, a `None` workspace sees those of every workspace, creating them in
+/// the personal workspace of the owner.
 #[derive(Debug, Clone)]
 pub struct TodoRepositoryForDb {
     pool: PgPool,
+    owner: Option<i32>,
+    workspace: Option<i32>,
 }
 
 impl TodoRepositoryForDb {
     pub fn new(pool: PgPool) -> Self {
-        TodoRepositoryForDb { pool }
+        TodoRepositoryForDb {
+            pool,
+            owner: None,
+            workspace: None,
+        }
     }
-}
 
-#[async_trait]
-impl TodoRepositoryTrait for TodoRepositoryForDb {
-    async fn create(&self, payload: CreateTodo) -> anyhow::Result<Todo> {
+    async fn insert(
+        &self,
+        conn: &mut PgConnection,
+        payload: CreateTodo,
+        series_id: Option<i32>,
+    ) -> Result<Todo, RepositoryError> {
+        if payload.list_id.is_none() && payload.parent_id.is_none() {
+            create_inbox(&mut *conn, self.owner, self.workspace).await?;
+        }
+        // taken until commit, so that concurrent inserts don't append after the same position
+        lock_positions(&mut *conn).await?;
         let todo = sqlx::query_as::<_, Todo>(
             r#"
-            insert into todos (text, completed)
-            values ($1, false)
+            with workspace as (
+                select coalesce($11, (select id from workspaces where personal_of=$10)) as id
+            )
+            insert into todos (text, completed, starts_at, due_at, priority, position, list_id, parent_id, rrule, series_id, user_id, workspace_id)
+            select $1, false, $2, $3, $4, (select coalesce(max(position), 0) from todos) + $5,
+                coalesce($6, (select list_id from todos where id=$7), (select lists.id from lists where inbox and user_id=$10 and workspace_id=workspace.id)),
+                $7, $8, $9, $10, workspace.id
+            from workspace
             returning *
             "#,
         )
-        .bind(payload.text.clone())
-        .fetch_one(&self.pool)
+        .bind(payload.text)
+        .bind(payload.starts_at)
+        .bind(payload.due_at)
+        .bind(payload.priority)
+        .bind(POSITION_GAP)
+        .bind(payload.list_id)
+        .bind(payload.parent_id)
+        .bind(payload.rrule)
+        .bind(series_id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(conn)
         .await?;
         Ok(todo)
     }
 
+    /// Why a write to `id` expecting `version` matched no row.
+    async fn missed(&self, id: i32, version: Option<i32>) -> RepositoryError {
+        let current = sqlx::query_scalar::<_, i32>(
+            "select version from todos where id=$1 and todo_visible(user_id, list_id, workspace_id, $2, $3)",
+        )
+        .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_optional(&self.pool)
+        .await;
+        match (current, version) {
+            (Ok(Some(current)), Some(expected)) => RepositoryError::Conflict(format!(
+                "todo {id} is at version {current}, not {expected}"
+            )),
+            (Ok(_), _) => RepositoryError::NotFound(id),
+            (Err(e), _) => e.into(),
+        }
+    }
+
+    fn order_by(sort: TodoSort) -> &'static str {
+        match sort {
+            TodoSort::Position => "position, id",
+            TodoSort::Priority => "priority desc, position, id",
+            TodoSort::DueDate => "coalesce(due_at, 'infinity'), position, id",
+            TodoSort::CreatedAt => "created_at desc, id desc",
+        }
+    }
+
+    /// Todos sorted after the cursor bound to `$11..$15`, mirroring `order_by`.
+    fn after_cursor(sort: TodoSort) -> &'static str {
+        match sort {
+            TodoSort::Position => "(position, id) > ($12, $11)",
+            TodoSort::Priority => "(-priority, position, id) > (-$13::smallint, $12, $11)",
+            TodoSort::DueDate => {
+                "(coalesce(due_at, 'infinity'), position, id) > (coalesce($14, 'infinity'::timestamptz), $12, $11)"
+            }
+            TodoSort::CreatedAt => "(created_at, id) < ($15, $11)",
+        }
+    }
+}
+
+#[async_trait]
+impl TodoRepositoryTrait for TodoRepositoryForDb {
+    fn owned_by(&self, owner: i32) -> Self {
+        TodoRepositoryForDb {
+            owner: Some(owner),
+            ..self.clone()
+        }
+    }
+
+    fn in_workspace(&self, workspace: i32) -> Self {
+        TodoRepositoryForDb {
+            workspace: Some(workspace),
+            ..self.clone()
+        }
+    }
+
+    async fn create(&self, payload: CreateTodo) -> anyhow::Result<Todo> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        let series_id = match &payload.rrule {
+            Some(rrule) => Some(
+                sqlx::query_scalar::<_, i32>(
+                    r#"
+                    insert into todo_series (rrule, text, priority, user_id, workspace_id)
+                    values ($1, $2, $3, $4, coalesce($5, (select id from workspaces where personal_of=$4)))
+                    returning id
+                    "#,
+                )
+                .bind(rrule)
+                .bind(&payload.text)
+                .bind(payload.priority)
+                .bind(self.owner)
+                .bind(self.workspace)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(RepositoryError::from)?,
+            ),
+            None => None,
+        };
+        let todo = self.insert(&mut tx, payload, series_id).await?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+        Ok(todo)
+    }
+
     async fn find(&self, id: i32) -> anyhow::Result<Todo> {
         let todo = sqlx::query_as::<_, Todo>(
             r#"
-            select * from todos where id=$1
+            select * from todos where id=$1 and todo_visible(user_id, list_id, workspace_id, $2, $3)
             "#,
         )
         .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
         .fetch_one(&self.pool)
         .await
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => RepositoryError::NotFound(id),
-            _ => RepositoryError::Unexpected(e.to_string()),
-        })?;
+        .map_err(RepositoryError::from_sqlx(id))?;
 
         Ok(todo)
     }
 
-    async fn all(&self) -> anyhow::Result<Vec<Todo>> {
-        let todos = sqlx::query_as::<_, Todo>(
+    async fn all(&self, query: &TodoQuery) -> anyhow::Result<Vec<Todo>> {
+        let cursor = query.cursor.as_ref();
+        let todos = sqlx::query_as::<_, Todo>(&format!(
             r#"
             select * from todos
-            order by id desc;
+            {FILTER}
+            and ($11::int is null or {})
+            order by {}
+            limit $16;
             "#,
-        )
+            Self::after_cursor(query.sort),
+            Self::order_by(query.sort)
+        ))
+        .bind(&query.labels)
+        .bind(query.list_id)
+        .bind(query.completed)
+        .bind(&query.text)
+        .bind(query.due_from)
+        .bind(query.due_to)
+        .bind(query.created_from)
+        .bind(query.created_to)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .bind(cursor.map(|c| c.id))
+        .bind(cursor.map(|c| c.position))
+        .bind(cursor.map(|c| c.priority))
+        .bind(cursor.and_then(|c| c.due_at))
+        .bind(cursor.map(|c| c.created_at))
+        .bind(query.limit.map(i64::from))
         .fetch_all(&self.pool)
-        .await?;
+        .await
+        .map_err(RepositoryError::from)?;
 
         Ok(todos)
     }
 
+    async fn count(&self, query: &TodoQuery) -> anyhow::Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(&format!(
+            r#"
+            select count(*) from todos
+            {FILTER};
+            "#
+        ))
+        .bind(&query.labels)
+        .bind(query.list_id)
+        .bind(query.completed)
+        .bind(&query.text)
+        .bind(query.due_from)
+        .bind(query.due_to)
+        .bind(query.created_from)
+        .bind(query.created_to)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(count)
+    }
+
+    async fn search(&self, query: &str, limit: u32) -> anyhow::Result<Vec<TodoSearchHit>> {
+        let hits = sqlx::query_as::<_, TodoSearchHit>(
+            r#"
+            select todos.*, ts_rank(search, query, 2) as rank, ts_headline('simple', text, query, $3) as snippet
+            from todos, plainto_tsquery('simple', $1) as query
+            where search @@ query and todo_visible(user_id, list_id, workspace_id, $4, $5)
+            order by rank desc, id
+            limit $2;
+            "#,
+        )
+        .bind(query)
+        .bind(i64::from(limit))
+        .bind(format!(
+            "StartSel={}, StopSel={}, HighlightAll=true",
+            HIGHLIGHT.0, HIGHLIGHT.1
+        ))
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(hits)
+    }
+
     async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<Todo> {
         let todo = sqlx::query_as::<_, Todo>(
             r#"
-            update todos set text=coalesce($1, text), completed=coalesce($2, completed)
-            where id=$3
+            update todos set text=coalesce($1, text), completed=coalesce($2, completed),
+                starts_at=case when $3 then $4 else starts_at end,
+                due_at=case when $5 then $6 else due_at end,
+                priority=coalesce($7, priority), list_id=coalesce($8, list_id),
+                parent_id=case when $9 then $10 else parent_id end, version=version+1
+            where id=$11 and ($12::int is null or version=$12) and todo_visible(user_id, list_id, workspace_id, $13, $14)
             returning *
             "#,
         )
         .bind(payload.text)
         .bind(payload.completed)
+        .bind(payload.starts_at.is_some())
+        .bind(payload.starts_at.flatten())
+        .bind(payload.due_at.is_some())
+        .bind(payload.due_at.flatten())
+        .bind(payload.priority)
+        .bind(payload.list_id)
+        .bind(payload.parent_id.is_some())
+        .bind(payload.parent_id.flatten())
         .bind(id)
-        .fetch_one(&self.pool)
-        .await?;
+        .bind(payload.version)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
 
-        Ok(todo)
+        match todo {
+            Some(todo) => Ok(todo),
+            None => Err(self.missed(id, payload.version).await.into()),
+        }
     }
 
-    async fn delete(&self, id: i32) -> anyhow::Result<()> {
-        sqlx::query::<_>(
+    async fn delete(&self, id: i32, version: Option<i32>) -> anyhow::Result<()> {
+        let result = sqlx::query::<_>(
             r#"
-            delete from todos where id=$1
+            delete from todos
+            where id=$1 and ($2::int is null or version=$2) and todo_visible(user_id, list_id, workspace_id, $3, $4)
             "#,
         )
         .bind(id)
+        .bind(version)
+        .bind(self.owner)
+        .bind(self.workspace)
         .execute(&self.pool)
         .await
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => RepositoryError::NotFound(id),
-            _ => RepositoryError::Unexpected(e.to_string()),
-        })?;
+        .map_err(RepositoryError::from)?;
+
+        if result.rows_affected() == 0 {
+            return Err(self.missed(id, version).await.into());
+        }
 
         Ok(())
     }
-}
 
-#[cfg(test)]
-#[cfg(feature = "database-test")]
-mod test {
-    use super::*;
-    use dotenv::dotenv;
-    use sqlx::PgPool;
-    use std::env;
+    async fn subtree(&self, id: i32) -> anyhow::Result<Vec<Todo>> {
+        let todos = sqlx::query_as::<_, Todo>(
+            r#"
+            with recursive subtree as (
+                select * from todos where id=$1 and todo_visible(user_id, list_id, workspace_id, $2, $3)
+                union all
+                select todos.* from todos join subtree on todos.parent_id=subtree.id
+            )
+            select * from subtree
+            "#,
+        )
+        .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
 
-    #[tokio::test]
-    async fn crud_scenario() {
-        dotenv().ok();
+        if todos.is_empty() {
+            return Err(RepositoryError::NotFound(id).into());
+        }
 
-        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
-        let pool = PgPool::connect(database_url).await.expect(&format!(
-            "failed to connect a database, url is [{}]",
-            database_url
-        ));
+        Ok(todos)
+    }
 
-        let repository = TodoRepositoryForDb::new(pool.clone());
-        let todo_text = "[crud_senario] text";
+    async fn ancestor_ids(&self, id: i32) -> anyhow::Result<Vec<i32>> {
+        let ids = sqlx::query_scalar::<_, i32>(
+            r#"
+            with recursive ancestors(id, parent_id, depth) as (
+                select id, parent_id, 0 from todos where id=$1 and todo_visible(user_id, list_id, workspace_id, $2, $3)
+                union all
+                select todos.id, todos.parent_id, ancestors.depth + 1
+                from todos join ancestors on todos.id=ancestors.parent_id
+            )
+            select id from ancestors where depth > 0
+            order by depth
+            "#,
+        )
+        .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
 
-        // create
-        let created = repository
-            .create(CreateTodo::new(todo_text.to_string()))
-            .await
-            .expect("[create] returned Err");
+        Ok(ids)
+    }
 
-        assert_eq!(created.text, todo_text);
-        assert!(!created.completed);
+    async fn move_before(&self, id: i32, before: Option<i32>) -> anyhow::Result<Todo> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        lock_positions(&mut tx).await?;
 
-        // find
-        let todo = repository
-            .find(created.id)
-            .await
-            .expect("[find] returned Err");
+        let position = match before {
+            Some(before) if before != id => loop {
+                let next: i64 = sqlx::query_scalar(
+                    "select position from todos where id=$1 and todo_visible(user_id, list_id, workspace_id, $2, $3)",
+                )
+                .bind(before)
+                .bind(self.owner)
+                .bind(self.workspace)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(RepositoryError::from_sqlx(before))?;
+                let prev: Option<i64> = sqlx::query_scalar(
+                    r#"
+                    select position from todos
+                    where id<>$1 and (position, id) < ($2, $3) and todo_visible(user_id, list_id, workspace_id, $4, $5)
+                    order by position desc, id desc
+                    limit 1
+                    "#,
+                )
+                .bind(id)
+                .bind(next)
+                .bind(before)
+                .bind(self.owner)
+                .bind(self.workspace)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(RepositoryError::from)?;
 
-        assert_eq!(created, todo);
+                if let Some(position) = position_between(prev, next) {
+                    break Some(position);
+                }
+                sqlx::query(
+                    r#"
+                    update todos set position=ordered.rank * $1, version=version+1
+                    from (
+                        select id, row_number() over (order by position, id) as rank from todos
+                        where todo_visible(user_id, list_id, workspace_id, $2, $3)
+                    ) ordered
+                    where todos.id=ordered.id
+                    "#,
+                )
+                .bind(POSITION_GAP)
+                .bind(self.owner)
+                .bind(self.workspace)
+                .execute(&mut *tx)
+                .await
+                .map_err(RepositoryError::from)?;
+            },
+            Some(_) => None,
+            None => Some(
+                sqlx::query_scalar::<_, i64>(
+                    r#"
+                    select coalesce(max(position), 0) + $2 from todos
+                    where id<>$1 and todo_visible(user_id, list_id, workspace_id, $3, $4)
+                    "#,
+                )
+                .bind(id)
+                .bind(POSITION_GAP)
+                .bind(self.owner)
+                .bind(self.workspace)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(RepositoryError::from)?,
+            ),
+        };
 
-        // all
-        let todos = repository.all().await.expect("[all] returned Err");
+        let todo = sqlx::query_as::<_, Todo>(
+            r#"
+            update todos set position=coalesce($1, position), version=version+1
+            where id=$2 and todo_visible(user_id, list_id, workspace_id, $3, $4)
+            returning *
+            "#,
+        )
+        .bind(position)
+        .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+        tx.commit().await.map_err(RepositoryError::from)?;
 
-        let todo = todos.first().unwrap();
-        assert_eq!(created, *todo);
+        Ok(todo)
+    }
 
-        // update
-        let updated_text = "[crud_scenario] updated text";
-        let todo = repository
-            .update(
-                todo.id,
-                UpdateTodo {
-                    text: Some(updated_text.to_string()),
-                    completed: Some(true),
-                },
-            )
-            .await
-            .expect("[update] returned Err");
-        assert_eq!(created.id, todo.id);
-        assert_eq!(todo.text, updated_text);
+    async fn label_ids(&self, id: i32) -> anyhow::Result<Vec<i32>> {
+        let ids = sqlx::query_scalar::<_, i32>(
+            r#"
+            select label_id from todo_labels join todos on todos.id=todo_labels.todo_id
+            where todo_id=$1 and todo_visible(user_id, list_id, workspace_id, $2, $3)
+            order by label_id
+            "#,
+        )
+        .bind(id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
 
-        // delete
-        let _ = repository
-            .delete(todo.id)
-            .await
-            .expect("[delete] returned Err");
+        Ok(ids)
+    }
 
-        let res = repository.find(created.id).await;
-        assert!(res.is_err());
+    async fn attach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            insert into todo_labels (todo_id, label_id)
+            select id, $2 from todos where id=$1 and todo_visible(user_id, list_id, workspace_id, $3, $4)
+            on conflict do nothing
+            "#,
+        )
+        .bind(id)
+        .bind(label_id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
 
-        let todo_rows = sqlx::query::<_>(r#"select * from todos where id=$1"#)
-            .bind(todo.id)
-            .fetch_all(&pool)
-            .await
-            .expect("[delete] todo_labels fetch error");
-        assert!(todo_rows.len() == 0);
+        Ok(())
     }
-}
 
-#[cfg(test)]
-pub mod test_utils {
-    use super::*;
-    use anyhow::Context;
-    use std::{
-        collections::HashMap,
-        sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
-    };
+    async fn detach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            delete from todo_labels using todos
+            where todo_id=$1 and label_id=$2
+                and todos.id=todo_labels.todo_id and todo_visible(user_id, list_id, workspace_id, $3, $4)
+            "#,
+        )
+        .bind(id)
+        .bind(label_id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
 
-    impl CreateTodo {
-        pub fn new(text: String) -> Self {
-            Self { text }
-        }
+        Ok(())
     }
 
-    impl Todo {
-        pub fn new(id: i32, text: String) -> Self {
-            Self {
-                id,
-                text,
-                completed: false,
-            }
-        }
+    async fn remove_label(&self, label_id: i32) -> anyhow::Result<()> {
+        // todo_labels cascades on delete from labels, this only matters if it is called first
+        sqlx::query(
+            r#"
+            delete from todo_labels where label_id=$1
+            "#,
+        )
+        .bind(label_id)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
     }
 
-    type TodoData = HashMap<i32, Todo>;
+    async fn move_list(&self, from: i32, to: i32) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            update todos set list_id=$2, version=version+1
+            where list_id=$1 and todo_visible(user_id, list_id, workspace_id, $3, $4)
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
 
-    #[derive(Debug, Clone)]
-    pub struct TodoRepositoryForMemory {
-        pub store: Arc<RwLock<TodoData>>,
+        Ok(())
     }
 
-    impl TodoRepositoryForMemory {
-        pub fn new() -> Self {
-            TodoRepositoryForMemory {
-                store: Arc::default(),
-            }
-        }
-
-        fn write_store_ref(&self) -> RwLockWriteGuard<TodoData> {
-            self.store.write().unwrap()
-        }
+    async fn delete_list(&self, list_id: i32) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            delete from todos where list_id=$1 and todo_visible(user_id, list_id, workspace_id, $2, $3)
+            "#,
+        )
+        .bind(list_id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
 
-        fn read_store_ref(&self) -> RwLockReadGuard<TodoData> {
-            self.store.read().unwrap()
-        }
+        Ok(())
     }
 
-    #[async_trait]
-    impl TodoRepositoryTrait for TodoRepositoryForMemory {
-        async fn create(&self, payload: CreateTodo) -> anyhow::Result<Todo> {
-            let mut store = self.write_store_ref();
-            let id = (store.len() + 1) as i32;
-            let todo = Todo::new(id, payload.text.clone());
-            store.insert(id, todo.clone());
-            Ok(todo)
-        }
+    async fn add_occurrence(&self, series_id: i32, payload: CreateTodo) -> anyhow::Result<Todo> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        let todo = self.insert(&mut tx, payload, Some(series_id)).await?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+        Ok(todo)
+    }
 
-        async fn find(&self, id: i32) -> anyhow::Result<Todo> {
-            let store = self.read_store_ref();
-            let todo = store
-                .get(&id)
-                .map(|todo| todo.clone())
-                .ok_or(RepositoryError::NotFound(id))?;
-            Ok(todo)
+    async fn find_series(&self, series_id: i32) -> anyhow::Result<TodoSeries> {
+        let series = sqlx::query_as::<_, TodoSeries>(
+            r#"
+            select * from todo_series where id=$1 and series_visible(id, user_id, workspace_id, $2, $3)
+            "#,
+        )
+        .bind(series_id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(series_id))?;
+
+        Ok(series)
+    }
+
+    async fn series_occurrences(&self, series_id: i32) -> anyhow::Result<Vec<Todo>> {
+        let todos = sqlx::query_as::<_, Todo>(
+            r#"
+            select * from todos where series_id=$1 and todo_visible(user_id, list_id, workspace_id, $2, $3)
+            order by due_at, id
+            "#,
+        )
+        .bind(series_id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(todos)
+    }
+
+    async fn start_series(&self, id: i32, rrule: String) -> anyhow::Result<Todo> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        let series_id = sqlx::query_scalar::<_, i32>(
+            r#"
+            insert into todo_series (rrule, text, priority, user_id, workspace_id)
+            select $2, text, priority, user_id, workspace_id from todos
+            where id=$1 and todo_visible(user_id, list_id, workspace_id, $3, $4)
+            returning id
+            "#,
+        )
+        .bind(id)
+        .bind(&rrule)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+        let todo = sqlx::query_as::<_, Todo>(
+            r#"
+            update todos set rrule=$2, series_id=$3, version=version+1
+            where id=$1
+            returning *
+            "#,
+        )
+        .bind(id)
+        .bind(rrule)
+        .bind(series_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+
+        Ok(todo)
+    }
+
+    async fn update_series(
+        &self,
+        series_id: i32,
+        payload: &UpdateTodo,
+    ) -> anyhow::Result<TodoSeries> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        let series = sqlx::query_as::<_, TodoSeries>(
+            r#"
+            update todo_series set text=coalesce($1, text), priority=coalesce($2, priority),
+                rrule=coalesce($3, rrule)
+            where id=$4 and series_visible(id, user_id, workspace_id, $5, $6)
+            returning *
+            "#,
+        )
+        .bind(&payload.text)
+        .bind(payload.priority)
+        .bind(payload.rrule.clone().flatten())
+        .bind(series_id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(RepositoryError::from_sqlx(series_id))?;
+        sqlx::query(
+            r#"
+            update todos set text=coalesce($1, text), priority=coalesce($2, priority),
+                rrule=case when $3 then $4 else rrule end, version=version+1
+            where series_id=$5 and not completed and todo_visible(user_id, list_id, workspace_id, $6, $7)
+            "#,
+        )
+        .bind(&payload.text)
+        .bind(payload.priority)
+        .bind(payload.rrule.is_some())
+        .bind(payload.rrule.clone().flatten())
+        .bind(series_id)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .execute(&mut *tx)
+        .await
+        .map_err(RepositoryError::from)?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+
+        Ok(series)
+    }
+
+    async fn find_due(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Todo>> {
+        let todos = sqlx::query_as::<_, Todo>(
+            r#"
+            select * from todos
+            where not completed and due_at < $2 and ($1::timestamptz is null or due_at >= $1)
+                and todo_visible(user_id, list_id, workspace_id, $3, $4)
+            order by due_at, id;
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(todos)
+    }
+
+    async fn changes(&self, cursor: i64, limit: u32) -> anyhow::Result<Vec<TodoChange>> {
+        #[derive(sqlx::FromRow)]
+        struct Changed {
+            #[sqlx(flatten)]
+            todo: Todo,
+            change_seq: i64,
+            updated_at: DateTime<Utc>,
         }
 
-        async fn all(&self) -> anyhow::Result<Vec<Todo>> {
-            let store = self.read_store_ref();
-            Ok(Vec::from_iter(store.values().map(|todo| todo.clone())))
+        let changed = sqlx::query_as::<_, Changed>(
+            r#"
+            select * from todos
+            where change_seq > $1 and todo_visible(user_id, list_id, workspace_id, $2, $3)
+            order by change_seq
+            limit $4;
+            "#,
+        )
+        .bind(cursor)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+        let deleted = sqlx::query_as::<_, (i32, i64, DateTime<Utc>)>(
+            r#"
+            select todo_id, change_seq, deleted_at from todo_tombstones
+            where change_seq > $1 and todo_visible(user_id, list_id, workspace_id, $2, $3)
+            order by change_seq
+            limit $4;
+            "#,
+        )
+        .bind(cursor)
+        .bind(self.owner)
+        .bind(self.workspace)
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        let mut changes = Vec::from_iter(
+            changed
+                .into_iter()
+                .map(|changed| TodoChange::Upserted {
+                    seq: changed.change_seq,
+                    updated_at: changed.updated_at,
+                    todo: changed.todo,
+                })
+                .chain(
+                    deleted
+                        .into_iter()
+                        .map(|(id, seq, deleted_at)| TodoChange::Deleted {
+                            seq,
+                            deleted_at,
+                            id,
+                        }),
+                ),
+        );
+        changes.sort_by_key(TodoChange::seq);
+        changes.truncate(limit as usize);
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use crate::labels::{
+        model::CreateLabel, repository::LabelRepositoryForDb, repository::LabelRepositoryTrait,
+    };
+    use crate::lists::{
+        model::CreateList, repository::ListRepositoryForDb, repository::ListRepositoryTrait,
+    };
+    use crate::members::{
+        model::Role, repository::MemberRepositoryForDb, repository::MemberRepositoryTrait,
+    };
+    use crate::todos::repository::scenario::{self, Fixtures};
+    use dotenv::dotenv;
+    use sqlx::PgPool;
+    use std::env;
+
+    /// Lists and labels of user `.1`.
+    struct DbFixtures(PgPool, i32);
+
+    async fn create_user(pool: &PgPool, email: String) -> i32 {
+        sqlx::query_scalar::<_, i32>(
+            "insert into users (email, password_hash) values ($1, '') returning id",
+        )
+        .bind(email)
+        .fetch_one(pool)
+        .await
+        .expect("[create user] returned Err")
+    }
+
+    #[async_trait]
+    impl Fixtures for DbFixtures {
+        async fn inbox_id(&self) -> i32 {
+            ListRepositoryForDb::new(self.0.clone())
+                .owned_by(self.1)
+                .inbox()
+                .await
+                .expect("[inbox] returned Err")
+                .id
         }
 
-        async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<Todo> {
-            let mut store = self.write_store_ref();
-            let todo = store.get(&id).context(RepositoryError::NotFound(id))?;
-            let text = payload.text.unwrap_or(todo.text.clone());
-            let completed = payload.completed.unwrap_or(todo.completed);
-            let todo = Todo {
-                id,
-                text,
-                completed,
-            };
-            store.insert(id, todo.clone());
-            Ok(todo)
+        async fn create_list(&self, name: &str) -> i32 {
+            let list = ListRepositoryForDb::new(self.0.clone())
+                .owned_by(self.1)
+                .create(CreateList {
+                    name: name.to_string(),
+                })
+                .await
+                .expect("[create list] returned Err");
+            MemberRepositoryForDb::new(self.0.clone())
+                .add(list.id, self.1, Role::Owner)
+                .await
+                .expect("[add member] returned Err");
+            list.id
         }
 
-        async fn delete(&self, id: i32) -> anyhow::Result<()> {
-            let mut store = self.write_store_ref();
-            store.remove(&id).ok_or(RepositoryError::NotFound(id))?;
-            Ok(())
+        async fn delete_list(&self, id: i32) {
+            ListRepositoryForDb::new(self.0.clone())
+                .owned_by(self.1)
+                .delete(id)
+                .await
+                .expect("[delete list] returned Err");
+        }
+
+        async fn create_label(&self, name: &str) -> i32 {
+            LabelRepositoryForDb::new(self.0.clone())
+                .owned_by(self.1)
+                .create(CreateLabel {
+                    name: name.to_string(),
+                    color: None,
+                })
+                .await
+                .expect("[create label] returned Err")
+                .id
+        }
+
+        async fn delete_label(&self, id: i32) {
+            LabelRepositoryForDb::new(self.0.clone())
+                .owned_by(self.1)
+                .delete(id)
+                .await
+                .expect("[delete label] returned Err");
+        }
+
+        async fn todo_rows(&self, id: i32) -> usize {
+            sqlx::query::<_>(r#"select * from todos where id=$1"#)
+                .bind(id)
+                .fetch_all(&self.0)
+                .await
+                .expect("[delete] todo_labels fetch error")
+                .len()
         }
     }
 
-    #[cfg(test)]
-    mod test {
-        use super::*;
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
 
-        #[tokio::test]
-        async fn todo_crud_scenario() {
-            let text = "todo text".to_string();
-            let id = 1;
-            let expected = Todo::new(id, text.clone());
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
 
-            // create
-            let repository = TodoRepositoryForMemory::new();
-            let todo = repository
-                .create(CreateTodo { text })
+        let user = create_user(
+            &pool,
+            format!(
+                "crud_scenario.{}@example.com",
+                Utc::now().timestamp_micros()
+            ),
+        )
+        .await;
+        let repository = TodoRepositoryForDb::new(pool.clone());
+        scenario::crud_scenario(&repository.owned_by(user), &DbFixtures(pool.clone(), user)).await;
+
+        // without an owner nothing is seen, nor created
+        assert_eq!(
+            repository
+                .count(&TodoQuery::default())
                 .await
-                .expect("failed to create a todo");
-            assert_eq!(expected, todo);
+                .expect("[count] returned Err"),
+            0
+        );
+        assert!(repository
+            .create(CreateTodo::new("[crud_scenario] ownerless".to_string()))
+            .await
+            .is_err());
+
+        sqlx::query("delete from users where id=$1")
+            .bind(user)
+            .execute(&pool)
+            .await
+            .expect("[delete users] returned Err");
+    }
 
-            // find
-            let todo = repository.find(todo.id).await.unwrap();
-            assert_eq!(expected, todo);
+    #[tokio::test]
+    async fn owner_scenario() {
+        dotenv().ok();
 
-            // all
-            let todo = repository.all().await.expect("failed to get all todos");
-            assert_eq!(vec![expected], todo);
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
 
-            // update
-            let text = "update todo".to_string();
-            let todo = repository
-                .update(
-                    1,
-                    UpdateTodo {
-                        text: Some(text.clone()),
-                        completed: Some(true),
+        let mut owners = vec![];
+        for name in ["alice", "bob"] {
+            let id = sqlx::query_scalar::<_, i32>(
+                "insert into users (email, password_hash) values ($1, '') returning id",
+            )
+            .bind(format!(
+                "owner_scenario.{name}.{}@example.com",
+                Utc::now().timestamp_micros()
+            ))
+            .fetch_one(&pool)
+            .await
+            .expect("[create user] returned Err");
+            owners.push(id);
+        }
+
+        let repository = TodoRepositoryForDb::new(pool.clone());
+        scenario::owner_scenario(
+            &repository.owned_by(owners[0]),
+            &repository.owned_by(owners[1]),
+        )
+        .await;
+
+        sqlx::query("delete from users where id=any($1)")
+            .bind(&owners)
+            .execute(&pool)
+            .await
+            .expect("[delete users] returned Err");
+    }
+
+    #[tokio::test]
+    async fn changes_scenario() {
+        dotenv().ok();
+
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
+
+        let mut owners = vec![];
+        for name in ["alice", "bob"] {
+            let id = sqlx::query_scalar::<_, i32>(
+                "insert into users (email, password_hash) values ($1, '') returning id",
+            )
+            .bind(format!(
+                "changes_scenario.{name}.{}@example.com",
+                Utc::now().timestamp_micros()
+            ))
+            .fetch_one(&pool)
+            .await
+            .expect("[create user] returned Err");
+            owners.push(id);
+        }
+
+        let repository = TodoRepositoryForDb::new(pool.clone());
+        scenario::changes_scenario(
+            &repository.owned_by(owners[0]),
+            &repository.owned_by(owners[1]),
+        )
+        .await;
+
+        sqlx::query("delete from users where id=any($1)")
+            .bind(&owners)
+            .execute(&pool)
+            .await
+            .expect("[delete users] returned Err");
+    }
+
+    #[tokio::test]
+    async fn concurrent_creates_get_distinct_positions() {
+        dotenv().ok();
+
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
+        let owner = create_user(
+            &pool,
+            format!(
+                "concurrent_creates_get_distinct_positions.{}@example.com",
+                Utc::now().timestamp_micros()
+            ),
+        )
+        .await;
+        let repository = TodoRepositoryForDb::new(pool.clone()).owned_by(owner);
+
+        let creates = (0..8).map(|i| {
+            let repository = repository.clone();
+            tokio::spawn(async move {
+                repository
+                    .create(CreateTodo::new(format!(
+                        "[concurrent_creates_get_distinct_positions] {i}"
+                    )))
+                    .await
+                    .expect("[create] returned Err")
+            })
+        });
+        let mut positions = BTreeSet::new();
+        for create in creates.collect::<Vec<_>>() {
+            positions.insert(create.await.unwrap().position);
+        }
+        assert_eq!(positions.len(), 8);
+
+        sqlx::query("delete from users where id=$1")
+            .bind(owner)
+            .execute(&pool)
+            .await
+            .expect("[delete user] returned Err");
+    }
+
+    #[tokio::test]
+    async fn changes_commit_in_order() {
+        dotenv().ok();
+
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
+        let owner = create_user(
+            &pool,
+            format!(
+                "changes_commit_in_order.{}@example.com",
+                Utc::now().timestamp_micros()
+            ),
+        )
+        .await;
+        let repository = TodoRepositoryForDb::new(pool.clone()).owned_by(owner);
+        let todo = repository
+            .create(CreateTodo::new(
+                "[changes_commit_in_order] first".to_string(),
+            ))
+            .await
+            .expect("[create] returned Err");
+        let start = repository
+            .changes(0, u32::MAX)
+            .await
+            .unwrap()
+            .last()
+            .unwrap()
+            .seq();
+
+        // takes a change and holds it uncommitted
+        let mut tx = pool.begin().await.unwrap();
+        sqlx::query("update todos set text='[changes_commit_in_order] updated' where id=$1")
+            .bind(todo.id)
+            .execute(&mut *tx)
+            .await
+            .expect("[update] returned Err");
+        let later = tokio::spawn({
+            let repository = repository.clone();
+            async move {
+                repository
+                    .create(CreateTodo::new(
+                        "[changes_commit_in_order] later".to_string(),
+                    ))
+                    .await
+                    .expect("[create] returned Err")
+            }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        // a later change committed first would have moved the cursor past the held one
+        assert!(repository.changes(start, 10).await.unwrap().is_empty());
+        tx.commit().await.unwrap();
+        let later = later.await.unwrap();
+
+        let ids = Vec::from_iter(
+            repository
+                .changes(start, 10)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|change| match change {
+                    TodoChange::Upserted { todo, .. } => todo.id,
+                    change => panic!("[changes] unexpected {change:?}"),
+                }),
+        );
+        assert_eq!(ids, vec![todo.id, later.id]);
+
+        sqlx::query("delete from users where id=$1")
+            .bind(owner)
+            .execute(&pool)
+            .await
+            .expect("[delete user] returned Err");
+    }
+
+    #[tokio::test]
+    async fn workspace_scenario() {
+        use crate::workspaces::model::CreateWorkspace;
+        use crate::workspaces::repository::{WorkspaceRepositoryForDb, WorkspaceRepositoryTrait};
+
+        dotenv().ok();
+
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
+
+        let stamp = Utc::now().timestamp_micros();
+        let user = sqlx::query_scalar::<_, i32>(
+            "insert into users (email, password_hash) values ($1, '') returning id",
+        )
+        .bind(format!("workspace_scenario.{stamp}@example.com"))
+        .fetch_one(&pool)
+        .await
+        .expect("[create user] returned Err");
+        let workspaces = WorkspaceRepositoryForDb::new(pool.clone());
+        let mut ids = vec![];
+        for name in ["acme", "globex"] {
+            let workspace = workspaces
+                .create(
+                    user,
+                    CreateWorkspace {
+                        name: format!("workspace_scenario.{name}.{stamp}"),
                     },
                 )
                 .await
-                .expect("failed update todo.");
-            assert_eq!(
-                Todo {
-                    id,
-                    text,
-                    completed: true,
+                .expect("[create workspace] returned Err");
+            ids.push(workspace.id);
+        }
+
+        // one user in two workspaces is two tenants
+        let repository = TodoRepositoryForDb::new(pool.clone()).owned_by(user);
+        scenario::owner_scenario(
+            &repository.in_workspace(ids[0]),
+            &repository.in_workspace(ids[1]),
+        )
+        .await;
+
+        // todos are stored in the workspace they are created in
+        let todo = repository
+            .in_workspace(ids[0])
+            .create(CreateTodo::new("[workspace_scenario] acme".to_string()))
+            .await
+            .expect("[create] returned Err");
+        let workspace_id =
+            sqlx::query_scalar::<_, i32>("select workspace_id from todos where id=$1")
+                .bind(todo.id)
+                .fetch_one(&pool)
+                .await
+                .expect("[select workspace_id] returned Err");
+        assert_eq!(workspace_id, ids[0]);
+        let globex = repository.in_workspace(ids[1]);
+        assert!(globex.find(todo.id).await.is_err());
+        assert_eq!(
+            globex
+                .count(&TodoQuery::default())
+                .await
+                .expect("[count] returned Err"),
+            0
+        );
+
+        // nor are lists, which only hold the todos of their workspace
+        let list = ListRepositoryForDb::new(pool.clone())
+            .owned_by(user)
+            .in_workspace(ids[0])
+            .create(CreateList {
+                name: "[workspace_scenario] acme".to_string(),
+            })
+            .await
+            .expect("[create list] returned Err");
+        let res = globex
+            .create(CreateTodo {
+                list_id: Some(list.id),
+                ..CreateTodo::new("[workspace_scenario] globex".to_string())
+            })
+            .await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::Invalid(_))
+        ));
+
+        sqlx::query("delete from workspaces where id=any($1)")
+            .bind(&ids)
+            .execute(&pool)
+            .await
+            .expect("[delete workspaces] returned Err");
+        sqlx::query("delete from users where id=$1")
+            .bind(user)
+            .execute(&pool)
+            .await
+            .expect("[delete users] returned Err");
+    }
+
+    #[tokio::test]
+    async fn shared_list_scenario() {
+        dotenv().ok();
+
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
+
+        let stamp = Utc::now().timestamp_micros();
+        let mut users = vec![];
+        for name in ["alice", "bob"] {
+            let id = sqlx::query_scalar::<_, i32>(
+                "insert into users (email, password_hash) values ($1, '') returning id",
+            )
+            .bind(format!("shared_list_scenario.{name}.{stamp}@example.com"))
+            .fetch_one(&pool)
+            .await
+            .expect("[create user] returned Err");
+            users.push(id);
+        }
+        let list = ListRepositoryForDb::new(pool.clone())
+            .owned_by(users[0])
+            .create(CreateList {
+                name: format!("shared_list_scenario.{stamp}"),
+            })
+            .await
+            .expect("[create list] returned Err");
+        let members = MemberRepositoryForDb::new(pool.clone());
+        members.add(list.id, users[0], Role::Owner).await.unwrap();
+
+        let repository = TodoRepositoryForDb::new(pool.clone());
+        let (alice, bob) = (repository.owned_by(users[0]), repository.owned_by(users[1]));
+        let shared = alice
+            .create(CreateTodo {
+                list_id: Some(list.id),
+                ..CreateTodo::new("[shared_list_scenario] shared".to_string())
+            })
+            .await
+            .expect("[create] returned Err");
+        let private = bob
+            .create(CreateTodo::new(
+                "[shared_list_scenario] private".to_string(),
+            ))
+            .await
+            .expect("[create] returned Err");
+        let in_list = TodoQuery {
+            list_id: Some(list.id),
+            ..Default::default()
+        };
+        assert!(bob.find(shared.id).await.is_err());
+        assert_eq!(bob.count(&in_list).await.unwrap(), 0);
+
+        // members see every todo of the list, and only those
+        members.add(list.id, users[1], Role::Viewer).await.unwrap();
+        assert_eq!(bob.find(shared.id).await.unwrap(), shared);
+        assert_eq!(bob.all(&in_list).await.unwrap(), vec![shared.clone()]);
+        assert!(alice.find(private.id).await.is_err());
+
+        members.remove(list.id, users[1]).await.unwrap();
+        assert!(bob.find(shared.id).await.is_err());
+
+        sqlx::query("delete from todos where id=any($1)")
+            .bind([shared.id, private.id])
+            .execute(&pool)
+            .await
+            .expect("[delete todos] returned Err");
+        sqlx::query("delete from lists where id=$1")
+            .bind(list.id)
+            .execute(&pool)
+            .await
+            .expect("[delete list] returned Err");
+        sqlx::query("delete from users where id=any($1)")
+            .bind(&users)
+            .execute(&pool)
+            .await
+            .expect("[delete users] returned Err");
+    }
+}
+
+/// The scenario every database-backed repository has to pass.
+#[cfg(test)]
+pub(crate) mod scenario {
+    use super::*;
+    use crate::todos::model::Priority;
+
+    /// Rows of the other domains the scenario needs, written however the database allows.
+    #[async_trait]
+    pub(crate) trait Fixtures {
+        async fn inbox_id(&self) -> i32;
+        async fn create_list(&self, name: &str) -> i32;
+        async fn delete_list(&self, id: i32);
+        async fn create_label(&self, name: &str) -> i32;
+        async fn delete_label(&self, id: i32);
+        /// How many rows of `todos` have `id`, read around the repository.
+        async fn todo_rows(&self, id: i32) -> usize;
+    }
+
+    pub(crate) async fn crud_scenario<R, F>(repository: &R, fixtures: &F)
+    where
+        R: TodoRepositoryTrait,
+        F: Fixtures + Sync,
+    {
+        let todo_text = "[crud_senario] text";
+
+        // create
+        let created = repository
+            .create(CreateTodo::new(todo_text.to_string()))
+            .await
+            .expect("[create] returned Err");
+
+        assert_eq!(created.text, todo_text);
+        assert!(!created.completed);
+
+        // find
+        let todo = repository
+            .find(created.id)
+            .await
+            .expect("[find] returned Err");
+
+        assert_eq!(created, todo);
+
+        // all
+        let todos = repository
+            .all(&TodoQuery::default())
+            .await
+            .expect("[all] returned Err");
+
+        let todo = todos.first().unwrap();
+        assert_eq!(created, *todo);
+
+        // move
+        let last = repository
+            .create(CreateTodo::new("[crud_scenario] last".to_string()))
+            .await
+            .expect("[create] returned Err");
+        let moved = repository
+            .move_before(last.id, Some(created.id))
+            .await
+            .expect("[move_before] returned Err");
+        assert!(moved.position < created.position);
+        let todos = repository
+            .all(&TodoQuery {
+                sort: TodoSort::Position,
+                ..Default::default()
+            })
+            .await
+            .expect("[all] returned Err");
+        let index = |id: i32| todos.iter().position(|t| t.id == id).unwrap();
+        assert_eq!(index(last.id) + 1, index(created.id));
+        repository
+            .delete(last.id, None)
+            .await
+            .expect("[delete] returned Err");
+
+        // update
+        let updated_text = "[crud_scenario] updated text";
+        let todo = repository
+            .update(
+                todo.id,
+                UpdateTodo {
+                    text: Some(updated_text.to_string()),
+                    completed: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        assert_eq!(created.id, todo.id);
+        assert_eq!(todo.text, updated_text);
+
+        // versions
+        let stale = todo.version;
+        let todo = repository
+            .update(
+                todo.id,
+                UpdateTodo {
+                    completed: Some(true),
+                    version: Some(stale),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        assert_eq!(todo.version, stale + 1);
+        let lost = UpdateTodo {
+            text: Some("[crud_scenario] lost update".to_string()),
+            version: Some(stale),
+            ..Default::default()
+        };
+        let res = repository.update(todo.id, lost.clone()).await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::Conflict(_))
+        ));
+        let res = repository.delete(todo.id, Some(stale)).await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::Conflict(_))
+        ));
+        let res = repository.update(-1, lost).await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::NotFound(-1))
+        ));
+        assert_eq!(
+            repository.find(todo.id).await.expect("[find] returned Err"),
+            todo
+        );
+
+        // schedule
+        let due_at = Utc::now() + chrono::Duration::hours(1);
+        let todo = repository
+            .update(
+                todo.id,
+                UpdateTodo {
+                    completed: Some(false),
+                    due_at: Some(Some(due_at)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        assert_eq!(todo.due_at.map(|d| d.timestamp()), Some(due_at.timestamp()));
+
+        let todos = repository
+            .find_due(None, due_at + chrono::Duration::seconds(1))
+            .await
+            .expect("[find_due] returned Err");
+        assert!(todos.iter().any(|t| t.id == todo.id));
+
+        let todos = repository
+            .find_due(None, due_at - chrono::Duration::seconds(1))
+            .await
+            .expect("[find_due] returned Err");
+        assert!(!todos.iter().any(|t| t.id == todo.id));
+
+        // labels
+        let label_id = fixtures.create_label("[crud_scenario] todo label").await;
+        repository
+            .attach_label(todo.id, label_id)
+            .await
+            .expect("[attach_label] returned Err");
+        repository
+            .attach_label(todo.id, label_id)
+            .await
+            .expect("[attach_label] returned Err");
+        let todos = repository
+            .all(&TodoQuery {
+                labels: vec![label_id],
+                ..Default::default()
+            })
+            .await
+            .expect("[all] returned Err");
+        assert_eq!(todos, vec![todo.clone()]);
+        let label_ids = repository
+            .label_ids(todo.id)
+            .await
+            .expect("[label_ids] returned Err");
+        assert_eq!(label_ids, vec![label_id]);
+        fixtures.delete_label(label_id).await;
+        let label_ids = repository
+            .label_ids(todo.id)
+            .await
+            .expect("[label_ids] returned Err");
+        assert!(label_ids.is_empty());
+
+        // subtasks
+        let subtask = repository
+            .create(CreateTodo {
+                parent_id: Some(todo.id),
+                ..CreateTodo::new("[crud_scenario] subtask".to_string())
+            })
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(subtask.list_id, todo.list_id);
+        let nested = repository
+            .create(CreateTodo {
+                parent_id: Some(subtask.id),
+                ..CreateTodo::new("[crud_scenario] nested subtask".to_string())
+            })
+            .await
+            .expect("[create] returned Err");
+        let ancestor_ids = repository
+            .ancestor_ids(nested.id)
+            .await
+            .expect("[ancestor_ids] returned Err");
+        assert_eq!(ancestor_ids, vec![subtask.id, todo.id]);
+        let mut subtree = repository
+            .subtree(todo.id)
+            .await
+            .expect("[subtree] returned Err");
+        subtree.sort_by_key(|t| t.id);
+        assert_eq!(subtree, vec![todo.clone(), subtask.clone(), nested.clone()]);
+        repository
+            .delete(subtask.id, None)
+            .await
+            .expect("[delete] returned Err");
+        let res = repository.find(nested.id).await;
+        assert!(res.is_err());
+
+        // series
+        let recurring = repository
+            .create(CreateTodo {
+                due_at: Some(due_at),
+                rrule: Some("FREQ=DAILY".to_string()),
+                ..CreateTodo::new("[crud_scenario] recurring".to_string())
+            })
+            .await
+            .expect("[create] returned Err");
+        let series_id = recurring.series_id.expect("[create] didn't start a series");
+        let next = repository
+            .add_occurrence(
+                series_id,
+                CreateTodo {
+                    due_at: Some(due_at + chrono::Duration::days(1)),
+                    rrule: recurring.rrule.clone(),
+                    ..CreateTodo::new("[crud_scenario] recurring".to_string())
+                },
+            )
+            .await
+            .expect("[add_occurrence] returned Err");
+        let series = repository
+            .update_series(
+                series_id,
+                &UpdateTodo {
+                    text: Some("[crud_scenario] every other day".to_string()),
+                    rrule: Some(Some("FREQ=DAILY;INTERVAL=2".to_string())),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("[update_series] returned Err");
+        assert_eq!(series.rrule, "FREQ=DAILY;INTERVAL=2");
+        let occurrences = repository
+            .series_occurrences(series_id)
+            .await
+            .expect("[series_occurrences] returned Err");
+        assert_eq!(
+            occurrences.iter().map(|t| t.id).collect::<Vec<_>>(),
+            vec![recurring.id, next.id]
+        );
+        assert!(occurrences
+            .iter()
+            .all(|t| t.text == series.text && t.rrule.as_ref() == Some(&series.rrule)));
+        let started = repository
+            .start_series(todo.id, "FREQ=WEEKLY".to_string())
+            .await
+            .expect("[start_series] returned Err");
+        assert!(started.series_id.is_some_and(|id| id != series_id));
+        repository
+            .delete(recurring.id, None)
+            .await
+            .expect("[delete] returned Err");
+        repository
+            .delete(next.id, None)
+            .await
+            .expect("[delete] returned Err");
+
+        // lists
+        let inbox_id = fixtures.inbox_id().await;
+        assert_eq!(todo.list_id, inbox_id);
+        let list_id = fixtures.create_list("[crud_scenario] todo list").await;
+        let todo = repository
+            .update(
+                todo.id,
+                UpdateTodo {
+                    list_id: Some(list_id),
+                    ..Default::default()
                 },
-                todo
-            );
+            )
+            .await
+            .expect("[update] returned Err");
+        assert_eq!(todo.list_id, list_id);
+        let todos = repository
+            .all(&TodoQuery {
+                list_id: Some(list_id),
+                ..Default::default()
+            })
+            .await
+            .expect("[all] returned Err");
+        assert_eq!(todos, vec![todo.clone()]);
+        repository
+            .move_list(list_id, inbox_id)
+            .await
+            .expect("[move_list] returned Err");
+        let todo = repository.find(todo.id).await.expect("[find] returned Err");
+        assert_eq!(todo.list_id, inbox_id);
 
-            // delete
-            let res = repository.delete(id).await;
-            assert!(res.is_ok())
+        // pages
+        let due = |day: u32| format!("2024-05-{day:02}T09:00:00Z").parse().ok();
+        let mut paged = Vec::new();
+        for (text, due_at, priority) in [
+            ("[crud_scenario] Buy milk", due(3), Priority::High),
+            ("[crud_scenario] buy bread", None, Priority::High),
+            ("[crud_scenario] walk", due(1), Priority::Low),
+            ("[crud_scenario] buy tea", due(1), Priority::None),
+        ] {
+            let todo = repository
+                .create(CreateTodo {
+                    list_id: Some(list_id),
+                    due_at,
+                    priority,
+                    ..CreateTodo::new(text.to_string())
+                })
+                .await
+                .expect("[create] returned Err");
+            paged.push(todo.id);
+        }
+        for (sort, text, expected) in [
+            (
+                TodoSort::DueDate,
+                None,
+                vec![paged[2], paged[3], paged[0], paged[1]],
+            ),
+            (
+                TodoSort::Priority,
+                None,
+                vec![paged[0], paged[1], paged[2], paged[3]],
+            ),
+            (
+                TodoSort::CreatedAt,
+                Some("BUY"),
+                vec![paged[3], paged[1], paged[0]],
+            ),
+        ] {
+            let mut query = TodoQuery {
+                sort,
+                list_id: Some(list_id),
+                text: text.map(str::to_string),
+                limit: Some(2),
+                ..Default::default()
+            };
+            let mut ids = Vec::new();
+            loop {
+                let page = repository.page(&query).await.expect("[page] returned Err");
+                assert_eq!(page.total, expected.len() as i64);
+                ids.extend(page.todos.iter().map(|t| t.id));
+                match page.next_cursor {
+                    Some(cursor) => query.cursor = Some(cursor.parse().unwrap()),
+                    None => break,
+                }
+            }
+            assert_eq!(ids, expected, "{sort:?}");
+        }
+        let count = repository
+            .count(&TodoQuery {
+                list_id: Some(list_id),
+                completed: Some(false),
+                due_from: due(1),
+                due_to: due(3),
+                ..Default::default()
+            })
+            .await
+            .expect("[count] returned Err");
+        assert_eq!(count, 2);
+        let hits = Vec::from_iter(
+            repository
+                .search("crud_scenario BUY", 200)
+                .await
+                .expect("[search] returned Err")
+                .into_iter()
+                .filter(|hit| paged.contains(&hit.todo.id)),
+        );
+        assert_eq!(
+            Vec::from_iter(hits.iter().map(|hit| hit.todo.id)),
+            vec![paged[0], paged[1], paged[3]]
+        );
+        assert_eq!(
+            hits[0].snippet,
+            "[<mark>crud</mark>_<mark>scenario</mark>] <mark>Buy</mark> milk"
+        );
+        assert!(hits[0].rank >= hits[1].rank);
+        repository
+            .delete_list(list_id)
+            .await
+            .expect("[delete_list] returned Err");
+
+        fixtures.delete_list(list_id).await;
+        let res = repository
+            .update(
+                todo.id,
+                UpdateTodo {
+                    list_id: Some(list_id),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::Invalid(_))
+        ));
+
+        // delete
+        repository
+            .delete(todo.id, None)
+            .await
+            .expect("[delete] returned Err");
+
+        let res = repository.find(created.id).await;
+        assert!(res.is_err());
+
+        let res = repository.delete(created.id, None).await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::NotFound(id)) if id == created.id
+        ));
+
+        assert_eq!(fixtures.todo_rows(todo.id).await, 0);
+    }
+
+    /// Nothing of `alice` can be read or written through `bob`, be it another user or the same
+    /// one in another workspace.
+    pub(crate) async fn owner_scenario<R>(alice: &R, bob: &R)
+    where
+        R: TodoRepositoryTrait,
+    {
+        let todo = alice
+            .create(CreateTodo {
+                rrule: Some("FREQ=DAILY".to_string()),
+                ..CreateTodo::new("[owner_scenario] ownedtodo".to_string())
+            })
+            .await
+            .expect("[create] returned Err");
+        let series_id = todo.series_id.expect("[create] started no series");
+        let not_found = |res: anyhow::Result<()>| {
+            matches!(
+                res.unwrap_err().downcast::<RepositoryError>(),
+                Ok(RepositoryError::NotFound(_))
+            )
+        };
+
+        assert!(not_found(bob.find(todo.id).await.map(drop)));
+        assert!(not_found(bob.subtree(todo.id).await.map(drop)));
+        assert!(not_found(bob.move_before(todo.id, None).await.map(drop)));
+        assert!(not_found(bob.find_series(series_id).await.map(drop)));
+        assert!(not_found(
+            bob.update(todo.id, UpdateTodo::default()).await.map(drop)
+        ));
+        assert!(not_found(bob.delete(todo.id, None).await));
+
+        let all = bob
+            .all(&TodoQuery::default())
+            .await
+            .expect("[all] returned Err");
+        assert!(!all.iter().any(|t| t.id == todo.id));
+        let hits = bob
+            .search("ownedtodo", 10)
+            .await
+            .expect("[search] returned Err");
+        assert!(hits.is_empty());
+        let occurrences = bob
+            .series_occurrences(series_id)
+            .await
+            .expect("[series_occurrences] returned Err");
+        assert!(occurrences.is_empty());
+
+        // untouched for its owner
+        assert_eq!(
+            alice.find(todo.id).await.expect("[find] returned Err"),
+            todo
+        );
+        let all = alice
+            .all(&TodoQuery::default())
+            .await
+            .expect("[all] returned Err");
+        assert!(all.contains(&todo));
+        let hits = alice
+            .search("ownedtodo", 10)
+            .await
+            .expect("[search] returned Err");
+        assert_eq!(hits.len(), 1);
+
+        alice
+            .delete(todo.id, None)
+            .await
+            .expect("[delete] returned Err");
+    }
+
+    /// `repository` only sees the todos of the scenario, and bob doesn't see any of them.
+    pub(crate) async fn changes_scenario<R>(repository: &R, bob: &R)
+    where
+        R: TodoRepositoryTrait,
+    {
+        let changes = |cursor: i64, limit: u32| async move {
+            repository
+                .changes(cursor, limit)
+                .await
+                .expect("[changes] returned Err")
+        };
+        let start = changes(0, u32::MAX).await.last().map_or(0, TodoChange::seq);
+
+        let parent = repository
+            .create(CreateTodo::new("[changes_scenario] parent".to_string()))
+            .await
+            .expect("[create] returned Err");
+        let child = repository
+            .create(CreateTodo {
+                parent_id: Some(parent.id),
+                ..CreateTodo::new("[changes_scenario] child".to_string())
+            })
+            .await
+            .expect("[create] returned Err");
+
+        let created = changes(start, 10).await;
+        let todos = Vec::from_iter(created.iter().map(|change| match change {
+            TodoChange::Upserted { todo, .. } => todo.clone(),
+            change => panic!("[changes] unexpected {change:?}"),
+        }));
+        assert_eq!(todos, vec![parent.clone(), child.clone()]);
+        assert!(created[0].seq() < created[1].seq());
+        assert_eq!(changes(start, 1).await, created[..1]);
+        assert!(bob
+            .changes(start, 10)
+            .await
+            .expect("[changes] returned Err")
+            .is_empty());
+
+        // only the last change of a todo is kept
+        let cursor = created[1].seq();
+        let updated = repository
+            .update(
+                parent.id,
+                UpdateTodo {
+                    text: Some("[changes_scenario] updated".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        let changed = changes(start, 10).await;
+        assert_eq!(changed.len(), 2);
+        assert!(matches!(
+            &changed[..],
+            [TodoChange::Upserted { todo: first, .. }, TodoChange::Upserted { todo: last, seq, .. }]
+                if *first == child && *last == updated && *seq > cursor
+        ));
+
+        // the subtask goes along with its parent
+        let cursor = changed[1].seq();
+        repository
+            .delete(parent.id, None)
+            .await
+            .expect("[delete] returned Err");
+        let mut deleted =
+            Vec::from_iter(changes(cursor, 10).await.iter().map(|change| match change {
+                TodoChange::Deleted { id, seq, .. } if *seq > cursor => *id,
+                change => panic!("[changes] unexpected {change:?}"),
+            }));
+        deleted.sort();
+        assert_eq!(deleted, vec![parent.id, child.id]);
+        assert_eq!(changes(start, 10).await.len(), 2);
+    }
+}
+
+/// Constructors for tests, also of the crates running against the memory backends.
+#[cfg(any(test, feature = "memory"))]
+pub mod test_utils {
+    use super::*;
+    use crate::todos::model::Priority;
+
+    impl CreateTodo {
+        pub fn new(text: String) -> Self {
+            Self {
+                text,
+                list_id: None,
+                parent_id: None,
+                starts_at: None,
+                rrule: None,
+                due_at: None,
+                priority: Priority::None,
+            }
+        }
+    }
+
+    impl Todo {
+        pub fn new(id: i32, text: String) -> Self {
+            Self {
+                id,
+                list_id: 1,
+                parent_id: None,
+                text,
+                completed: false,
+                starts_at: None,
+                due_at: None,
+                priority: Priority::None,
+                position: id as i64 * POSITION_GAP,
+                created_at: DateTime::UNIX_EPOCH,
+                rrule: None,
+                series_id: None,
+                version: 1,
+            }
         }
     }
 }
@@ -0,0 +1,903 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    io,
+    sync::{
+        atomic::{AtomicI32, AtomicI64, Ordering},
+        Arc, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+};
+
+use super::{
+    check_version, position_between, sort_and_page, Filter, RepositoryError, TodoRepositoryTrait,
+    POSITION_GAP,
+};
+use crate::todos::model::{
+    CreateTodo, Todo, TodoChange, TodoQuery, TodoSearchHit, TodoSeries, UpdateTodo,
+};
+use crate::todos::search::{self, SearchIndex};
+
+type TodoData = HashMap<i32, Todo>;
+type TodoLabelData = HashMap<i32, BTreeSet<i32>>;
+type TodoSeriesData = HashMap<i32, TodoSeries>;
+type PartitionData = HashMap<(Option<i32>, Option<i32>), Partition>;
+
+/// The last change of each todo, by id, for `changes`.
+#[derive(Debug, Default)]
+struct ChangeLog {
+    changed: HashMap<i32, (i64, DateTime<Utc>)>,
+    deleted: HashMap<i32, (i64, DateTime<Utc>)>,
+}
+
+/// Todos held in memory, lost with the process unless saved with `snapshot`. The todos of each
+/// owner in each workspace are kept apart, `new` holds the ones without either. Lists shared with
+/// an owner are not seen, members only matter to the database.
+#[derive(Debug, Clone, Default)]
+pub struct TodoRepositoryForMemory {
+    store: Arc<RwLock<TodoData>>,
+    labels: Arc<RwLock<TodoLabelData>>,
+    series: Arc<RwLock<TodoSeriesData>>,
+    index: Arc<RwLock<SearchIndex>>,
+    log: Arc<RwLock<ChangeLog>>,
+    /// Keyed by workspace and owner.
+    partitions: Arc<RwLock<PartitionData>>,
+    workspace: Option<i32>,
+    owner: Option<i32>,
+    /// Highest ids handed out so far, ids of deleted todos and series aren't reused.
+    last_id: Arc<AtomicI32>,
+    last_series_id: Arc<AtomicI32>,
+    /// Ordered across partitions, like the sequence of the database.
+    last_change: Arc<AtomicI64>,
+}
+
+/// The todos of one owner in one workspace, see `owned_by` and `in_workspace`.
+#[derive(Debug, Clone, Default)]
+struct Partition {
+    store: Arc<RwLock<TodoData>>,
+    labels: Arc<RwLock<TodoLabelData>>,
+    series: Arc<RwLock<TodoSeriesData>>,
+    index: Arc<RwLock<SearchIndex>>,
+    log: Arc<RwLock<ChangeLog>>,
+}
+
+/// What `TodoRepositoryForMemory::snapshot` writes.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    todos: Vec<Todo>,
+    labels: TodoLabelData,
+    series: Vec<TodoSeries>,
+    last_id: i32,
+    last_series_id: i32,
+}
+
+impl TodoRepositoryForMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes every todo, attached label and series to `writer` as JSON, those of other owners
+    /// aren't included.
+    pub fn snapshot(&self, writer: impl io::Write) -> Result<(), RepositoryError> {
+        let store = self.read_store_ref();
+        let snapshot = Snapshot {
+            todos: Vec::from_iter(store.values().cloned()),
+            labels: self.labels.read().unwrap().clone(),
+            series: Vec::from_iter(self.series.read().unwrap().values().cloned()),
+            last_id: self.last_id.load(Ordering::SeqCst),
+            last_series_id: self.last_series_id.load(Ordering::SeqCst),
+        };
+        serde_json::to_writer(writer, &snapshot)
+            .map_err(|e| RepositoryError::Storage(e.to_string()))
+    }
+
+    /// A repository holding what `snapshot` wrote to `reader`.
+    pub fn load(reader: impl io::Read) -> Result<Self, RepositoryError> {
+        let snapshot: Snapshot =
+            serde_json::from_reader(reader).map_err(|e| RepositoryError::Storage(e.to_string()))?;
+        let mut index = SearchIndex::default();
+        for todo in &snapshot.todos {
+            index.insert(todo.id, &todo.text);
+        }
+        let repository = TodoRepositoryForMemory {
+            store: Arc::new(RwLock::new(TodoData::from_iter(
+                snapshot.todos.into_iter().map(|todo| (todo.id, todo)),
+            ))),
+            labels: Arc::new(RwLock::new(snapshot.labels)),
+            series: Arc::new(RwLock::new(TodoSeriesData::from_iter(
+                snapshot
+                    .series
+                    .into_iter()
+                    .map(|series| (series.id, series)),
+            ))),
+            index: Arc::new(RwLock::new(index)),
+            log: Default::default(),
+            partitions: Default::default(),
+            workspace: None,
+            owner: None,
+            last_id: Arc::new(AtomicI32::new(snapshot.last_id)),
+            last_series_id: Arc::new(AtomicI32::new(snapshot.last_series_id)),
+            last_change: Default::default(),
+        };
+        // the changes before the snapshot are gone, every todo is changed as far as clients know
+        let ids = Vec::from_iter(repository.read_store_ref().keys().copied());
+        repository.touch(ids);
+        Ok(repository)
+    }
+
+    /// The todos of `owner` in `workspace`, at least one of them being set.
+    fn partition(&self, workspace: Option<i32>, owner: Option<i32>) -> Self {
+        let mut partitions = self.partitions.write().unwrap();
+        let partition = partitions.entry((workspace, owner)).or_default();
+        TodoRepositoryForMemory {
+            store: partition.store.clone(),
+            labels: partition.labels.clone(),
+            series: partition.series.clone(),
+            index: partition.index.clone(),
+            log: partition.log.clone(),
+            partitions: self.partitions.clone(),
+            workspace,
+            owner,
+            last_id: self.last_id.clone(),
+            last_series_id: self.last_series_id.clone(),
+            last_change: self.last_change.clone(),
+        }
+    }
+
+    /// Records a change of each of `ids`.
+    fn touch(&self, ids: impl IntoIterator<Item = i32>) {
+        let mut log = self.log.write().unwrap();
+        for id in ids {
+            let seq = self.last_change.fetch_add(1, Ordering::SeqCst) + 1;
+            log.changed.insert(id, (seq, Utc::now()));
+        }
+    }
+
+    /// Records the deletion of the todos of `before` missing from `store`.
+    fn forget(&self, before: &BTreeSet<i32>, store: &TodoData) {
+        let mut log = self.log.write().unwrap();
+        for id in before.iter().filter(|id| !store.contains_key(id)) {
+            let seq = self.last_change.fetch_add(1, Ordering::SeqCst) + 1;
+            log.changed.remove(id);
+            log.deleted.insert(*id, (seq, Utc::now()));
+        }
+    }
+
+    fn write_store_ref(&self) -> RwLockWriteGuard<'_, TodoData> {
+        self.store.write().unwrap()
+    }
+
+    fn read_store_ref(&self) -> RwLockReadGuard<'_, TodoData> {
+        self.store.read().unwrap()
+    }
+
+    /// Todos matching the filters of `query`, unsorted.
+    fn matching(&self, query: &TodoQuery) -> Vec<Todo> {
+        let store = self.read_store_ref();
+        let labels = self.labels.read().unwrap();
+        let filter = Filter::new(query);
+        Vec::from_iter(
+            store
+                .values()
+                .filter(|t| filter.matches(t, labels.get(&t.id)))
+                .cloned(),
+        )
+    }
+
+    /// Deletes todos whose parent is gone, like `on delete cascade` does.
+    fn remove_orphans(&self, store: &mut TodoData) {
+        let mut labels = self.labels.write().unwrap();
+        loop {
+            let orphans = Vec::from_iter(
+                store
+                    .values()
+                    .filter(|t| t.parent_id.is_some_and(|p| !store.contains_key(&p)))
+                    .map(|t| t.id),
+            );
+            if orphans.is_empty() {
+                return;
+            }
+            let mut index = self.index.write().unwrap();
+            for id in orphans {
+                store.remove(&id);
+                labels.remove(&id);
+                index.remove(id);
+            }
+        }
+    }
+
+    fn insert(&self, payload: CreateTodo, series_id: Option<i32>) -> anyhow::Result<Todo> {
+        let mut store = self.write_store_ref();
+        let id = self.last_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let position = store.values().map(|todo| todo.position).max().unwrap_or(0);
+        let parent_list_id = payload
+            .parent_id
+            .and_then(|parent_id| store.get(&parent_id))
+            .map(|parent| parent.list_id);
+        let todo = Todo {
+            id,
+            // the memory list repository seeds its inbox with id 1
+            list_id: payload.list_id.or(parent_list_id).unwrap_or(1),
+            parent_id: payload.parent_id,
+            text: payload.text,
+            completed: false,
+            starts_at: payload.starts_at,
+            due_at: payload.due_at,
+            priority: payload.priority,
+            position: position + POSITION_GAP,
+            created_at: Utc::now(),
+            rrule: payload.rrule,
+            series_id,
+            version: 1,
+        };
+        store.insert(id, todo.clone());
+        self.index.write().unwrap().insert(id, &todo.text);
+        self.touch([id]);
+        Ok(todo)
+    }
+}
+
+#[async_trait]
+impl TodoRepositoryTrait for TodoRepositoryForMemory {
+    fn owned_by(&self, owner: i32) -> Self {
+        self.partition(self.workspace, Some(owner))
+    }
+
+    fn in_workspace(&self, workspace: i32) -> Self {
+        self.partition(Some(workspace), self.owner)
+    }
+
+    async fn create(&self, payload: CreateTodo) -> anyhow::Result<Todo> {
+        let series_id = match &payload.rrule {
+            Some(rrule) => {
+                let mut series = self.series.write().unwrap();
+                let id = self.last_series_id.fetch_add(1, Ordering::SeqCst) + 1;
+                let todo_series = TodoSeries {
+                    id,
+                    rrule: rrule.clone(),
+                    text: payload.text.clone(),
+                    priority: payload.priority,
+                };
+                series.insert(id, todo_series);
+                Some(id)
+            }
+            None => None,
+        };
+        self.insert(payload, series_id)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Todo> {
+        let store = self.read_store_ref();
+        let todo = store
+            .get(&id)
+            .cloned()
+            .ok_or(RepositoryError::NotFound(id))?;
+        Ok(todo)
+    }
+
+    async fn all(&self, query: &TodoQuery) -> anyhow::Result<Vec<Todo>> {
+        Ok(sort_and_page(query, self.matching(query)))
+    }
+
+    async fn count(&self, query: &TodoQuery) -> anyhow::Result<i64> {
+        Ok(self.matching(query).len() as i64)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<Todo> {
+        let mut store = self.write_store_ref();
+        let todo = store.get(&id).ok_or(RepositoryError::NotFound(id))?;
+        check_version(todo, payload.version)?;
+        let text = payload.text.unwrap_or(todo.text.clone());
+        let completed = payload.completed.unwrap_or(todo.completed);
+        let starts_at = payload.starts_at.unwrap_or(todo.starts_at);
+        let due_at = payload.due_at.unwrap_or(todo.due_at);
+        let priority = payload.priority.unwrap_or(todo.priority);
+        let list_id = payload.list_id.unwrap_or(todo.list_id);
+        let parent_id = payload.parent_id.unwrap_or(todo.parent_id);
+        let todo = Todo {
+            id,
+            list_id,
+            parent_id,
+            text,
+            completed,
+            starts_at,
+            due_at,
+            priority,
+            version: todo.version + 1,
+            ..todo.clone()
+        };
+        store.insert(id, todo.clone());
+        self.index.write().unwrap().insert(id, &todo.text);
+        self.touch([id]);
+        Ok(todo)
+    }
+
+    async fn search(&self, query: &str, limit: u32) -> anyhow::Result<Vec<TodoSearchHit>> {
+        let store = self.read_store_ref();
+        let hits = self.index.read().unwrap().search(query);
+        Ok(Vec::from_iter(
+            hits.into_iter()
+                .filter_map(|(id, rank)| {
+                    let todo = store.get(&id)?.clone();
+                    Some(TodoSearchHit {
+                        snippet: search::snippet(&todo.text, query),
+                        todo,
+                        rank,
+                    })
+                })
+                .take(limit as usize),
+        ))
+    }
+
+    async fn delete(&self, id: i32, version: Option<i32>) -> anyhow::Result<()> {
+        let mut store = self.write_store_ref();
+        check_version(
+            store.get(&id).ok_or(RepositoryError::NotFound(id))?,
+            version,
+        )?;
+        let before = BTreeSet::from_iter(store.keys().copied());
+        store.remove(&id);
+        self.labels.write().unwrap().remove(&id);
+        self.index.write().unwrap().remove(id);
+        self.remove_orphans(&mut store);
+        self.forget(&before, &store);
+        Ok(())
+    }
+
+    async fn subtree(&self, id: i32) -> anyhow::Result<Vec<Todo>> {
+        let store = self.read_store_ref();
+        let mut todos = vec![store
+            .get(&id)
+            .cloned()
+            .ok_or(RepositoryError::NotFound(id))?];
+        let mut next = 0;
+        while let Some(parent_id) = todos.get(next).map(|todo| todo.id) {
+            todos.extend(
+                store
+                    .values()
+                    .filter(|todo| todo.parent_id == Some(parent_id))
+                    .cloned(),
+            );
+            next += 1;
+        }
+        Ok(todos)
+    }
+
+    async fn ancestor_ids(&self, id: i32) -> anyhow::Result<Vec<i32>> {
+        let store = self.read_store_ref();
+        let mut ids = Vec::new();
+        let mut current = store.get(&id).and_then(|todo| todo.parent_id);
+        while let Some(parent_id) = current {
+            ids.push(parent_id);
+            current = store.get(&parent_id).and_then(|todo| todo.parent_id);
+        }
+        Ok(ids)
+    }
+
+    async fn find_due(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Todo>> {
+        let store = self.read_store_ref();
+        let mut todos = Vec::from_iter(
+            store
+                .values()
+                .filter(|todo| {
+                    !todo.completed
+                        && todo.due_at.is_some_and(|due_at| {
+                            due_at < to && from.is_none_or(|from| due_at >= from)
+                        })
+                })
+                .cloned(),
+        );
+        todos.sort_by_key(|todo| (todo.due_at, todo.id));
+        Ok(todos)
+    }
+
+    async fn move_before(&self, id: i32, before: Option<i32>) -> anyhow::Result<Todo> {
+        let mut store = self.write_store_ref();
+        if !store.contains_key(&id) {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+        let position = match before {
+            Some(before) if before != id => loop {
+                let next = store
+                    .get(&before)
+                    .ok_or(RepositoryError::NotFound(before))?
+                    .position;
+                let prev = store
+                    .values()
+                    .filter(|t| t.id != id && (t.position, t.id) < (next, before))
+                    .map(|t| (t.position, t.id))
+                    .max()
+                    .map(|(position, _)| position);
+                if let Some(position) = position_between(prev, next) {
+                    break position;
+                }
+                let mut ordered = Vec::from_iter(store.values_mut());
+                ordered.sort_by_key(|t| (t.position, t.id));
+                for (rank, todo) in ordered.into_iter().enumerate() {
+                    todo.position = (rank as i64 + 1) * POSITION_GAP;
+                    todo.version += 1;
+                }
+                self.touch(store.keys().copied());
+            },
+            Some(_) => store[&id].position,
+            None => {
+                store
+                    .values()
+                    .filter(|t| t.id != id)
+                    .map(|t| t.position)
+                    .max()
+                    .unwrap_or(0)
+                    + POSITION_GAP
+            }
+        };
+        let todo = store.get_mut(&id).ok_or(RepositoryError::NotFound(id))?;
+        todo.position = position;
+        todo.version += 1;
+        self.touch([id]);
+        Ok(todo.clone())
+    }
+
+    async fn label_ids(&self, id: i32) -> anyhow::Result<Vec<i32>> {
+        let labels = self.labels.read().unwrap();
+        Ok(Vec::from_iter(
+            labels.get(&id).into_iter().flatten().copied(),
+        ))
+    }
+
+    async fn attach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()> {
+        let store = self.read_store_ref();
+        if !store.contains_key(&id) {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+        self.labels
+            .write()
+            .unwrap()
+            .entry(id)
+            .or_default()
+            .insert(label_id);
+        Ok(())
+    }
+
+    async fn detach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()> {
+        if let Some(labels) = self.labels.write().unwrap().get_mut(&id) {
+            labels.remove(&label_id);
+        }
+        Ok(())
+    }
+
+    async fn remove_label(&self, label_id: i32) -> anyhow::Result<()> {
+        for labels in self.labels.write().unwrap().values_mut() {
+            labels.remove(&label_id);
+        }
+        Ok(())
+    }
+
+    async fn move_list(&self, from: i32, to: i32) -> anyhow::Result<()> {
+        let mut store = self.write_store_ref();
+        for todo in store.values_mut().filter(|t| t.list_id == from) {
+            todo.list_id = to;
+            todo.version += 1;
+            self.touch([todo.id]);
+        }
+        Ok(())
+    }
+
+    async fn delete_list(&self, list_id: i32) -> anyhow::Result<()> {
+        let mut store = self.write_store_ref();
+        let before = BTreeSet::from_iter(store.keys().copied());
+        let ids = Vec::from_iter(
+            store
+                .values()
+                .filter(|t| t.list_id == list_id)
+                .map(|t| t.id),
+        );
+        let mut labels = self.labels.write().unwrap();
+        let mut index = self.index.write().unwrap();
+        for id in ids {
+            store.remove(&id);
+            labels.remove(&id);
+            index.remove(id);
+        }
+        drop((labels, index));
+        self.remove_orphans(&mut store);
+        self.forget(&before, &store);
+        Ok(())
+    }
+
+    async fn add_occurrence(&self, series_id: i32, payload: CreateTodo) -> anyhow::Result<Todo> {
+        self.insert(payload, Some(series_id))
+    }
+
+    async fn find_series(&self, series_id: i32) -> anyhow::Result<TodoSeries> {
+        let series = self.series.read().unwrap();
+        let todo_series = series
+            .get(&series_id)
+            .cloned()
+            .ok_or(RepositoryError::NotFound(series_id))?;
+        Ok(todo_series)
+    }
+
+    async fn series_occurrences(&self, series_id: i32) -> anyhow::Result<Vec<Todo>> {
+        let store = self.read_store_ref();
+        let mut todos = Vec::from_iter(
+            store
+                .values()
+                .filter(|todo| todo.series_id == Some(series_id))
+                .cloned(),
+        );
+        todos.sort_by_key(|todo| (todo.due_at.is_none(), todo.due_at, todo.id));
+        Ok(todos)
+    }
+
+    async fn start_series(&self, id: i32, rrule: String) -> anyhow::Result<Todo> {
+        let mut store = self.write_store_ref();
+        let todo = store.get_mut(&id).ok_or(RepositoryError::NotFound(id))?;
+        let mut series = self.series.write().unwrap();
+        let series_id = self.last_series_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let todo_series = TodoSeries {
+            id: series_id,
+            rrule: rrule.clone(),
+            text: todo.text.clone(),
+            priority: todo.priority,
+        };
+        series.insert(series_id, todo_series);
+        todo.rrule = Some(rrule);
+        todo.series_id = Some(series_id);
+        todo.version += 1;
+        self.touch([id]);
+        Ok(todo.clone())
+    }
+
+    async fn update_series(
+        &self,
+        series_id: i32,
+        payload: &UpdateTodo,
+    ) -> anyhow::Result<TodoSeries> {
+        // the store is locked before the series everywhere
+        let mut store = self.write_store_ref();
+        let mut series = self.series.write().unwrap();
+        let todo_series = series
+            .get_mut(&series_id)
+            .ok_or(RepositoryError::NotFound(series_id))?;
+        if let Some(text) = &payload.text {
+            todo_series.text = text.clone();
+        }
+        if let Some(priority) = payload.priority {
+            todo_series.priority = priority;
+        }
+        if let Some(Some(rrule)) = &payload.rrule {
+            todo_series.rrule = rrule.clone();
+        }
+        for todo in store
+            .values_mut()
+            .filter(|todo| todo.series_id == Some(series_id) && !todo.completed)
+        {
+            todo.text = todo_series.text.clone();
+            todo.priority = todo_series.priority;
+            todo.version += 1;
+            self.index.write().unwrap().insert(todo.id, &todo.text);
+            self.touch([todo.id]);
+            if let Some(rrule) = &payload.rrule {
+                todo.rrule = rrule.clone();
+            }
+        }
+        Ok(todo_series.clone())
+    }
+
+    async fn changes(&self, cursor: i64, limit: u32) -> anyhow::Result<Vec<TodoChange>> {
+        let store = self.read_store_ref();
+        let log = self.log.read().unwrap();
+        let upserted = log.changed.iter().filter_map(|(id, (seq, updated_at))| {
+            Some(TodoChange::Upserted {
+                seq: *seq,
+                updated_at: *updated_at,
+                todo: store.get(id)?.clone(),
+            })
+        });
+        let deleted = log
+            .deleted
+            .iter()
+            .map(|(id, (seq, deleted_at))| TodoChange::Deleted {
+                seq: *seq,
+                deleted_at: *deleted_at,
+                id: *id,
+            });
+        let mut changes = Vec::from_iter(
+            upserted
+                .chain(deleted)
+                .filter(|change| change.seq() > cursor),
+        );
+        changes.sort_by_key(TodoChange::seq);
+        changes.truncate(limit as usize);
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::todos::model::{TodoCursor, TodoSort};
+    use crate::todos::repository::scenario;
+
+    #[tokio::test]
+    async fn owner_scenario() {
+        let repository = TodoRepositoryForMemory::new();
+        scenario::owner_scenario(&repository.owned_by(1), &repository.owned_by(2)).await;
+
+        // ids stay unique across owners
+        let todo = repository
+            .owned_by(1)
+            .create(CreateTodo::new("alice".to_string()))
+            .await
+            .unwrap();
+        let other = repository
+            .owned_by(2)
+            .create(CreateTodo::new("bob".to_string()))
+            .await
+            .unwrap();
+        assert_ne!(todo.id, other.id);
+        assert_eq!(repository.owned_by(1).find(todo.id).await.unwrap(), todo);
+    }
+
+    #[tokio::test]
+    async fn workspace_scenario() {
+        let repository = TodoRepositoryForMemory::new().owned_by(1);
+        scenario::owner_scenario(&repository.in_workspace(1), &repository.in_workspace(2)).await;
+
+        // the owner is kept when switching workspaces
+        let todo = repository
+            .in_workspace(2)
+            .create(CreateTodo::new("globex".to_string()))
+            .await
+            .unwrap();
+        assert!(repository.find(todo.id).await.is_err());
+        assert!(repository
+            .in_workspace(2)
+            .owned_by(2)
+            .find(todo.id)
+            .await
+            .is_err());
+        assert_eq!(
+            repository
+                .owned_by(1)
+                .in_workspace(2)
+                .find(todo.id)
+                .await
+                .unwrap(),
+            todo
+        );
+    }
+
+    #[tokio::test]
+    async fn changes_scenario() {
+        let repository = TodoRepositoryForMemory::new();
+        scenario::changes_scenario(&repository.owned_by(1), &repository.owned_by(2)).await;
+    }
+
+    #[tokio::test]
+    async fn todo_crud_scenario() {
+        let text = "todo text".to_string();
+        let id = 1;
+        let expected = Todo::new(id, text.clone());
+
+        // create
+        let repository = TodoRepositoryForMemory::new();
+        let todo = repository
+            .create(CreateTodo::new(text))
+            .await
+            .expect("failed to create a todo");
+        let expected = Todo {
+            created_at: todo.created_at,
+            ..expected
+        };
+        assert_eq!(expected, todo);
+
+        // find
+        let todo = repository.find(todo.id).await.unwrap();
+        assert_eq!(expected, todo);
+
+        // all
+        let todo = repository
+            .all(&TodoQuery::default())
+            .await
+            .expect("failed to get all todos");
+        assert_eq!(vec![expected.clone()], todo);
+
+        // update
+        let text = "update todo".to_string();
+        let todo = repository
+            .update(
+                1,
+                UpdateTodo {
+                    text: Some(text.clone()),
+                    completed: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("failed update todo.");
+        assert_eq!(
+            Todo {
+                text,
+                completed: true,
+                version: 2,
+                ..expected
+            },
+            todo
+        );
+
+        // delete
+        let res = repository.delete(id, None).await;
+        assert!(res.is_ok())
+    }
+
+    #[tokio::test]
+    async fn pages_through_filtered_todos() {
+        let repository = TodoRepositoryForMemory::new();
+        let due = |day: u32| format!("2024-05-{day:02}T09:00:00Z").parse().ok();
+        for (text, due_at) in [
+            ("Buy milk", due(3)),
+            ("buy bread", None),
+            ("walk the dog", due(1)),
+            ("buy eggs", due(2)),
+            ("Buy tea", due(1)),
+        ] {
+            repository
+                .create(CreateTodo {
+                    due_at,
+                    ..CreateTodo::new(text.to_string())
+                })
+                .await
+                .unwrap();
+        }
+        repository
+            .update(
+                4,
+                UpdateTodo {
+                    completed: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        // ties on the due date are broken by position, undated todos come last
+        let mut query = TodoQuery {
+            sort: TodoSort::DueDate,
+            text: Some("BUY".to_string()),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut pages = Vec::new();
+        loop {
+            let page = repository.page(&query).await.unwrap();
+            assert_eq!(page.total, 4);
+            pages.push(Vec::from_iter(page.todos.iter().map(|t| t.id)));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor.parse().unwrap()),
+                None => break,
+            }
+        }
+        assert_eq!(pages, vec![vec![5, 4], vec![1, 2]]);
+
+        let query = TodoQuery {
+            sort: TodoSort::Position,
+            completed: Some(false),
+            due_from: due(1),
+            due_to: due(3),
+            ..Default::default()
+        };
+        let ids = |todos: Vec<Todo>| Vec::from_iter(todos.into_iter().map(|t| t.id));
+        assert_eq!(ids(repository.all(&query).await.unwrap()), vec![3, 5]);
+        assert_eq!(repository.count(&query).await.unwrap(), 2);
+
+        // the cursor outlives the todo it was taken from
+        let cursor = TodoCursor::from(&repository.find(3).await.unwrap());
+        repository.delete(3, None).await.unwrap();
+        let query = TodoQuery {
+            sort: TodoSort::Position,
+            cursor: Some(cursor.to_string().parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(ids(repository.all(&query).await.unwrap()), vec![4, 5]);
+    }
+
+    #[tokio::test]
+    async fn move_before_keeps_other_positions() {
+        let repository = TodoRepositoryForMemory::new();
+        for text in ["a", "b", "c"] {
+            repository
+                .create(CreateTodo::new(text.to_string()))
+                .await
+                .unwrap();
+        }
+        let texts = |todos: Vec<Todo>| todos.into_iter().map(|t| t.text).collect::<Vec<_>>();
+        let by_position = TodoQuery {
+            sort: TodoSort::Position,
+            ..Default::default()
+        };
+
+        let moved = repository.move_before(3, Some(1)).await.unwrap();
+        assert_eq!(moved.position, 0);
+        let todos = repository.all(&by_position).await.unwrap();
+        assert_eq!(texts(todos), vec!["c", "a", "b"]);
+        assert_eq!(repository.find(1).await.unwrap().position, POSITION_GAP);
+
+        repository.move_before(3, None).await.unwrap();
+        let todos = repository.all(&by_position).await.unwrap();
+        assert_eq!(texts(todos), vec!["a", "b", "c"]);
+
+        // squeeze "c" between "a" and "b" until they run out of room
+        for _ in 0..20 {
+            repository.move_before(3, Some(2)).await.unwrap();
+            repository.move_before(2, Some(3)).await.unwrap();
+        }
+        let todos = repository.all(&by_position).await.unwrap();
+        assert_eq!(texts(todos), vec!["a", "b", "c"]);
+
+        let res = repository.move_before(1, Some(42)).await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::NotFound(42))
+        ));
+    }
+
+    #[tokio::test]
+    async fn snapshots_keep_ids_unique() {
+        let repository = TodoRepositoryForMemory::new();
+        for text in ["buy milk", "walk the dog", "buy bread"] {
+            repository
+                .create(CreateTodo::new(text.to_string()))
+                .await
+                .unwrap();
+        }
+        repository.attach_label(3, 7).await.unwrap();
+        repository
+            .start_series(1, "FREQ=DAILY".to_string())
+            .await
+            .unwrap();
+        repository.delete(2, None).await.unwrap();
+
+        let mut snapshot = Vec::new();
+        repository.snapshot(&mut snapshot).unwrap();
+        let repository = TodoRepositoryForMemory::load(snapshot.as_slice()).unwrap();
+
+        let texts = |todos: Vec<Todo>| todos.into_iter().map(|t| t.text).collect::<Vec<_>>();
+        let by_position = TodoQuery {
+            sort: TodoSort::Position,
+            ..Default::default()
+        };
+        let todos = repository.all(&by_position).await.unwrap();
+        assert_eq!(texts(todos), vec!["buy milk", "buy bread"]);
+        assert_eq!(repository.label_ids(3).await.unwrap(), vec![7]);
+        assert_eq!(repository.find_series(1).await.unwrap().rrule, "FREQ=DAILY");
+        let hits = repository.search("buy", 10).await.unwrap();
+        assert_eq!(
+            Vec::from_iter(hits.iter().map(|hit| hit.todo.id)),
+            vec![1, 3]
+        );
+
+        // the deleted todo's id stays taken
+        let todo = repository
+            .create(CreateTodo::new("buy tea".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(todo.id, 4);
+        let todo = repository
+            .create(CreateTodo {
+                rrule: Some("FREQ=WEEKLY".to_string()),
+                ..CreateTodo::new("water the plants".to_string())
+            })
+            .await
+            .unwrap();
+        assert_eq!(todo.series_id, Some(2));
+
+        let res = TodoRepositoryForMemory::load("{".as_bytes());
+        assert!(matches!(res, Err(RepositoryError::Storage(_))));
+    }
+}
@@ -0,0 +1,821 @@
+use ::sled::{Batch, Db, Tree};
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+use super::{
+    check_version, position_between, sort_and_page, Filter, RepositoryError, TodoRepositoryTrait,
+    POSITION_GAP,
+};
+use crate::lists::repository::INBOX_ID;
+use crate::todos::model::{
+    CreateTodo, Priority, Todo, TodoQuery, TodoSearchHit, TodoSeries, UpdateTodo,
+};
+use crate::todos::search::{self, SearchIndex};
+
+impl From<::sled::Error> for RepositoryError {
+    fn from(e: ::sled::Error) -> Self {
+        RepositoryError::Storage(e.to_string())
+    }
+}
+
+pub(crate) fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, RepositoryError> {
+    serde_json::to_vec(value).map_err(|e| RepositoryError::Unexpected(e.to_string()))
+}
+
+pub(crate) fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, RepositoryError> {
+    serde_json::from_slice(bytes).map_err(|e| RepositoryError::Storage(e.to_string()))
+}
+
+/// Keys are big-endian so that trees iterate in id order.
+pub(crate) fn key(id: i32) -> [u8; 4] {
+    id.to_be_bytes()
+}
+
+/// Key of the pair `(a, b)`, prefixed by `a` to scan the pairs starting with it.
+pub(crate) fn pair_key(a: i32, b: i32) -> [u8; 8] {
+    let mut key = [0; 8];
+    key[..4].copy_from_slice(&a.to_be_bytes());
+    key[4..].copy_from_slice(&b.to_be_bytes());
+    key
+}
+
+/// Key of `label_id` attached to `id`, prefixed by `id` to scan the labels of a todo.
+fn label_key(id: i32, label_id: i32) -> [u8; 8] {
+    pair_key(id, label_id)
+}
+
+/// The id of a `key`.
+fn key_id(key: &[u8]) -> Result<i32, RepositoryError> {
+    <[u8; 4]>::try_from(key)
+        .map(i32::from_be_bytes)
+        .map_err(|_| RepositoryError::Storage(format!("malformed key {key:?}")))
+}
+
+/// The next id of `tree`, whose entries are under `key`s. Ids follow each other from 1 across
+/// restarts, the last one is kept in the `ids` tree of `db` under the name of `tree`. A tree
+/// without one yet goes on from its greatest key.
+pub(crate) fn generate_id(db: &Db, tree: &Tree) -> Result<i32, RepositoryError> {
+    let ids = db.open_tree("ids")?;
+    if !ids.contains_key(tree.name())? {
+        let last = match tree.last()? {
+            Some((last, _)) => key_id(&last)?,
+            None => 0,
+        };
+        // another write may have started it meanwhile
+        let _ = ids.compare_and_swap(tree.name(), None as Option<&[u8]>, Some(&key(last)))?;
+    }
+    let last = ids.fetch_and_update(tree.name(), |last| {
+        let last = last.and_then(|last| key_id(last).ok()).unwrap_or_default();
+        Some(key(last.saturating_add(1)).to_vec())
+    })?;
+    match last.map_or(Ok(0), |last| key_id(&last))? {
+        i32::MAX => Err(RepositoryError::Storage("ran out of ids".to_string())),
+        last => Ok(last + 1),
+    }
+}
+
+/// The database of `crate::store`, which the repositories of the desktop app share.
+pub(crate) fn store_db() -> Result<Db, RepositoryError> {
+    crate::store::db()
+        .ok_or_else(|| RepositoryError::Storage("the store has no database".to_string()))
+}
+
+/// The todo and label ids of a `label_key`.
+fn label_key_ids(key: &[u8]) -> Result<(i32, i32), RepositoryError> {
+    let key = <[u8; 8]>::try_from(key)
+        .map_err(|_| RepositoryError::Storage(format!("malformed todo label key {key:?}")))?;
+    let (id, label_id) = key.split_at(4);
+    Ok((
+        i32::from_be_bytes(id.try_into().unwrap()),
+        i32::from_be_bytes(label_id.try_into().unwrap()),
+    ))
+}
+
+/// Todos serialized as JSON into trees of a sled database, for the desktop app. Lists and
+/// labels aren't kept here: list ids are checked against the lists of the same database, label
+/// ids are stored as is.
+#[derive(Debug, Clone)]
+pub struct TodoRepositoryForSled {
+    db: Db,
+    todos: Tree,
+    /// Empty values under `label_key`.
+    labels: Tree,
+    series: Tree,
+    /// Kept by `ListRepositoryForSled`, only read here.
+    lists: Tree,
+    /// Rebuilt from `todos` when opening.
+    index: Arc<RwLock<SearchIndex>>,
+    /// Held by every write, which reads todos and writes them back.
+    write: Arc<Mutex<()>>,
+}
+
+impl TodoRepositoryForSled {
+    pub fn open(db: &Db) -> Result<Self, RepositoryError> {
+        let repository = TodoRepositoryForSled {
+            db: db.clone(),
+            todos: db.open_tree("todos")?,
+            labels: db.open_tree("todo_labels")?,
+            series: db.open_tree("todo_series")?,
+            lists: db.open_tree("lists")?,
+            index: Arc::default(),
+            write: Arc::default(),
+        };
+        let mut index = repository.index.write().unwrap();
+        for todo in repository.load()? {
+            index.insert(todo.id, &todo.text);
+        }
+        drop(index);
+        Ok(repository)
+    }
+
+    /// Todos kept in the database of `crate::store`.
+    pub fn from_store() -> Result<Self, RepositoryError> {
+        Self::open(&store_db()?)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.write.lock().unwrap()
+    }
+
+    fn get(&self, id: i32) -> Result<Todo, RepositoryError> {
+        match self.todos.get(key(id))? {
+            Some(bytes) => decode(&bytes),
+            None => Err(RepositoryError::NotFound(id)),
+        }
+    }
+
+    /// Every todo, in id order.
+    fn load(&self) -> Result<Vec<Todo>, RepositoryError> {
+        self.todos
+            .iter()
+            .values()
+            .map(|bytes| decode(&bytes?))
+            .collect()
+    }
+
+    fn save<'a>(&self, todos: impl IntoIterator<Item = &'a Todo>) -> Result<(), RepositoryError> {
+        let mut batch = Batch::default();
+        let mut index = self.index.write().unwrap();
+        for todo in todos {
+            batch.insert(&key(todo.id), encode(todo)?);
+            index.insert(todo.id, &todo.text);
+        }
+        self.todos.apply_batch(batch)?;
+        Ok(())
+    }
+
+    /// Deletes `ids` with their descendants, like `on delete cascade` does.
+    fn remove(&self, ids: impl IntoIterator<Item = i32>) -> Result<(), RepositoryError> {
+        let todos = self.load()?;
+        let mut removed = BTreeSet::from_iter(ids);
+        loop {
+            let orphans = Vec::from_iter(
+                todos
+                    .iter()
+                    .filter(|t| !removed.contains(&t.id))
+                    .filter(|t| t.parent_id.is_some_and(|p| removed.contains(&p)))
+                    .map(|t| t.id),
+            );
+            if orphans.is_empty() {
+                break;
+            }
+            removed.extend(orphans);
+        }
+        let mut batch = Batch::default();
+        let mut labels = Batch::default();
+        let mut index = self.index.write().unwrap();
+        for id in removed {
+            batch.remove(&key(id));
+            for entry in self.labels.scan_prefix(key(id)).keys() {
+                labels.remove(entry?);
+            }
+            index.remove(id);
+        }
+        self.todos.apply_batch(batch)?;
+        self.labels.apply_batch(labels)?;
+        Ok(())
+    }
+
+    /// Attached label ids of every todo having some.
+    fn label_map(&self) -> Result<HashMap<i32, BTreeSet<i32>>, RepositoryError> {
+        let mut labels = HashMap::<i32, BTreeSet<i32>>::new();
+        for entry in self.labels.iter().keys() {
+            let (id, label_id) = label_key_ids(&entry?)?;
+            labels.entry(id).or_default().insert(label_id);
+        }
+        Ok(labels)
+    }
+
+    /// Todos matching the filters of `query`, unsorted.
+    fn matching(&self, query: &TodoQuery) -> Result<Vec<Todo>, RepositoryError> {
+        let labels = if query.labels.is_empty() {
+            HashMap::new()
+        } else {
+            self.label_map()?
+        };
+        let filter = Filter::new(query);
+        Ok(Vec::from_iter(
+            self.load()?
+                .into_iter()
+                .filter(|t| filter.matches(t, labels.get(&t.id))),
+        ))
+    }
+
+    /// Fails like the foreign key of the other backends when `list_id` isn't a list.
+    fn check_list(&self, list_id: i32) -> Result<(), RepositoryError> {
+        // the inbox is there before the lists are first opened
+        if list_id == INBOX_ID || self.lists.contains_key(key(list_id))? {
+            Ok(())
+        } else {
+            Err(RepositoryError::Invalid(format!(
+                "list {list_id} doesn't exist"
+            )))
+        }
+    }
+
+    fn insert(&self, payload: CreateTodo, series_id: Option<i32>) -> Result<Todo, RepositoryError> {
+        if let Some(list_id) = payload.list_id {
+            self.check_list(list_id)?;
+        }
+        let position = self
+            .load()?
+            .iter()
+            .map(|todo| todo.position)
+            .max()
+            .unwrap_or(0);
+        let parent_list_id = match payload.parent_id {
+            Some(parent_id) => Some(
+                self.get(parent_id)
+                    .map_err(|e| match e {
+                        RepositoryError::NotFound(_) => RepositoryError::Invalid(format!(
+                            "parent todo {parent_id} doesn't exist"
+                        )),
+                        e => e,
+                    })?
+                    .list_id,
+            ),
+            None => None,
+        };
+        let todo = Todo {
+            id: generate_id(&self.db, &self.todos)?,
+            list_id: payload.list_id.or(parent_list_id).unwrap_or(INBOX_ID),
+            parent_id: payload.parent_id,
+            text: payload.text,
+            completed: false,
+            starts_at: payload.starts_at,
+            due_at: payload.due_at,
+            priority: payload.priority,
+            position: position + POSITION_GAP,
+            created_at: Utc::now(),
+            rrule: payload.rrule,
+            series_id,
+            version: 1,
+        };
+        self.save([&todo])?;
+        Ok(todo)
+    }
+
+    fn find_series_in(&self, series_id: i32) -> Result<TodoSeries, RepositoryError> {
+        match self.series.get(key(series_id))? {
+            Some(bytes) => decode(&bytes),
+            None => Err(RepositoryError::NotFound(series_id)),
+        }
+    }
+
+    fn insert_series(
+        &self,
+        rrule: String,
+        text: String,
+        priority: Priority,
+    ) -> Result<TodoSeries, RepositoryError> {
+        let series = TodoSeries {
+            id: generate_id(&self.db, &self.series)?,
+            rrule,
+            text,
+            priority,
+        };
+        self.series.insert(key(series.id), encode(&series)?)?;
+        Ok(series)
+    }
+}
+
+#[async_trait]
+impl TodoRepositoryTrait for TodoRepositoryForSled {
+    /// A sled database holds the todos of a single user.
+    fn owned_by(&self, _owner: i32) -> Self {
+        self.clone()
+    }
+
+    /// A sled database holds the todos of a single workspace.
+    fn in_workspace(&self, _workspace: i32) -> Self {
+        self.clone()
+    }
+
+    async fn create(&self, payload: CreateTodo) -> anyhow::Result<Todo> {
+        let _write = self.lock();
+        let series_id = match &payload.rrule {
+            Some(rrule) => Some(
+                self.insert_series(rrule.clone(), payload.text.clone(), payload.priority)?
+                    .id,
+            ),
+            None => None,
+        };
+        Ok(self.insert(payload, series_id)?)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Todo> {
+        Ok(self.get(id)?)
+    }
+
+    async fn all(&self, query: &TodoQuery) -> anyhow::Result<Vec<Todo>> {
+        Ok(sort_and_page(query, self.matching(query)?))
+    }
+
+    async fn count(&self, query: &TodoQuery) -> anyhow::Result<i64> {
+        Ok(self.matching(query)?.len() as i64)
+    }
+
+    async fn search(&self, query: &str, limit: u32) -> anyhow::Result<Vec<TodoSearchHit>> {
+        let hits = self.index.read().unwrap().search(query);
+        let mut found = Vec::new();
+        for (id, rank) in hits.into_iter().take(limit as usize) {
+            let todo = self.get(id)?;
+            found.push(TodoSearchHit {
+                snippet: search::snippet(&todo.text, query),
+                todo,
+                rank,
+            });
+        }
+        Ok(found)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<Todo> {
+        let _write = self.lock();
+        let todo = self.get(id)?;
+        check_version(&todo, payload.version)?;
+        if let Some(list_id) = payload.list_id {
+            self.check_list(list_id)?;
+        }
+        let todo = Todo {
+            text: payload.text.unwrap_or(todo.text),
+            completed: payload.completed.unwrap_or(todo.completed),
+            starts_at: payload.starts_at.unwrap_or(todo.starts_at),
+            due_at: payload.due_at.unwrap_or(todo.due_at),
+            priority: payload.priority.unwrap_or(todo.priority),
+            list_id: payload.list_id.unwrap_or(todo.list_id),
+            parent_id: payload.parent_id.unwrap_or(todo.parent_id),
+            version: todo.version + 1,
+            ..todo
+        };
+        self.save([&todo])?;
+        Ok(todo)
+    }
+
+    async fn delete(&self, id: i32, version: Option<i32>) -> anyhow::Result<()> {
+        let _write = self.lock();
+        check_version(&self.get(id)?, version)?;
+        self.remove([id])?;
+        Ok(())
+    }
+
+    async fn subtree(&self, id: i32) -> anyhow::Result<Vec<Todo>> {
+        let all = self.load()?;
+        let mut todos = vec![self.get(id)?];
+        let mut next = 0;
+        while let Some(parent_id) = todos.get(next).map(|todo| todo.id) {
+            todos.extend(
+                all.iter()
+                    .filter(|todo| todo.parent_id == Some(parent_id))
+                    .cloned(),
+            );
+            next += 1;
+        }
+        Ok(todos)
+    }
+
+    async fn ancestor_ids(&self, id: i32) -> anyhow::Result<Vec<i32>> {
+        let mut ids = Vec::new();
+        let mut current = self.get(id).ok().and_then(|todo| todo.parent_id);
+        while let Some(parent_id) = current {
+            ids.push(parent_id);
+            current = self.get(parent_id).ok().and_then(|todo| todo.parent_id);
+        }
+        Ok(ids)
+    }
+
+    async fn move_before(&self, id: i32, before: Option<i32>) -> anyhow::Result<Todo> {
+        let _write = self.lock();
+        let mut todo = self.get(id)?;
+        let position = match before {
+            Some(before) if before != id => loop {
+                let next = self.get(before)?.position;
+                let prev = self
+                    .load()?
+                    .into_iter()
+                    .filter(|t| t.id != id && (t.position, t.id) < (next, before))
+                    .map(|t| (t.position, t.id))
+                    .max()
+                    .map(|(position, _)| position);
+                if let Some(position) = position_between(prev, next) {
+                    break position;
+                }
+                let mut ordered = self.load()?;
+                ordered.sort_by_key(|t| (t.position, t.id));
+                for (rank, todo) in ordered.iter_mut().enumerate() {
+                    todo.position = (rank as i64 + 1) * POSITION_GAP;
+                    todo.version += 1;
+                }
+                self.save(&ordered)?;
+                todo = self.get(id)?;
+            },
+            Some(_) => todo.position,
+            None => {
+                self.load()?
+                    .iter()
+                    .filter(|t| t.id != id)
+                    .map(|t| t.position)
+                    .max()
+                    .unwrap_or(0)
+                    + POSITION_GAP
+            }
+        };
+        todo.position = position;
+        todo.version += 1;
+        self.save([&todo])?;
+        Ok(todo)
+    }
+
+    async fn label_ids(&self, id: i32) -> anyhow::Result<Vec<i32>> {
+        let mut ids = Vec::new();
+        for entry in self.labels.scan_prefix(key(id)).keys() {
+            let (_, label_id) = label_key_ids(&entry.map_err(RepositoryError::from)?)?;
+            ids.push(label_id);
+        }
+        Ok(ids)
+    }
+
+    async fn attach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()> {
+        let _write = self.lock();
+        self.get(id)?;
+        self.labels
+            .insert(label_key(id, label_id), &[])
+            .map_err(RepositoryError::from)?;
+        Ok(())
+    }
+
+    async fn detach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()> {
+        self.labels
+            .remove(label_key(id, label_id))
+            .map_err(RepositoryError::from)?;
+        Ok(())
+    }
+
+    async fn remove_label(&self, label_id: i32) -> anyhow::Result<()> {
+        let _write = self.lock();
+        let mut batch = Batch::default();
+        for entry in self.labels.iter().keys() {
+            let entry = entry.map_err(RepositoryError::from)?;
+            if label_key_ids(&entry)?.1 == label_id {
+                batch.remove(entry);
+            }
+        }
+        self.labels
+            .apply_batch(batch)
+            .map_err(RepositoryError::from)?;
+        Ok(())
+    }
+
+    async fn move_list(&self, from: i32, to: i32) -> anyhow::Result<()> {
+        let _write = self.lock();
+        self.check_list(to)?;
+        let mut todos = Vec::from_iter(self.load()?.into_iter().filter(|t| t.list_id == from));
+        for todo in &mut todos {
+            todo.list_id = to;
+            todo.version += 1;
+        }
+        self.save(&todos)?;
+        Ok(())
+    }
+
+    async fn delete_list(&self, list_id: i32) -> anyhow::Result<()> {
+        let _write = self.lock();
+        let ids = self
+            .load()?
+            .into_iter()
+            .filter(|t| t.list_id == list_id)
+            .map(|t| t.id);
+        self.remove(ids)?;
+        Ok(())
+    }
+
+    async fn add_occurrence(&self, series_id: i32, payload: CreateTodo) -> anyhow::Result<Todo> {
+        let _write = self.lock();
+        Ok(self.insert(payload, Some(series_id))?)
+    }
+
+    async fn find_series(&self, series_id: i32) -> anyhow::Result<TodoSeries> {
+        Ok(self.find_series_in(series_id)?)
+    }
+
+    async fn series_occurrences(&self, series_id: i32) -> anyhow::Result<Vec<Todo>> {
+        let mut todos = Vec::from_iter(
+            self.load()?
+                .into_iter()
+                .filter(|todo| todo.series_id == Some(series_id)),
+        );
+        todos.sort_by_key(|todo| (todo.due_at.is_none(), todo.due_at, todo.id));
+        Ok(todos)
+    }
+
+    async fn start_series(&self, id: i32, rrule: String) -> anyhow::Result<Todo> {
+        let _write = self.lock();
+        let todo = self.get(id)?;
+        let series = self.insert_series(rrule, todo.text.clone(), todo.priority)?;
+        let todo = Todo {
+            rrule: Some(series.rrule),
+            series_id: Some(series.id),
+            version: todo.version + 1,
+            ..todo
+        };
+        self.save([&todo])?;
+        Ok(todo)
+    }
+
+    async fn update_series(
+        &self,
+        series_id: i32,
+        payload: &UpdateTodo,
+    ) -> anyhow::Result<TodoSeries> {
+        let _write = self.lock();
+        let mut series = self.find_series_in(series_id)?;
+        if let Some(text) = &payload.text {
+            series.text = text.clone();
+        }
+        if let Some(priority) = payload.priority {
+            series.priority = priority;
+        }
+        if let Some(Some(rrule)) = &payload.rrule {
+            series.rrule = rrule.clone();
+        }
+        self.series
+            .insert(key(series_id), encode(&series)?)
+            .map_err(RepositoryError::from)?;
+        let mut todos = Vec::from_iter(
+            self.load()?
+                .into_iter()
+                .filter(|todo| todo.series_id == Some(series_id) && !todo.completed),
+        );
+        for todo in &mut todos {
+            todo.text = series.text.clone();
+            todo.priority = series.priority;
+            todo.version += 1;
+            if let Some(rrule) = &payload.rrule {
+                todo.rrule = rrule.clone();
+            }
+        }
+        self.save(&todos)?;
+        Ok(series)
+    }
+
+    async fn find_due(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Todo>> {
+        let mut todos = Vec::from_iter(self.load()?.into_iter().filter(|todo| {
+            !todo.completed
+                && todo
+                    .due_at
+                    .is_some_and(|due_at| due_at < to && from.is_none_or(|from| due_at >= from))
+        }));
+        todos.sort_by_key(|todo| (todo.due_at, todo.id));
+        Ok(todos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::labels::model::CreateLabel;
+    use crate::labels::repository::{sled::LabelRepositoryForSled, LabelRepositoryTrait};
+    use crate::lists::model::CreateList;
+    use crate::lists::repository::{sled::ListRepositoryForSled, ListRepositoryTrait};
+    use crate::todos::model::TodoSort;
+    use crate::todos::repository::scenario::{self, Fixtures};
+
+    /// Lists and labels kept in the same database, removed from the todos as their services do.
+    struct SledFixtures(Db, TodoRepositoryForSled);
+
+    #[async_trait]
+    impl Fixtures for SledFixtures {
+        async fn inbox_id(&self) -> i32 {
+            ListRepositoryForSled::open(&self.0)
+                .unwrap()
+                .inbox()
+                .await
+                .expect("[inbox] returned Err")
+                .id
+        }
+
+        async fn create_list(&self, name: &str) -> i32 {
+            ListRepositoryForSled::open(&self.0)
+                .unwrap()
+                .create(CreateList {
+                    name: name.to_string(),
+                })
+                .await
+                .expect("[create list] returned Err")
+                .id
+        }
+
+        async fn delete_list(&self, id: i32) {
+            ListRepositoryForSled::open(&self.0)
+                .unwrap()
+                .delete(id)
+                .await
+                .expect("[delete list] returned Err");
+        }
+
+        async fn create_label(&self, name: &str) -> i32 {
+            LabelRepositoryForSled::open(&self.0)
+                .unwrap()
+                .create(CreateLabel {
+                    name: name.to_string(),
+                    color: None,
+                })
+                .await
+                .expect("[create label] returned Err")
+                .id
+        }
+
+        async fn delete_label(&self, id: i32) {
+            LabelRepositoryForSled::open(&self.0)
+                .unwrap()
+                .delete(id)
+                .await
+                .expect("[delete label] returned Err");
+            self.1
+                .remove_label(id)
+                .await
+                .expect("[remove label] returned Err");
+        }
+
+        async fn todo_rows(&self, id: i32) -> usize {
+            self.0
+                .open_tree("todos")
+                .and_then(|todos| todos.contains_key(key(id)))
+                .expect("[delete] todos read error") as usize
+        }
+    }
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        let db = ::sled::Config::new().temporary(true).open().unwrap();
+        let repository = TodoRepositoryForSled::open(&db).unwrap();
+
+        scenario::crud_scenario(&repository, &SledFixtures(db, repository.clone())).await;
+    }
+
+    #[tokio::test]
+    async fn todos_outlive_the_database() {
+        let path = std::env::temp_dir().join(format!(
+            "todos-{}.sled",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        // without the background flusher, which keeps the database locked after a drop
+        let open = || {
+            ::sled::Config::new()
+                .path(&path)
+                .flush_every_ms(None)
+                .open()
+                .unwrap()
+        };
+
+        let db = open();
+        let repository = TodoRepositoryForSled::open(&db).unwrap();
+        let mut ids = Vec::new();
+        for text in ["buy milk", "walk the dog", "buy bread"] {
+            let todo = repository
+                .create(CreateTodo::new(text.to_string()))
+                .await
+                .expect("[create] returned Err");
+            ids.push(todo.id);
+        }
+        let subtask = repository
+            .create(CreateTodo {
+                parent_id: Some(ids[0]),
+                ..CreateTodo::new("subtask".to_string())
+            })
+            .await
+            .expect("[create] returned Err");
+        repository
+            .attach_label(ids[2], 7)
+            .await
+            .expect("[attach_label] returned Err");
+        repository
+            .move_before(ids[2], Some(ids[0]))
+            .await
+            .expect("[move_before] returned Err");
+        repository
+            .delete(ids[0], None)
+            .await
+            .expect("[delete] returned Err");
+        let res = repository.find(subtask.id).await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::NotFound(id)) if id == subtask.id
+        ));
+        drop((repository, db));
+
+        let db = open();
+        let repository = TodoRepositoryForSled::open(&db).unwrap();
+        let by_position = TodoQuery {
+            sort: TodoSort::Position,
+            ..Default::default()
+        };
+        let todos = repository.all(&by_position).await.unwrap();
+        assert_eq!(
+            Vec::from_iter(todos.iter().map(|t| t.id)),
+            vec![ids[2], ids[1]]
+        );
+        assert_eq!(repository.label_ids(ids[2]).await.unwrap(), vec![7]);
+        let labelled = TodoQuery {
+            labels: vec![7],
+            ..Default::default()
+        };
+        assert_eq!(repository.count(&labelled).await.unwrap(), 1);
+        let hits = repository.search("BUY", 10).await.unwrap();
+        assert_eq!(
+            Vec::from_iter(hits.iter().map(|hit| hit.todo.id)),
+            vec![ids[2]]
+        );
+
+        // ids are never handed out twice, even after a delete
+        let todo = repository
+            .create(CreateTodo::new("buy tea".to_string()))
+            .await
+            .expect("[create] returned Err");
+        assert!(ids.iter().chain([&subtask.id]).all(|id| *id < todo.id));
+
+        let res = repository
+            .update(
+                todo.id,
+                UpdateTodo {
+                    completed: Some(true),
+                    version: Some(todo.version + 1),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::Conflict(_))
+        ));
+
+        drop((repository, db));
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn ids_stay_dense_across_reopens() {
+        let path = std::env::temp_dir().join(format!(
+            "todo-ids-{}.sled",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        let open = || {
+            ::sled::Config::new()
+                .path(&path)
+                .flush_every_ms(None)
+                .open()
+                .unwrap()
+        };
+
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let db = open();
+            let repository = TodoRepositoryForSled::open(&db).unwrap();
+            for text in ["buy milk", "walk the dog"] {
+                let todo = repository
+                    .create(CreateTodo::new(text.to_string()))
+                    .await
+                    .expect("[create] returned Err");
+                ids.push(todo.id);
+            }
+            db.flush().unwrap();
+        }
+        assert_eq!(ids, Vec::from_iter(1..=6));
+
+        // a tree filled before it had an id goes on from its greatest key
+        let db = open();
+        let tree = db.open_tree("legacy").unwrap();
+        tree.insert(key(4_000_001), vec![]).unwrap();
+        assert_eq!(generate_id(&db, &tree).unwrap(), 4_000_002);
+        assert_eq!(generate_id(&db, &tree).unwrap(), 4_000_003);
+
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,725 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::migrate::Migrator;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use super::{position_between, RepositoryError, TodoRepositoryTrait, POSITION_GAP};
+use crate::todos::model::{
+    CreateTodo, Todo, TodoQuery, TodoSearchHit, TodoSeries, TodoSort, UpdateTodo,
+};
+use crate::todos::search::{words, HIGHLIGHT};
+
+/// Schema of the embedded database, the same tables the postgres migrations of src-cloud
+/// end up with.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// Filters of a `TodoQuery`, bound to `?1..?8` in field order with the labels as a JSON array.
+/// Unlike postgres, SQLite only folds the case of ASCII letters when matching `text`.
+const FILTER: &str = r#"
+    where (?2 is null or list_id=?2)
+    and (?3 is null or completed=?3)
+    and (?4 is null or instr(lower(text), lower(?4)) > 0)
+    and (?5 is null or due_at >= ?5)
+    and (?6 is null or due_at < ?6)
+    and (?7 is null or created_at >= ?7)
+    and (?8 is null or created_at < ?8)
+    and not exists (
+        select 1 from json_each(?1) as wanted
+        where not exists (
+            select 1 from todo_labels
+            where todo_labels.todo_id=todos.id and todo_labels.label_id=wanted.value
+        )
+    )
+"#;
+
+/// Todos in a local SQLite database, for the desktop app. The pool must have been migrated
+/// with `MIGRATOR`.
+#[derive(Debug, Clone)]
+pub struct TodoRepositoryForSqlite {
+    pool: SqlitePool,
+}
+
+impl TodoRepositoryForSqlite {
+    pub fn new(pool: SqlitePool) -> Self {
+        TodoRepositoryForSqlite { pool }
+    }
+
+    async fn insert(
+        conn: &mut SqliteConnection,
+        payload: CreateTodo,
+        series_id: Option<i32>,
+    ) -> Result<Todo, RepositoryError> {
+        let todo = sqlx::query_as::<_, Todo>(
+            r#"
+            insert into todos (text, completed, starts_at, due_at, priority, position, list_id, parent_id, rrule, series_id, created_at)
+            values (?1, false, ?2, ?3, ?4, (select coalesce(max(position), 0) from todos) + ?5,
+                coalesce(?6, (select list_id from todos where id=?7), (select id from lists where inbox)), ?7, ?8, ?9, ?10)
+            returning *
+            "#,
+        )
+        .bind(payload.text)
+        .bind(payload.starts_at)
+        .bind(payload.due_at)
+        .bind(payload.priority)
+        .bind(POSITION_GAP)
+        .bind(payload.list_id)
+        .bind(payload.parent_id)
+        .bind(payload.rrule)
+        .bind(series_id)
+        .bind(Utc::now())
+        .fetch_one(conn)
+        .await?;
+        Ok(todo)
+    }
+
+    /// Why a write to `id` expecting `version` matched no row.
+    async fn missed(&self, id: i32, version: Option<i32>) -> RepositoryError {
+        let current = sqlx::query_scalar::<_, i32>("select version from todos where id=?1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await;
+        match (current, version) {
+            (Ok(Some(current)), Some(expected)) => RepositoryError::Conflict(format!(
+                "todo {id} is at version {current}, not {expected}"
+            )),
+            (Ok(_), _) => RepositoryError::NotFound(id),
+            (Err(e), _) => e.into(),
+        }
+    }
+
+    /// `labels` as the JSON array `FILTER` expects.
+    fn json_ids(labels: &[i32]) -> String {
+        serde_json::Value::from(labels).to_string()
+    }
+
+    fn order_by(sort: TodoSort) -> &'static str {
+        match sort {
+            TodoSort::Position => "position, id",
+            TodoSort::Priority => "priority desc, position, id",
+            TodoSort::DueDate => "coalesce(due_at, 'infinity'), position, id",
+            TodoSort::CreatedAt => "created_at desc, id desc",
+        }
+    }
+
+    /// Todos sorted after the cursor bound to `?9..?13`, mirroring `order_by`. Timestamps are
+    /// RFC 3339 text, which sorts before `'infinity'`.
+    fn after_cursor(sort: TodoSort) -> &'static str {
+        match sort {
+            TodoSort::Position => "(position, id) > (?10, ?9)",
+            TodoSort::Priority => "(-priority, position, id) > (-?11, ?10, ?9)",
+            TodoSort::DueDate => {
+                "(coalesce(due_at, 'infinity'), position, id) > (coalesce(?12, 'infinity'), ?10, ?9)"
+            }
+            TodoSort::CreatedAt => "(created_at, id) < (?13, ?9)",
+        }
+    }
+}
+
+#[async_trait]
+impl TodoRepositoryTrait for TodoRepositoryForSqlite {
+    /// A database file holds the todos of a single user.
+    fn owned_by(&self, _owner: i32) -> Self {
+        self.clone()
+    }
+
+    /// A database file holds the todos of a single workspace.
+    fn in_workspace(&self, _workspace: i32) -> Self {
+        self.clone()
+    }
+
+    async fn create(&self, payload: CreateTodo) -> anyhow::Result<Todo> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        let series_id = match &payload.rrule {
+            Some(rrule) => Some(
+                sqlx::query_scalar::<_, i32>(
+                    r#"
+                    insert into todo_series (rrule, text, priority)
+                    values (?1, ?2, ?3)
+                    returning id
+                    "#,
+                )
+                .bind(rrule)
+                .bind(&payload.text)
+                .bind(payload.priority)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(RepositoryError::from)?,
+            ),
+            None => None,
+        };
+        let todo = Self::insert(&mut tx, payload, series_id).await?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+        Ok(todo)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Todo> {
+        let todo = sqlx::query_as::<_, Todo>(
+            r#"
+            select * from todos where id=?1
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+
+        Ok(todo)
+    }
+
+    async fn all(&self, query: &TodoQuery) -> anyhow::Result<Vec<Todo>> {
+        let cursor = query.cursor.as_ref();
+        let todos = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            select * from todos
+            {FILTER}
+            and (?9 is null or {})
+            order by {}
+            limit coalesce(?14, -1);
+            "#,
+            Self::after_cursor(query.sort),
+            Self::order_by(query.sort)
+        ))
+        .bind(Self::json_ids(&query.labels))
+        .bind(query.list_id)
+        .bind(query.completed)
+        .bind(&query.text)
+        .bind(query.due_from)
+        .bind(query.due_to)
+        .bind(query.created_from)
+        .bind(query.created_to)
+        .bind(cursor.map(|c| c.id))
+        .bind(cursor.map(|c| c.position))
+        .bind(cursor.map(|c| c.priority))
+        .bind(cursor.and_then(|c| c.due_at))
+        .bind(cursor.map(|c| c.created_at))
+        .bind(query.limit.map(i64::from))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(todos)
+    }
+
+    async fn count(&self, query: &TodoQuery) -> anyhow::Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(&format!(
+            r#"
+            select count(*) from todos
+            {FILTER};
+            "#
+        ))
+        .bind(Self::json_ids(&query.labels))
+        .bind(query.list_id)
+        .bind(query.completed)
+        .bind(&query.text)
+        .bind(query.due_from)
+        .bind(query.due_to)
+        .bind(query.created_from)
+        .bind(query.created_to)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(count)
+    }
+
+    async fn search(&self, query: &str, limit: u32) -> anyhow::Result<Vec<TodoSearchHit>> {
+        // every word as a quoted phrase, so nothing in `query` is read as FTS5 syntax
+        let phrases = Vec::from_iter(words(query).map(|(_, word)| format!("\"{word}\"")));
+        if phrases.is_empty() {
+            return Ok(Vec::new());
+        }
+        let hits = sqlx::query_as::<_, TodoSearchHit>(
+            r#"
+            select todos.*, -bm25(todos_search) as rank,
+                highlight(todos_search, 0, ?3, ?4) as snippet
+            from todos_search join todos on todos.id=todos_search.rowid
+            where todos_search match ?1
+            order by bm25(todos_search), todos.id
+            limit ?2;
+            "#,
+        )
+        .bind(phrases.join(" "))
+        .bind(i64::from(limit))
+        .bind(HIGHLIGHT.0)
+        .bind(HIGHLIGHT.1)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(hits)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<Todo> {
+        let todo = sqlx::query_as::<_, Todo>(
+            r#"
+            update todos set text=coalesce(?1, text), completed=coalesce(?2, completed),
+                starts_at=case when ?3 then ?4 else starts_at end,
+                due_at=case when ?5 then ?6 else due_at end,
+                priority=coalesce(?7, priority), list_id=coalesce(?8, list_id),
+                parent_id=case when ?9 then ?10 else parent_id end, version=version+1
+            where id=?11 and (?12 is null or version=?12)
+            returning *
+            "#,
+        )
+        .bind(payload.text)
+        .bind(payload.completed)
+        .bind(payload.starts_at.is_some())
+        .bind(payload.starts_at.flatten())
+        .bind(payload.due_at.is_some())
+        .bind(payload.due_at.flatten())
+        .bind(payload.priority)
+        .bind(payload.list_id)
+        .bind(payload.parent_id.is_some())
+        .bind(payload.parent_id.flatten())
+        .bind(id)
+        .bind(payload.version)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        match todo {
+            Some(todo) => Ok(todo),
+            None => Err(self.missed(id, payload.version).await.into()),
+        }
+    }
+
+    async fn delete(&self, id: i32, version: Option<i32>) -> anyhow::Result<()> {
+        let result = sqlx::query::<_>(
+            r#"
+            delete from todos where id=?1 and (?2 is null or version=?2)
+            "#,
+        )
+        .bind(id)
+        .bind(version)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        if result.rows_affected() == 0 {
+            return Err(self.missed(id, version).await.into());
+        }
+
+        Ok(())
+    }
+
+    async fn subtree(&self, id: i32) -> anyhow::Result<Vec<Todo>> {
+        let todos = sqlx::query_as::<_, Todo>(
+            r#"
+            with recursive subtree as (
+                select * from todos where id=?1
+                union all
+                select todos.* from todos join subtree on todos.parent_id=subtree.id
+            )
+            select * from subtree
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        if todos.is_empty() {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+
+        Ok(todos)
+    }
+
+    async fn ancestor_ids(&self, id: i32) -> anyhow::Result<Vec<i32>> {
+        let ids = sqlx::query_scalar::<_, i32>(
+            r#"
+            with recursive ancestors(id, parent_id, depth) as (
+                select id, parent_id, 0 from todos where id=?1
+                union all
+                select todos.id, todos.parent_id, ancestors.depth + 1
+                from todos join ancestors on todos.id=ancestors.parent_id
+            )
+            select id from ancestors where depth > 0
+            order by depth
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(ids)
+    }
+
+    async fn move_before(&self, id: i32, before: Option<i32>) -> anyhow::Result<Todo> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        // writing first takes the database's write lock, which serializes moves with each other
+        sqlx::query("update todos set position=position where id=?1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepositoryError::from)?;
+
+        let position = match before {
+            Some(before) if before != id => loop {
+                let next: i64 = sqlx::query_scalar("select position from todos where id=?1")
+                    .bind(before)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .map_err(RepositoryError::from_sqlx(before))?;
+                let prev: Option<i64> = sqlx::query_scalar(
+                    r#"
+                    select position from todos
+                    where id<>?1 and (position, id) < (?2, ?3)
+                    order by position desc, id desc
+                    limit 1
+                    "#,
+                )
+                .bind(id)
+                .bind(next)
+                .bind(before)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(RepositoryError::from)?;
+
+                if let Some(position) = position_between(prev, next) {
+                    break Some(position);
+                }
+                sqlx::query(
+                    r#"
+                    update todos set position=ordered.rank * ?1, version=version+1
+                    from (select id, row_number() over (order by position, id) as rank from todos) ordered
+                    where todos.id=ordered.id
+                    "#,
+                )
+                .bind(POSITION_GAP)
+                .execute(&mut *tx)
+                .await
+                .map_err(RepositoryError::from)?;
+            },
+            Some(_) => None,
+            None => Some(
+                sqlx::query_scalar::<_, i64>(
+                    "select coalesce(max(position), 0) + ?2 from todos where id<>?1",
+                )
+                .bind(id)
+                .bind(POSITION_GAP)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(RepositoryError::from)?,
+            ),
+        };
+
+        let todo = sqlx::query_as::<_, Todo>(
+            r#"
+            update todos set position=coalesce(?1, position), version=version+1
+            where id=?2
+            returning *
+            "#,
+        )
+        .bind(position)
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+
+        Ok(todo)
+    }
+
+    async fn label_ids(&self, id: i32) -> anyhow::Result<Vec<i32>> {
+        let ids = sqlx::query_scalar::<_, i32>(
+            r#"
+            select label_id from todo_labels where todo_id=?1
+            order by label_id
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(ids)
+    }
+
+    async fn attach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            insert into todo_labels (todo_id, label_id)
+            values (?1, ?2)
+            on conflict do nothing
+            "#,
+        )
+        .bind(id)
+        .bind(label_id)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    async fn detach_label(&self, id: i32, label_id: i32) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            delete from todo_labels where todo_id=?1 and label_id=?2
+            "#,
+        )
+        .bind(id)
+        .bind(label_id)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    async fn remove_label(&self, label_id: i32) -> anyhow::Result<()> {
+        // todo_labels cascades on delete from labels, this only matters if it is called first
+        sqlx::query(
+            r#"
+            delete from todo_labels where label_id=?1
+            "#,
+        )
+        .bind(label_id)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    async fn move_list(&self, from: i32, to: i32) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            update todos set list_id=?2, version=version+1 where list_id=?1
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    async fn delete_list(&self, list_id: i32) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            delete from todos where list_id=?1
+            "#,
+        )
+        .bind(list_id)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    async fn add_occurrence(&self, series_id: i32, payload: CreateTodo) -> anyhow::Result<Todo> {
+        let mut conn = self.pool.acquire().await.map_err(RepositoryError::from)?;
+        let todo = Self::insert(&mut conn, payload, Some(series_id)).await?;
+        Ok(todo)
+    }
+
+    async fn find_series(&self, series_id: i32) -> anyhow::Result<TodoSeries> {
+        let series = sqlx::query_as::<_, TodoSeries>(
+            r#"
+            select * from todo_series where id=?1
+            "#,
+        )
+        .bind(series_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(series_id))?;
+
+        Ok(series)
+    }
+
+    async fn series_occurrences(&self, series_id: i32) -> anyhow::Result<Vec<Todo>> {
+        // nulls sort first in SQLite and last in postgres
+        let todos = sqlx::query_as::<_, Todo>(
+            r#"
+            select * from todos where series_id=?1
+            order by due_at nulls last, id
+            "#,
+        )
+        .bind(series_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(todos)
+    }
+
+    async fn start_series(&self, id: i32, rrule: String) -> anyhow::Result<Todo> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        let series_id = sqlx::query_scalar::<_, i32>(
+            r#"
+            insert into todo_series (rrule, text, priority)
+            select ?2, text, priority from todos where id=?1
+            returning id
+            "#,
+        )
+        .bind(id)
+        .bind(&rrule)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+        let todo = sqlx::query_as::<_, Todo>(
+            r#"
+            update todos set rrule=?2, series_id=?3, version=version+1
+            where id=?1
+            returning *
+            "#,
+        )
+        .bind(id)
+        .bind(rrule)
+        .bind(series_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+
+        Ok(todo)
+    }
+
+    async fn update_series(
+        &self,
+        series_id: i32,
+        payload: &UpdateTodo,
+    ) -> anyhow::Result<TodoSeries> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        let series = sqlx::query_as::<_, TodoSeries>(
+            r#"
+            update todo_series set text=coalesce(?1, text), priority=coalesce(?2, priority),
+                rrule=coalesce(?3, rrule)
+            where id=?4
+            returning *
+            "#,
+        )
+        .bind(&payload.text)
+        .bind(payload.priority)
+        .bind(payload.rrule.clone().flatten())
+        .bind(series_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(RepositoryError::from_sqlx(series_id))?;
+        sqlx::query(
+            r#"
+            update todos set text=coalesce(?1, text), priority=coalesce(?2, priority),
+                rrule=case when ?3 then ?4 else rrule end, version=version+1
+            where series_id=?5 and not completed
+            "#,
+        )
+        .bind(&payload.text)
+        .bind(payload.priority)
+        .bind(payload.rrule.is_some())
+        .bind(payload.rrule.clone().flatten())
+        .bind(series_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(RepositoryError::from)?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+
+        Ok(series)
+    }
+
+    async fn find_due(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Todo>> {
+        let todos = sqlx::query_as::<_, Todo>(
+            r#"
+            select * from todos
+            where not completed and due_at < ?2 and (?1 is null or due_at >= ?1)
+            order by due_at, id;
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(todos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::todos::repository::scenario::{self, Fixtures};
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    /// The lists and labels domains only have postgres repositories, their rows are written here.
+    struct SqliteFixtures(SqlitePool);
+
+    #[async_trait]
+    impl Fixtures for SqliteFixtures {
+        async fn inbox_id(&self) -> i32 {
+            sqlx::query_scalar("select id from lists where inbox")
+                .fetch_one(&self.0)
+                .await
+                .expect("[inbox] returned Err")
+        }
+
+        async fn create_list(&self, name: &str) -> i32 {
+            sqlx::query_scalar("insert into lists (name) values (?1) returning id")
+                .bind(name)
+                .fetch_one(&self.0)
+                .await
+                .expect("[create list] returned Err")
+        }
+
+        async fn delete_list(&self, id: i32) {
+            sqlx::query("delete from lists where id=?1")
+                .bind(id)
+                .execute(&self.0)
+                .await
+                .expect("[delete list] returned Err");
+        }
+
+        async fn create_label(&self, name: &str) -> i32 {
+            sqlx::query_scalar("insert into labels (name) values (?1) returning id")
+                .bind(name)
+                .fetch_one(&self.0)
+                .await
+                .expect("[create label] returned Err")
+        }
+
+        async fn delete_label(&self, id: i32) {
+            sqlx::query("delete from labels where id=?1")
+                .bind(id)
+                .execute(&self.0)
+                .await
+                .expect("[delete label] returned Err");
+        }
+
+        async fn todo_rows(&self, id: i32) -> usize {
+            sqlx::query("select * from todos where id=?1")
+                .bind(id)
+                .fetch_all(&self.0)
+                .await
+                .expect("[delete] todo_labels fetch error")
+                .len()
+        }
+    }
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        // every connection to `sqlite::memory:` opens a database of its own, keep the one
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .expect("failed to open an in-memory database");
+        MIGRATOR.run(&pool).await.expect("failed to migrate");
+
+        scenario::crud_scenario(
+            &TodoRepositoryForSqlite::new(pool.clone()),
+            &SqliteFixtures(pool),
+        )
+        .await;
+    }
+}
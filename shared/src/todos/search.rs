@@ -0,0 +1,147 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// Put around the matched words of a snippet. The text in between isn't escaped.
+pub const HIGHLIGHT: (&str, &str) = ("<mark>", "</mark>");
+
+/// The lowercased words of `text` with their byte ranges, split like the `simple` text search
+/// configuration of postgres does: on anything that isn't alphanumeric.
+pub fn words(text: &str) -> impl Iterator<Item = (Range<usize>, String)> + '_ {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, _) = chars.find(|(_, c)| c.is_alphanumeric())?;
+        let mut end = text.len();
+        while let Some(&(i, c)) = chars.peek() {
+            if !c.is_alphanumeric() {
+                end = i;
+                break;
+            }
+            chars.next();
+        }
+        Some((start..end, text[start..end].to_lowercase()))
+    })
+}
+
+/// `text` with every word of `query` highlighted.
+pub fn snippet(text: &str, query: &str) -> String {
+    let wanted = HashSet::<String>::from_iter(words(query).map(|(_, word)| word));
+    let mut snippet = String::with_capacity(text.len());
+    let mut last = 0;
+    for (range, _) in words(text).filter(|(_, word)| wanted.contains(word)) {
+        snippet.push_str(&text[last..range.start]);
+        snippet.push_str(HIGHLIGHT.0);
+        snippet.push_str(&text[range.clone()]);
+        snippet.push_str(HIGHLIGHT.1);
+        last = range.end;
+    }
+    snippet.push_str(&text[last..]);
+    snippet
+}
+
+/// Inverted index over the text of todos, for the repositories without a full-text search of
+/// their own.
+#[derive(Debug, Default, Clone)]
+pub struct SearchIndex {
+    /// How many times each word appears in each todo.
+    postings: HashMap<String, HashMap<i32, u32>>,
+    /// Words of each todo, to unindex it.
+    documents: HashMap<i32, Vec<String>>,
+}
+
+impl SearchIndex {
+    /// Indexes `text` as the text of `id`, replacing what it had.
+    pub fn insert(&mut self, id: i32, text: &str) {
+        self.remove(id);
+        let document = Vec::from_iter(words(text).map(|(_, word)| word));
+        for word in &document {
+            *self
+                .postings
+                .entry(word.clone())
+                .or_default()
+                .entry(id)
+                .or_default() += 1;
+        }
+        self.documents.insert(id, document);
+    }
+
+    pub fn remove(&mut self, id: i32) {
+        for word in self.documents.remove(&id).into_iter().flatten() {
+            if let Some(ids) = self.postings.get_mut(&word) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.postings.remove(&word);
+                }
+            }
+        }
+    }
+
+    /// Ids of the todos holding every word of `query` with their rank, highest first. The rank
+    /// is the share of their words matching the query.
+    pub fn search(&self, query: &str) -> Vec<(i32, f32)> {
+        let wanted = HashSet::<String>::from_iter(words(query).map(|(_, word)| word));
+        let mut postings = Vec::with_capacity(wanted.len());
+        for word in &wanted {
+            match self.postings.get(word) {
+                Some(ids) => postings.push(ids),
+                None => return Vec::new(),
+            }
+        }
+        let Some(first) = postings.first() else {
+            return Vec::new();
+        };
+        let mut hits = Vec::from_iter(first.keys().filter_map(|id| {
+            let matches = postings
+                .iter()
+                .map(|ids| ids.get(id).copied())
+                .sum::<Option<u32>>()?;
+            Some((*id, matches as f32 / self.documents[id].len() as f32))
+        }));
+        hits.sort_by(|(a_id, a_rank), (b_id, b_rank)| {
+            b_rank.total_cmp(a_rank).then(a_id.cmp(b_id))
+        });
+        hits
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ranks_todos_holding_every_word() {
+        let mut index = SearchIndex::default();
+        index.insert(1, "Buy milk");
+        index.insert(2, "buy bread and milk, then more milk");
+        index.insert(3, "walk the dog");
+        index.insert(4, "Milk-shake");
+
+        assert_eq!(
+            Vec::from_iter(index.search("MILK buy").into_iter().map(|(id, _)| id)),
+            vec![1, 2]
+        );
+        assert_eq!(
+            Vec::from_iter(index.search("milk").into_iter().map(|(id, _)| id)),
+            vec![1, 4, 2]
+        );
+        assert!(index.search("milk cat").is_empty());
+        assert!(index.search("?!").is_empty());
+
+        index.insert(1, "sell milk");
+        index.remove(4);
+        assert_eq!(
+            Vec::from_iter(index.search("milk").into_iter().map(|(id, _)| id)),
+            vec![1, 2]
+        );
+        assert!(index.search("buy milk").iter().all(|(id, _)| *id == 2));
+    }
+
+    #[test]
+    fn highlights_matched_words() {
+        assert_eq!(
+            snippet("Buy milk, then Milk-shake", "milk"),
+            "Buy <mark>milk</mark>, then <mark>Milk</mark>-shake"
+        );
+        assert_eq!(snippet("café au lait", "CAFÉ"), "<mark>café</mark> au lait");
+        assert_eq!(snippet("walk the dog", "cat"), "walk the dog");
+    }
+}
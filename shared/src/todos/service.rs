@@ -1,17 +1,34 @@
 //use std::sync::Arc;
 
 use axum::async_trait;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveTime, Utc};
+use validator::Validate;
 
 // TODO: move this to shared
-use super::model::{CreateTodo, Todo, UpdateTodo};
+use super::error::TodoError;
+use super::model::{
+    check_limit, CreateTodo, SeriesOccurrences, Todo, TodoPage, TodoQuery, TodoSearchHit, TodoTree,
+    UpdateTodo, DEFAULT_PAGE_SIZE, MAX_DEPTH,
+};
+use super::recurrence::Recurrence;
 use super::repository::TodoRepositoryTrait;
+use super::search::words;
+use crate::members::{model::Role, repository::MemberRepositoryTrait, service::role_of};
 
+/// Todos are stored by `TR`, the roles of the members of their lists by `MR`. Reading a todo
+/// takes the `Viewer` role in its list and changing it the `Editor` role, queries returning many
+/// todos only return those the repository lets the user see.
 #[derive(Debug, Clone)]
-pub struct TodoService<TR>
+pub struct TodoService<TR, MR>
 where
     TR: TodoRepositoryTrait,
+    MR: MemberRepositoryTrait,
 {
     todo_repository: TR,
+    member_repository: MR,
+    /// Whose roles are checked, `None` is allowed everything.
+    user: Option<i32>,
+    auto_complete_parents: bool,
 }
 
 #[async_trait]
@@ -19,66 +36,816 @@ pub trait TodoServiceTrait
 where
     Self: Clone + std::marker::Send + std::marker::Sync + 'static,
 {
-    async fn create(&self, payload: CreateTodo) -> Result<Todo, &str>;
-    async fn find(&self, id: i32) -> Result<Todo, &str>;
-    async fn find_all(&self) -> Result<Vec<Todo>, &str>;
-    async fn update(&self, id: i32, payload: UpdateTodo) -> Result<Todo, &str>;
-    async fn delete(&self, id: i32) -> Result<&str, &str>;
+    async fn create(&self, payload: CreateTodo) -> Result<Todo, TodoError>;
+    async fn find(&self, id: i32) -> Result<Todo, TodoError>;
+    /// The todo with all of its subtasks.
+    async fn find_tree(&self, id: i32) -> Result<TodoTree, TodoError>;
+    /// A page of the todos matching `query`, with how many there are in total.
+    async fn find_all(&self, query: TodoQuery) -> Result<TodoPage, TodoError>;
+    /// Todos holding every word of `query`, most relevant first.
+    async fn search(
+        &self,
+        query: &str,
+        limit: Option<u32>,
+    ) -> Result<Vec<TodoSearchHit>, TodoError>;
+    /// Only updates this occurrence of a recurring todo. Completing it creates the next one.
+    async fn update(&self, id: i32, payload: UpdateTodo) -> Result<Todo, TodoError>;
+    /// Also updates the series the next occurrences are created from, and can start or end it.
+    async fn update_all_future(&self, id: i32, payload: UpdateTodo) -> Result<Todo, TodoError>;
+    async fn find_series(&self, series_id: i32) -> Result<SeriesOccurrences, TodoError>;
+    /// Fails with `Conflict` when `version` is set and the todo is at another version.
+    async fn delete(&self, id: i32, version: Option<i32>) -> Result<(), TodoError>;
+    async fn reorder(&self, id: i32, before: Option<i32>) -> Result<Todo, TodoError>;
+    async fn find_overdue(&self, now: DateTime<Utc>) -> Result<Vec<Todo>, TodoError>;
+    /// `now` carries the caller's offset, which decides where "today" starts.
+    async fn find_due_today(&self, now: DateTime<FixedOffset>) -> Result<Vec<Todo>, TodoError>;
+    /// Weeks start on monday.
+    async fn find_due_this_week(&self, now: DateTime<FixedOffset>) -> Result<Vec<Todo>, TodoError>;
 }
 
-impl<TR> TodoService<TR>
+impl<TR, MR> TodoService<TR, MR>
 where
     TR: TodoRepositoryTrait,
+    MR: MemberRepositoryTrait,
 {
-    pub fn new(todo_repository: TR) -> Self {
-        Self { todo_repository }
+    pub fn new(todo_repository: TR, member_repository: MR) -> Self {
+        Self {
+            todo_repository,
+            member_repository,
+            user: None,
+            auto_complete_parents: false,
+        }
+    }
+
+    /// Completes a parent as soon as its last open subtask gets completed.
+    pub fn auto_complete_parents(self, enabled: bool) -> Self {
+        Self {
+            auto_complete_parents: enabled,
+            ..self
+        }
+    }
+
+    /// The same service over the todos of user `owner`, checking their roles.
+    pub fn owned_by(&self, owner: i32) -> Self {
+        Self {
+            todo_repository: self.todo_repository.owned_by(owner),
+            member_repository: self.member_repository.clone(),
+            user: Some(owner),
+            auto_complete_parents: self.auto_complete_parents,
+        }
+    }
+
+    /// The same service over the todos of workspace `workspace`.
+    pub fn in_workspace(&self, workspace: i32) -> Self {
+        Self {
+            todo_repository: self.todo_repository.in_workspace(workspace),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) async fn check_role(&self, list_id: i32, needed: Role) -> Result<(), TodoError> {
+        match role_of(&self.member_repository, self.user, list_id).await? {
+            Some(role) if role >= needed => Ok(()),
+            _ => Err(TodoError::Forbidden(format!(
+                "needs to be {needed:?} of list {list_id}"
+            ))),
+        }
+    }
+
+    /// `todo` is about to change, and maybe to move to list `to`.
+    pub(crate) async fn check_edit(&self, todo: &Todo, to: Option<i32>) -> Result<(), TodoError> {
+        self.check_role(todo.list_id, Role::Editor).await?;
+        match to {
+            Some(list_id) if list_id != todo.list_id => {
+                self.check_role(list_id, Role::Editor).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// `payload` is about to be created.
+    async fn check_create(&self, payload: &CreateTodo) -> Result<(), TodoError> {
+        // payloads from tauri commands don't go through ValidatedJson
+        payload.validate()?;
+        if let Some(parent_id) = payload.parent_id {
+            self.check_parent(None, parent_id, 1).await?;
+        }
+        // subtasks go to the list of their parent, other todos to the inbox, which isn't shared
+        let list_id = match (payload.list_id, payload.parent_id) {
+            (Some(list_id), _) => Some(list_id),
+            (None, Some(parent_id)) => Some(self.todo_repository.find(parent_id).await?.list_id),
+            (None, None) => None,
+        };
+        if let Some(list_id) = list_id {
+            self.check_role(list_id, Role::Editor).await?;
+        }
+        Ok(())
+    }
+
+    /// Creates `payload` as an occurrence of series `series_id`, like the one completing the
+    /// previous occurrence creates.
+    pub(crate) async fn add_occurrence(
+        &self,
+        series_id: i32,
+        payload: CreateTodo,
+    ) -> Result<Todo, TodoError> {
+        self.check_create(&payload).await?;
+        let todo = self
+            .todo_repository
+            .add_occurrence(series_id, payload)
+            .await?;
+        Ok(todo)
+    }
+
+    /// Checks that `height` levels fit under `parent_id`, and that `id` isn't one of its ancestors.
+    async fn check_parent(
+        &self,
+        id: Option<i32>,
+        parent_id: i32,
+        height: usize,
+    ) -> Result<(), TodoError> {
+        let parent =
+            self.todo_repository.find(parent_id).await.map_err(|_| {
+                TodoError::Validation(format!("parent todo {parent_id} doesn't exist"))
+            })?;
+        let ancestor_ids = self.todo_repository.ancestor_ids(parent.id).await?;
+        if id.is_some_and(|id| id == parent.id || ancestor_ids.contains(&id)) {
+            return Err(TodoError::Validation(
+                "a todo can't be moved under its own subtask".to_string(),
+            ));
+        }
+        if ancestor_ids.len() + 1 + height > MAX_DEPTH {
+            return Err(TodoError::Validation(format!(
+                "subtasks can't be nested deeper than {MAX_DEPTH} levels"
+            )));
+        }
+        Ok(())
+    }
+
+    async fn apply(&self, id: i32, payload: UpdateTodo) -> Result<Todo, TodoError> {
+        if let Some(Some(parent_id)) = payload.parent_id {
+            let tree = self.find_tree(id).await?;
+            self.check_parent(Some(id), parent_id, tree.height())
+                .await?;
+        }
+        let completes = payload.completed == Some(true) && !self.find(id).await?.completed;
+        let todo = self.todo_repository.update(id, payload).await?;
+        if completes {
+            self.schedule_next(&todo).await?;
+            if self.auto_complete_parents {
+                self.complete_ancestors(&todo).await?;
+            }
+        }
+        Ok(todo)
+    }
+
+    /// Creates the occurrence following `todo` unless the series is over or already has it.
+    async fn schedule_next(&self, todo: &Todo) -> Result<Option<Todo>, TodoError> {
+        let (Some(series_id), Some(rrule), Some(due_at)) =
+            (todo.series_id, &todo.rrule, todo.due_at)
+        else {
+            return Ok(None);
+        };
+        let recurrence = rrule.parse::<Recurrence>().map_err(TodoError::Validation)?;
+        let occurrences = self.todo_repository.series_occurrences(series_id).await?;
+        let Some(next_due_at) = recurrence.next(due_at, occurrences.len()) else {
+            return Ok(None);
+        };
+        if occurrences.iter().any(|t| t.due_at == Some(next_due_at)) {
+            return Ok(None);
+        }
+        let series = self.todo_repository.find_series(series_id).await?;
+        let payload = CreateTodo {
+            text: series.text,
+            list_id: Some(todo.list_id),
+            parent_id: todo.parent_id,
+            starts_at: todo
+                .starts_at
+                .map(|starts_at| starts_at + (next_due_at - due_at)),
+            due_at: Some(next_due_at),
+            priority: series.priority,
+            rrule: Some(rrule.clone()),
+        };
+        let next = self
+            .todo_repository
+            .add_occurrence(series_id, payload)
+            .await?;
+        Ok(Some(next))
+    }
+
+    async fn complete_ancestors(&self, todo: &Todo) -> Result<(), TodoError> {
+        let mut parent_id = todo.parent_id;
+        while let Some(id) = parent_id {
+            let siblings = self.todo_repository.subtree(id).await?;
+            let parent = siblings.iter().find(|t| t.id == id).cloned();
+            let done = siblings
+                .iter()
+                .filter(|t| t.parent_id == Some(id))
+                .all(|t| t.completed);
+            match parent {
+                Some(parent) if done && !parent.completed => {
+                    let update = UpdateTodo {
+                        completed: Some(true),
+                        ..Default::default()
+                    };
+                    let parent = self.todo_repository.update(id, update).await?;
+                    self.schedule_next(&parent).await?;
+                    parent_id = parent.parent_id;
+                }
+                _ => break,
+            }
+        }
+        Ok(())
     }
 }
 
 #[async_trait]
-impl<TR> TodoServiceTrait for TodoService<TR>
+impl<TR, MR> TodoServiceTrait for TodoService<TR, MR>
 where
     TR: TodoRepositoryTrait,
+    MR: MemberRepositoryTrait,
 {
-    async fn create(&self, payload: CreateTodo) -> Result<Todo, &str> {
-        let todo = self
-            .todo_repository
-            .create(payload)
-            .await
-            .or(Err("couldn't create a todo"))?;
+    async fn create(&self, payload: CreateTodo) -> Result<Todo, TodoError> {
+        self.check_create(&payload).await?;
+        let todo = self.todo_repository.create(payload).await?;
 
         Ok(todo)
     }
 
-    async fn find(&self, id: i32) -> Result<Todo, &str> {
-        let todo = self
-            .todo_repository
-            .find(id)
-            .await
-            .or(Err("couldn't find the todo"))?;
+    async fn find(&self, id: i32) -> Result<Todo, TodoError> {
+        let todo = self.todo_repository.find(id).await?;
+        self.check_role(todo.list_id, Role::Viewer).await?;
         Ok(todo)
     }
 
-    async fn find_all(&self) -> Result<Vec<Todo>, &str> {
-        let todo = self.todo_repository.all().await.unwrap();
-        Ok(todo)
+    async fn find_tree(&self, id: i32) -> Result<TodoTree, TodoError> {
+        let todos = self.todo_repository.subtree(id).await?;
+        let tree = TodoTree::build(id, todos).ok_or(TodoError::NotFound(id))?;
+        self.check_role(tree.todo.list_id, Role::Viewer).await?;
+        Ok(tree)
     }
 
-    async fn update(&self, id: i32, payload: UpdateTodo) -> Result<Todo, &str> {
-        let todo = self
+    async fn find_all(&self, query: TodoQuery) -> Result<TodoPage, TodoError> {
+        check_limit(query.limit).map_err(TodoError::Validation)?;
+        let page = self.todo_repository.page(&query).await?;
+        Ok(page)
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        limit: Option<u32>,
+    ) -> Result<Vec<TodoSearchHit>, TodoError> {
+        check_limit(limit).map_err(TodoError::Validation)?;
+        if words(query).next().is_none() {
+            return Err(TodoError::Validation(
+                "search query has no words".to_string(),
+            ));
+        }
+        let hits = self
             .todo_repository
-            .update(id, payload)
-            .await
-            .or(Err("couldn't update the todo"))?;
+            .search(query, limit.unwrap_or(DEFAULT_PAGE_SIZE))
+            .await?;
+        Ok(hits)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateTodo) -> Result<Todo, TodoError> {
+        payload.validate()?;
+        if payload.rrule.is_some() {
+            return Err(TodoError::Validation(
+                "rrule can only be changed for all future occurrences".to_string(),
+            ));
+        }
+        let todo = self.todo_repository.find(id).await?;
+        self.check_edit(&todo, payload.list_id).await?;
+        self.apply(id, payload).await
+    }
+
+    async fn update_all_future(&self, id: i32, payload: UpdateTodo) -> Result<Todo, TodoError> {
+        payload.validate()?;
+        let todo = self.todo_repository.find(id).await?;
+        self.check_edit(&todo, payload.list_id).await?;
+        // updating the series bumps the version of this occurrence, so it is checked first
+        if let Some(version) = payload.version.filter(|version| *version != todo.version) {
+            return Err(TodoError::Conflict(format!(
+                "todo {id} is at version {}, not {version}",
+                todo.version
+            )));
+        }
+        match (todo.series_id, &payload.rrule) {
+            (Some(series_id), _) => {
+                self.todo_repository
+                    .update_series(series_id, &payload)
+                    .await?;
+            }
+            (None, Some(Some(rrule))) => {
+                if payload.due_at.unwrap_or(todo.due_at).is_none() {
+                    return Err(TodoError::Validation(
+                        "a recurring todo needs due_at".to_string(),
+                    ));
+                }
+                self.todo_repository.start_series(id, rrule.clone()).await?;
+            }
+            (None, _) => {}
+        }
+        self.apply(
+            id,
+            UpdateTodo {
+                rrule: None,
+                version: None,
+                ..payload
+            },
+        )
+        .await
+    }
+
+    async fn find_series(&self, series_id: i32) -> Result<SeriesOccurrences, TodoError> {
+        let series =
+            self.todo_repository.find_series(series_id).await.map_err(
+                |e| match TodoError::from(e) {
+                    TodoError::NotFound(id) => TodoError::SeriesNotFound(id),
+                    e => e,
+                },
+            )?;
+        let occurrences = self.todo_repository.series_occurrences(series_id).await?;
+        if let Some(todo) = occurrences.first() {
+            self.check_role(todo.list_id, Role::Viewer).await?;
+        }
+        Ok(SeriesOccurrences {
+            series,
+            occurrences,
+        })
+    }
+
+    async fn delete(&self, id: i32, version: Option<i32>) -> Result<(), TodoError> {
+        let todo = self.todo_repository.find(id).await?;
+        self.check_edit(&todo, None).await?;
+        self.todo_repository.delete(id, version).await?;
+        Ok(())
+    }
+
+    async fn reorder(&self, id: i32, before: Option<i32>) -> Result<Todo, TodoError> {
+        let todo = self.todo_repository.find(id).await?;
+        self.check_edit(&todo, None).await?;
+        let todo = self.todo_repository.move_before(id, before).await?;
         Ok(todo)
     }
 
-    async fn delete(&self, id: i32) -> Result<&str, &str> {
-        Ok(self
+    async fn find_overdue(&self, now: DateTime<Utc>) -> Result<Vec<Todo>, TodoError> {
+        let todos = self.todo_repository.find_due(None, now).await?;
+        Ok(todos)
+    }
+
+    async fn find_due_today(&self, now: DateTime<FixedOffset>) -> Result<Vec<Todo>, TodoError> {
+        let from = start_of_day(now);
+        let todos = self
+            .todo_repository
+            .find_due(Some(from), from + Duration::days(1))
+            .await?;
+        Ok(todos)
+    }
+
+    async fn find_due_this_week(&self, now: DateTime<FixedOffset>) -> Result<Vec<Todo>, TodoError> {
+        let from = start_of_day(now) - Duration::days(now.weekday().num_days_from_monday() as i64);
+        let todos = self
             .todo_repository
-            .delete(id)
+            .find_due(Some(from), from + Duration::weeks(1))
+            .await?;
+        Ok(todos)
+    }
+}
+
+fn start_of_day(now: DateTime<FixedOffset>) -> DateTime<Utc> {
+    // a fixed offset has no DST gaps, so midnight always exists
+    now.with_time(NaiveTime::MIN)
+        .single()
+        .unwrap_or(now)
+        .with_timezone(&Utc)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::members::repository::memory::MemberRepositoryForMemory;
+    use crate::todos::repository::memory::TodoRepositoryForMemory;
+
+    fn service() -> TodoService<TodoRepositoryForMemory, MemberRepositoryForMemory> {
+        TodoService::new(
+            TodoRepositoryForMemory::new(),
+            MemberRepositoryForMemory::new(),
+        )
+    }
+
+    #[tokio::test]
+    async fn errors_carry_their_cause() {
+        let service = service();
+
+        let res = service.find(1).await;
+        assert_eq!(res, Err(TodoError::NotFound(1)));
+
+        let res = service
+            .update(
+                1,
+                UpdateTodo {
+                    text: Some("text".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert_eq!(res, Err(TodoError::NotFound(1)));
+
+        let res = service.delete(1, None).await;
+        assert_eq!(res, Err(TodoError::NotFound(1)));
+
+        let res = service.create(CreateTodo::new("".to_string())).await;
+        assert!(matches!(res, Err(TodoError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn stale_versions_conflict() {
+        let service = service();
+        let todo = service
+            .create(CreateTodo::new("text".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(todo.version, 1);
+        let edit = |text: &str, version| UpdateTodo {
+            text: Some(text.to_string()),
+            version: Some(version),
+            ..Default::default()
+        };
+
+        let todo = service.update(todo.id, edit("first tab", 1)).await.unwrap();
+        assert_eq!(todo.version, 2);
+        let res = service.update(todo.id, edit("second tab", 1)).await;
+        assert!(matches!(res, Err(TodoError::Conflict(_))));
+        let res = service
+            .update_all_future(todo.id, edit("second tab", 1))
+            .await;
+        assert!(matches!(res, Err(TodoError::Conflict(_))));
+        assert_eq!(service.find(todo.id).await.unwrap().text, "first tab");
+
+        // moving a todo changes it as well
+        let todo = service.reorder(todo.id, None).await.unwrap();
+        assert_eq!(todo.version, 3);
+        let res = service.delete(todo.id, Some(2)).await;
+        assert!(matches!(res, Err(TodoError::Conflict(_))));
+        service.delete(todo.id, Some(3)).await.unwrap();
+        assert_eq!(
+            service.delete(todo.id, Some(3)).await,
+            Err(TodoError::NotFound(todo.id))
+        );
+    }
+
+    #[tokio::test]
+    async fn search_follows_edits() {
+        let service = service();
+        for text in ["Buy milk", "buy bread and milk", "walk the dog"] {
+            service
+                .create(CreateTodo::new(text.to_string()))
+                .await
+                .unwrap();
+        }
+        let ids = |hits: Vec<TodoSearchHit>| Vec::from_iter(hits.iter().map(|h| h.todo.id));
+
+        let hits = service.search("milk BUY", None).await.unwrap();
+        assert_eq!(ids(hits.clone()), vec![1, 2]);
+        assert_eq!(hits[0].snippet, "<mark>Buy</mark> <mark>milk</mark>");
+        assert!(hits[0].rank > hits[1].rank);
+        assert_eq!(ids(service.search("milk", Some(1)).await.unwrap()), vec![1]);
+
+        service
+            .update(
+                3,
+                UpdateTodo {
+                    text: Some("walk the dog, buy milk".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        service.delete(1, None).await.unwrap();
+        assert_eq!(
+            ids(service.search("milk buy", None).await.unwrap()),
+            vec![2, 3]
+        );
+
+        assert!(matches!(
+            service.search(" ?! ", None).await,
+            Err(TodoError::Validation(_))
+        ));
+        assert!(matches!(
+            service.search("milk", Some(0)).await,
+            Err(TodoError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn due_queries_use_the_callers_calendar() {
+        let service = service();
+        // wednesday 2024-05-15 10:00 in UTC+09:00
+        let now = DateTime::parse_from_rfc3339("2024-05-15T10:00:00+09:00").unwrap();
+        let due = |rfc3339: &str| CreateTodo {
+            due_at: Some(DateTime::parse_from_rfc3339(rfc3339).unwrap().to_utc()),
+            ..CreateTodo::new(rfc3339.to_string())
+        };
+
+        let yesterday = service
+            .create(due("2024-05-14T23:00:00+09:00"))
+            .await
+            .unwrap();
+        let today = service
+            .create(due("2024-05-15T00:00:00+09:00"))
+            .await
+            .unwrap();
+        let later_today = service
+            .create(due("2024-05-15T23:59:00+09:00"))
+            .await
+            .unwrap();
+        let sunday = service
+            .create(due("2024-05-19T12:00:00+09:00"))
+            .await
+            .unwrap();
+        let next_week = service
+            .create(due("2024-05-20T00:00:00+09:00"))
+            .await
+            .unwrap();
+        let done = service
+            .create(due("2024-05-13T09:00:00+09:00"))
+            .await
+            .unwrap();
+        service
+            .update(
+                done.id,
+                UpdateTodo {
+                    completed: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        service
+            .create(CreateTodo::new("no due".to_string()))
+            .await
+            .unwrap();
+
+        let ids = |todos: Vec<Todo>| todos.into_iter().map(|t| t.id).collect::<Vec<_>>();
+
+        let overdue = service.find_overdue(now.to_utc()).await.unwrap();
+        assert_eq!(ids(overdue), vec![yesterday.id, today.id]);
+
+        let due_today = service.find_due_today(now).await.unwrap();
+        assert_eq!(ids(due_today), vec![today.id, later_today.id]);
+
+        let this_week = ids(service.find_due_this_week(now).await.unwrap());
+        assert_eq!(
+            this_week,
+            vec![yesterday.id, today.id, later_today.id, sunday.id]
+        );
+        assert!(!this_week.contains(&next_week.id));
+
+        // monday at midnight starts the next week
+        let monday = DateTime::parse_from_rfc3339("2024-05-20T00:00:00+09:00").unwrap();
+        let following_week = ids(service.find_due_this_week(monday).await.unwrap());
+        assert_eq!(following_week, vec![next_week.id]);
+    }
+
+    #[tokio::test]
+    async fn recurring_scenario() {
+        let service = service();
+        let at = |rfc3339: &str| DateTime::parse_from_rfc3339(rfc3339).unwrap().to_utc();
+        let complete = UpdateTodo {
+            completed: Some(true),
+            ..Default::default()
+        };
+
+        let res = service
+            .create(CreateTodo {
+                rrule: Some("FREQ=WEEKLY".to_string()),
+                ..CreateTodo::new("no due date".to_string())
+            })
+            .await;
+        assert!(matches!(res, Err(TodoError::Validation(_))));
+        let res = service
+            .create(CreateTodo {
+                due_at: Some(at("2024-05-15T09:00:00Z")),
+                rrule: Some("FREQ=SOMETIMES".to_string()),
+                ..CreateTodo::new("bad rule".to_string())
+            })
+            .await;
+        assert!(matches!(res, Err(TodoError::Validation(_))));
+
+        let trash = service
+            .create(CreateTodo {
+                starts_at: Some(at("2024-05-15T08:00:00Z")),
+                due_at: Some(at("2024-05-15T09:00:00Z")),
+                rrule: Some("FREQ=WEEKLY;COUNT=3".to_string()),
+                ..CreateTodo::new("trash".to_string())
+            })
+            .await
+            .unwrap();
+        let series_id = trash.series_id.unwrap();
+
+        // completing creates the next occurrence, completing again doesn't create another one
+        service.update(trash.id, complete.clone()).await.unwrap();
+        service.update(trash.id, complete.clone()).await.unwrap();
+        let series = service.find_series(series_id).await.unwrap();
+        assert_eq!(series.occurrences.len(), 2);
+        assert_eq!(
+            service.find_series(42).await,
+            Err(TodoError::SeriesNotFound(42))
+        );
+        let second = series.occurrences[1].clone();
+        assert_eq!(second.due_at, Some(at("2024-05-22T09:00:00Z")));
+        assert_eq!(second.starts_at, Some(at("2024-05-22T08:00:00Z")));
+        assert_eq!(second.text, "trash");
+
+        // "this one" doesn't touch the series, the rule can't be changed for one occurrence
+        let renamed = UpdateTodo {
+            text: Some("trash, recycling too".to_string()),
+            ..Default::default()
+        };
+        service.update(second.id, renamed).await.unwrap();
+        let res = service
+            .update(
+                second.id,
+                UpdateTodo {
+                    rrule: Some(Some("FREQ=DAILY".to_string())),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(res, Err(TodoError::Validation(_))));
+        service.update(second.id, complete.clone()).await.unwrap();
+        let third = service.find_series(series_id).await.unwrap().occurrences[2].clone();
+        assert_eq!(third.text, "trash");
+
+        // "all future" changes the series, COUNT=3 ends it after the third occurrence
+        let renamed = UpdateTodo {
+            text: Some("bins".to_string()),
+            ..Default::default()
+        };
+        let third = service.update_all_future(third.id, renamed).await.unwrap();
+        assert_eq!(third.text, "bins");
+        assert_eq!(
+            service.find_series(series_id).await.unwrap().series.text,
+            "bins"
+        );
+        service.update(third.id, complete.clone()).await.unwrap();
+        assert_eq!(
+            service
+                .find_series(series_id)
+                .await
+                .unwrap()
+                .occurrences
+                .len(),
+            3
+        );
+
+        // a plain todo becomes recurring through "all future"
+        let water = service
+            .create(CreateTodo {
+                due_at: Some(at("2024-05-15T09:00:00Z")),
+                ..CreateTodo::new("water plants".to_string())
+            })
+            .await
+            .unwrap();
+        let water = service
+            .update_all_future(
+                water.id,
+                UpdateTodo {
+                    rrule: Some(Some("FREQ=DAILY;INTERVAL=3".to_string())),
+                    ..Default::default()
+                },
+            )
             .await
-            .map(|_| "todo was not found")
-            .unwrap_or("error"))
+            .unwrap();
+        assert!(water.series_id.is_some());
+        service.update(water.id, complete.clone()).await.unwrap();
+        let series = service.find_series(water.series_id.unwrap()).await.unwrap();
+        assert_eq!(
+            series.occurrences[1].due_at,
+            Some(at("2024-05-18T09:00:00Z"))
+        );
+    }
+
+    #[tokio::test]
+    async fn subtasks_scenario() {
+        let service = service().auto_complete_parents(true);
+        let subtask = |parent_id: i32, text: &str| CreateTodo {
+            parent_id: Some(parent_id),
+            ..CreateTodo::new(text.to_string())
+        };
+        let complete = UpdateTodo {
+            completed: Some(true),
+            ..Default::default()
+        };
+
+        let trip = service
+            .create(CreateTodo::new("trip".to_string()))
+            .await
+            .unwrap();
+        let pack = service.create(subtask(trip.id, "pack")).await.unwrap();
+        let socks = service.create(subtask(pack.id, "socks")).await.unwrap();
+        let book = service.create(subtask(trip.id, "book")).await.unwrap();
+        assert!(matches!(
+            service.create(subtask(socks.id, "too deep")).await,
+            Err(TodoError::Validation(_))
+        ));
+        assert!(matches!(
+            service.create(subtask(42, "orphan")).await,
+            Err(TodoError::Validation(_))
+        ));
+
+        let tree = service.find_tree(trip.id).await.unwrap();
+        assert_eq!(tree.height(), 3);
+        assert_eq!(
+            tree.children
+                .iter()
+                .map(|child| child.todo.id)
+                .collect::<Vec<_>>(),
+            vec![pack.id, book.id]
+        );
+        assert_eq!(tree.children[0].children[0].todo, socks);
+
+        // moving under a subtask would make a cycle, moving "pack" under "book" is too deep
+        let under = |parent_id: i32| UpdateTodo {
+            parent_id: Some(Some(parent_id)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            service.update(trip.id, under(socks.id)).await,
+            Err(TodoError::Validation(_))
+        ));
+        assert!(matches!(
+            service.update(pack.id, under(book.id)).await,
+            Err(TodoError::Validation(_))
+        ));
+        service.update(book.id, under(pack.id)).await.unwrap();
+
+        // completing the last open subtask completes every ancestor
+        service.update(socks.id, complete.clone()).await.unwrap();
+        assert!(!service.find(pack.id).await.unwrap().completed);
+        service.update(book.id, complete.clone()).await.unwrap();
+        assert!(service.find(pack.id).await.unwrap().completed);
+        assert!(service.find(trip.id).await.unwrap().completed);
+
+        // subtasks go along with their parent
+        service.delete(trip.id, None).await.unwrap();
+        assert_eq!(
+            service.find(socks.id).await,
+            Err(TodoError::NotFound(socks.id))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_enforce_roles() {
+        const LIST: i32 = 2;
+        let member_repository = MemberRepositoryForMemory::new();
+        let service = TodoService::new(TodoRepositoryForMemory::new(), member_repository.clone());
+        let bob = service.owned_by(2);
+        let in_list = |text: &str| CreateTodo {
+            list_id: Some(LIST),
+            ..CreateTodo::new(text.to_string())
+        };
+        let forbidden = |res: Result<Todo, TodoError>| matches!(res, Err(TodoError::Forbidden(_)));
+        member_repository.add(LIST, 1, Role::Owner).await.unwrap();
+        member_repository.add(LIST, 2, Role::Editor).await.unwrap();
+
+        // editors create and change todos
+        assert!(forbidden(
+            service.owned_by(3).create(in_list("carol")).await
+        ));
+        let todo = bob.create(in_list("bob")).await.unwrap();
+        let complete = UpdateTodo {
+            completed: Some(true),
+            ..Default::default()
+        };
+        bob.update(todo.id, complete.clone()).await.unwrap();
+
+        // viewers only read them
+        member_repository
+            .update_role(LIST, 2, Role::Viewer)
+            .await
+            .unwrap();
+        assert_eq!(bob.find(todo.id).await.map(|t| t.id), Ok(todo.id));
+        assert!(forbidden(bob.create(in_list("viewer")).await));
+        let subtask = CreateTodo {
+            parent_id: Some(todo.id),
+            ..CreateTodo::new("subtask".to_string())
+        };
+        assert!(forbidden(bob.create(subtask).await));
+        assert!(forbidden(bob.update(todo.id, complete).await));
+        assert!(forbidden(bob.reorder(todo.id, None).await));
+        assert!(matches!(
+            bob.delete(todo.id, None).await,
+            Err(TodoError::Forbidden(_))
+        ));
+
+        // former members don't even read them, while their inbox stays theirs
+        member_repository.remove(LIST, 2).await.unwrap();
+        assert!(forbidden(bob.find(todo.id).await));
+        let inbox = bob.create(CreateTodo::new("inbox".to_string())).await;
+        assert!(inbox.is_ok());
     }
 }
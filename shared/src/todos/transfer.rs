@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use super::error::TodoError;
+use super::model::{CreateTodo, Todo, TodoQuery, UpdateTodo, MAX_PAGE_SIZE};
+use super::service::TodoServiceTrait;
+use crate::progress::{ProgressSink, Stage, Step};
+
+/// Todos created per step of an import, a page like those exported.
+const IMPORT_PAGE_SIZE: usize = MAX_PAGE_SIZE as usize;
+
+/// Every todo the service sees, read page by page. Cancelling it drops what was read so far.
+pub async fn export(
+    service: &impl TodoServiceTrait,
+    progress: &dyn ProgressSink,
+) -> Result<Vec<Todo>, TodoError> {
+    let mut todos = Vec::new();
+    let mut cursor = None;
+    loop {
+        check(progress)?;
+        let page = service
+            .find_all(TodoQuery {
+                cursor,
+                limit: Some(MAX_PAGE_SIZE),
+                ..Default::default()
+            })
+            .await?;
+        todos.extend(page.todos);
+        progress.report(Step::new(
+            Stage::Export,
+            todos.len(),
+            Some(page.total as usize),
+        ));
+        match page.next_cursor {
+            Some(next) => cursor = Some(next.parse().map_err(TodoError::Unexpected)?),
+            None => return Ok(todos),
+        }
+    }
+}
+
+/// Creates `todos`, as exported from another store, in the inbox page by page and returns
+/// them. Subtasks go under the todos they were exported with, and completed todos are completed
+/// again. Cancelling it keeps the todos created so far.
+pub async fn import(
+    service: &impl TodoServiceTrait,
+    mut todos: Vec<Todo>,
+    progress: &dyn ProgressSink,
+) -> Result<Vec<Todo>, TodoError> {
+    // parents first, so subtasks are created under them
+    let parents: HashMap<i32, Option<i32>> = todos.iter().map(|t| (t.id, t.parent_id)).collect();
+    todos.sort_by_cached_key(|todo| {
+        let mut depth = 0;
+        let mut parent_id = todo.parent_id;
+        while let Some(id) = parent_id.filter(|_| depth < parents.len()) {
+            depth += 1;
+            parent_id = parents.get(&id).copied().flatten();
+        }
+        depth
+    });
+
+    let mut ids = HashMap::new();
+    let mut imported = Vec::with_capacity(todos.len());
+    for page in todos.chunks(IMPORT_PAGE_SIZE) {
+        check(progress)?;
+        for todo in page {
+            let mut created = service
+                .create(CreateTodo {
+                    text: todo.text.clone(),
+                    list_id: None,
+                    parent_id: todo.parent_id.and_then(|id| ids.get(&id).copied()),
+                    starts_at: todo.starts_at,
+                    due_at: todo.due_at,
+                    priority: todo.priority,
+                    // completing an occurrence schedules the next one, which is exported too
+                    rrule: todo.rrule.clone().filter(|_| !todo.completed),
+                })
+                .await?;
+            if todo.completed {
+                let patch = UpdateTodo {
+                    completed: Some(true),
+                    ..Default::default()
+                };
+                created = service.update(created.id, patch).await?;
+            }
+            ids.insert(todo.id, created.id);
+            imported.push(created);
+        }
+        progress.report(Step::new(Stage::Import, imported.len(), Some(todos.len())));
+    }
+    Ok(imported)
+}
+
+fn check(progress: &dyn ProgressSink) -> Result<(), TodoError> {
+    match progress.is_cancelled() {
+        true => Err(TodoError::Cancelled),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::members::repository::memory::MemberRepositoryForMemory;
+    use crate::progress::NoProgress;
+    use crate::todos::repository::memory::TodoRepositoryForMemory;
+    use crate::todos::service::TodoService;
+    use std::sync::Mutex;
+
+    fn service() -> TodoService<TodoRepositoryForMemory, MemberRepositoryForMemory> {
+        TodoService::new(
+            TodoRepositoryForMemory::new(),
+            MemberRepositoryForMemory::new(),
+        )
+    }
+
+    /// Cancels once `pages` steps were reported.
+    struct CancelAfter {
+        pages: usize,
+        steps: Mutex<Vec<Step>>,
+    }
+
+    impl ProgressSink for CancelAfter {
+        fn report(&self, step: Step) {
+            self.steps.lock().unwrap().push(step);
+        }
+
+        fn is_cancelled(&self) -> bool {
+            self.steps.lock().unwrap().len() >= self.pages
+        }
+    }
+
+    #[tokio::test]
+    async fn should_export_and_import_todos() {
+        let from = service();
+        let parent = from
+            .create(CreateTodo::new("parent".to_string()))
+            .await
+            .unwrap();
+        let child = from
+            .create(CreateTodo {
+                parent_id: Some(parent.id),
+                ..CreateTodo::new("child".to_string())
+            })
+            .await
+            .unwrap();
+        let patch = UpdateTodo {
+            completed: Some(true),
+            ..Default::default()
+        };
+        from.update(child.id, patch).await.unwrap();
+
+        let exported = export(&from, &NoProgress).await.unwrap();
+        assert_eq!(exported.len(), 2);
+        let to = service();
+        // subtasks listed before their parent still go under it
+        let imported = import(&to, exported.into_iter().rev().collect(), &NoProgress)
+            .await
+            .unwrap();
+        assert_eq!(
+            Vec::from_iter(imported.iter().map(|t| (&t.text[..], t.completed))),
+            vec![("parent", false), ("child", true)]
+        );
+        assert_eq!(imported[1].parent_id, Some(imported[0].id));
+    }
+
+    #[tokio::test]
+    async fn should_stop_between_pages_when_cancelled() {
+        let from = service();
+        for i in 0..IMPORT_PAGE_SIZE + 1 {
+            from.create(CreateTodo::new(format!("todo {i}")))
+                .await
+                .unwrap();
+        }
+        let progress = CancelAfter {
+            pages: 1,
+            steps: Mutex::default(),
+        };
+        assert_eq!(export(&from, &progress).await, Err(TodoError::Cancelled));
+        assert_eq!(
+            *progress.steps.lock().unwrap(),
+            vec![Step::new(
+                Stage::Export,
+                IMPORT_PAGE_SIZE,
+                Some(IMPORT_PAGE_SIZE + 1)
+            )]
+        );
+
+        let exported = export(&from, &NoProgress).await.unwrap();
+        let to = service();
+        let progress = CancelAfter {
+            pages: 1,
+            steps: Mutex::default(),
+        };
+        assert_eq!(
+            import(&to, exported, &progress).await,
+            Err(TodoError::Cancelled)
+        );
+        // the first page is kept
+        let page = to.find_all(TodoQuery::default()).await.unwrap();
+        assert_eq!(page.total, IMPORT_PAGE_SIZE as i64);
+    }
+}
@@ -0,0 +1,80 @@
+use axum::http::StatusCode;
+use thiserror::Error;
+use validator::ValidationErrors;
+
+use crate::todos::repository::RepositoryError;
+
+/// Error returned by `ApiTokenServiceTrait`, serialized like `TodoError`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ApiTokenError {
+    #[error("Api token not found, id is {0}")]
+    NotFound(i32),
+    /// The secret is unknown or its token was revoked, which isn't told apart.
+    #[error("Invalid api token")]
+    InvalidToken,
+    #[error("Forbidden: [{0}]")]
+    Forbidden(String),
+    #[error("Validation error: [{0}]")]
+    Validation(String),
+    #[error("Conflict: [{0}]")]
+    Conflict(String),
+    #[error("Storage error: [{0}]")]
+    Storage(String),
+    #[error("Unexpected Error: [{0}]")]
+    Unexpected(String),
+}
+
+impl ApiTokenError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ApiTokenError::NotFound(_) => "NotFound",
+            ApiTokenError::InvalidToken => "InvalidToken",
+            ApiTokenError::Forbidden(_) => "Forbidden",
+            ApiTokenError::Validation(_) => "Validation",
+            ApiTokenError::Conflict(_) => "Conflict",
+            ApiTokenError::Storage(_) => "Storage",
+            ApiTokenError::Unexpected(_) => "Unexpected",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ApiTokenError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiTokenError::InvalidToken => StatusCode::UNAUTHORIZED,
+            ApiTokenError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiTokenError::Validation(_) => StatusCode::BAD_REQUEST,
+            ApiTokenError::Conflict(_) => StatusCode::CONFLICT,
+            ApiTokenError::Storage(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiTokenError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<RepositoryError> for ApiTokenError {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::NotFound(id) => ApiTokenError::NotFound(id),
+            RepositoryError::Invalid(message) => ApiTokenError::Validation(message),
+            RepositoryError::Conflict(message) => ApiTokenError::Conflict(message),
+            RepositoryError::Storage(message) => ApiTokenError::Storage(message),
+            RepositoryError::Unexpected(message) => ApiTokenError::Unexpected(message),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiTokenError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<RepositoryError>() {
+            Ok(e) => e.into(),
+            Err(e) => ApiTokenError::Unexpected(e.to_string()),
+        }
+    }
+}
+
+impl From<ValidationErrors> for ApiTokenError {
+    fn from(e: ValidationErrors) -> Self {
+        ApiTokenError::Validation(e.to_string().replace('\n', ", "))
+    }
+}
+
+crate::impl_error_response!(ApiTokenError);
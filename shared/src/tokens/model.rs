@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// What a request authenticated with an API token may do.
+#[derive(
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    sqlx::Type,
+    ToSchema,
+)]
+#[repr(i16)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    /// Only requests that change nothing, like `GET`.
+    #[default]
+    Read = 0,
+    ReadWrite = 1,
+}
+
+impl TokenScope {
+    /// Whether a request with `method` is allowed.
+    pub fn allows(&self, method: &axum::http::Method) -> bool {
+        match self {
+            TokenScope::Read => method.is_safe(),
+            TokenScope::ReadWrite => true,
+        }
+    }
+}
+
+/// A personal API token. Its secret is only known when it's created, then only its hash is kept.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct ApiToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub scope: TokenScope,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// Set once revoked, the token isn't accepted anymore.
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Validate, ToSchema)]
+pub struct CreateApiToken {
+    /// What the token is for, like the script using it.
+    #[validate(length(min = 1, max = 50, message = "Can not be empty and over name length"))]
+    pub name: String,
+    #[serde(default)]
+    pub scope: TokenScope,
+}
+
+/// A token as created, along with the secret to send as `Authorization: Bearer <secret>`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct NewApiToken {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    /// Shown this once, store it somewhere safe.
+    pub secret: String,
+}
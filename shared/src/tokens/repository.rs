@@ -0,0 +1,399 @@
+use axum::async_trait;
+use sqlx::PgPool;
+
+use super::model::{ApiToken, CreateApiToken};
+use crate::todos::repository::RepositoryError;
+
+#[async_trait]
+pub trait ApiTokenRepositoryTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    async fn create(
+        &self,
+        user_id: i32,
+        payload: CreateApiToken,
+        token_hash: &str,
+    ) -> anyhow::Result<ApiToken>;
+    /// Every token of the user, revoked ones included, oldest first.
+    async fn all(&self, user_id: i32) -> anyhow::Result<Vec<ApiToken>>;
+    /// Fails with `NotFound` when the token isn't the user's. Revoking again keeps the first time.
+    async fn revoke(&self, user_id: i32, id: i32) -> anyhow::Result<ApiToken>;
+    /// The unrevoked token with `token_hash`, marked as used just now.
+    async fn touch(&self, token_hash: &str) -> anyhow::Result<Option<ApiToken>>;
+}
+
+const COLUMNS: &str = "id, user_id, name, scope, created_at, last_used_at, revoked_at";
+
+#[derive(Debug, Clone)]
+pub struct ApiTokenRepositoryForDb {
+    pool: PgPool,
+}
+
+impl ApiTokenRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        ApiTokenRepositoryForDb { pool }
+    }
+}
+
+#[async_trait]
+impl ApiTokenRepositoryTrait for ApiTokenRepositoryForDb {
+    async fn create(
+        &self,
+        user_id: i32,
+        payload: CreateApiToken,
+        token_hash: &str,
+    ) -> anyhow::Result<ApiToken> {
+        let token = sqlx::query_as::<_, ApiToken>(&format!(
+            r#"
+            insert into api_tokens (user_id, name, scope, token_hash)
+            values ($1, $2, $3, $4)
+            returning {COLUMNS}
+            "#
+        ))
+        .bind(user_id)
+        .bind(payload.name)
+        .bind(payload.scope)
+        .bind(token_hash)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(token)
+    }
+
+    async fn all(&self, user_id: i32) -> anyhow::Result<Vec<ApiToken>> {
+        let tokens = sqlx::query_as::<_, ApiToken>(&format!(
+            r#"
+            select {COLUMNS} from api_tokens where user_id=$1 order by id
+            "#
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(tokens)
+    }
+
+    async fn revoke(&self, user_id: i32, id: i32) -> anyhow::Result<ApiToken> {
+        let token = sqlx::query_as::<_, ApiToken>(&format!(
+            r#"
+            update api_tokens set revoked_at=coalesce(revoked_at, now())
+            where id=$1 and user_id=$2
+            returning {COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+
+        Ok(token)
+    }
+
+    async fn touch(&self, token_hash: &str) -> anyhow::Result<Option<ApiToken>> {
+        let token = sqlx::query_as::<_, ApiToken>(&format!(
+            r#"
+            update api_tokens set last_used_at=now()
+            where token_hash=$1 and revoked_at is null
+            returning {COLUMNS}
+            "#
+        ))
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(token)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use crate::tokens::model::TokenScope;
+    use crate::users::repository::{UserRepositoryForDb, UserRepositoryTrait};
+    use dotenv::dotenv;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
+
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
+
+        let stamp = chrono::Utc::now().timestamp_micros();
+        let user = UserRepositoryForDb::new(pool.clone())
+            .create(&format!("tokens.{stamp}@example.com"), "hash")
+            .await
+            .expect("failed to create a user");
+        let repository = ApiTokenRepositoryForDb::new(pool.clone());
+        let hash = format!("crud_scenario.{stamp}");
+
+        // create
+        let created = repository
+            .create(
+                user.id,
+                CreateApiToken {
+                    name: "backup".to_string(),
+                    scope: TokenScope::ReadWrite,
+                },
+                &hash,
+            )
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(created.scope, TokenScope::ReadWrite);
+        assert_eq!(created.last_used_at, None);
+
+        // touch
+        let touched = repository
+            .touch(&hash)
+            .await
+            .expect("[touch] returned Err")
+            .expect("[touch] found no token");
+        assert!(touched.last_used_at.is_some());
+        assert_eq!(repository.touch("unknown").await.unwrap(), None);
+
+        // all
+        let tokens = repository.all(user.id).await.expect("[all] returned Err");
+        assert_eq!(tokens, vec![touched]);
+
+        // revoke
+        let res = repository.revoke(user.id + 1, created.id).await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::NotFound(_))
+        ));
+        let revoked = repository
+            .revoke(user.id, created.id)
+            .await
+            .expect("[revoke] returned Err");
+        assert!(revoked.revoked_at.is_some());
+        assert_eq!(repository.touch(&hash).await.unwrap(), None);
+
+        sqlx::query("delete from users where id=$1")
+            .bind(user.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
+
+#[cfg(any(test, feature = "memory"))]
+pub mod memory {
+    use super::*;
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, RwLock},
+    };
+
+    #[derive(Debug, Clone, Default)]
+    pub struct ApiTokenRepositoryForMemory {
+        store: Arc<RwLock<BTreeMap<i32, (ApiToken, String)>>>,
+    }
+
+    impl ApiTokenRepositoryForMemory {
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+
+    #[async_trait]
+    impl ApiTokenRepositoryTrait for ApiTokenRepositoryForMemory {
+        async fn create(
+            &self,
+            user_id: i32,
+            payload: CreateApiToken,
+            token_hash: &str,
+        ) -> anyhow::Result<ApiToken> {
+            let mut store = self.store.write().unwrap();
+            let id = store.keys().max().copied().unwrap_or(0) + 1;
+            let token = ApiToken {
+                id,
+                user_id,
+                name: payload.name,
+                scope: payload.scope,
+                created_at: chrono::Utc::now(),
+                last_used_at: None,
+                revoked_at: None,
+            };
+            store.insert(id, (token.clone(), token_hash.to_string()));
+            Ok(token)
+        }
+
+        async fn all(&self, user_id: i32) -> anyhow::Result<Vec<ApiToken>> {
+            let store = self.store.read().unwrap();
+            Ok(store
+                .values()
+                .map(|(token, _)| token)
+                .filter(|token| token.user_id == user_id)
+                .cloned()
+                .collect())
+        }
+
+        async fn revoke(&self, user_id: i32, id: i32) -> anyhow::Result<ApiToken> {
+            let mut store = self.store.write().unwrap();
+            let (token, _) = store
+                .get_mut(&id)
+                .filter(|(token, _)| token.user_id == user_id)
+                .ok_or(RepositoryError::NotFound(id))?;
+            token.revoked_at.get_or_insert_with(chrono::Utc::now);
+            Ok(token.clone())
+        }
+
+        async fn touch(&self, token_hash: &str) -> anyhow::Result<Option<ApiToken>> {
+            let mut store = self.store.write().unwrap();
+            Ok(store
+                .values_mut()
+                .find(|(token, hash)| hash == token_hash && token.revoked_at.is_none())
+                .map(|(token, _)| {
+                    token.last_used_at = Some(chrono::Utc::now());
+                    token.clone()
+                }))
+        }
+    }
+}
+
+pub mod sled {
+    use super::*;
+    use crate::todos::repository::sled::{decode, encode, generate_id, key, store_db};
+    use ::sled::{Db, Tree};
+    use std::sync::{Arc, Mutex};
+
+    /// A token along with the hash of its secret, as kept in the tree.
+    type Entry = (ApiToken, String);
+
+    /// Tokens as JSON in a tree of the sled database of the todos, for the desktop app.
+    #[derive(Debug, Clone)]
+    pub struct ApiTokenRepositoryForSled {
+        db: Db,
+        tree: Tree,
+        /// Held by every write, which reads the token first.
+        write: Arc<Mutex<()>>,
+    }
+
+    impl ApiTokenRepositoryForSled {
+        pub fn open(db: &Db) -> Result<Self, RepositoryError> {
+            Ok(Self {
+                db: db.clone(),
+                tree: db.open_tree("api_tokens")?,
+                write: Arc::default(),
+            })
+        }
+
+        /// Tokens kept in the database of `crate::store`.
+        pub fn from_store() -> Result<Self, RepositoryError> {
+            Self::open(&store_db()?)
+        }
+
+        /// Every token in id order, which is the order they were created in.
+        fn load(&self) -> Result<Vec<Entry>, RepositoryError> {
+            self.tree
+                .iter()
+                .values()
+                .map(|bytes| decode(&bytes?))
+                .collect()
+        }
+
+        fn save(&self, (token, hash): &Entry) -> Result<(), RepositoryError> {
+            self.tree.insert(key(token.id), encode(&(token, hash))?)?;
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl ApiTokenRepositoryTrait for ApiTokenRepositoryForSled {
+        async fn create(
+            &self,
+            user_id: i32,
+            payload: CreateApiToken,
+            token_hash: &str,
+        ) -> anyhow::Result<ApiToken> {
+            let token = ApiToken {
+                id: generate_id(&self.db, &self.tree)?,
+                user_id,
+                name: payload.name,
+                scope: payload.scope,
+                created_at: chrono::Utc::now(),
+                last_used_at: None,
+                revoked_at: None,
+            };
+            self.save(&(token.clone(), token_hash.to_string()))?;
+            Ok(token)
+        }
+
+        async fn all(&self, user_id: i32) -> anyhow::Result<Vec<ApiToken>> {
+            Ok(self
+                .load()?
+                .into_iter()
+                .map(|(token, _)| token)
+                .filter(|token| token.user_id == user_id)
+                .collect())
+        }
+
+        async fn revoke(&self, user_id: i32, id: i32) -> anyhow::Result<ApiToken> {
+            let _write = self.write.lock().unwrap();
+            let (mut token, hash) = match self.tree.get(key(id)).map_err(RepositoryError::from)? {
+                Some(bytes) => decode::<Entry>(&bytes)?,
+                None => return Err(RepositoryError::NotFound(id).into()),
+            };
+            if token.user_id != user_id {
+                return Err(RepositoryError::NotFound(id).into());
+            }
+            token.revoked_at.get_or_insert_with(chrono::Utc::now);
+            self.save(&(token.clone(), hash))?;
+            Ok(token)
+        }
+
+        async fn touch(&self, token_hash: &str) -> anyhow::Result<Option<ApiToken>> {
+            let _write = self.write.lock().unwrap();
+            let entry = self
+                .load()?
+                .into_iter()
+                .find(|(token, hash)| hash == token_hash && token.revoked_at.is_none());
+            let Some((mut token, hash)) = entry else {
+                return Ok(None);
+            };
+            token.last_used_at = Some(chrono::Utc::now());
+            self.save(&(token.clone(), hash))?;
+            Ok(Some(token))
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use crate::tokens::model::TokenScope;
+
+        #[tokio::test]
+        async fn sled_scenario() {
+            let db = ::sled::Config::new().temporary(true).open().unwrap();
+            let repository = ApiTokenRepositoryForSled::open(&db).unwrap();
+            let token = repository
+                .create(
+                    1,
+                    CreateApiToken {
+                        name: "cli".to_string(),
+                        scope: TokenScope::default(),
+                    },
+                    "hash",
+                )
+                .await
+                .unwrap();
+
+            let reopened = ApiTokenRepositoryForSled::open(&db).unwrap();
+            let touched = reopened.touch("hash").await.unwrap().unwrap();
+            assert!(touched.last_used_at.is_some());
+            assert!(reopened.revoke(2, token.id).await.is_err());
+            reopened.revoke(1, token.id).await.unwrap();
+            assert_eq!(repository.touch("hash").await.unwrap(), None);
+            assert_eq!(repository.all(1).await.unwrap().len(), 1);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use axum::async_trait;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use validator::Validate;
+
+use super::error::ApiTokenError;
+use super::model::{ApiToken, CreateApiToken, NewApiToken};
+use super::repository::ApiTokenRepositoryTrait;
+
+/// Starts every secret, telling them apart from the session JWTs sent the same way.
+pub const SECRET_PREFIX: &str = "tdo_";
+
+/// A new random secret starting with `prefix`.
+pub(crate) fn generate_secret(prefix: &str) -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("{prefix}{}", hex::encode(bytes))
+}
+
+/// Secrets are random enough that a fast hash is as good as a password hash, and it can be looked
+/// up on every request.
+pub fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiTokenService<KR>
+where
+    KR: ApiTokenRepositoryTrait,
+{
+    token_repository: KR,
+}
+
+#[async_trait]
+pub trait ApiTokenServiceTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    async fn create(
+        &self,
+        user_id: i32,
+        payload: CreateApiToken,
+    ) -> Result<NewApiToken, ApiTokenError>;
+    async fn all(&self, user_id: i32) -> Result<Vec<ApiToken>, ApiTokenError>;
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<ApiToken, ApiTokenError>;
+    /// The token `secret` was issued for, unless it was revoked.
+    async fn authenticate(&self, secret: &str) -> Result<ApiToken, ApiTokenError>;
+}
+
+impl<KR> ApiTokenService<KR>
+where
+    KR: ApiTokenRepositoryTrait,
+{
+    pub fn new(token_repository: KR) -> Self {
+        Self { token_repository }
+    }
+}
+
+#[async_trait]
+impl<KR> ApiTokenServiceTrait for ApiTokenService<KR>
+where
+    KR: ApiTokenRepositoryTrait,
+{
+    async fn create(
+        &self,
+        user_id: i32,
+        payload: CreateApiToken,
+    ) -> Result<NewApiToken, ApiTokenError> {
+        payload.validate()?;
+        let secret = generate_secret(SECRET_PREFIX);
+        let token = self
+            .token_repository
+            .create(user_id, payload, &hash_secret(&secret))
+            .await?;
+        Ok(NewApiToken { token, secret })
+    }
+
+    async fn all(&self, user_id: i32) -> Result<Vec<ApiToken>, ApiTokenError> {
+        let tokens = self.token_repository.all(user_id).await?;
+        Ok(tokens)
+    }
+
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<ApiToken, ApiTokenError> {
+        let token = self.token_repository.revoke(user_id, id).await?;
+        Ok(token)
+    }
+
+    async fn authenticate(&self, secret: &str) -> Result<ApiToken, ApiTokenError> {
+        if !secret.starts_with(SECRET_PREFIX) {
+            return Err(ApiTokenError::InvalidToken);
+        }
+        self.token_repository
+            .touch(&hash_secret(secret))
+            .await?
+            .ok_or(ApiTokenError::InvalidToken)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tokens::model::TokenScope;
+    use crate::tokens::repository::memory::ApiTokenRepositoryForMemory;
+
+    fn payload(name: &str) -> CreateApiToken {
+        CreateApiToken {
+            name: name.to_string(),
+            scope: TokenScope::Read,
+        }
+    }
+
+    #[tokio::test]
+    async fn should_authenticate_until_revoked() {
+        let service = ApiTokenService::new(ApiTokenRepositoryForMemory::new());
+
+        let created = service.create(1, payload("backup")).await.unwrap();
+        assert!(created.secret.starts_with(SECRET_PREFIX));
+        assert_ne!(created.secret, generate_secret(SECRET_PREFIX));
+
+        let token = service.authenticate(&created.secret).await.unwrap();
+        assert_eq!(token.id, created.token.id);
+        assert!(token.last_used_at.is_some());
+        assert_eq!(service.all(1).await.unwrap(), vec![token]);
+        assert_eq!(service.all(2).await.unwrap(), vec![]);
+
+        assert_eq!(
+            service.revoke(2, created.token.id).await,
+            Err(ApiTokenError::NotFound(created.token.id))
+        );
+        service.revoke(1, created.token.id).await.unwrap();
+        assert_eq!(
+            service.authenticate(&created.secret).await,
+            Err(ApiTokenError::InvalidToken)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_reject_unknown_secrets_and_invalid_names() {
+        let service = ApiTokenService::new(ApiTokenRepositoryForMemory::new());
+
+        for secret in ["", "tdo_unknown", "eyJhbGciOiJIUzI1NiJ9"] {
+            assert_eq!(
+                service.authenticate(secret).await,
+                Err(ApiTokenError::InvalidToken)
+            );
+        }
+        let res = service.create(1, payload("")).await;
+        assert!(matches!(res, Err(ApiTokenError::Validation(_))), "{res:?}");
+    }
+}
@@ -0,0 +1,79 @@
+use axum::http::StatusCode;
+use thiserror::Error;
+use validator::ValidationErrors;
+
+use crate::todos::repository::RepositoryError;
+
+/// Error returned by `UserServiceTrait` and by authentication, serialized like `TodoError`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum UserError {
+    #[error("User not found, id is {0}")]
+    NotFound(i32),
+    /// Doesn't tell whether the email or the password is wrong.
+    #[error("Invalid email or password")]
+    InvalidCredentials,
+    #[error("Unauthorized: [{0}]")]
+    Unauthorized(String),
+    #[error("Validation error: [{0}]")]
+    Validation(String),
+    #[error("Conflict: [{0}]")]
+    Conflict(String),
+    #[error("Storage error: [{0}]")]
+    Storage(String),
+    #[error("Unexpected Error: [{0}]")]
+    Unexpected(String),
+}
+
+impl UserError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            UserError::NotFound(_) => "NotFound",
+            UserError::InvalidCredentials => "InvalidCredentials",
+            UserError::Unauthorized(_) => "Unauthorized",
+            UserError::Validation(_) => "Validation",
+            UserError::Conflict(_) => "Conflict",
+            UserError::Storage(_) => "Storage",
+            UserError::Unexpected(_) => "Unexpected",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            UserError::NotFound(_) => StatusCode::NOT_FOUND,
+            UserError::InvalidCredentials | UserError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            UserError::Validation(_) => StatusCode::BAD_REQUEST,
+            UserError::Conflict(_) => StatusCode::CONFLICT,
+            UserError::Storage(_) => StatusCode::SERVICE_UNAVAILABLE,
+            UserError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<RepositoryError> for UserError {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::NotFound(id) => UserError::NotFound(id),
+            RepositoryError::Invalid(message) => UserError::Validation(message),
+            RepositoryError::Conflict(message) => UserError::Conflict(message),
+            RepositoryError::Storage(message) => UserError::Storage(message),
+            RepositoryError::Unexpected(message) => UserError::Unexpected(message),
+        }
+    }
+}
+
+impl From<anyhow::Error> for UserError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<RepositoryError>() {
+            Ok(e) => e.into(),
+            Err(e) => UserError::Unexpected(e.to_string()),
+        }
+    }
+}
+
+impl From<ValidationErrors> for UserError {
+    fn from(e: ValidationErrors) -> Self {
+        UserError::Validation(e.to_string().replace('\n', ", "))
+    }
+}
+
+crate::impl_error_response!(UserError);
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::fmt;
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct User {
+    pub id: i32,
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What a user signs up and logs in with.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Validate, ToSchema)]
+pub struct Credentials {
+    #[validate(email(message = "Must be an email address"))]
+    pub email: String,
+    #[validate(length(min = 8, max = 128, message = "Must be 8 to 128 characters long"))]
+    pub password: String,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("email", &self.email)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+/// Changes the password of the authenticated user, who proves they know the current one.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Validate, ToSchema)]
+pub struct ChangePassword {
+    pub current_password: String,
+    #[validate(length(min = 8, max = 128, message = "Must be 8 to 128 characters long"))]
+    pub new_password: String,
+}
+
+impl fmt::Debug for ChangePassword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangePassword")
+            .field("current_password", &"***")
+            .field("new_password", &"***")
+            .finish()
+    }
+}
@@ -0,0 +1,490 @@
+use axum::async_trait;
+use sqlx::{FromRow, PgPool};
+
+use super::model::User;
+use crate::todos::repository::RepositoryError;
+
+#[async_trait]
+pub trait UserRepositoryTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    /// Fails with `Conflict` when another user has the same email, whatever its case.
+    async fn create(&self, email: &str, password_hash: &str) -> anyhow::Result<User>;
+    async fn find(&self, id: i32) -> anyhow::Result<User>;
+    /// The user with `email` whatever its case, along with its password hash.
+    async fn find_by_email(&self, email: &str) -> anyhow::Result<Option<(User, String)>>;
+    async fn password_hash(&self, id: i32) -> anyhow::Result<String>;
+    /// Also revokes the refresh tokens of the user, see `revoke_tokens`.
+    async fn update_password(&self, id: i32, password_hash: &str) -> anyhow::Result<()>;
+    /// Refresh tokens are only valid while they carry the current version of their user.
+    async fn token_version(&self, id: i32) -> anyhow::Result<i32>;
+    /// Bumps the token version of the user, revoking every refresh token issued so far.
+    async fn revoke_tokens(&self, id: i32) -> anyhow::Result<()>;
+}
+
+#[derive(FromRow)]
+struct UserRow {
+    #[sqlx(flatten)]
+    user: User,
+    password_hash: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserRepositoryForDb {
+    pool: PgPool,
+}
+
+impl UserRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        UserRepositoryForDb { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepositoryTrait for UserRepositoryForDb {
+    async fn create(&self, email: &str, password_hash: &str) -> anyhow::Result<User> {
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            insert into users (email, password_hash)
+            values ($1, $2)
+            returning id, email, created_at
+            "#,
+        )
+        .bind(email)
+        .bind(password_hash)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(user)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<User> {
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            select id, email, created_at from users where id=$1
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+
+        Ok(user)
+    }
+
+    async fn find_by_email(&self, email: &str) -> anyhow::Result<Option<(User, String)>> {
+        let row = sqlx::query_as::<_, UserRow>(
+            r#"
+            select * from users where lower(email)=lower($1)
+            "#,
+        )
+        .bind(email)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(row.map(|row| (row.user, row.password_hash)))
+    }
+
+    async fn password_hash(&self, id: i32) -> anyhow::Result<String> {
+        let hash = sqlx::query_scalar::<_, String>(
+            r#"
+            select password_hash from users where id=$1
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+
+        Ok(hash)
+    }
+
+    async fn update_password(&self, id: i32, password_hash: &str) -> anyhow::Result<()> {
+        let result = sqlx::query(
+            r#"
+            update users set password_hash=$2, token_version=token_version + 1
+            where id=$1
+            "#,
+        )
+        .bind(id)
+        .bind(password_hash)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+
+        Ok(())
+    }
+
+    async fn token_version(&self, id: i32) -> anyhow::Result<i32> {
+        let version = sqlx::query_scalar::<_, i32>(
+            r#"
+            select token_version from users where id=$1
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(id))?;
+
+        Ok(version)
+    }
+
+    async fn revoke_tokens(&self, id: i32) -> anyhow::Result<()> {
+        let result = sqlx::query(
+            r#"
+            update users set token_version=token_version + 1 where id=$1
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use dotenv::dotenv;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
+
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
+
+        let repository = UserRepositoryForDb::new(pool.clone());
+        let email = format!(
+            "crud_scenario.{}@example.com",
+            chrono::Utc::now().timestamp_micros()
+        );
+
+        // create
+        let created = repository
+            .create(&email, "hash")
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(created.email, email);
+
+        let res = repository.create(&email.to_uppercase(), "hash").await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<RepositoryError>(),
+            Ok(RepositoryError::Conflict(_))
+        ));
+
+        // find
+        let user = repository
+            .find(created.id)
+            .await
+            .expect("[find] returned Err");
+        assert_eq!(created, user);
+
+        // find_by_email
+        let found = repository
+            .find_by_email(&email.to_uppercase())
+            .await
+            .expect("[find_by_email] returned Err");
+        assert_eq!(found, Some((created, "hash".to_string())));
+
+        let found = repository
+            .find_by_email("nobody@example.com")
+            .await
+            .expect("[find_by_email] returned Err");
+        assert_eq!(found, None);
+
+        // update_password revokes tokens, like revoke_tokens
+        let version = repository
+            .token_version(user.id)
+            .await
+            .expect("[token_version] returned Err");
+        assert_eq!(version, 0);
+        repository
+            .revoke_tokens(user.id)
+            .await
+            .expect("[revoke_tokens] returned Err");
+        repository
+            .update_password(user.id, "new hash")
+            .await
+            .expect("[update_password] returned Err");
+        let version = repository
+            .token_version(user.id)
+            .await
+            .expect("[token_version] returned Err");
+        assert_eq!(version, 2);
+        let hash = repository
+            .password_hash(user.id)
+            .await
+            .expect("[password_hash] returned Err");
+        assert_eq!(hash, "new hash");
+
+        sqlx::query("delete from users where id=$1")
+            .bind(user.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
+
+#[cfg(any(test, feature = "memory"))]
+pub mod memory {
+    use super::*;
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, RwLock},
+    };
+
+    #[derive(Debug, Clone)]
+    struct UserEntry {
+        user: User,
+        password_hash: String,
+        token_version: i32,
+    }
+
+    #[derive(Debug, Clone, Default)]
+    pub struct UserRepositoryForMemory {
+        store: Arc<RwLock<BTreeMap<i32, UserEntry>>>,
+    }
+
+    impl UserRepositoryForMemory {
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+
+    #[async_trait]
+    impl UserRepositoryTrait for UserRepositoryForMemory {
+        async fn create(&self, email: &str, password_hash: &str) -> anyhow::Result<User> {
+            let mut store = self.store.write().unwrap();
+            if store
+                .values()
+                .any(|entry| entry.user.email.eq_ignore_ascii_case(email))
+            {
+                return Err(RepositoryError::Conflict(format!("{email} is taken")).into());
+            }
+            let id = store.keys().max().copied().unwrap_or(0) + 1;
+            let user = User {
+                id,
+                email: email.to_string(),
+                created_at: chrono::Utc::now(),
+            };
+            store.insert(
+                id,
+                UserEntry {
+                    user: user.clone(),
+                    password_hash: password_hash.to_string(),
+                    token_version: 0,
+                },
+            );
+            Ok(user)
+        }
+
+        async fn find(&self, id: i32) -> anyhow::Result<User> {
+            let store = self.store.read().unwrap();
+            let entry = store.get(&id).ok_or(RepositoryError::NotFound(id))?;
+            Ok(entry.user.clone())
+        }
+
+        async fn find_by_email(&self, email: &str) -> anyhow::Result<Option<(User, String)>> {
+            let store = self.store.read().unwrap();
+            Ok(store
+                .values()
+                .find(|entry| entry.user.email.eq_ignore_ascii_case(email))
+                .map(|entry| (entry.user.clone(), entry.password_hash.clone())))
+        }
+
+        async fn password_hash(&self, id: i32) -> anyhow::Result<String> {
+            let store = self.store.read().unwrap();
+            let entry = store.get(&id).ok_or(RepositoryError::NotFound(id))?;
+            Ok(entry.password_hash.clone())
+        }
+
+        async fn update_password(&self, id: i32, password_hash: &str) -> anyhow::Result<()> {
+            let mut store = self.store.write().unwrap();
+            let entry = store.get_mut(&id).ok_or(RepositoryError::NotFound(id))?;
+            entry.password_hash = password_hash.to_string();
+            entry.token_version += 1;
+            Ok(())
+        }
+
+        async fn token_version(&self, id: i32) -> anyhow::Result<i32> {
+            let store = self.store.read().unwrap();
+            let entry = store.get(&id).ok_or(RepositoryError::NotFound(id))?;
+            Ok(entry.token_version)
+        }
+
+        async fn revoke_tokens(&self, id: i32) -> anyhow::Result<()> {
+            let mut store = self.store.write().unwrap();
+            let entry = store.get_mut(&id).ok_or(RepositoryError::NotFound(id))?;
+            entry.token_version += 1;
+            Ok(())
+        }
+    }
+}
+
+pub mod sled {
+    use super::*;
+    use crate::todos::repository::sled::{decode, encode, generate_id, key, store_db};
+    use ::sled::{Db, Tree};
+    use serde::{Deserialize, Serialize};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct UserEntry {
+        user: User,
+        password_hash: String,
+        token_version: i32,
+    }
+
+    /// Users as JSON in a tree of the sled database of the todos, for the desktop app.
+    #[derive(Debug, Clone)]
+    pub struct UserRepositoryForSled {
+        db: Db,
+        tree: Tree,
+        /// Held by every write, which checks the emails of the other users or reads the user
+        /// first.
+        write: Arc<Mutex<()>>,
+    }
+
+    impl UserRepositoryForSled {
+        pub fn open(db: &Db) -> Result<Self, RepositoryError> {
+            Ok(Self {
+                db: db.clone(),
+                tree: db.open_tree("users")?,
+                write: Arc::default(),
+            })
+        }
+
+        /// Users kept in the database of `crate::store`.
+        pub fn from_store() -> Result<Self, RepositoryError> {
+            Self::open(&store_db()?)
+        }
+
+        fn get(&self, id: i32) -> Result<UserEntry, RepositoryError> {
+            match self.tree.get(key(id))? {
+                Some(bytes) => decode(&bytes),
+                None => Err(RepositoryError::NotFound(id)),
+            }
+        }
+
+        fn find_entry(&self, email: &str) -> Result<Option<UserEntry>, RepositoryError> {
+            for bytes in self.tree.iter().values() {
+                let entry: UserEntry = decode(&bytes?)?;
+                if entry.user.email.eq_ignore_ascii_case(email) {
+                    return Ok(Some(entry));
+                }
+            }
+            Ok(None)
+        }
+
+        fn save(&self, entry: &UserEntry) -> Result<(), RepositoryError> {
+            self.tree.insert(key(entry.user.id), encode(entry)?)?;
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl UserRepositoryTrait for UserRepositoryForSled {
+        async fn create(&self, email: &str, password_hash: &str) -> anyhow::Result<User> {
+            let _write = self.write.lock().unwrap();
+            if self.find_entry(email)?.is_some() {
+                return Err(RepositoryError::Conflict(format!("{email} is taken")).into());
+            }
+            let user = User {
+                id: generate_id(&self.db, &self.tree)?,
+                email: email.to_string(),
+                created_at: chrono::Utc::now(),
+            };
+            self.save(&UserEntry {
+                user: user.clone(),
+                password_hash: password_hash.to_string(),
+                token_version: 0,
+            })?;
+            Ok(user)
+        }
+
+        async fn find(&self, id: i32) -> anyhow::Result<User> {
+            Ok(self.get(id)?.user)
+        }
+
+        async fn find_by_email(&self, email: &str) -> anyhow::Result<Option<(User, String)>> {
+            Ok(self
+                .find_entry(email)?
+                .map(|entry| (entry.user, entry.password_hash)))
+        }
+
+        async fn password_hash(&self, id: i32) -> anyhow::Result<String> {
+            Ok(self.get(id)?.password_hash)
+        }
+
+        async fn update_password(&self, id: i32, password_hash: &str) -> anyhow::Result<()> {
+            let _write = self.write.lock().unwrap();
+            let mut entry = self.get(id)?;
+            entry.password_hash = password_hash.to_string();
+            entry.token_version += 1;
+            self.save(&entry)?;
+            Ok(())
+        }
+
+        async fn token_version(&self, id: i32) -> anyhow::Result<i32> {
+            Ok(self.get(id)?.token_version)
+        }
+
+        async fn revoke_tokens(&self, id: i32) -> anyhow::Result<()> {
+            let _write = self.write.lock().unwrap();
+            let mut entry = self.get(id)?;
+            entry.token_version += 1;
+            self.save(&entry)?;
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[tokio::test]
+        async fn sled_scenario() {
+            let db = ::sled::Config::new().temporary(true).open().unwrap();
+            let repository = UserRepositoryForSled::open(&db).unwrap();
+            let user = repository
+                .create("alice@example.com", "hash")
+                .await
+                .unwrap();
+            assert!(repository
+                .create("Alice@Example.com", "hash")
+                .await
+                .is_err());
+            repository
+                .update_password(user.id, "new hash")
+                .await
+                .unwrap();
+
+            let reopened = UserRepositoryForSled::open(&db).unwrap();
+            assert_eq!(
+                reopened.find_by_email("ALICE@example.com").await.unwrap(),
+                Some((user.clone(), "new hash".to_string()))
+            );
+            assert_eq!(reopened.token_version(user.id).await.unwrap(), 1);
+            reopened.revoke_tokens(user.id).await.unwrap();
+            assert_eq!(repository.token_version(user.id).await.unwrap(), 2);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::async_trait;
+use once_cell::sync::Lazy;
+use rand_core::OsRng;
+use validator::Validate;
+
+use super::error::UserError;
+use super::model::{ChangePassword, Credentials, User};
+use super::repository::UserRepositoryTrait;
+
+/// Checked when nobody has the email, so a login takes as long whether the user exists or not.
+static UNKNOWN_USER_HASH: Lazy<String> =
+    Lazy::new(|| hash_password("unknown user").expect("failed to hash a constant password"));
+
+/// An argon2 PHC string holding a random salt and the parameters it was hashed with.
+pub fn hash_password(password: &str) -> Result<String, UserError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| UserError::Unexpected(e.to_string()))
+}
+
+/// Whether `password` is the one `hash` was made from. No password matches a hash that isn't a
+/// PHC string, like the one of the account claiming the rows from before users existed.
+pub fn verify_password(password: &str, hash: &str) -> Result<bool, UserError> {
+    let Ok(hash) = PasswordHash::new(hash) else {
+        return Ok(false);
+    };
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .is_ok())
+}
+
+/// Runs the hashing off the async runtime, it takes tens of milliseconds by design.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, UserError> + Send + 'static,
+) -> Result<T, UserError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| UserError::Unexpected(e.to_string()))?
+}
+
+#[derive(Debug, Clone)]
+pub struct UserService<UR>
+where
+    UR: UserRepositoryTrait,
+{
+    user_repository: UR,
+}
+
+#[async_trait]
+pub trait UserServiceTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    /// Emails are unique whatever their case.
+    async fn register(&self, payload: Credentials) -> Result<User, UserError>;
+    /// Fails with `InvalidCredentials` whether the email or the password is wrong.
+    async fn login(&self, payload: Credentials) -> Result<User, UserError>;
+    async fn find(&self, id: i32) -> Result<User, UserError>;
+    /// What the refresh tokens of the user must carry, see `UserRepositoryTrait::token_version`.
+    async fn token_version(&self, id: i32) -> Result<i32, UserError>;
+    /// Revokes every refresh token of the user.
+    async fn logout(&self, id: i32) -> Result<(), UserError>;
+    /// Fails with `InvalidCredentials` when the current password is wrong, revokes every refresh
+    /// token of the user otherwise.
+    async fn change_password(&self, id: i32, payload: ChangePassword) -> Result<(), UserError>;
+}
+
+impl<UR> UserService<UR>
+where
+    UR: UserRepositoryTrait,
+{
+    pub fn new(user_repository: UR) -> Self {
+        Self { user_repository }
+    }
+}
+
+#[async_trait]
+impl<UR> UserServiceTrait for UserService<UR>
+where
+    UR: UserRepositoryTrait,
+{
+    async fn register(&self, payload: Credentials) -> Result<User, UserError> {
+        payload.validate()?;
+        let email = payload.email.trim().to_string();
+        let password_hash = blocking(move || hash_password(&payload.password)).await?;
+        let user = self
+            .user_repository
+            .create(&email, &password_hash)
+            .await
+            .map_err(|e| match UserError::from(e) {
+                UserError::Conflict(_) => UserError::Conflict(format!("{email} is already taken")),
+                e => e,
+            })?;
+        Ok(user)
+    }
+
+    async fn login(&self, payload: Credentials) -> Result<User, UserError> {
+        let found = self
+            .user_repository
+            .find_by_email(payload.email.trim())
+            .await?;
+        let (user, hash) = match found {
+            Some((user, hash)) => (Some(user), hash),
+            None => (None, UNKNOWN_USER_HASH.clone()),
+        };
+        let verified = blocking(move || verify_password(&payload.password, &hash)).await?;
+        match user {
+            Some(user) if verified => Ok(user),
+            _ => Err(UserError::InvalidCredentials),
+        }
+    }
+
+    async fn find(&self, id: i32) -> Result<User, UserError> {
+        let user = self.user_repository.find(id).await?;
+        Ok(user)
+    }
+
+    async fn token_version(&self, id: i32) -> Result<i32, UserError> {
+        let version = self.user_repository.token_version(id).await?;
+        Ok(version)
+    }
+
+    async fn logout(&self, id: i32) -> Result<(), UserError> {
+        self.user_repository.revoke_tokens(id).await?;
+        Ok(())
+    }
+
+    async fn change_password(&self, id: i32, payload: ChangePassword) -> Result<(), UserError> {
+        payload.validate()?;
+        let hash = self.user_repository.password_hash(id).await?;
+        let ChangePassword {
+            current_password,
+            new_password,
+        } = payload;
+        let verified = blocking(move || verify_password(&current_password, &hash)).await?;
+        if !verified {
+            return Err(UserError::InvalidCredentials);
+        }
+        let hash = blocking(move || hash_password(&new_password)).await?;
+        self.user_repository.update_password(id, &hash).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::users::repository::memory::UserRepositoryForMemory;
+
+    fn credentials(email: &str, password: &str) -> Credentials {
+        Credentials {
+            email: email.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    #[test]
+    fn should_verify_hashed_passwords() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert_ne!(hash, hash_password("correct horse").unwrap());
+        assert!(verify_password("correct horse", &hash).unwrap());
+        assert!(!verify_password("battery staple", &hash).unwrap());
+        assert!(!verify_password("", "!").unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_register_and_login() {
+        let service = UserService::new(UserRepositoryForMemory::new());
+
+        let user = service
+            .register(credentials("alice@example.com", "correct horse"))
+            .await
+            .unwrap();
+        assert_eq!(service.find(user.id).await, Ok(user.clone()));
+        assert_eq!(
+            service
+                .login(credentials("Alice@Example.com", "correct horse"))
+                .await,
+            Ok(user)
+        );
+
+        for (email, password) in [
+            ("alice@example.com", "battery staple"),
+            ("bob@example.com", "correct horse"),
+        ] {
+            assert_eq!(
+                service.login(credentials(email, password)).await,
+                Err(UserError::InvalidCredentials)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn should_revoke_tokens_on_logout_and_password_change() {
+        let service = UserService::new(UserRepositoryForMemory::new());
+        let user = service
+            .register(credentials("alice@example.com", "correct horse"))
+            .await
+            .unwrap();
+        assert_eq!(service.token_version(user.id).await, Ok(0));
+
+        service.logout(user.id).await.unwrap();
+        assert_eq!(service.token_version(user.id).await, Ok(1));
+
+        let change = |current: &str, new: &str| ChangePassword {
+            current_password: current.to_string(),
+            new_password: new.to_string(),
+        };
+        assert_eq!(
+            service
+                .change_password(user.id, change("battery staple", "battery staple"))
+                .await,
+            Err(UserError::InvalidCredentials)
+        );
+        assert!(matches!(
+            service
+                .change_password(user.id, change("correct horse", "short"))
+                .await,
+            Err(UserError::Validation(_))
+        ));
+        assert_eq!(service.token_version(user.id).await, Ok(1));
+
+        service
+            .change_password(user.id, change("correct horse", "battery staple"))
+            .await
+            .unwrap();
+        assert_eq!(service.token_version(user.id).await, Ok(2));
+        assert_eq!(
+            service
+                .login(credentials("alice@example.com", "correct horse"))
+                .await,
+            Err(UserError::InvalidCredentials)
+        );
+        assert_eq!(
+            service
+                .login(credentials("alice@example.com", "battery staple"))
+                .await,
+            Ok(user)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_registrations() {
+        let service = UserService::new(UserRepositoryForMemory::new());
+        service
+            .register(credentials("alice@example.com", "correct horse"))
+            .await
+            .unwrap();
+
+        let res = service
+            .register(credentials("ALICE@example.com", "battery staple"))
+            .await;
+        assert!(matches!(res, Err(UserError::Conflict(_))));
+        for (email, password) in [("alice", "correct horse"), ("bob@example.com", "short")] {
+            let res = service.register(credentials(email, password)).await;
+            assert!(matches!(res, Err(UserError::Validation(_))), "{res:?}");
+        }
+    }
+}
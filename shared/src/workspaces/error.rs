@@ -0,0 +1,81 @@
+use axum::http::StatusCode;
+use thiserror::Error;
+use validator::ValidationErrors;
+
+use crate::todos::repository::RepositoryError;
+
+/// Error returned by `WorkspaceServiceTrait`, serialized like `TodoError`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum WorkspaceError {
+    /// Also returned for the workspaces the user isn't a member of.
+    #[error("Workspace not found, id is {0}")]
+    NotFound(i32),
+    /// The token is unknown, expired or already taken, which isn't told apart.
+    #[error("Invalid invitation")]
+    InvalidInvitation,
+    #[error("Forbidden: [{0}]")]
+    Forbidden(String),
+    #[error("Validation error: [{0}]")]
+    Validation(String),
+    #[error("Conflict: [{0}]")]
+    Conflict(String),
+    #[error("Storage error: [{0}]")]
+    Storage(String),
+    #[error("Unexpected Error: [{0}]")]
+    Unexpected(String),
+}
+
+impl WorkspaceError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WorkspaceError::NotFound(_) => "NotFound",
+            WorkspaceError::InvalidInvitation => "InvalidInvitation",
+            WorkspaceError::Forbidden(_) => "Forbidden",
+            WorkspaceError::Validation(_) => "Validation",
+            WorkspaceError::Conflict(_) => "Conflict",
+            WorkspaceError::Storage(_) => "Storage",
+            WorkspaceError::Unexpected(_) => "Unexpected",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            WorkspaceError::NotFound(_) => StatusCode::NOT_FOUND,
+            WorkspaceError::InvalidInvitation => StatusCode::BAD_REQUEST,
+            WorkspaceError::Forbidden(_) => StatusCode::FORBIDDEN,
+            WorkspaceError::Validation(_) => StatusCode::BAD_REQUEST,
+            WorkspaceError::Conflict(_) => StatusCode::CONFLICT,
+            WorkspaceError::Storage(_) => StatusCode::SERVICE_UNAVAILABLE,
+            WorkspaceError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<RepositoryError> for WorkspaceError {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::NotFound(id) => WorkspaceError::NotFound(id),
+            RepositoryError::Invalid(message) => WorkspaceError::Validation(message),
+            RepositoryError::Conflict(message) => WorkspaceError::Conflict(message),
+            RepositoryError::Storage(message) => WorkspaceError::Storage(message),
+            RepositoryError::Unexpected(message) => WorkspaceError::Unexpected(message),
+        }
+    }
+}
+
+impl From<anyhow::Error> for WorkspaceError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<RepositoryError>() {
+            Ok(e) => e.into(),
+            Err(e) => WorkspaceError::Unexpected(e.to_string()),
+        }
+    }
+}
+
+impl From<ValidationErrors> for WorkspaceError {
+    fn from(e: ValidationErrors) -> Self {
+        WorkspaceError::Validation(e.to_string().replace('\n', ", "))
+    }
+}
+
+crate::impl_error_response!(WorkspaceError);
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// A tenant, its todos, lists and labels are never seen from another workspace. Every user has a
+/// personal one, made along with them.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct Workspace {
+    pub id: i32,
+    pub name: String,
+    /// The personal workspace of the user, used when a request doesn't name one.
+    pub personal: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Validate, ToSchema)]
+pub struct CreateWorkspace {
+    #[validate(length(min = 1, max = 50, message = "Can not be empty and over name length"))]
+    pub name: String,
+}
+
+/// What a member may do with a workspace, each role being allowed what the ones before it are.
+#[derive(
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    sqlx::Type,
+    ToSchema,
+)]
+#[repr(i16)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkspaceRole {
+    /// Works in the workspace.
+    #[default]
+    Member = 0,
+    /// Invites members.
+    Admin = 1,
+    /// Invites owners too.
+    Owner = 2,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct WorkspaceMember {
+    pub workspace_id: i32,
+    pub user_id: i32,
+    pub role: WorkspaceRole,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct CreateWorkspaceInvitation {
+    /// Given to whoever accepts the invitation.
+    #[serde(default)]
+    pub role: WorkspaceRole,
+}
+
+/// Joins a workspace once accepted with its token, like `Invitation` does a list.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct WorkspaceInvitation {
+    pub id: i32,
+    pub workspace_id: i32,
+    pub role: WorkspaceRole,
+    pub created_by: i32,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_by: Option<i32>,
+    pub accepted_at: Option<DateTime<Utc>>,
+}
+
+/// A workspace invitation as created, along with the token to hand to the invited user.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct NewWorkspaceInvitation {
+    #[serde(flatten)]
+    pub invitation: WorkspaceInvitation,
+    /// Shown this once, accepting the invitation takes it.
+    pub token: String,
+}
@@ -0,0 +1,761 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use super::model::{
+    CreateWorkspace, Workspace, WorkspaceInvitation, WorkspaceMember, WorkspaceRole,
+};
+use crate::todos::repository::RepositoryError;
+
+#[async_trait]
+pub trait WorkspaceRepositoryTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    /// The user who creates a workspace is its first owner.
+    async fn create(&self, user_id: i32, payload: CreateWorkspace) -> anyhow::Result<Workspace>;
+    /// The personal workspace of the user, which they own. The database makes it along with the
+    /// user, memory stores when it's first asked for.
+    async fn personal(&self, user_id: i32) -> anyhow::Result<Workspace>;
+    /// The workspaces of the user, their personal one first, then in the order they joined them.
+    async fn all(&self, user_id: i32) -> anyhow::Result<Vec<Workspace>>;
+    /// The role of the user in the workspace, `None` when they aren't a member.
+    async fn role(&self, id: i32, user_id: i32) -> anyhow::Result<Option<WorkspaceRole>>;
+    /// A user who is already a member keeps the highest of both roles.
+    async fn add_member(
+        &self,
+        id: i32,
+        user_id: i32,
+        role: WorkspaceRole,
+    ) -> anyhow::Result<WorkspaceMember>;
+    async fn create_invitation(
+        &self,
+        id: i32,
+        role: WorkspaceRole,
+        created_by: i32,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> anyhow::Result<WorkspaceInvitation>;
+    /// Takes the invitation with `token_hash` for the user unless it expired or was taken before.
+    async fn accept_invitation(
+        &self,
+        token_hash: &str,
+        user_id: i32,
+    ) -> anyhow::Result<Option<WorkspaceInvitation>>;
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkspaceRepositoryForDb {
+    pool: PgPool,
+}
+
+impl WorkspaceRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        WorkspaceRepositoryForDb { pool }
+    }
+}
+
+#[async_trait]
+impl WorkspaceRepositoryTrait for WorkspaceRepositoryForDb {
+    async fn create(&self, user_id: i32, payload: CreateWorkspace) -> anyhow::Result<Workspace> {
+        let mut tx = self.pool.begin().await.map_err(RepositoryError::from)?;
+        let workspace = sqlx::query_as::<_, Workspace>(
+            r#"
+            insert into workspaces (name) values ($1)
+            returning id, name, false as personal, created_at
+            "#,
+        )
+        .bind(payload.name)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(RepositoryError::from)?;
+        sqlx::query(
+            "insert into workspace_members (workspace_id, user_id, role) values ($1, $2, $3)",
+        )
+        .bind(workspace.id)
+        .bind(user_id)
+        .bind(WorkspaceRole::Owner)
+        .execute(&mut *tx)
+        .await
+        .map_err(RepositoryError::from)?;
+        tx.commit().await.map_err(RepositoryError::from)?;
+
+        Ok(workspace)
+    }
+
+    async fn personal(&self, user_id: i32) -> anyhow::Result<Workspace> {
+        let workspace = sqlx::query_as::<_, Workspace>(
+            r#"
+            select id, name, true as personal, created_at from workspaces where personal_of=$1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from_sqlx(user_id))?;
+
+        Ok(workspace)
+    }
+
+    async fn all(&self, user_id: i32) -> anyhow::Result<Vec<Workspace>> {
+        let workspaces = sqlx::query_as::<_, Workspace>(
+            r#"
+            select id, name, coalesce(personal_of=$1, false) as personal, workspaces.created_at
+            from workspaces
+            join workspace_members on workspace_id=id and user_id=$1
+            order by personal desc, workspace_members.created_at, id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(workspaces)
+    }
+
+    async fn role(&self, id: i32, user_id: i32) -> anyhow::Result<Option<WorkspaceRole>> {
+        let role = sqlx::query_scalar::<_, WorkspaceRole>(
+            r#"
+            select role from workspace_members where workspace_id=$1 and user_id=$2
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(role)
+    }
+
+    async fn add_member(
+        &self,
+        id: i32,
+        user_id: i32,
+        role: WorkspaceRole,
+    ) -> anyhow::Result<WorkspaceMember> {
+        let member = sqlx::query_as::<_, WorkspaceMember>(
+            r#"
+            insert into workspace_members (workspace_id, user_id, role)
+            values ($1, $2, $3)
+            on conflict (workspace_id, user_id) do update set role=greatest(workspace_members.role, excluded.role)
+            returning *
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(role)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(member)
+    }
+
+    async fn create_invitation(
+        &self,
+        id: i32,
+        role: WorkspaceRole,
+        created_by: i32,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> anyhow::Result<WorkspaceInvitation> {
+        let invitation = sqlx::query_as::<_, WorkspaceInvitation>(
+            r#"
+            insert into workspace_invitations (workspace_id, role, created_by, token_hash, expires_at)
+            values ($1, $2, $3, $4, $5)
+            returning id, workspace_id, role, created_by, created_at, expires_at, accepted_by, accepted_at
+            "#,
+        )
+        .bind(id)
+        .bind(role)
+        .bind(created_by)
+        .bind(token_hash)
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(invitation)
+    }
+
+    async fn accept_invitation(
+        &self,
+        token_hash: &str,
+        user_id: i32,
+    ) -> anyhow::Result<Option<WorkspaceInvitation>> {
+        let invitation = sqlx::query_as::<_, WorkspaceInvitation>(
+            r#"
+            update workspace_invitations set accepted_by=$2, accepted_at=now()
+            where token_hash=$1 and accepted_at is null and expires_at > now()
+            returning id, workspace_id, role, created_by, created_at, expires_at, accepted_by, accepted_at
+            "#,
+        )
+        .bind(token_hash)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(invitation)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use crate::users::repository::{UserRepositoryForDb, UserRepositoryTrait};
+    use chrono::Duration;
+    use dotenv::dotenv;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
+
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("failed to connect a database, url is [{}]", database_url));
+
+        let stamp = chrono::Utc::now().timestamp_micros();
+        let users = UserRepositoryForDb::new(pool.clone());
+        let alice = users
+            .create(&format!("workspaces.alice.{stamp}@example.com"), "hash")
+            .await
+            .expect("failed to create a user");
+        let bob = users
+            .create(&format!("workspaces.bob.{stamp}@example.com"), "hash")
+            .await
+            .expect("failed to create a user");
+        let repository = WorkspaceRepositoryForDb::new(pool.clone());
+
+        // personal, made along with the user
+        let personal = repository
+            .personal(alice.id)
+            .await
+            .expect("[personal] returned Err");
+        assert!(personal.personal);
+        assert_ne!(repository.personal(bob.id).await.unwrap().id, personal.id);
+        assert_eq!(
+            repository.role(personal.id, alice.id).await.unwrap(),
+            Some(WorkspaceRole::Owner)
+        );
+        assert_eq!(repository.role(personal.id, bob.id).await.unwrap(), None);
+
+        // create
+        let workspace = repository
+            .create(
+                alice.id,
+                CreateWorkspace {
+                    name: format!("workspaces.{stamp}"),
+                },
+            )
+            .await
+            .expect("[create] returned Err");
+        assert!(!workspace.personal);
+
+        // all
+        let ids = |workspaces: Vec<Workspace>| Vec::from_iter(workspaces.iter().map(|w| w.id));
+        assert_eq!(
+            ids(repository.all(alice.id).await.unwrap()),
+            vec![personal.id, workspace.id]
+        );
+        assert_eq!(repository.all(bob.id).await.unwrap().len(), 1);
+
+        // invitation
+        let hash = format!("workspaces.{stamp}");
+        let expires_at = Utc::now() + Duration::days(1);
+        repository
+            .create_invitation(
+                workspace.id,
+                WorkspaceRole::Admin,
+                alice.id,
+                &hash,
+                expires_at,
+            )
+            .await
+            .expect("[create_invitation] returned Err");
+        let invitation = repository
+            .accept_invitation(&hash, bob.id)
+            .await
+            .expect("[accept_invitation] returned Err")
+            .expect("[accept_invitation] found no invitation");
+        assert_eq!(invitation.accepted_by, Some(bob.id));
+        assert_eq!(
+            repository.accept_invitation(&hash, bob.id).await.unwrap(),
+            None
+        );
+
+        // add_member keeps the highest role
+        repository
+            .add_member(workspace.id, bob.id, WorkspaceRole::Admin)
+            .await
+            .expect("[add_member] returned Err");
+        let member = repository
+            .add_member(workspace.id, bob.id, WorkspaceRole::Member)
+            .await
+            .expect("[add_member] returned Err");
+        assert_eq!(member.role, WorkspaceRole::Admin);
+        assert_eq!(
+            repository.role(workspace.id, bob.id).await.unwrap(),
+            Some(WorkspaceRole::Admin)
+        );
+        let workspaces = repository.all(bob.id).await.unwrap();
+        assert_eq!(workspaces.len(), 2);
+        assert!(workspaces[0].personal && !workspaces[1].personal);
+
+        sqlx::query("delete from workspaces where id=$1")
+            .bind(workspace.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("delete from users where id=any($1)")
+            .bind(vec![alice.id, bob.id])
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
+
+#[cfg(any(test, feature = "memory"))]
+pub mod memory {
+    use super::*;
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::{Arc, RwLock},
+    };
+
+    /// Workspaces by id, along with the user whose personal workspace it is.
+    type WorkspaceData = BTreeMap<i32, (Option<i32>, Workspace)>;
+
+    #[derive(Debug, Clone, Default)]
+    pub struct WorkspaceRepositoryForMemory {
+        workspaces: Arc<RwLock<WorkspaceData>>,
+        members: Arc<RwLock<BTreeMap<(i32, i32), WorkspaceMember>>>,
+        invitations: Arc<RwLock<HashMap<i32, (WorkspaceInvitation, String)>>>,
+    }
+
+    impl WorkspaceRepositoryForMemory {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        fn insert(
+            &self,
+            workspaces: &mut WorkspaceData,
+            user_id: i32,
+            name: String,
+            personal: bool,
+        ) -> Workspace {
+            let id = workspaces.keys().max().copied().unwrap_or_default() + 1;
+            let workspace = Workspace {
+                id,
+                name,
+                personal,
+                created_at: Utc::now(),
+            };
+            workspaces.insert(id, (personal.then_some(user_id), workspace.clone()));
+            self.members.write().unwrap().insert(
+                (id, user_id),
+                WorkspaceMember {
+                    workspace_id: id,
+                    user_id,
+                    role: WorkspaceRole::Owner,
+                    created_at: workspace.created_at,
+                },
+            );
+            workspace
+        }
+    }
+
+    #[async_trait]
+    impl WorkspaceRepositoryTrait for WorkspaceRepositoryForMemory {
+        async fn create(
+            &self,
+            user_id: i32,
+            payload: CreateWorkspace,
+        ) -> anyhow::Result<Workspace> {
+            let mut workspaces = self.workspaces.write().unwrap();
+            Ok(self.insert(&mut workspaces, user_id, payload.name, false))
+        }
+
+        async fn personal(&self, user_id: i32) -> anyhow::Result<Workspace> {
+            let mut workspaces = self.workspaces.write().unwrap();
+            let personal = workspaces
+                .values()
+                .find(|(owner, _)| *owner == Some(user_id))
+                .map(|(_, workspace)| workspace.clone());
+            Ok(personal.unwrap_or_else(|| {
+                self.insert(&mut workspaces, user_id, "Personal".to_string(), true)
+            }))
+        }
+
+        async fn all(&self, user_id: i32) -> anyhow::Result<Vec<Workspace>> {
+            let personal = self.personal(user_id).await?;
+            let workspaces = self.workspaces.read().unwrap();
+            let members = self.members.read().unwrap();
+            let mut joined = Vec::from_iter(
+                members
+                    .values()
+                    .filter(|member| member.user_id == user_id)
+                    .filter(|member| member.workspace_id != personal.id)
+                    .map(|member| (member.created_at, member.workspace_id)),
+            );
+            joined.sort();
+            Ok(Vec::from_iter(
+                [personal.id]
+                    .into_iter()
+                    .chain(joined.into_iter().map(|(_, id)| id))
+                    .filter_map(|id| workspaces.get(&id))
+                    .map(|(owner, workspace)| Workspace {
+                        personal: *owner == Some(user_id),
+                        ..workspace.clone()
+                    }),
+            ))
+        }
+
+        async fn role(&self, id: i32, user_id: i32) -> anyhow::Result<Option<WorkspaceRole>> {
+            let members = self.members.read().unwrap();
+            Ok(members.get(&(id, user_id)).map(|member| member.role))
+        }
+
+        async fn add_member(
+            &self,
+            id: i32,
+            user_id: i32,
+            role: WorkspaceRole,
+        ) -> anyhow::Result<WorkspaceMember> {
+            if !self.workspaces.read().unwrap().contains_key(&id) {
+                return Err(RepositoryError::Invalid(format!("no workspace {id}")).into());
+            }
+            let mut members = self.members.write().unwrap();
+            let member = members.entry((id, user_id)).or_insert(WorkspaceMember {
+                workspace_id: id,
+                user_id,
+                role,
+                created_at: Utc::now(),
+            });
+            member.role = member.role.max(role);
+            Ok(member.clone())
+        }
+
+        async fn create_invitation(
+            &self,
+            id: i32,
+            role: WorkspaceRole,
+            created_by: i32,
+            token_hash: &str,
+            expires_at: DateTime<Utc>,
+        ) -> anyhow::Result<WorkspaceInvitation> {
+            let mut invitations = self.invitations.write().unwrap();
+            let invitation = WorkspaceInvitation {
+                id: invitations.keys().max().copied().unwrap_or(0) + 1,
+                workspace_id: id,
+                role,
+                created_by,
+                created_at: Utc::now(),
+                expires_at,
+                accepted_by: None,
+                accepted_at: None,
+            };
+            invitations.insert(invitation.id, (invitation.clone(), token_hash.to_string()));
+            Ok(invitation)
+        }
+
+        async fn accept_invitation(
+            &self,
+            token_hash: &str,
+            user_id: i32,
+        ) -> anyhow::Result<Option<WorkspaceInvitation>> {
+            let mut invitations = self.invitations.write().unwrap();
+            let now = Utc::now();
+            Ok(invitations
+                .values_mut()
+                .find(|(invitation, hash)| {
+                    hash == token_hash
+                        && invitation.accepted_at.is_none()
+                        && invitation.expires_at > now
+                })
+                .map(|(invitation, _)| {
+                    invitation.accepted_by = Some(user_id);
+                    invitation.accepted_at = Some(now);
+                    invitation.clone()
+                }))
+        }
+    }
+}
+
+pub mod sled {
+    use super::*;
+    use crate::todos::repository::sled::{decode, encode, generate_id, key, pair_key, store_db};
+    use ::sled::{Db, Tree};
+    use std::sync::{Arc, Mutex};
+
+    /// A workspace along with the user whose personal workspace it is, as kept in the tree.
+    type Entry = (Option<i32>, Workspace);
+
+    /// Workspaces, their members and invitations as JSON in trees of the sled database of the
+    /// todos, for the desktop app. Personal workspaces are made when first asked for, like in
+    /// memory.
+    #[derive(Debug, Clone)]
+    pub struct WorkspaceRepositoryForSled {
+        db: Db,
+        workspaces: Tree,
+        /// Members under the `pair_key` of their workspace and user.
+        members: Tree,
+        /// Invitations along with the hash of their token.
+        invitations: Tree,
+        /// Held by every write, which reads rows and writes them back.
+        write: Arc<Mutex<()>>,
+    }
+
+    impl WorkspaceRepositoryForSled {
+        pub fn open(db: &Db) -> Result<Self, RepositoryError> {
+            Ok(Self {
+                db: db.clone(),
+                workspaces: db.open_tree("workspaces")?,
+                members: db.open_tree("workspace_members")?,
+                invitations: db.open_tree("workspace_invitations")?,
+                write: Arc::default(),
+            })
+        }
+
+        /// Workspaces kept in the database of `crate::store`.
+        pub fn from_store() -> Result<Self, RepositoryError> {
+            Self::open(&store_db()?)
+        }
+
+        fn get(&self, id: i32) -> Result<Option<Entry>, RepositoryError> {
+            match self.workspaces.get(key(id))? {
+                Some(bytes) => Ok(Some(decode(&bytes)?)),
+                None => Ok(None),
+            }
+        }
+
+        fn member(
+            &self,
+            id: i32,
+            user_id: i32,
+        ) -> Result<Option<WorkspaceMember>, RepositoryError> {
+            match self.members.get(pair_key(id, user_id))? {
+                Some(bytes) => Ok(Some(decode(&bytes)?)),
+                None => Ok(None),
+            }
+        }
+
+        fn save_member(&self, member: &WorkspaceMember) -> Result<(), RepositoryError> {
+            self.members.insert(
+                pair_key(member.workspace_id, member.user_id),
+                encode(member)?,
+            )?;
+            Ok(())
+        }
+
+        /// Makes a workspace owned by the user, to be called with `write` held.
+        fn insert(
+            &self,
+            user_id: i32,
+            name: String,
+            personal: bool,
+        ) -> Result<Workspace, RepositoryError> {
+            let workspace = Workspace {
+                id: generate_id(&self.db, &self.workspaces)?,
+                name,
+                personal,
+                created_at: Utc::now(),
+            };
+            self.workspaces.insert(
+                key(workspace.id),
+                encode(&(personal.then_some(user_id), &workspace))?,
+            )?;
+            self.save_member(&WorkspaceMember {
+                workspace_id: workspace.id,
+                user_id,
+                role: WorkspaceRole::Owner,
+                created_at: workspace.created_at,
+            })?;
+            Ok(workspace)
+        }
+    }
+
+    #[async_trait]
+    impl WorkspaceRepositoryTrait for WorkspaceRepositoryForSled {
+        async fn create(
+            &self,
+            user_id: i32,
+            payload: CreateWorkspace,
+        ) -> anyhow::Result<Workspace> {
+            let _write = self.write.lock().unwrap();
+            Ok(self.insert(user_id, payload.name, false)?)
+        }
+
+        async fn personal(&self, user_id: i32) -> anyhow::Result<Workspace> {
+            let _write = self.write.lock().unwrap();
+            for bytes in self.workspaces.iter().values() {
+                let (owner, workspace): Entry = decode(&bytes.map_err(RepositoryError::from)?)?;
+                if owner == Some(user_id) {
+                    return Ok(workspace);
+                }
+            }
+            Ok(self.insert(user_id, "Personal".to_string(), true)?)
+        }
+
+        async fn all(&self, user_id: i32) -> anyhow::Result<Vec<Workspace>> {
+            let personal = self.personal(user_id).await?;
+            let mut joined = Vec::new();
+            for bytes in self.members.iter().values() {
+                let member: WorkspaceMember = decode(&bytes.map_err(RepositoryError::from)?)?;
+                if member.user_id == user_id && member.workspace_id != personal.id {
+                    joined.push((member.created_at, member.workspace_id));
+                }
+            }
+            joined.sort();
+            let mut workspaces = vec![personal];
+            for (_, id) in joined {
+                if let Some((owner, workspace)) = self.get(id)? {
+                    workspaces.push(Workspace {
+                        personal: owner == Some(user_id),
+                        ..workspace
+                    });
+                }
+            }
+            Ok(workspaces)
+        }
+
+        async fn role(&self, id: i32, user_id: i32) -> anyhow::Result<Option<WorkspaceRole>> {
+            Ok(self.member(id, user_id)?.map(|member| member.role))
+        }
+
+        async fn add_member(
+            &self,
+            id: i32,
+            user_id: i32,
+            role: WorkspaceRole,
+        ) -> anyhow::Result<WorkspaceMember> {
+            let _write = self.write.lock().unwrap();
+            if self.get(id)?.is_none() {
+                return Err(RepositoryError::Invalid(format!("no workspace {id}")).into());
+            }
+            let mut member = self.member(id, user_id)?.unwrap_or(WorkspaceMember {
+                workspace_id: id,
+                user_id,
+                role,
+                created_at: Utc::now(),
+            });
+            member.role = member.role.max(role);
+            self.save_member(&member)?;
+            Ok(member)
+        }
+
+        async fn create_invitation(
+            &self,
+            id: i32,
+            role: WorkspaceRole,
+            created_by: i32,
+            token_hash: &str,
+            expires_at: DateTime<Utc>,
+        ) -> anyhow::Result<WorkspaceInvitation> {
+            let invitation = WorkspaceInvitation {
+                id: generate_id(&self.db, &self.invitations)?,
+                workspace_id: id,
+                role,
+                created_by,
+                created_at: Utc::now(),
+                expires_at,
+                accepted_by: None,
+                accepted_at: None,
+            };
+            self.invitations
+                .insert(key(invitation.id), encode(&(&invitation, token_hash))?)
+                .map_err(RepositoryError::from)?;
+            Ok(invitation)
+        }
+
+        async fn accept_invitation(
+            &self,
+            token_hash: &str,
+            user_id: i32,
+        ) -> anyhow::Result<Option<WorkspaceInvitation>> {
+            let _write = self.write.lock().unwrap();
+            let now = Utc::now();
+            for bytes in self.invitations.iter().values() {
+                let (mut invitation, hash): (WorkspaceInvitation, String) =
+                    decode(&bytes.map_err(RepositoryError::from)?)?;
+                if hash == token_hash
+                    && invitation.accepted_at.is_none()
+                    && invitation.expires_at > now
+                {
+                    invitation.accepted_by = Some(user_id);
+                    invitation.accepted_at = Some(now);
+                    self.invitations
+                        .insert(key(invitation.id), encode(&(&invitation, hash))?)
+                        .map_err(RepositoryError::from)?;
+                    return Ok(Some(invitation));
+                }
+            }
+            Ok(None)
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[tokio::test]
+        async fn sled_scenario() {
+            let db = ::sled::Config::new().temporary(true).open().unwrap();
+            let repository = WorkspaceRepositoryForSled::open(&db).unwrap();
+            let personal = repository.personal(1).await.unwrap();
+            assert!(personal.personal);
+            let team = repository
+                .create(
+                    1,
+                    CreateWorkspace {
+                        name: "team".to_string(),
+                    },
+                )
+                .await
+                .unwrap();
+            repository
+                .create_invitation(
+                    team.id,
+                    WorkspaceRole::Admin,
+                    1,
+                    "hash",
+                    Utc::now() + chrono::Days::new(1),
+                )
+                .await
+                .unwrap();
+
+            // the personal workspace is made once and kept
+            let reopened = WorkspaceRepositoryForSled::open(&db).unwrap();
+            assert_eq!(reopened.personal(1).await.unwrap(), personal);
+            let invitation = reopened
+                .accept_invitation("hash", 2)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(repository.accept_invitation("hash", 3).await.unwrap(), None);
+            reopened
+                .add_member(invitation.workspace_id, 2, invitation.role)
+                .await
+                .unwrap();
+            assert_eq!(
+                repository.role(team.id, 2).await.unwrap(),
+                Some(WorkspaceRole::Admin)
+            );
+            let workspaces = repository.all(2).await.unwrap();
+            assert_eq!(
+                Vec::from_iter(workspaces.iter().map(|w| (w.personal, &w.name[..]))),
+                vec![(true, "Personal"), (false, "team")]
+            );
+            assert!(repository
+                .add_member(0, 2, WorkspaceRole::Member)
+                .await
+                .is_err());
+        }
+    }
+}
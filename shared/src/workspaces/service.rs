@@ -0,0 +1,260 @@
+use axum::async_trait;
+use chrono::Utc;
+use validator::Validate;
+
+use super::error::WorkspaceError;
+use super::model::{
+    CreateWorkspace, CreateWorkspaceInvitation, NewWorkspaceInvitation, Workspace, WorkspaceMember,
+    WorkspaceRole,
+};
+use super::repository::WorkspaceRepositoryTrait;
+use crate::members::model::AcceptInvitation;
+use crate::members::service::{INVITATION_PREFIX, INVITATION_TTL};
+use crate::tokens::service::{generate_secret, hash_secret};
+
+#[derive(Debug, Clone)]
+pub struct WorkspaceService<WR>
+where
+    WR: WorkspaceRepositoryTrait,
+{
+    workspace_repository: WR,
+}
+
+#[async_trait]
+pub trait WorkspaceServiceTrait
+where
+    Self: Clone + std::marker::Send + std::marker::Sync + 'static,
+{
+    async fn create(
+        &self,
+        user_id: i32,
+        payload: CreateWorkspace,
+    ) -> Result<Workspace, WorkspaceError>;
+    async fn all(&self, user_id: i32) -> Result<Vec<Workspace>, WorkspaceError>;
+    /// Admins invite members and admins, owners invite anyone.
+    async fn invite(
+        &self,
+        user_id: i32,
+        id: i32,
+        payload: CreateWorkspaceInvitation,
+    ) -> Result<NewWorkspaceInvitation, WorkspaceError>;
+    /// Joins the workspace of the invitation, an invitation is only accepted once.
+    async fn accept(
+        &self,
+        user_id: i32,
+        payload: AcceptInvitation,
+    ) -> Result<WorkspaceMember, WorkspaceError>;
+    /// The workspace a request of the user works in: `requested` when they are a member of it,
+    /// otherwise their personal one.
+    async fn resolve(&self, user_id: i32, requested: Option<i32>) -> Result<i32, WorkspaceError>;
+}
+
+impl<WR> WorkspaceService<WR>
+where
+    WR: WorkspaceRepositoryTrait,
+{
+    pub fn new(workspace_repository: WR) -> Self {
+        Self {
+            workspace_repository,
+        }
+    }
+
+    /// Workspaces the user isn't a member of are not found, those where the role is too low are
+    /// forbidden.
+    async fn check_role(
+        &self,
+        user_id: i32,
+        id: i32,
+        needed: WorkspaceRole,
+    ) -> Result<WorkspaceRole, WorkspaceError> {
+        match self.workspace_repository.role(id, user_id).await? {
+            Some(role) if role >= needed => Ok(role),
+            Some(role) => Err(WorkspaceError::Forbidden(format!(
+                "{role:?} of workspace {id}, not {needed:?}"
+            ))),
+            None => Err(WorkspaceError::NotFound(id)),
+        }
+    }
+}
+
+#[async_trait]
+impl<WR> WorkspaceServiceTrait for WorkspaceService<WR>
+where
+    WR: WorkspaceRepositoryTrait,
+{
+    async fn create(
+        &self,
+        user_id: i32,
+        payload: CreateWorkspace,
+    ) -> Result<Workspace, WorkspaceError> {
+        payload.validate()?;
+        let workspace = self.workspace_repository.create(user_id, payload).await?;
+        Ok(workspace)
+    }
+
+    async fn all(&self, user_id: i32) -> Result<Vec<Workspace>, WorkspaceError> {
+        let workspaces = self.workspace_repository.all(user_id).await?;
+        Ok(workspaces)
+    }
+
+    async fn invite(
+        &self,
+        user_id: i32,
+        id: i32,
+        payload: CreateWorkspaceInvitation,
+    ) -> Result<NewWorkspaceInvitation, WorkspaceError> {
+        let role = self.check_role(user_id, id, WorkspaceRole::Admin).await?;
+        if payload.role > role {
+            return Err(WorkspaceError::Forbidden(format!(
+                "{role:?} of workspace {id} can't invite {:?}",
+                payload.role
+            )));
+        }
+        let token = generate_secret(INVITATION_PREFIX);
+        let invitation = self
+            .workspace_repository
+            .create_invitation(
+                id,
+                payload.role,
+                user_id,
+                &hash_secret(&token),
+                Utc::now() + INVITATION_TTL,
+            )
+            .await?;
+        Ok(NewWorkspaceInvitation { invitation, token })
+    }
+
+    async fn accept(
+        &self,
+        user_id: i32,
+        payload: AcceptInvitation,
+    ) -> Result<WorkspaceMember, WorkspaceError> {
+        let invitation = self
+            .workspace_repository
+            .accept_invitation(&hash_secret(payload.token.trim()), user_id)
+            .await?
+            .ok_or(WorkspaceError::InvalidInvitation)?;
+        let member = self
+            .workspace_repository
+            .add_member(invitation.workspace_id, user_id, invitation.role)
+            .await?;
+        Ok(member)
+    }
+
+    async fn resolve(&self, user_id: i32, requested: Option<i32>) -> Result<i32, WorkspaceError> {
+        match requested {
+            Some(id) => {
+                self.check_role(user_id, id, WorkspaceRole::Member).await?;
+                Ok(id)
+            }
+            None => Ok(self.workspace_repository.personal(user_id).await?.id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::workspaces::repository::memory::WorkspaceRepositoryForMemory;
+
+    const ALICE: i32 = 1;
+    const BOB: i32 = 2;
+    const CAROL: i32 = 3;
+
+    #[tokio::test]
+    async fn should_resolve_workspaces_of_members() {
+        let service = WorkspaceService::new(WorkspaceRepositoryForMemory::new());
+        let personal = service.resolve(ALICE, None).await.unwrap();
+        assert_ne!(service.resolve(BOB, None).await, Ok(personal));
+        assert_eq!(
+            service.resolve(BOB, Some(personal)).await,
+            Err(WorkspaceError::NotFound(personal))
+        );
+
+        let team = service
+            .create(
+                ALICE,
+                CreateWorkspace {
+                    name: "team".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(service.resolve(ALICE, None).await, Ok(personal));
+        assert_eq!(service.resolve(ALICE, Some(team.id)).await, Ok(team.id));
+        assert_eq!(
+            service.resolve(BOB, Some(team.id)).await,
+            Err(WorkspaceError::NotFound(team.id))
+        );
+        let workspaces = service.all(ALICE).await.unwrap();
+        assert_eq!(
+            Vec::from_iter(workspaces.iter().map(|w| (w.id, w.personal))),
+            vec![(personal, true), (team.id, false)]
+        );
+
+        let res = service
+            .create(
+                BOB,
+                CreateWorkspace {
+                    name: String::new(),
+                },
+            )
+            .await;
+        assert!(matches!(res, Err(WorkspaceError::Validation(_))), "{res:?}");
+    }
+
+    #[tokio::test]
+    async fn should_join_workspaces_by_invitation() {
+        let service = WorkspaceService::new(WorkspaceRepositoryForMemory::new());
+        let team = service
+            .create(
+                ALICE,
+                CreateWorkspace {
+                    name: "team".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        let invite =
+            |user_id, role| service.invite(user_id, team.id, CreateWorkspaceInvitation { role });
+        let accept = |user_id, token: &str| {
+            service.accept(
+                user_id,
+                AcceptInvitation {
+                    token: token.to_string(),
+                },
+            )
+        };
+
+        // outsiders don't invite themselves
+        assert_eq!(
+            invite(BOB, WorkspaceRole::Owner).await,
+            Err(WorkspaceError::NotFound(team.id))
+        );
+
+        // invitations are taken once
+        let invitation = invite(ALICE, WorkspaceRole::Member).await.unwrap();
+        assert!(invitation.token.starts_with(INVITATION_PREFIX));
+        let bob = accept(BOB, &invitation.token).await.unwrap();
+        assert_eq!(bob.role, WorkspaceRole::Member);
+        assert_eq!(
+            accept(CAROL, &invitation.token).await,
+            Err(WorkspaceError::InvalidInvitation)
+        );
+        assert_eq!(service.resolve(BOB, Some(team.id)).await, Ok(team.id));
+
+        // members don't invite, admins don't make owners
+        assert!(matches!(
+            invite(BOB, WorkspaceRole::Member).await,
+            Err(WorkspaceError::Forbidden(_))
+        ));
+        let invitation = invite(ALICE, WorkspaceRole::Admin).await.unwrap();
+        let carol = accept(CAROL, &invitation.token).await.unwrap();
+        assert_eq!(carol.role, WorkspaceRole::Admin);
+        assert!(matches!(
+            invite(CAROL, WorkspaceRole::Owner).await,
+            Err(WorkspaceError::Forbidden(_))
+        ));
+        invite(CAROL, WorkspaceRole::Admin).await.unwrap();
+    }
+}
@@ -0,0 +1,6 @@
+ALTER TABLE todos
+    ADD COLUMN starts_at TIMESTAMPTZ,
+    ADD COLUMN due_at TIMESTAMPTZ,
+    ADD CONSTRAINT todos_schedule_check CHECK (starts_at IS NULL OR due_at IS NULL OR starts_at <= due_at);
+
+CREATE INDEX todos_due_at_idx ON todos (due_at) WHERE NOT completed;
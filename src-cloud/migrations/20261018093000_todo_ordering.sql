@@ -0,0 +1,9 @@
+ALTER TABLE todos
+    ADD COLUMN priority SMALLINT NOT NULL DEFAULT 0 CHECK (priority BETWEEN 0 AND 4),
+    ADD COLUMN position BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+-- keep the current order (oldest first) and leave room between neighbours
+UPDATE todos SET position = id * 65536;
+
+CREATE INDEX todos_position_idx ON todos (position, id);
@@ -0,0 +1,18 @@
+CREATE TABLE lists
+(
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    inbox BOOLEAN NOT NULL DEFAULT false,
+    archived BOOLEAN NOT NULL DEFAULT false,
+    CHECK (NOT (inbox AND archived))
+);
+
+-- there is exactly one inbox, todos created without a list end up there
+CREATE UNIQUE INDEX lists_inbox_idx ON lists (inbox) WHERE inbox;
+INSERT INTO lists (name, inbox) VALUES ('Inbox', true);
+
+ALTER TABLE todos ADD COLUMN list_id INTEGER REFERENCES lists (id);
+UPDATE todos SET list_id = (SELECT id FROM lists WHERE inbox);
+ALTER TABLE todos ALTER COLUMN list_id SET NOT NULL;
+
+CREATE INDEX todos_list_id_idx ON todos (list_id);
@@ -0,0 +1,4 @@
+ALTER TABLE todos ADD COLUMN parent_id INTEGER REFERENCES todos (id) ON DELETE CASCADE;
+ALTER TABLE todos ADD CONSTRAINT todos_parent_id_check CHECK (parent_id <> id);
+
+CREATE INDEX todos_parent_id_idx ON todos (parent_id);
@@ -0,0 +1,13 @@
+-- new occurrences of a recurring todo are created from its series
+CREATE TABLE todo_series
+(
+    id SERIAL PRIMARY KEY,
+    rrule TEXT NOT NULL,
+    text TEXT NOT NULL,
+    priority SMALLINT NOT NULL DEFAULT 0
+);
+
+ALTER TABLE todos ADD COLUMN rrule TEXT;
+ALTER TABLE todos ADD COLUMN series_id INTEGER REFERENCES todo_series (id) ON DELETE SET NULL;
+
+CREATE INDEX todos_series_id_idx ON todos (series_id);
@@ -0,0 +1,5 @@
+-- the simple configuration doesn't stem, so matches don't depend on the language of a todo
+ALTER TABLE todos ADD COLUMN search TSVECTOR
+    GENERATED ALWAYS AS (to_tsvector('simple', text)) STORED;
+
+CREATE INDEX todos_search_idx ON todos USING GIN (search);
@@ -0,0 +1,2 @@
+-- bumped by every update, clients send back the version they edited to detect lost updates
+ALTER TABLE todos ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
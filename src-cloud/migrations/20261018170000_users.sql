@@ -0,0 +1,37 @@
+CREATE TABLE users
+(
+    id SERIAL PRIMARY KEY,
+    email TEXT NOT NULL,
+    -- argon2 PHC string, salt and parameters included
+    password_hash TEXT NOT NULL,
+    -- bumped on logout and password changes, refresh tokens carrying an older one are revoked
+    token_version INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX users_email_idx ON users (lower(email));
+
+-- the rows from before users existed are claimed by an account nobody can log into, its hash
+-- isn't a PHC string. an operator hands them over by setting its email and password.
+INSERT INTO users (email, password_hash)
+SELECT 'legacy@localhost', '!'
+WHERE EXISTS (SELECT 1 FROM todos) OR EXISTS (SELECT 1 FROM todo_series)
+    OR EXISTS (SELECT 1 FROM labels);
+
+ALTER TABLE todos ADD COLUMN user_id INTEGER REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE todo_series ADD COLUMN user_id INTEGER REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE labels ADD COLUMN user_id INTEGER REFERENCES users (id) ON DELETE CASCADE;
+
+UPDATE todos SET user_id = (SELECT id FROM users WHERE email = 'legacy@localhost');
+UPDATE todo_series SET user_id = (SELECT id FROM users WHERE email = 'legacy@localhost');
+UPDATE labels SET user_id = (SELECT id FROM users WHERE email = 'legacy@localhost');
+
+ALTER TABLE todos ALTER COLUMN user_id SET NOT NULL;
+ALTER TABLE todo_series ALTER COLUMN user_id SET NOT NULL;
+ALTER TABLE labels ALTER COLUMN user_id SET NOT NULL;
+
+CREATE INDEX todos_user_id_idx ON todos (user_id);
+
+-- label names are only unique among the labels of a user
+ALTER TABLE labels DROP CONSTRAINT labels_name_key;
+CREATE UNIQUE INDEX labels_user_id_name_idx ON labels (user_id, name);
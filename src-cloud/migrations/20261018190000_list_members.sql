@@ -0,0 +1,81 @@
+CREATE TABLE list_members
+(
+    list_id INTEGER NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    -- 0 viewer, 1 editor, 2 owner
+    role SMALLINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (list_id, user_id)
+);
+
+CREATE INDEX list_members_user_id_idx ON list_members (user_id);
+
+-- every list has an owner. the inbox is one per user, owned without being a member, the other
+-- lists are only seen by their members, starting with their owner.
+ALTER TABLE lists ADD COLUMN user_id INTEGER REFERENCES users (id) ON DELETE CASCADE;
+
+-- lists from before are claimed like the rows from before users, see 20261018170000_users.sql
+INSERT INTO users (email, password_hash)
+SELECT 'legacy@localhost', '!' WHERE EXISTS (SELECT 1 FROM lists WHERE NOT inbox)
+ON CONFLICT DO NOTHING;
+UPDATE lists SET user_id = (SELECT id FROM users WHERE email = 'legacy@localhost')
+WHERE NOT inbox;
+
+-- the shared inbox is split into one per user, which takes the todos of the user
+DROP INDEX lists_inbox_idx;
+INSERT INTO lists (name, inbox, user_id) SELECT 'Inbox', true, id FROM users;
+UPDATE todos SET list_id = (
+    SELECT id FROM lists WHERE inbox AND lists.user_id = todos.user_id
+)
+WHERE list_id = (SELECT id FROM lists WHERE inbox AND user_id IS NULL);
+DELETE FROM lists WHERE inbox AND user_id IS NULL;
+
+ALTER TABLE lists ALTER COLUMN user_id SET NOT NULL;
+CREATE UNIQUE INDEX lists_inbox_idx ON lists (user_id) WHERE inbox;
+
+-- users with todos in a list from before keep seeing it
+INSERT INTO list_members (list_id, user_id, role)
+SELECT id, user_id, 2 FROM lists WHERE NOT inbox
+UNION
+SELECT DISTINCT todos.list_id, todos.user_id, 2 FROM todos
+JOIN lists ON lists.id = todos.list_id AND NOT lists.inbox;
+
+CREATE TABLE list_invitations
+(
+    id SERIAL PRIMARY KEY,
+    list_id INTEGER NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+    role SMALLINT NOT NULL,
+    -- sha256 of the token, which is only shown when the invitation is created
+    token_hash TEXT NOT NULL UNIQUE,
+    created_by INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    accepted_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    accepted_at TIMESTAMPTZ
+);
+
+-- inboxes are seen by their owner, other lists by their members
+CREATE FUNCTION list_visible(list INTEGER, list_inbox BOOLEAN, list_owner INTEGER, viewer INTEGER)
+RETURNS BOOLEAN
+LANGUAGE SQL STABLE AS $$
+    SELECT (list_inbox AND list_owner = viewer) OR EXISTS (
+        SELECT 1 FROM list_members WHERE list_id = list AND user_id = viewer
+    )
+$$;
+
+-- users see their own todos and every todo of the lists they are a member of, a NULL viewer
+-- sees nothing
+CREATE FUNCTION todo_visible(todo_owner INTEGER, todo_list INTEGER, viewer INTEGER) RETURNS BOOLEAN
+LANGUAGE SQL STABLE AS $$
+    SELECT todo_owner = viewer OR EXISTS (
+        SELECT 1 FROM list_members WHERE list_id = todo_list AND user_id = viewer
+    )
+$$;
+
+-- a series is seen along with any of its occurrences
+CREATE FUNCTION series_visible(series INTEGER, series_owner INTEGER, viewer INTEGER) RETURNS BOOLEAN
+LANGUAGE SQL STABLE AS $$
+    SELECT series_owner = viewer OR EXISTS (
+        SELECT 1 FROM todos WHERE series_id = series AND todo_visible(user_id, list_id, viewer)
+    )
+$$;
@@ -0,0 +1,155 @@
+CREATE TABLE workspaces
+(
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- the user whose personal workspace it is, every user has one
+    personal_of INTEGER UNIQUE REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE workspace_members
+(
+    workspace_id INTEGER NOT NULL REFERENCES workspaces (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    -- 0 member, 1 admin, 2 owner
+    role SMALLINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX workspace_members_user_id_idx ON workspace_members (user_id);
+
+CREATE TABLE workspace_invitations
+(
+    id SERIAL PRIMARY KEY,
+    workspace_id INTEGER NOT NULL REFERENCES workspaces (id) ON DELETE CASCADE,
+    role SMALLINT NOT NULL,
+    -- sha256 of the token, which is only shown when the invitation is created
+    token_hash TEXT NOT NULL UNIQUE,
+    created_by INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    accepted_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    accepted_at TIMESTAMPTZ
+);
+
+-- users own their personal workspace from the start, including those from before
+CREATE FUNCTION create_personal_workspace() RETURNS TRIGGER
+LANGUAGE plpgsql AS $$
+BEGIN
+    WITH workspace AS (
+        INSERT INTO workspaces (name, personal_of) VALUES ('Personal', NEW.id) RETURNING id
+    )
+    INSERT INTO workspace_members (workspace_id, user_id, role) SELECT id, NEW.id, 2 FROM workspace;
+    RETURN NEW;
+END
+$$;
+
+CREATE TRIGGER users_personal_workspace AFTER INSERT ON users
+    FOR EACH ROW EXECUTE FUNCTION create_personal_workspace();
+
+INSERT INTO workspaces (name, personal_of) SELECT 'Personal', id FROM users;
+INSERT INTO workspace_members (workspace_id, user_id, role) SELECT id, personal_of, 2 FROM workspaces;
+
+-- rows from before go to the personal workspace of their owner, except that the todos of a list
+-- follow it, so the members of a shared list join the workspace of its owner
+ALTER TABLE lists ADD COLUMN workspace_id INTEGER REFERENCES workspaces (id) ON DELETE CASCADE;
+UPDATE lists SET workspace_id = (SELECT id FROM workspaces WHERE personal_of = lists.user_id);
+ALTER TABLE lists ALTER COLUMN workspace_id SET NOT NULL;
+ALTER TABLE lists ADD UNIQUE (id, workspace_id);
+
+DROP INDEX lists_inbox_idx;
+CREATE UNIQUE INDEX lists_inbox_idx ON lists (workspace_id, user_id) WHERE inbox;
+
+INSERT INTO workspace_members (workspace_id, user_id, role)
+SELECT DISTINCT lists.workspace_id, list_members.user_id, 0
+FROM list_members JOIN lists ON lists.id = list_members.list_id
+ON CONFLICT DO NOTHING;
+
+-- accepting an invitation to a list also joins the workspace it was shared from
+ALTER TABLE list_invitations ADD COLUMN workspace_id INTEGER REFERENCES workspaces (id) ON DELETE CASCADE;
+UPDATE list_invitations SET workspace_id = (
+    SELECT workspace_id FROM lists WHERE id = list_invitations.list_id
+);
+
+ALTER TABLE todos ADD COLUMN workspace_id INTEGER REFERENCES workspaces (id) ON DELETE CASCADE;
+UPDATE todos SET workspace_id = (SELECT workspace_id FROM lists WHERE id = todos.list_id);
+ALTER TABLE todos ALTER COLUMN workspace_id SET NOT NULL;
+ALTER TABLE todos ADD FOREIGN KEY (list_id, workspace_id) REFERENCES lists (id, workspace_id);
+CREATE INDEX todos_workspace_id_idx ON todos (workspace_id);
+
+ALTER TABLE todo_series ADD COLUMN workspace_id INTEGER REFERENCES workspaces (id) ON DELETE CASCADE;
+UPDATE todo_series SET workspace_id = coalesce(
+    (SELECT workspace_id FROM todos WHERE series_id = todo_series.id ORDER BY id DESC LIMIT 1),
+    (SELECT id FROM workspaces WHERE personal_of = todo_series.user_id)
+);
+ALTER TABLE todo_series ALTER COLUMN workspace_id SET NOT NULL;
+
+-- label names only conflict within the labels of the same user in the same workspace
+ALTER TABLE labels ADD COLUMN workspace_id INTEGER REFERENCES workspaces (id) ON DELETE CASCADE;
+UPDATE labels SET workspace_id = (SELECT id FROM workspaces WHERE personal_of = labels.user_id);
+ALTER TABLE labels ALTER COLUMN workspace_id SET NOT NULL;
+DROP INDEX labels_user_id_name_idx;
+CREATE UNIQUE INDEX labels_workspace_id_user_id_name_idx ON labels (workspace_id, user_id, name);
+
+-- the workspace is checked along with the owner, no workspace sees all of them but a NULL viewer
+-- sees nothing
+DROP FUNCTION series_visible(INTEGER, INTEGER, INTEGER);
+DROP FUNCTION todo_visible(INTEGER, INTEGER, INTEGER);
+DROP FUNCTION list_visible(INTEGER, BOOLEAN, INTEGER, INTEGER);
+
+CREATE FUNCTION list_visible(
+    list INTEGER, list_inbox BOOLEAN, list_owner INTEGER, list_workspace INTEGER, viewer INTEGER,
+    workspace INTEGER
+) RETURNS BOOLEAN
+LANGUAGE SQL STABLE AS $$
+    SELECT (workspace IS NULL OR list_workspace = workspace) AND (
+        (list_inbox AND list_owner = viewer) OR EXISTS (
+            SELECT 1 FROM list_members WHERE list_id = list AND user_id = viewer
+        )
+    )
+$$;
+
+CREATE FUNCTION todo_visible(
+    todo_owner INTEGER, todo_list INTEGER, todo_workspace INTEGER, viewer INTEGER, workspace INTEGER
+) RETURNS BOOLEAN
+LANGUAGE SQL STABLE AS $$
+    SELECT (workspace IS NULL OR todo_workspace = workspace) AND (
+        todo_owner = viewer OR EXISTS (
+            SELECT 1 FROM list_members WHERE list_id = todo_list AND user_id = viewer
+        )
+    )
+$$;
+
+CREATE FUNCTION series_visible(
+    series INTEGER, series_owner INTEGER, series_workspace INTEGER, viewer INTEGER, workspace INTEGER
+) RETURNS BOOLEAN
+LANGUAGE SQL STABLE AS $$
+    SELECT (workspace IS NULL OR series_workspace = workspace) AND (
+        series_owner = viewer OR EXISTS (
+            SELECT 1 FROM todos
+            WHERE series_id = series AND todo_visible(user_id, list_id, workspace_id, viewer, workspace)
+        )
+    )
+$$;
+
+-- The server filters every query by workspace itself, and as the owner of the tables it isn't
+-- subject to these policies. Other roles, such as one for reporting, only see the rows of the
+-- workspace they set with `SET app.workspace_id = '<id>'`, and none until they do.
+ALTER TABLE todos ENABLE ROW LEVEL SECURITY;
+ALTER TABLE todo_series ENABLE ROW LEVEL SECURITY;
+ALTER TABLE lists ENABLE ROW LEVEL SECURITY;
+ALTER TABLE labels ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY todos_workspace_isolation ON todos
+    USING (workspace_id = nullif(current_setting('app.workspace_id', true), '')::INTEGER)
+    WITH CHECK (workspace_id = nullif(current_setting('app.workspace_id', true), '')::INTEGER);
+CREATE POLICY todo_series_workspace_isolation ON todo_series
+    USING (workspace_id = nullif(current_setting('app.workspace_id', true), '')::INTEGER)
+    WITH CHECK (workspace_id = nullif(current_setting('app.workspace_id', true), '')::INTEGER);
+CREATE POLICY lists_workspace_isolation ON lists
+    USING (workspace_id = nullif(current_setting('app.workspace_id', true), '')::INTEGER)
+    WITH CHECK (workspace_id = nullif(current_setting('app.workspace_id', true), '')::INTEGER);
+CREATE POLICY labels_workspace_isolation ON labels
+    USING (workspace_id = nullif(current_setting('app.workspace_id', true), '')::INTEGER)
+    WITH CHECK (workspace_id = nullif(current_setting('app.workspace_id', true), '')::INTEGER);
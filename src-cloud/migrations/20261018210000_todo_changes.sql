@@ -0,0 +1,67 @@
+-- every insert, update and delete of a todo takes the next change, sync clients pull the changes
+-- after the last one they saw
+CREATE SEQUENCE todo_changes;
+
+ALTER TABLE todos
+    ADD COLUMN change_seq BIGINT NOT NULL DEFAULT nextval('todo_changes'),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+-- taken by `todo_changed` instead, under the lock of `next_todo_change`
+ALTER TABLE todos ALTER COLUMN change_seq DROP DEFAULT;
+
+CREATE INDEX todos_change_seq ON todos (change_seq);
+
+-- A change taken by a transaction still running would be seen after a later one already
+-- committed, and skipped by the clients that pulled past it. The changes of a workspace are
+-- taken under a lock held until commit instead, so they commit in the order they were taken.
+CREATE FUNCTION next_todo_change(workspace INTEGER) RETURNS BIGINT
+LANGUAGE plpgsql AS $$
+BEGIN
+    PERFORM pg_advisory_xact_lock(hashtext('todo_changes'), workspace);
+    RETURN nextval('todo_changes');
+END
+$$;
+
+CREATE FUNCTION todo_changed() RETURNS TRIGGER
+LANGUAGE plpgsql AS $$
+BEGIN
+    IF TG_OP = 'INSERT' OR NEW IS DISTINCT FROM OLD THEN
+        NEW.change_seq := next_todo_change(NEW.workspace_id);
+        NEW.updated_at := now();
+    END IF;
+    RETURN NEW;
+END
+$$;
+
+CREATE TRIGGER todos_changed BEFORE INSERT OR UPDATE ON todos
+    FOR EACH ROW EXECUTE FUNCTION todo_changed();
+
+-- deleted todos, subtasks deleted along with their parent included, with what `todo_visible`
+-- needs to tell who may see them go
+CREATE TABLE todo_tombstones (
+    todo_id INTEGER PRIMARY KEY,
+    user_id INTEGER,
+    list_id INTEGER NOT NULL,
+    workspace_id INTEGER NOT NULL,
+    change_seq BIGINT NOT NULL,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX todo_tombstones_change_seq ON todo_tombstones (change_seq);
+
+CREATE FUNCTION todo_deleted() RETURNS TRIGGER
+LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO todo_tombstones (todo_id, user_id, list_id, workspace_id, change_seq)
+    VALUES (OLD.id, OLD.user_id, OLD.list_id, OLD.workspace_id, next_todo_change(OLD.workspace_id));
+    RETURN OLD;
+END
+$$;
+
+CREATE TRIGGER todos_deleted AFTER DELETE ON todos
+    FOR EACH ROW EXECUTE FUNCTION todo_deleted();
+
+ALTER TABLE todo_tombstones ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY todo_tombstones_workspace_isolation ON todo_tombstones
+    USING (workspace_id = nullif(current_setting('app.workspace_id', true), '')::INTEGER)
+    WITH CHECK (workspace_id = nullif(current_setting('app.workspace_id', true), '')::INTEGER);
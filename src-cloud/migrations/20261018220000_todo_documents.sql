@@ -0,0 +1,13 @@
+-- the text of todos as a CRDT, the document clients merge their edits into. `todos.text` is
+-- what the document reads, written along with it.
+CREATE TABLE todo_documents (
+    todo_id INTEGER PRIMARY KEY REFERENCES todos (id) ON DELETE CASCADE,
+    document JSONB NOT NULL
+);
+
+ALTER TABLE todo_documents ENABLE ROW LEVEL SECURITY;
+
+-- documents go with their todo, which is only seen from its workspace
+CREATE POLICY todo_documents_workspace_isolation ON todo_documents
+    USING (EXISTS (SELECT 1 FROM todos WHERE todos.id = todo_id))
+    WITH CHECK (EXISTS (SELECT 1 FROM todos WHERE todos.id = todo_id));
@@ -0,0 +1,13 @@
+-- the manual order of the todos of a list as a CRDT, the document clients merge their moves
+-- into. `todos.position` follows it, written along with it.
+CREATE TABLE list_orders (
+    list_id INTEGER PRIMARY KEY REFERENCES lists (id) ON DELETE CASCADE,
+    document JSONB NOT NULL
+);
+
+ALTER TABLE list_orders ENABLE ROW LEVEL SECURITY;
+
+-- orders go with their list, which is only seen from its workspace
+CREATE POLICY list_orders_workspace_isolation ON list_orders
+    USING (EXISTS (SELECT 1 FROM lists WHERE lists.id = list_id))
+    WITH CHECK (EXISTS (SELECT 1 FROM lists WHERE lists.id = list_id));
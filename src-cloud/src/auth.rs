@@ -0,0 +1,344 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header::AUTHORIZATION, request::Parts, HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::domains::users::dto::TokenPair;
+use shared::tokens::error::ApiTokenError;
+use shared::tokens::model::TokenScope;
+use shared::tokens::repository::ApiTokenRepositoryTrait;
+use shared::tokens::service::{ApiTokenService, ApiTokenServiceTrait, SECRET_PREFIX};
+use shared::users::error::UserError;
+use shared::workspaces::error::WorkspaceError;
+use shared::workspaces::repository::WorkspaceRepositoryTrait;
+use shared::workspaces::service::{WorkspaceService, WorkspaceServiceTrait};
+
+/// Short lived, a stolen access token is only good until it expires.
+pub const ACCESS_TTL: Duration = Duration::minutes(15);
+pub const REFRESH_TTL: Duration = Duration::days(30);
+
+/// Picks the workspace of a request among those of its user, who works in their personal one
+/// without it.
+pub const WORKSPACE_HEADER: HeaderName = HeaderName::from_static("x-workspace-id");
+
+/// HS256 needs a key at least as long as its output.
+const MIN_SECRET_LEN: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum AuthConfigError {
+    #[error("undefined [JWT_SECRET]")]
+    Missing,
+    #[error("[JWT_SECRET] is shorter than {MIN_SECRET_LEN} bytes")]
+    TooShort,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    Access,
+    Refresh,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    /// Id of the user.
+    pub sub: String,
+    pub iat: i64,
+    pub exp: i64,
+    /// A refresh token can't be used as an access token and the other way around.
+    pub kind: TokenKind,
+    /// The token version of the user when the token was issued, a refresh token is revoked once
+    /// it's bumped.
+    #[serde(default)]
+    pub ver: i32,
+}
+
+/// Signs and checks the tokens with the `JWT_SECRET`, handed to the routes as an `Extension`.
+#[derive(Clone)]
+pub struct AuthKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+impl AuthKeys {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+        }
+    }
+
+    /// `secret` is read from the environment or the Shuttle secrets.
+    pub fn from_secret(secret: Option<String>) -> Result<Self, AuthConfigError> {
+        let secret = secret
+            .filter(|secret| !secret.is_empty())
+            .ok_or(AuthConfigError::Missing)?;
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(AuthConfigError::TooShort);
+        }
+        Ok(Self::new(secret.as_bytes()))
+    }
+
+    /// A new pair of tokens for user `user_id`, whose token version is `version`.
+    pub fn issue(&self, user_id: i32, version: i32) -> Result<TokenPair, UserError> {
+        Ok(TokenPair {
+            access_token: self.sign(user_id, version, TokenKind::Access, ACCESS_TTL)?,
+            refresh_token: self.sign(user_id, version, TokenKind::Refresh, REFRESH_TTL)?,
+            token_type: "Bearer".to_string(),
+            expires_in: ACCESS_TTL.num_seconds(),
+        })
+    }
+
+    /// The user a valid, unexpired token of `kind` was issued to, and their token version then.
+    pub fn verify(&self, token: &str, kind: TokenKind) -> Result<(i32, i32), UserError> {
+        let claims = jsonwebtoken::decode::<Claims>(token, &self.decoding, &Validation::default())
+            .map_err(|e| UserError::Unauthorized(e.to_string()))?
+            .claims;
+        if claims.kind != kind {
+            return Err(UserError::Unauthorized("wrong kind of token".to_string()));
+        }
+        let id = claims
+            .sub
+            .parse()
+            .map_err(|_| UserError::Unauthorized("invalid subject".to_string()))?;
+        Ok((id, claims.ver))
+    }
+
+    fn sign(
+        &self,
+        user_id: i32,
+        version: i32,
+        kind: TokenKind,
+        ttl: Duration,
+    ) -> Result<String, UserError> {
+        let now = Utc::now();
+        let claims = Claims {
+            sub: user_id.to_string(),
+            iat: now.timestamp(),
+            exp: (now + ttl).timestamp(),
+            kind,
+            ver: version,
+        };
+        jsonwebtoken::encode(&Header::default(), &claims, &self.encoding)
+            .map_err(|e| UserError::Unexpected(e.to_string()))
+    }
+}
+
+/// The user a request was authenticated as by `authenticate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthUser {
+    pub id: i32,
+    /// Set when authenticated with a personal API token rather than a session access token.
+    pub scope: Option<TokenScope>,
+    /// The workspace the request works in, see `WORKSPACE_HEADER`.
+    pub workspace_id: i32,
+}
+
+impl AuthUser {
+    /// Rejects API tokens, so one can't be used to mint or revoke others.
+    pub fn require_session(&self) -> Result<(), ApiTokenError> {
+        match self.scope {
+            None => Ok(()),
+            Some(_) => Err(ApiTokenError::Forbidden(
+                "only allowed with a session access token".to_string(),
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+{
+    type Rejection = UserError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AuthUser>()
+            .copied()
+            .ok_or_else(|| UserError::Unauthorized("missing bearer token".to_string()))
+    }
+}
+
+/// State of `authenticate`.
+#[derive(Clone)]
+pub struct Authenticator<KR, WR>
+where
+    KR: ApiTokenRepositoryTrait,
+    WR: WorkspaceRepositoryTrait,
+{
+    keys: AuthKeys,
+    token_service: ApiTokenService<KR>,
+    workspace_service: WorkspaceService<WR>,
+}
+
+impl<KR, WR> Authenticator<KR, WR>
+where
+    KR: ApiTokenRepositoryTrait,
+    WR: WorkspaceRepositoryTrait,
+{
+    pub fn new(keys: AuthKeys, token_repository: KR, workspace_repository: WR) -> Self {
+        Self {
+            keys,
+            token_service: ApiTokenService::new(token_repository),
+            workspace_service: WorkspaceService::new(workspace_repository),
+        }
+    }
+
+    /// The workspace `user_id` asked for with `WORKSPACE_HEADER`, or their personal one.
+    async fn workspace(
+        &self,
+        user_id: i32,
+        requested: Option<&HeaderValue>,
+    ) -> Result<i32, WorkspaceError> {
+        let requested = match requested {
+            Some(value) => Some(
+                value
+                    .to_str()
+                    .ok()
+                    .and_then(|value| value.trim().parse().ok())
+                    .ok_or_else(|| {
+                        WorkspaceError::Validation(format!("invalid [{WORKSPACE_HEADER}]"))
+                    })?,
+            ),
+            None => None,
+        };
+        self.workspace_service.resolve(user_id, requested).await
+    }
+
+    /// The user and the scope of their API token, if they authenticated with one.
+    async fn user(
+        &self,
+        method: &Method,
+        authorization: Option<&HeaderValue>,
+    ) -> Result<Option<(i32, Option<TokenScope>)>, Response> {
+        let Some(value) = authorization else {
+            return Ok(None);
+        };
+        let token = value
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or_else(|| {
+                UserError::Unauthorized("expected a bearer token".to_string()).into_response()
+            })?;
+
+        if !token.starts_with(SECRET_PREFIX) {
+            let (id, _) = self
+                .keys
+                .verify(token, TokenKind::Access)
+                .map_err(IntoResponse::into_response)?;
+            return Ok(Some((id, None)));
+        }
+        let api_token = self
+            .token_service
+            .authenticate(token)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        if !api_token.scope.allows(method) {
+            return Err(ApiTokenError::Forbidden(format!(
+                "the token can't {method} with scope {:?}",
+                api_token.scope
+            ))
+            .into_response());
+        }
+        Ok(Some((api_token.user_id, Some(api_token.scope))))
+    }
+}
+
+/// Authenticates `Authorization: Bearer` with either a session access token or a personal API
+/// token, the latter starting with `SECRET_PREFIX`, resolves the workspace of the request and
+/// hands the `AuthUser` to the handlers. Requests without the header go through, handlers taking
+/// an `AuthUser` reject them.
+pub async fn authenticate<KR, WR>(
+    State(authenticator): State<Authenticator<KR, WR>>,
+    mut req: Request,
+    next: Next,
+) -> Response
+where
+    KR: ApiTokenRepositoryTrait,
+    WR: WorkspaceRepositoryTrait,
+{
+    let authorization = req.headers().get(AUTHORIZATION).cloned();
+    let requested = req.headers().get(WORKSPACE_HEADER).cloned();
+    let user = match authenticator
+        .user(req.method(), authorization.as_ref())
+        .await
+    {
+        Ok(user) => user,
+        Err(res) => return res,
+    };
+    if let Some((id, scope)) = user {
+        let workspace_id = match authenticator.workspace(id, requested.as_ref()).await {
+            Ok(workspace_id) => workspace_id,
+            Err(e) => return e.into_response(),
+        };
+        req.extensions_mut().insert(AuthUser {
+            id,
+            scope,
+            workspace_id,
+        });
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_verify_issued_tokens() {
+        let keys = AuthKeys::new(b"a secret long enough for the tests");
+        let tokens = keys.issue(42, 3).unwrap();
+
+        assert_eq!(
+            keys.verify(&tokens.access_token, TokenKind::Access),
+            Ok((42, 3))
+        );
+        assert_eq!(
+            keys.verify(&tokens.refresh_token, TokenKind::Refresh),
+            Ok((42, 3))
+        );
+        assert!(keys
+            .verify(&tokens.refresh_token, TokenKind::Access)
+            .is_err());
+
+        let other = AuthKeys::new(b"another secret long enough for tests");
+        assert!(other
+            .verify(&tokens.access_token, TokenKind::Access)
+            .is_err());
+    }
+
+    #[test]
+    fn should_reject_expired_tokens() {
+        let keys = AuthKeys::new(b"a secret long enough for the tests");
+        // past the default leeway of a minute
+        let token = keys
+            .sign(42, 0, TokenKind::Access, Duration::minutes(-2))
+            .unwrap();
+        assert!(keys.verify(&token, TokenKind::Access).is_err());
+    }
+
+    #[test]
+    fn should_require_a_long_secret() {
+        assert!(matches!(
+            AuthKeys::from_secret(None),
+            Err(AuthConfigError::Missing)
+        ));
+        assert!(matches!(
+            AuthKeys::from_secret(Some("short".to_string())),
+            Err(AuthConfigError::TooShort)
+        ));
+        assert!(AuthKeys::from_secret(Some("x".repeat(MIN_SECRET_LEN))).is_ok());
+    }
+}
@@ -0,0 +1,153 @@
+//! The app without Shuttle. Configured by the environment or a `.env` file:
+//!
+//! - `STORAGE`: `postgres` by default, or `memory` to serve todos that are lost on shutdown
+//! - `DATABASE_URL`: postgres to migrate and serve todos from, required by `STORAGE=postgres`
+//! - `BIND_ADDRESS`: where to listen, `0.0.0.0:8000` by default
+//! - `JWT_SECRET`: signs the access and refresh tokens, at least 32 bytes, required
+//! - `CORS_ORIGINS`, `CORS_METHODS`, `CORS_HEADERS`: see `CorsConfig`
+
+use anyhow::Context;
+use axum::Router;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use tokio::net::TcpListener;
+use tower_http::cors::CorsLayer;
+
+use my_todo::auth::AuthKeys;
+use my_todo::cors::CorsConfig;
+use my_todo::routes::{create_app, create_memory_app};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    shared::init_env();
+
+    let bind_address = shared::get_env_or("BIND_ADDRESS", "0.0.0.0:8000");
+    let keys = AuthKeys::from_secret(std::env::var("JWT_SECRET").ok())?;
+    let cors = CorsConfig::from_env().layer()?;
+    let storage = Storage::open(&shared::get_env_or("STORAGE", "postgres")).await?;
+
+    let listener = TcpListener::bind(&bind_address)
+        .await
+        .with_context(|| format!("failed to bind [{bind_address}]"))?;
+    log::info!("listening on {bind_address}");
+    axum::serve(listener, storage.app(cors, keys))
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    log::info!("shutting down");
+    storage.close().await;
+    Ok(())
+}
+
+/// Where the todos are served from, chosen by `STORAGE`.
+enum Storage {
+    Postgres(PgPool),
+    Memory,
+}
+
+impl Storage {
+    async fn open(storage: &str) -> anyhow::Result<Self> {
+        match storage {
+            "postgres" => {
+                let database_url = shared::get_env("DATABASE_URL");
+                if database_url.is_empty() {
+                    anyhow::bail!("undefined [DATABASE_URL]");
+                }
+                let pool = PgPoolOptions::new()
+                    .connect(&database_url)
+                    .await
+                    .context("failed to connect the database")?;
+                sqlx::migrate!()
+                    .run(&pool)
+                    .await
+                    .context("failed to run migrations")?;
+                Ok(Storage::Postgres(pool))
+            }
+            "memory" => {
+                log::warn!("serving from memory, everything is lost on shutdown");
+                Ok(Storage::Memory)
+            }
+            storage => anyhow::bail!("unknown [STORAGE] {storage}, expected postgres or memory"),
+        }
+    }
+
+    fn app(&self, cors: CorsLayer, keys: AuthKeys) -> Router {
+        match self {
+            Storage::Postgres(pool) => create_app(pool.clone(), cors, keys),
+            Storage::Memory => create_memory_app(cors, keys),
+        }
+    }
+
+    async fn close(self) {
+        if let Storage::Postgres(pool) = self {
+            pool.close().await;
+        }
+    }
+}
+
+/// Resolves on Ctrl+C or SIGTERM, after which in-flight requests are finished.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to listen for Ctrl+C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header, Method, Request, StatusCode};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn serves_from_memory() {
+        let storage = Storage::open("memory").await.unwrap();
+        let keys = AuthKeys::new(b"a secret long enough for the tests");
+        let bearer = format!("Bearer {}", keys.issue(1, 0).unwrap().access_token);
+        let app = storage.app(CorsLayer::new(), keys);
+
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = Request::builder()
+            .uri("/todos")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &bearer)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{ "text": "served from memory" }"#))
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let req = Request::builder()
+            .uri("/todos")
+            .header(header::AUTHORIZATION, &bearer)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("served from memory"));
+        storage.close().await;
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_storage() {
+        assert!(Storage::open("sqlite").await.is_err());
+    }
+}
@@ -0,0 +1,268 @@
+use axum::http::{
+    header::{AUTHORIZATION, CONTENT_TYPE, ETAG, IF_MATCH},
+    HeaderName, HeaderValue, Method,
+};
+use globset::{GlobBuilder, GlobSetBuilder};
+use thiserror::Error;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
+
+use crate::auth::WORKSPACE_HEADER;
+
+/// Origins of the web app, allowed unless others are configured.
+pub const DEFAULT_ORIGINS: [&str; 2] =
+    ["http://localhost:3000", "https://rust-todo-two.vercel.app"];
+
+pub const DEFAULT_METHODS: [&str; 5] = ["GET", "POST", "PUT", "PATCH", "DELETE"];
+
+/// Allowed whatever is configured, the API doesn't work without them.
+pub const REQUIRED_HEADERS: [HeaderName; 4] =
+    [CONTENT_TYPE, AUTHORIZATION, IF_MATCH, WORKSPACE_HEADER];
+
+#[derive(Debug, Error)]
+pub enum CorsError {
+    #[error("invalid origin [{pattern}] in [CORS_ORIGINS]: {reason}")]
+    Origin { pattern: String, reason: String },
+    #[error("invalid method [{0}] in [CORS_METHODS]")]
+    Method(String),
+    #[error("invalid header [{0}] in [CORS_HEADERS]")]
+    Header(String),
+}
+
+/// Each list may hold `*` to allow anything. Origins are glob patterns,
+/// `https://*.example.com` allows every subdomain of `example.com`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorsConfig {
+    pub origins: Vec<String>,
+    pub methods: Vec<String>,
+    /// Added to `REQUIRED_HEADERS`.
+    pub headers: Vec<String>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            origins: DEFAULT_ORIGINS.map(String::from).to_vec(),
+            methods: DEFAULT_METHODS.map(String::from).to_vec(),
+            headers: vec![],
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Reads the comma separated `CORS_ORIGINS`, `CORS_METHODS` and `CORS_HEADERS`,
+    /// a missing or empty one keeps its default.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let list = |key: &str| {
+            var(key)
+                .map(|value| {
+                    Vec::from_iter(
+                        value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|item| !item.is_empty())
+                            .map(String::from),
+                    )
+                })
+                .filter(|items| !items.is_empty())
+        };
+        let default = Self::default();
+        Self {
+            origins: list("CORS_ORIGINS").unwrap_or(default.origins),
+            methods: list("CORS_METHODS").unwrap_or(default.methods),
+            headers: list("CORS_HEADERS").unwrap_or(default.headers),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::from_vars(|key| std::env::var(key).ok())
+    }
+
+    pub fn layer(&self) -> Result<CorsLayer, CorsError> {
+        Ok(CorsLayer::new()
+            .allow_origin(self.allow_origin()?)
+            .allow_methods(self.allow_methods()?)
+            .allow_headers(self.allow_headers()?)
+            .expose_headers(vec![ETAG]))
+    }
+
+    fn allow_origin(&self) -> Result<AllowOrigin, CorsError> {
+        if self.origins.iter().any(|pattern| pattern == "*") {
+            return Ok(Any.into());
+        }
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.origins {
+            let invalid = |reason: String| CorsError::Origin {
+                pattern: pattern.clone(),
+                reason,
+            };
+            if !pattern.contains("://") {
+                return Err(invalid("expected a scheme like https://".to_string()));
+            }
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| invalid(e.kind().to_string()))?;
+            builder.add(glob);
+        }
+        let origins = builder.build().map_err(|e| CorsError::Origin {
+            pattern: e.glob().unwrap_or_default().to_string(),
+            reason: e.kind().to_string(),
+        })?;
+
+        Ok(AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+            origin.to_str().is_ok_and(|origin| origins.is_match(origin))
+        }))
+    }
+
+    fn allow_methods(&self) -> Result<AllowMethods, CorsError> {
+        if self.methods.iter().any(|method| method == "*") {
+            return Ok(Any.into());
+        }
+        let methods = self
+            .methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.to_uppercase().as_bytes())
+                    .map_err(|_| CorsError::Method(method.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(methods.into())
+    }
+
+    fn allow_headers(&self) -> Result<AllowHeaders, CorsError> {
+        if self.headers.iter().any(|header| header == "*") {
+            return Ok(Any.into());
+        }
+        let mut headers = REQUIRED_HEADERS.to_vec();
+        for header in &self.headers {
+            let name = HeaderName::from_bytes(header.to_lowercase().as_bytes())
+                .map_err(|_| CorsError::Header(header.clone()))?;
+            if !headers.contains(&name) {
+                headers.push(name);
+            }
+        }
+
+        Ok(headers.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{header, HeaderMap, Request, StatusCode},
+        routing::get,
+        Router,
+    };
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    fn config(vars: &[(&str, &str)]) -> CorsConfig {
+        let vars = HashMap::<String, String>::from_iter(
+            vars.iter().map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+        CorsConfig::from_vars(|key| vars.get(key).cloned())
+    }
+
+    async fn preflight(config: &CorsConfig, origin: &str, headers: &str) -> HeaderMap {
+        let app = Router::new()
+            .route("/", get(|| async {}))
+            .layer(config.layer().unwrap());
+        let req = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PATCH")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, headers)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        res.headers().clone()
+    }
+
+    async fn allowed_origin(config: &CorsConfig, origin: &str) -> Option<HeaderValue> {
+        preflight(config, origin, "content-type")
+            .await
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .cloned()
+    }
+
+    #[test]
+    fn should_read_lists_from_vars() {
+        assert_eq!(config(&[]), CorsConfig::default());
+        assert_eq!(config(&[("CORS_ORIGINS", " , ")]), CorsConfig::default());
+
+        let config = config(&[
+            (
+                "CORS_ORIGINS",
+                "https://a.example.com, https://*.example.org",
+            ),
+            ("CORS_HEADERS", "x-request-id"),
+        ]);
+        assert_eq!(
+            config.origins,
+            vec!["https://a.example.com", "https://*.example.org"]
+        );
+        assert_eq!(config.methods, DEFAULT_METHODS.to_vec());
+        assert_eq!(config.headers, vec!["x-request-id"]);
+    }
+
+    #[tokio::test]
+    async fn should_allow_matching_origins() {
+        let config = config(&[(
+            "CORS_ORIGINS",
+            "http://localhost:3000,https://*.example.com",
+        )]);
+
+        for origin in [
+            "http://localhost:3000",
+            "https://app.example.com",
+            "https://a.b.example.com",
+        ] {
+            assert_eq!(allowed_origin(&config, origin).await.unwrap(), origin);
+        }
+        for origin in [
+            "http://localhost:3001",
+            "https://example.com",
+            "https://example.com.evil.io",
+            "https://evil.io/.example.com",
+        ] {
+            assert_eq!(allowed_origin(&config, origin).await, None, "{origin}");
+        }
+    }
+
+    #[tokio::test]
+    async fn should_allow_required_headers() {
+        let config = config(&[("CORS_HEADERS", "X-Request-Id")]);
+        let headers = preflight(&config, DEFAULT_ORIGINS[0], "authorization").await;
+        let allowed = headers[header::ACCESS_CONTROL_ALLOW_HEADERS]
+            .to_str()
+            .unwrap();
+        for header in [
+            "content-type",
+            "authorization",
+            "if-match",
+            "x-workspace-id",
+            "x-request-id",
+        ] {
+            assert!(allowed.contains(header), "{header} in {allowed}");
+        }
+    }
+
+    #[test]
+    fn should_reject_bad_config() {
+        for (key, value) in [
+            ("CORS_ORIGINS", "localhost:3000"),
+            ("CORS_ORIGINS", "https://[a.example.com"),
+            ("CORS_METHODS", "GET,PO ST"),
+            ("CORS_HEADERS", "x request"),
+        ] {
+            let err = config(&[(key, value)]).layer().unwrap_err();
+            assert!(err.to_string().contains(key), "{err}");
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use utoipa;
+
+use shared::labels::error::LabelError;
+use shared::labels::model::{CreateLabel, UpdateLabel};
+use shared::labels::repository::LabelRepositoryTrait;
+use shared::labels::service::{LabelService, LabelServiceTrait};
+use shared::todos::repository::TodoRepositoryTrait;
+
+use super::dependency::LabelDependency;
+use crate::auth::AuthUser;
+use crate::domains::todos::controller::ValidatedJson;
+
+type LabelState<L, T> = LabelDependency<LabelService<L, T>>;
+
+#[utoipa::path(
+    post,
+    path = "/labels",
+    request_body = CreateLabel,
+    responses(
+        (status = CREATED, description = "Created Label successfully", body = Label),
+        (status = BAD_REQUEST, description = "Label is invalid"),
+        (status = CONFLICT, description = "Label name is already used")
+    )
+)]
+pub async fn create<L: LabelRepositoryTrait, T: TodoRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<LabelState<L, T>>,
+    ValidatedJson(payload): ValidatedJson<CreateLabel>,
+) -> Result<impl IntoResponse, LabelError> {
+    let label = state
+        .label_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .create(payload)
+        .await?;
+    Ok((StatusCode::CREATED, Json(label)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/labels/{id}",
+    responses(
+        (status = 200, description = "Label found", body = Label),
+        (status = NOT_FOUND, description = "Label not found")
+    ),
+    params(
+        ("id" = i32, Path, description = "label id"),
+    )
+)]
+pub async fn find<L: LabelRepositoryTrait, T: TodoRepositoryTrait>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    State(state): State<LabelState<L, T>>,
+) -> Result<impl IntoResponse, LabelError> {
+    let label = state
+        .label_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .find(id)
+        .await?;
+    Ok((StatusCode::OK, Json(label)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/labels",
+    responses(
+        (status = 200, description = "Labels found", body = Vec<Label>)
+    )
+)]
+pub async fn find_all<L: LabelRepositoryTrait, T: TodoRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<LabelState<L, T>>,
+) -> Result<impl IntoResponse, LabelError> {
+    let labels = state
+        .label_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .find_all()
+        .await?;
+    Ok((StatusCode::OK, Json(labels)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/labels/{id}",
+    request_body = UpdateLabel,
+    responses(
+        (status = 200, description = "label successfully updated", body = Label),
+        (status = BAD_REQUEST, description = "label is invalid"),
+        (status = NOT_FOUND, description = "label not found"),
+        (status = CONFLICT, description = "label name is already used")
+    ),
+    params(
+        ("id" = i32, Path, description = "label id"),
+    )
+)]
+pub async fn update<L: LabelRepositoryTrait, T: TodoRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<LabelState<L, T>>,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateLabel>,
+) -> Result<impl IntoResponse, LabelError> {
+    let label = state
+        .label_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .update(id, payload)
+        .await?;
+    Ok((StatusCode::OK, Json(label)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/labels/{id}",
+    responses(
+        (status = NO_CONTENT, description = "label successfully deleted"),
+        (status = NOT_FOUND, description = "label not found")
+    ),
+    params(
+        ("id" = i32, Path, description = "label id"),
+    )
+)]
+pub async fn delete<L: LabelRepositoryTrait, T: TodoRepositoryTrait>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    State(state): State<LabelState<L, T>>,
+) -> Result<StatusCode, LabelError> {
+    state
+        .label_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .delete(id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/todos/{todo_id}/labels",
+    responses(
+        (status = 200, description = "Labels attached to the todo", body = Vec<Label>),
+        (status = NOT_FOUND, description = "todo not found")
+    ),
+    params(
+        ("todo_id" = i32, Path, description = "todo id"),
+    )
+)]
+pub async fn find_by_todo<L: LabelRepositoryTrait, T: TodoRepositoryTrait>(
+    user: AuthUser,
+    Path(todo_id): Path<i32>,
+    State(state): State<LabelState<L, T>>,
+) -> Result<impl IntoResponse, LabelError> {
+    let labels = state
+        .label_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .find_by_todo(todo_id)
+        .await?;
+    Ok((StatusCode::OK, Json(labels)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/todos/{todo_id}/labels/{id}",
+    responses(
+        (status = NO_CONTENT, description = "label attached to the todo"),
+        (status = NOT_FOUND, description = "todo or label not found")
+    ),
+    params(
+        ("todo_id" = i32, Path, description = "todo id"),
+        ("id" = i32, Path, description = "label id"),
+    )
+)]
+pub async fn attach<L: LabelRepositoryTrait, T: TodoRepositoryTrait>(
+    user: AuthUser,
+    Path((todo_id, id)): Path<(i32, i32)>,
+    State(state): State<LabelState<L, T>>,
+) -> Result<StatusCode, LabelError> {
+    state
+        .label_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .attach(todo_id, id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/todos/{todo_id}/labels/{id}",
+    responses(
+        (status = NO_CONTENT, description = "label detached from the todo"),
+        (status = NOT_FOUND, description = "todo not found")
+    ),
+    params(
+        ("todo_id" = i32, Path, description = "todo id"),
+        ("id" = i32, Path, description = "label id"),
+    )
+)]
+pub async fn detach<L: LabelRepositoryTrait, T: TodoRepositoryTrait>(
+    user: AuthUser,
+    Path((todo_id, id)): Path<(i32, i32)>,
+    State(state): State<LabelState<L, T>>,
+) -> Result<StatusCode, LabelError> {
+    state
+        .label_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .detach(todo_id, id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,9 @@
+use shared::labels::service::LabelServiceTrait;
+
+#[derive(Clone)]
+pub struct LabelDependency<LS>
+where
+    LS: LabelServiceTrait,
+{
+    pub label_service: LS,
+}
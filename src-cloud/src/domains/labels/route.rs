@@ -0,0 +1,45 @@
+use axum::{
+    routing::{get, put},
+    Router,
+};
+
+use shared::labels::repository::LabelRepositoryTrait;
+use shared::labels::service::LabelService;
+use shared::todos::repository::TodoRepositoryTrait;
+
+use super::controller;
+use super::dependency::LabelDependency;
+
+pub fn routes<L, T>(label_repository: L, todo_repository: T) -> Router
+where
+    L: LabelRepositoryTrait,
+    T: TodoRepositoryTrait,
+{
+    let dependency = LabelDependency {
+        label_service: LabelService::new(label_repository, todo_repository),
+    };
+    Router::new()
+        .nest(
+            "/labels",
+            Router::new()
+                .route(
+                    "/",
+                    get(controller::find_all::<L, T>).post(controller::create::<L, T>),
+                )
+                .route(
+                    "/:id",
+                    get(controller::find::<L, T>)
+                        .delete(controller::delete::<L, T>)
+                        .patch(controller::update::<L, T>),
+                ),
+        )
+        .route(
+            "/todos/:todo_id/labels",
+            get(controller::find_by_todo::<L, T>),
+        )
+        .route(
+            "/todos/:todo_id/labels/:id",
+            put(controller::attach::<L, T>).delete(controller::detach::<L, T>),
+        )
+        .with_state(dependency)
+}
@@ -0,0 +1,246 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use utoipa;
+
+use shared::lists::error::ListError;
+use shared::lists::model::{CreateList, UpdateList};
+use shared::lists::repository::ListRepositoryTrait;
+use shared::lists::service::{ListService, ListServiceTrait};
+use shared::members::repository::MemberRepositoryTrait;
+use shared::todos::model::{CreateTodo, TodoQuery};
+use shared::todos::repository::TodoRepositoryTrait;
+use shared::RespData;
+
+use super::dependency::ListDependency;
+use super::dto::{CascadeQuery, FindAllListsQuery};
+use crate::auth::AuthUser;
+use crate::domains::todos::controller::ValidatedJson;
+use crate::domains::todos::dto::FindAllQuery;
+
+type ListState<L, T, M> = ListDependency<ListService<L, T, M>>;
+
+#[utoipa::path(
+    post,
+    path = "/lists",
+    request_body = CreateList,
+    responses(
+        (status = CREATED, description = "Created List successfully", body = List),
+        (status = BAD_REQUEST, description = "List is invalid")
+    )
+)]
+pub async fn create<L: ListRepositoryTrait, T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<ListState<L, T, M>>,
+    ValidatedJson(payload): ValidatedJson<CreateList>,
+) -> Result<impl IntoResponse, ListError> {
+    let list = state
+        .list_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .create(payload)
+        .await?;
+    Ok((StatusCode::CREATED, Json(list)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{id}",
+    responses(
+        (status = 200, description = "List found", body = List),
+        (status = NOT_FOUND, description = "List not found")
+    ),
+    params(
+        ("id" = i32, Path, description = "list id"),
+    )
+)]
+pub async fn find<L: ListRepositoryTrait, T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    State(state): State<ListState<L, T, M>>,
+) -> Result<impl IntoResponse, ListError> {
+    let list = state
+        .list_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .find(id)
+        .await?;
+    Ok((StatusCode::OK, Json(list)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists",
+    responses(
+        (status = 200, description = "Lists found, the inbox first", body = Vec<List>)
+    ),
+    params(FindAllListsQuery)
+)]
+pub async fn find_all<L: ListRepositoryTrait, T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<ListState<L, T, M>>,
+    Query(query): Query<FindAllListsQuery>,
+) -> Result<impl IntoResponse, ListError> {
+    let lists = state
+        .list_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .find_all(query.archived.unwrap_or_default())
+        .await?;
+    Ok((StatusCode::OK, Json(lists)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/lists/{id}",
+    request_body = UpdateList,
+    responses(
+        (status = 200, description = "list successfully updated", body = List),
+        (status = BAD_REQUEST, description = "list is invalid"),
+        (status = NOT_FOUND, description = "list not found"),
+        (status = FORBIDDEN, description = "Role in the shared list is too low")
+    ),
+    params(
+        ("id" = i32, Path, description = "list id"),
+    )
+)]
+pub async fn update<L: ListRepositoryTrait, T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<ListState<L, T, M>>,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateList>,
+) -> Result<impl IntoResponse, ListError> {
+    let list = state
+        .list_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .update(id, payload)
+        .await?;
+    Ok((StatusCode::OK, Json(list)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/archive",
+    responses(
+        (status = 200, description = "list successfully archived", body = List),
+        (status = BAD_REQUEST, description = "the inbox can't be archived"),
+        (status = NOT_FOUND, description = "list not found"),
+        (status = FORBIDDEN, description = "Role in the shared list is too low")
+    ),
+    params(
+        ("id" = i32, Path, description = "list id"),
+        CascadeQuery
+    )
+)]
+pub async fn archive<L: ListRepositoryTrait, T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    Query(query): Query<CascadeQuery>,
+    State(state): State<ListState<L, T, M>>,
+) -> Result<impl IntoResponse, ListError> {
+    let list = state
+        .list_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .archive(id, query.todos.unwrap_or_default())
+        .await?;
+    Ok((StatusCode::OK, Json(list)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/lists/{id}",
+    responses(
+        (status = NO_CONTENT, description = "list successfully deleted"),
+        (status = BAD_REQUEST, description = "the inbox can't be deleted"),
+        (status = NOT_FOUND, description = "list not found"),
+        (status = FORBIDDEN, description = "Role in the shared list is too low")
+    ),
+    params(
+        ("id" = i32, Path, description = "list id"),
+        CascadeQuery
+    )
+)]
+pub async fn delete<L: ListRepositoryTrait, T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    Query(query): Query<CascadeQuery>,
+    State(state): State<ListState<L, T, M>>,
+) -> Result<StatusCode, ListError> {
+    state
+        .list_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .delete(id, query.todos.unwrap_or_default())
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{id}/todos",
+    responses(
+        (status = 200, description = "A page of todos of the list", body = TodoPageData),
+        (status = BAD_REQUEST, description = "Query is invalid"),
+        (status = NOT_FOUND, description = "list not found")
+    ),
+    params(
+        ("id" = i32, Path, description = "list id"),
+        FindAllQuery
+    )
+)]
+pub async fn find_todos<
+    L: ListRepositoryTrait,
+    T: TodoRepositoryTrait,
+    M: MemberRepositoryTrait,
+>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    State(state): State<ListState<L, T, M>>,
+    Query(query): Query<FindAllQuery>,
+) -> Result<impl IntoResponse, ListError> {
+    let page = state
+        .list_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .find_todos(id, TodoQuery::try_from(query)?)
+        .await?;
+    Ok((StatusCode::OK, Json(RespData::from(page))))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/todos",
+    request_body = CreateTodo,
+    responses(
+        (status = CREATED, description = "Created Todo in the list successfully", body = Todo),
+        (status = BAD_REQUEST, description = "Todo is invalid or the list is archived"),
+        (status = NOT_FOUND, description = "list not found"),
+        (status = FORBIDDEN, description = "Role in the shared list is too low")
+    ),
+    params(
+        ("id" = i32, Path, description = "list id"),
+    )
+)]
+pub async fn create_todo<
+    L: ListRepositoryTrait,
+    T: TodoRepositoryTrait,
+    M: MemberRepositoryTrait,
+>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    State(state): State<ListState<L, T, M>>,
+    ValidatedJson(payload): ValidatedJson<CreateTodo>,
+) -> Result<impl IntoResponse, ListError> {
+    let todo = state
+        .list_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .create_todo(id, payload)
+        .await?;
+    Ok((StatusCode::CREATED, Json(todo)))
+}
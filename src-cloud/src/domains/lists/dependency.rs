@@ -0,0 +1,9 @@
+use shared::lists::service::ListServiceTrait;
+
+#[derive(Clone)]
+pub struct ListDependency<LS>
+where
+    LS: ListServiceTrait,
+{
+    pub list_service: LS,
+}
@@ -0,0 +1,18 @@
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use shared::lists::model::ListCascade;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FindAllListsQuery {
+    /// also return archived lists, defaults to `false`
+    pub archived: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CascadeQuery {
+    /// what happens to the todos of the list, defaults to `move_to_inbox`
+    pub todos: Option<ListCascade>,
+}
@@ -0,0 +1,44 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+use shared::lists::repository::ListRepositoryTrait;
+use shared::lists::service::ListService;
+use shared::members::repository::MemberRepositoryTrait;
+use shared::todos::repository::TodoRepositoryTrait;
+
+use super::controller;
+use super::dependency::ListDependency;
+
+pub fn routes<L, T, M>(list_repository: L, todo_repository: T, member_repository: M) -> Router
+where
+    L: ListRepositoryTrait,
+    T: TodoRepositoryTrait,
+    M: MemberRepositoryTrait,
+{
+    let dependency = ListDependency {
+        list_service: ListService::new(list_repository, todo_repository, member_repository),
+    };
+    Router::new()
+        .nest(
+            "/lists",
+            Router::new()
+                .route(
+                    "/",
+                    get(controller::find_all::<L, T, M>).post(controller::create::<L, T, M>),
+                )
+                .route(
+                    "/:id",
+                    get(controller::find::<L, T, M>)
+                        .delete(controller::delete::<L, T, M>)
+                        .patch(controller::update::<L, T, M>),
+                )
+                .route("/:id/archive", post(controller::archive::<L, T, M>))
+                .route(
+                    "/:id/todos",
+                    get(controller::find_todos::<L, T, M>).post(controller::create_todo::<L, T, M>),
+                ),
+        )
+        .with_state(dependency)
+}
@@ -0,0 +1,165 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use utoipa;
+
+use shared::lists::repository::ListRepositoryTrait;
+use shared::members::error::MemberError;
+use shared::members::model::{AcceptInvitation, CreateInvitation, UpdateMember};
+use shared::members::repository::MemberRepositoryTrait;
+use shared::members::service::{MemberService, MemberServiceTrait};
+use shared::workspaces::repository::WorkspaceRepositoryTrait;
+
+use super::dependency::MemberDependency;
+use crate::auth::AuthUser;
+
+type MemberState<M, L, W> = MemberDependency<MemberService<M, L, W>>;
+
+#[utoipa::path(
+    get,
+    path = "/lists/{id}/members",
+    responses(
+        (status = 200, description = "Members of the list, starting with its owners", body = Vec<Member>),
+        (status = NOT_FOUND, description = "list not found")
+    ),
+    params(
+        ("id" = i32, Path, description = "list id"),
+    )
+)]
+pub async fn find_all<
+    M: MemberRepositoryTrait,
+    L: ListRepositoryTrait,
+    W: WorkspaceRepositoryTrait,
+>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    State(state): State<MemberState<M, L, W>>,
+) -> Result<impl IntoResponse, MemberError> {
+    let members = state
+        .member_service
+        .in_workspace(user.workspace_id)
+        .members(user.id, id)
+        .await?;
+    Ok((StatusCode::OK, Json(members)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/invitations",
+    request_body = CreateInvitation,
+    responses(
+        (status = CREATED, description = "Created Invitation successfully, with its token", body = NewInvitation),
+        (status = BAD_REQUEST, description = "the inbox can't be shared"),
+        (status = FORBIDDEN, description = "Not an owner of the list"),
+        (status = NOT_FOUND, description = "list not found")
+    ),
+    params(
+        ("id" = i32, Path, description = "list id"),
+    )
+)]
+pub async fn invite<
+    M: MemberRepositoryTrait,
+    L: ListRepositoryTrait,
+    W: WorkspaceRepositoryTrait,
+>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    State(state): State<MemberState<M, L, W>>,
+    Json(payload): Json<CreateInvitation>,
+) -> Result<impl IntoResponse, MemberError> {
+    let invitation = state
+        .member_service
+        .in_workspace(user.workspace_id)
+        .invite(user.id, id, payload)
+        .await?;
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/invitations/accept",
+    request_body = AcceptInvitation,
+    responses(
+        (status = 200, description = "Joined the list of the invitation and the workspace it was shared from", body = Member),
+        (status = BAD_REQUEST, description = "Invitation is unknown, expired or already accepted")
+    )
+)]
+pub async fn accept<
+    M: MemberRepositoryTrait,
+    L: ListRepositoryTrait,
+    W: WorkspaceRepositoryTrait,
+>(
+    user: AuthUser,
+    State(state): State<MemberState<M, L, W>>,
+    Json(payload): Json<AcceptInvitation>,
+) -> Result<impl IntoResponse, MemberError> {
+    let member = state.member_service.accept(user.id, payload).await?;
+    Ok((StatusCode::OK, Json(member)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/lists/{id}/members/{user_id}",
+    request_body = UpdateMember,
+    responses(
+        (status = 200, description = "member successfully updated", body = Member),
+        (status = FORBIDDEN, description = "Not an owner of the list"),
+        (status = NOT_FOUND, description = "list or member not found"),
+        (status = CONFLICT, description = "the list would have no owner left")
+    ),
+    params(
+        ("id" = i32, Path, description = "list id"),
+        ("user_id" = i32, Path, description = "user id of the member"),
+    )
+)]
+pub async fn update<
+    M: MemberRepositoryTrait,
+    L: ListRepositoryTrait,
+    W: WorkspaceRepositoryTrait,
+>(
+    user: AuthUser,
+    Path((id, user_id)): Path<(i32, i32)>,
+    State(state): State<MemberState<M, L, W>>,
+    Json(payload): Json<UpdateMember>,
+) -> Result<impl IntoResponse, MemberError> {
+    let member = state
+        .member_service
+        .in_workspace(user.workspace_id)
+        .update_role(user.id, id, user_id, payload)
+        .await?;
+    Ok((StatusCode::OK, Json(member)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/lists/{id}/members/{user_id}",
+    responses(
+        (status = NO_CONTENT, description = "member successfully removed"),
+        (status = FORBIDDEN, description = "Neither an owner nor the member"),
+        (status = NOT_FOUND, description = "list or member not found"),
+        (status = CONFLICT, description = "the list would have no owner left")
+    ),
+    params(
+        ("id" = i32, Path, description = "list id"),
+        ("user_id" = i32, Path, description = "user id of the member"),
+    )
+)]
+pub async fn remove<
+    M: MemberRepositoryTrait,
+    L: ListRepositoryTrait,
+    W: WorkspaceRepositoryTrait,
+>(
+    user: AuthUser,
+    Path((id, user_id)): Path<(i32, i32)>,
+    State(state): State<MemberState<M, L, W>>,
+) -> Result<StatusCode, MemberError> {
+    state
+        .member_service
+        .in_workspace(user.workspace_id)
+        .remove(user.id, id, user_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,9 @@
+use shared::members::service::MemberServiceTrait;
+
+#[derive(Clone)]
+pub struct MemberDependency<MS>
+where
+    MS: MemberServiceTrait,
+{
+    pub member_service: MS,
+}
@@ -0,0 +1,39 @@
+use axum::{
+    routing::{get, patch, post},
+    Router,
+};
+
+use shared::lists::repository::ListRepositoryTrait;
+use shared::members::repository::MemberRepositoryTrait;
+use shared::members::service::MemberService;
+use shared::workspaces::repository::WorkspaceRepositoryTrait;
+
+use super::controller;
+use super::dependency::MemberDependency;
+
+pub fn routes<M, L, W>(member_repository: M, list_repository: L, workspace_repository: W) -> Router
+where
+    M: MemberRepositoryTrait,
+    L: ListRepositoryTrait,
+    W: WorkspaceRepositoryTrait,
+{
+    let dependency = MemberDependency {
+        member_service: MemberService::new(
+            member_repository,
+            list_repository,
+            workspace_repository,
+        ),
+    };
+    Router::new()
+        .route("/lists/:id/members", get(controller::find_all::<M, L, W>))
+        .route(
+            "/lists/:id/members/:user_id",
+            patch(controller::update::<M, L, W>).delete(controller::remove::<M, L, W>),
+        )
+        .route(
+            "/lists/:id/invitations",
+            post(controller::invite::<M, L, W>),
+        )
+        .route("/invitations/accept", post(controller::accept::<M, L, W>))
+        .with_state(dependency)
+}
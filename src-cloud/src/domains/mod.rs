@@ -1 +1,8 @@
+pub mod labels;
+pub mod lists;
+pub mod members;
+pub mod sync;
 pub mod todos;
+pub mod tokens;
+pub mod users;
+pub mod workspaces;
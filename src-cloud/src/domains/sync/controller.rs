@@ -0,0 +1,102 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use utoipa;
+
+use shared::members::repository::MemberRepositoryTrait;
+use shared::sync::model::{DocumentRequest, PushRequest};
+use shared::sync::service::{SyncService, SyncServiceTrait};
+use shared::todos::document::TodoDocumentRepositoryTrait;
+use shared::todos::error::TodoError;
+use shared::todos::repository::TodoRepositoryTrait;
+
+use super::dependency::SyncDependency;
+use super::dto::PullQuery;
+use crate::auth::AuthUser;
+
+type SyncState<T, M, D> = SyncDependency<SyncService<T, M, D>>;
+
+#[utoipa::path(
+    get,
+    path = "/sync",
+    responses(
+        (status = 200, description = "Changes to the todos since the cursor, oldest first", body = ChangePage),
+        (status = BAD_REQUEST, description = "Query is invalid"),
+        (status = SERVICE_UNAVAILABLE, description = "Changes couldn't be loaded")
+    ),
+    params(PullQuery)
+)]
+pub async fn pull<
+    T: TodoRepositoryTrait,
+    M: MemberRepositoryTrait,
+    D: TodoDocumentRepositoryTrait,
+>(
+    user: AuthUser,
+    State(state): State<SyncState<T, M, D>>,
+    Query(query): Query<PullQuery>,
+) -> Result<impl IntoResponse, TodoError> {
+    let page = state
+        .sync_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .pull(query.cursor, query.limit)
+        .await?;
+    Ok((StatusCode::OK, Json(page)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/sync",
+    request_body = PushRequest,
+    responses(
+        (status = 200, description = "The result of each op, in order", body = PushResponse),
+        (status = BAD_REQUEST, description = "Request is invalid")
+    )
+)]
+pub async fn push<
+    T: TodoRepositoryTrait,
+    M: MemberRepositoryTrait,
+    D: TodoDocumentRepositoryTrait,
+>(
+    user: AuthUser,
+    State(state): State<SyncState<T, M, D>>,
+    Json(payload): Json<PushRequest>,
+) -> Result<impl IntoResponse, TodoError> {
+    let res = state
+        .sync_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .push(payload)
+        .await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/sync/documents",
+    request_body = DocumentRequest,
+    responses(
+        (status = 200, description = "The result of each document, in order", body = DocumentResponse),
+        (status = BAD_REQUEST, description = "Request is invalid")
+    )
+)]
+pub async fn exchange<
+    T: TodoRepositoryTrait,
+    M: MemberRepositoryTrait,
+    D: TodoDocumentRepositoryTrait,
+>(
+    user: AuthUser,
+    State(state): State<SyncState<T, M, D>>,
+    Json(payload): Json<DocumentRequest>,
+) -> Result<impl IntoResponse, TodoError> {
+    let res = state
+        .sync_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .exchange(payload)
+        .await?;
+    Ok((StatusCode::OK, Json(res)))
+}
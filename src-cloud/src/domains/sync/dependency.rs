@@ -0,0 +1,9 @@
+use shared::sync::service::SyncServiceTrait;
+
+#[derive(Clone)]
+pub struct SyncDependency<SS>
+where
+    SS: SyncServiceTrait,
+{
+    pub sync_service: SS,
+}
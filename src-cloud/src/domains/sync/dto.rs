@@ -0,0 +1,12 @@
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PullQuery {
+    /// `cursor` of the last page pulled, 0 to pull every change
+    #[serde(default)]
+    pub cursor: i64,
+    /// number of changes, 50 by default and 200 at most
+    pub limit: Option<u32>,
+}
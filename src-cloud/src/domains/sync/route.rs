@@ -0,0 +1,31 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+use shared::members::repository::MemberRepositoryTrait;
+use shared::sync::service::SyncService;
+use shared::todos::document::TodoDocumentRepositoryTrait;
+use shared::todos::repository::TodoRepositoryTrait;
+
+use super::controller;
+use super::dependency::SyncDependency;
+
+/// `document_repository` holds the documents `todo_repository` keeps.
+pub fn routes<T, M, D>(todo_repository: T, member_repository: M, document_repository: D) -> Router
+where
+    T: TodoRepositoryTrait,
+    M: MemberRepositoryTrait,
+    D: TodoDocumentRepositoryTrait,
+{
+    let dependency = SyncDependency {
+        sync_service: SyncService::new(todo_repository, member_repository, document_repository),
+    };
+    Router::new()
+        .route(
+            "/sync",
+            get(controller::pull::<T, M, D>).post(controller::push::<T, M, D>),
+        )
+        .route("/sync/documents", post(controller::exchange::<T, M, D>))
+        .with_state(dependency)
+}
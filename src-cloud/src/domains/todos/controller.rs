@@ -2,23 +2,34 @@
 
 use axum::{
     async_trait,
-    extract::{FromRequest, Path, Request, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{FromRequest, FromRequestParts, Path, Query, Request, State},
+    http::{
+        header::{ETAG, IF_MATCH},
+        request::Parts,
+        HeaderName, StatusCode,
+    },
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::de::DeserializeOwned;
 use utoipa;
 use validator::Validate;
 
-use shared::todos::model::{CreateTodo, UpdateTodo};
-use shared::todos::repository::{TodoRepositoryForDb, TodoRepositoryTrait};
+use shared::members::repository::MemberRepositoryTrait;
+use shared::todos::error::TodoError;
+use shared::todos::model::{CreateTodo, EditScope, MoveTodo, Todo, UpdateTodo};
+use shared::todos::repository::TodoRepositoryTrait;
 use shared::todos::service::{TodoService, TodoServiceTrait};
+use shared::RespData;
 
 use super::dependency::TodoDependency;
+use super::dto::{FindAllQuery, FindQuery, SearchQuery, TodoExpand, UpdateQuery};
+use crate::auth::AuthUser;
+
+type TodoState<T, M> = TodoDependency<TodoService<T, M>, T>;
 
 #[derive(Debug)]
-pub struct ValidatedJson<T>(T);
+pub struct ValidatedJson<T>(pub T);
 
 // TODO: move to more general place
 #[async_trait]
@@ -44,68 +55,167 @@ where
     }
 }
 
+/// The version a client expects a todo to be at, from an `If-Match` header carrying the `ETag`
+/// it got the todo with. `*` and a missing header match any version.
+#[derive(Debug)]
+pub struct IfMatch(pub Option<i32>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IfMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = TodoError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(value) = parts.headers.get(IF_MATCH) else {
+            return Ok(IfMatch(None));
+        };
+        let value = value.to_str().unwrap_or_default().trim();
+        if value == "*" {
+            return Ok(IfMatch(None));
+        }
+        value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .and_then(|version| version.parse().ok())
+            .map(|version| IfMatch(Some(version)))
+            .ok_or_else(|| TodoError::Validation(format!("If-Match isn't a todo ETag: {value}")))
+    }
+}
+
+impl IfMatch {
+    /// A version conflict fails the precondition when the client asked for that version.
+    fn precondition(&self, e: TodoError) -> TodoError {
+        match (self.0, e) {
+            (Some(_), TodoError::Conflict(message)) => TodoError::PreconditionFailed(message),
+            (_, e) => e,
+        }
+    }
+}
+
+fn etag(todo: &Todo) -> [(HeaderName, String); 1] {
+    [(ETAG, format!("\"{}\"", todo.version))]
+}
+
 #[utoipa::path(
     post,
     path = "/todos",
     request_body = CreateTodo,
     responses(
-        (status = CREATED, description = "Created Todo successfully", body = Todo),
-        (status = NOT_FOUND, description = "Todo couldn't be created")
+        (status = CREATED, description = "Created Todo successfully", body = Todo,
+            headers(("ETag" = String, description = "version of the todo"))),
+        (status = BAD_REQUEST, description = "Todo is invalid"),
+        (status = SERVICE_UNAVAILABLE, description = "Todo couldn't be stored"),
+        (status = FORBIDDEN, description = "Role in the shared list is too low")
     )
 )]
-pub async fn create<T>(
-    // TODO: Refactor generics
-    State(state): State<TodoDependency<TodoService<TodoRepositoryForDb>, TodoRepositoryForDb>>,
+pub async fn create<T, M>(
+    user: AuthUser,
+    State(state): State<TodoState<T, M>>,
     ValidatedJson(payload): ValidatedJson<CreateTodo>,
-) -> Result<impl IntoResponse, StatusCode>
+) -> Result<impl IntoResponse, TodoError>
 where
     T: TodoRepositoryTrait,
+    M: MemberRepositoryTrait,
 {
     let todo = state
         .todo_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
         .create(payload)
-        .await
-        .or(Err(StatusCode::NOT_FOUND))?;
+        .await?;
 
-    Ok((StatusCode::CREATED, Json(todo)))
+    Ok((StatusCode::CREATED, etag(&todo), Json(todo)))
 }
 
 #[utoipa::path(
     get,
     path = "/todos/{id}",
     responses(
-        (status = 200, description = "Todo found", body = Todo),
+        (status = 200, description = "Todo found, a TodoTree with `expand=children`", body = Todo,
+            headers(("ETag" = String, description = "version of the todo, not sent with `expand`"))),
         (status = NOT_FOUND, description = "Todo not found")
     ),
     params(
         ("id" = i32, Path, description = "todo id"),
+        FindQuery
     )
 )]
-pub async fn find<T: TodoRepositoryTrait>(
+pub async fn find<T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
     Path(id): Path<i32>,
-    State(state): State<TodoDependency<TodoService<TodoRepositoryForDb>, TodoRepositoryForDb>>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let todo = state
-        .todo_service
-        .find(id)
-        .await
-        .or(Err(StatusCode::NOT_FOUND))?;
-    Ok((StatusCode::OK, Json(todo)))
+    Query(query): Query<FindQuery>,
+    State(state): State<TodoState<T, M>>,
+) -> Result<Response, TodoError> {
+    let response = match query.expand {
+        Some(TodoExpand::Children) => {
+            let tree = state
+                .todo_service
+                .owned_by(user.id)
+                .in_workspace(user.workspace_id)
+                .find_tree(id)
+                .await?;
+            (StatusCode::OK, Json(tree)).into_response()
+        }
+        None => {
+            let todo = state
+                .todo_service
+                .owned_by(user.id)
+                .in_workspace(user.workspace_id)
+                .find(id)
+                .await?;
+            (StatusCode::OK, etag(&todo), Json(todo)).into_response()
+        }
+    };
+    Ok(response)
 }
 
 #[utoipa::path(
     get,
     path = "/todos",
     responses(
-        (status = 200, description = "Todos found", body = Vec<Todo>),
-        (status = NOT_FOUND, description = "Todos not found")
-    )
+        (status = 200, description = "A page of todos", body = TodoPageData),
+        (status = BAD_REQUEST, description = "Query is invalid"),
+        (status = SERVICE_UNAVAILABLE, description = "Todos couldn't be loaded")
+    ),
+    params(FindAllQuery)
 )]
-pub async fn find_all<T: TodoRepositoryTrait>(
-    State(state): State<TodoDependency<TodoService<TodoRepositoryForDb>, TodoRepositoryForDb>>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let todo = state.todo_service.find_all().await.unwrap();
-    Ok((StatusCode::OK, Json(todo)))
+pub async fn find_all<T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<TodoState<T, M>>,
+    Query(query): Query<FindAllQuery>,
+) -> Result<impl IntoResponse, TodoError> {
+    let page = state
+        .todo_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .find_all(query.try_into()?)
+        .await?;
+    Ok((StatusCode::OK, Json(RespData::from(page))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/todos/search",
+    responses(
+        (status = 200, description = "Matching todos, most relevant first", body = Vec<TodoSearchHit>),
+        (status = BAD_REQUEST, description = "Query is invalid")
+    ),
+    params(SearchQuery)
+)]
+pub async fn search<T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<TodoState<T, M>>,
+    Query(query): Query<SearchQuery>,
+) -> Result<impl IntoResponse, TodoError> {
+    let hits = state
+        .todo_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .search(&query.q, query.limit)
+        .await?;
+    Ok((StatusCode::OK, Json(hits)))
 }
 
 #[utoipa::path(
@@ -113,46 +223,135 @@ pub async fn find_all<T: TodoRepositoryTrait>(
     path = "/todos/{id}",
     request_body = UpdateTodo,
     responses(
-        (status = 200, description = "todo successfully updated", body = Todo),
-        (status = NOT_FOUND, description = "todo not updated")
+        (status = 200, description = "todo successfully updated", body = Todo,
+            headers(("ETag" = String, description = "version of the todo"))),
+        (status = BAD_REQUEST, description = "todo is invalid"),
+        (status = NOT_FOUND, description = "todo not found"),
+        (status = CONFLICT, description = "todo changed since `version`"),
+        (status = PRECONDITION_FAILED, description = "todo changed since the `If-Match` ETag"),
+        (status = FORBIDDEN, description = "Role in the shared list is too low")
     ),
     params(
         ("id" = i32, Path, description = "todo id"),
+        ("If-Match" = Option<String>, Header, description = "ETag the update is based on"),
+        UpdateQuery
     )
 )]
-pub async fn update<T: TodoRepositoryTrait>(
-    State(state): State<TodoDependency<TodoService<TodoRepositoryForDb>, TodoRepositoryForDb>>,
+pub async fn update<T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<TodoState<T, M>>,
     Path(id): Path<i32>,
+    Query(query): Query<UpdateQuery>,
+    if_match: IfMatch,
     ValidatedJson(payload): ValidatedJson<UpdateTodo>,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> Result<impl IntoResponse, TodoError> {
+    let payload = UpdateTodo {
+        version: if_match.0.or(payload.version),
+        ..payload
+    };
+    let result = match query.scope.unwrap_or_default() {
+        EditScope::This => {
+            state
+                .todo_service
+                .owned_by(user.id)
+                .in_workspace(user.workspace_id)
+                .update(id, payload)
+                .await
+        }
+        EditScope::AllFuture => {
+            state
+                .todo_service
+                .owned_by(user.id)
+                .in_workspace(user.workspace_id)
+                .update_all_future(id, payload)
+                .await
+        }
+    };
+    let todo = result.map_err(|e| if_match.precondition(e))?;
+    Ok((StatusCode::OK, etag(&todo), Json(todo)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/todos/series/{series_id}",
+    responses(
+        (status = 200, description = "Series found", body = SeriesOccurrences),
+        (status = NOT_FOUND, description = "Series not found")
+    ),
+    params(
+        ("series_id" = i32, Path, description = "series id"),
+    )
+)]
+pub async fn find_series<T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
+    Path(series_id): Path<i32>,
+    State(state): State<TodoState<T, M>>,
+) -> Result<impl IntoResponse, TodoError> {
+    let series = state
+        .todo_service
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .find_series(series_id)
+        .await?;
+    Ok((StatusCode::OK, Json(series)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/todos/{id}/move",
+    request_body = MoveTodo,
+    responses(
+        (status = 200, description = "todo successfully moved", body = Todo,
+            headers(("ETag" = String, description = "version of the todo"))),
+        (status = NOT_FOUND, description = "todo not found"),
+        (status = FORBIDDEN, description = "Role in the shared list is too low")
+    ),
+    params(
+        ("id" = i32, Path, description = "todo id"),
+    )
+)]
+pub async fn reorder<T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<TodoState<T, M>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<MoveTodo>,
+) -> Result<impl IntoResponse, TodoError> {
     let todo = state
         .todo_service
-        .update(id, payload)
-        .await
-        .or(Err(StatusCode::NOT_FOUND))?;
-    Ok((StatusCode::OK, Json(todo)))
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .reorder(id, payload.before)
+        .await?;
+    Ok((StatusCode::OK, etag(&todo), Json(todo)))
 }
 
 #[utoipa::path(
     delete,
     path = "/todos/{id}",
     responses(
-        (status = 200, description = "todo successfully deleted", body = Todo),
-        (status = NO_CONTENT, description = "todo not deleted"),
-        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error")
+        (status = NO_CONTENT, description = "todo successfully deleted"),
+        (status = NOT_FOUND, description = "todo not found"),
+        (status = PRECONDITION_FAILED, description = "todo changed since the `If-Match` ETag"),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error"),
+        (status = FORBIDDEN, description = "Role in the shared list is too low")
     ),
     params(
         ("id" = i32, Path, description = "todo id"),
+        ("If-Match" = Option<String>, Header, description = "ETag the deletion is based on"),
     )
 )]
-pub async fn delete<T: TodoRepositoryTrait>(
+pub async fn delete<T: TodoRepositoryTrait, M: MemberRepositoryTrait>(
+    user: AuthUser,
     Path(id): Path<i32>,
-    State(state): State<TodoDependency<TodoService<TodoRepositoryForDb>, TodoRepositoryForDb>>,
-) -> StatusCode {
+    if_match: IfMatch,
+    State(state): State<TodoState<T, M>>,
+) -> Result<StatusCode, TodoError> {
     state
         .todo_service
-        .delete(id)
+        .owned_by(user.id)
+        .in_workspace(user.workspace_id)
+        .delete(id, if_match.0)
         .await
-        .map(|_| StatusCode::NO_CONTENT)
-        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+        .map_err(|e| if_match.precondition(e))?;
+    Ok(StatusCode::NO_CONTENT)
 }
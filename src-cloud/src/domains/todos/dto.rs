@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use shared::todos::error::TodoError;
+use shared::todos::model::{EditScope, TodoCursor, TodoQuery, TodoSort};
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FindAllQuery {
+    /// defaults to `created_at`
+    pub sort: Option<TodoSort>,
+    /// list id
+    pub list: Option<i32>,
+    /// comma separated label ids, only todos carrying all of them are returned
+    pub labels: Option<String>,
+    pub completed: Option<bool>,
+    /// case insensitive substring of the text
+    pub text: Option<String>,
+    /// due at or after
+    pub due_from: Option<DateTime<Utc>>,
+    /// due before
+    pub due_to: Option<DateTime<Utc>>,
+    /// created at or after
+    pub created_from: Option<DateTime<Utc>>,
+    /// created before
+    pub created_to: Option<DateTime<Utc>>,
+    /// page size, 50 by default and 200 at most
+    pub limit: Option<u32>,
+    /// `meta.next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+impl TryFrom<FindAllQuery> for TodoQuery {
+    type Error = TodoError;
+
+    fn try_from(query: FindAllQuery) -> Result<Self, Self::Error> {
+        let labels = query
+            .labels
+            .iter()
+            .flat_map(|labels| labels.split(','))
+            .filter(|id| !id.trim().is_empty())
+            .map(|id| {
+                id.trim()
+                    .parse::<i32>()
+                    .map_err(|_| TodoError::Validation(format!("invalid label id: {id}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let cursor = query
+            .cursor
+            .map(|cursor| cursor.parse::<TodoCursor>())
+            .transpose()
+            .map_err(TodoError::Validation)?;
+
+        Ok(TodoQuery {
+            sort: query.sort.unwrap_or_default(),
+            list_id: query.list,
+            labels,
+            completed: query.completed,
+            text: query.text.filter(|text| !text.is_empty()),
+            due_from: query.due_from,
+            due_to: query.due_to,
+            created_from: query.created_from,
+            created_to: query.created_to,
+            cursor,
+            limit: query.limit,
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// words to look for, todos holding all of them are returned
+    pub q: String,
+    /// number of hits, 50 by default and 200 at most
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoExpand {
+    /// nests the subtasks under `children`
+    Children,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FindQuery {
+    pub expand: Option<TodoExpand>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UpdateQuery {
+    /// occurrences of a recurring todo to update, defaults to `this`
+    pub scope: Option<EditScope>,
+}
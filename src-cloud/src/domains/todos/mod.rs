@@ -1,3 +1,4 @@
 pub mod controller;
 pub mod dependency;
+pub mod dto;
 pub mod route;
@@ -2,23 +2,23 @@ use axum::{
     routing::{get, post},
     Router,
 };
-use sqlx::PgPool;
 
-use shared::todos::repository::TodoRepositoryForDb;
+use shared::members::repository::MemberRepositoryTrait;
+use shared::todos::repository::TodoRepositoryTrait;
 use shared::todos::service::TodoService;
 
 use super::controller;
 use super::dependency::TodoDependency;
 
-// TODO: change pool type for any db. is it better way to input pool for routes? injesting state is
-// more better..?
-// injesting state itself is more flexible for changing repository impl
-// TODO: probably specifying TodoRepositoryForDb is not correct in terms of more general coding
-pub fn routes(pool: PgPool) -> Router {
-    let todo_repository = TodoRepositoryForDb::new(pool);
+pub fn routes<T, M>(todo_repository: T, member_repository: M) -> Router
+where
+    T: TodoRepositoryTrait,
+    M: MemberRepositoryTrait,
+{
     let dependency = TodoDependency {
         // TODO: replace w/ Arc
-        todo_service: TodoService::new(todo_repository.clone()),
+        todo_service: TodoService::new(todo_repository.clone(), member_repository)
+            .auto_complete_parents(true),
         todo_repository,
     };
     Router::new()
@@ -27,15 +27,17 @@ pub fn routes(pool: PgPool) -> Router {
             Router::new()
                 .route(
                     "/",
-                    post(controller::create::<TodoRepositoryForDb>)
-                        .get(controller::find_all::<TodoRepositoryForDb>),
+                    post(controller::create::<T, M>).get(controller::find_all::<T, M>),
                 )
                 .route(
                     "/:id",
-                    get(controller::find::<TodoRepositoryForDb>)
-                        .delete(controller::delete::<TodoRepositoryForDb>)
-                        .patch(controller::update::<TodoRepositoryForDb>),
-                ),
+                    get(controller::find::<T, M>)
+                        .delete(controller::delete::<T, M>)
+                        .patch(controller::update::<T, M>),
+                )
+                .route("/:id/move", post(controller::reorder::<T, M>))
+                .route("/search", get(controller::search::<T, M>))
+                .route("/series/:series_id", get(controller::find_series::<T, M>)),
         )
         .with_state(dependency)
 }
@@ -0,0 +1,77 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use utoipa;
+
+use shared::tokens::error::ApiTokenError;
+use shared::tokens::model::CreateApiToken;
+use shared::tokens::repository::ApiTokenRepositoryTrait;
+use shared::tokens::service::{ApiTokenService, ApiTokenServiceTrait};
+
+use super::dependency::ApiTokenDependency;
+use crate::auth::AuthUser;
+use crate::domains::todos::controller::ValidatedJson;
+
+type ApiTokenState<K> = ApiTokenDependency<ApiTokenService<K>>;
+
+#[utoipa::path(
+    post,
+    path = "/tokens",
+    request_body = CreateApiToken,
+    responses(
+        (status = CREATED, description = "Created ApiToken successfully, with its secret", body = NewApiToken),
+        (status = BAD_REQUEST, description = "ApiToken is invalid"),
+        (status = FORBIDDEN, description = "Authenticated with an API token")
+    )
+)]
+pub async fn create<K: ApiTokenRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<ApiTokenState<K>>,
+    ValidatedJson(payload): ValidatedJson<CreateApiToken>,
+) -> Result<impl IntoResponse, ApiTokenError> {
+    user.require_session()?;
+    let token = state.token_service.create(user.id, payload).await?;
+    Ok((StatusCode::CREATED, Json(token)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tokens",
+    responses(
+        (status = 200, description = "ApiTokens of the user, revoked ones included", body = Vec<ApiToken>),
+        (status = FORBIDDEN, description = "Authenticated with an API token")
+    )
+)]
+pub async fn find_all<K: ApiTokenRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<ApiTokenState<K>>,
+) -> Result<impl IntoResponse, ApiTokenError> {
+    user.require_session()?;
+    let tokens = state.token_service.all(user.id).await?;
+    Ok((StatusCode::OK, Json(tokens)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/tokens/{id}",
+    responses(
+        (status = NO_CONTENT, description = "Revoked ApiToken successfully"),
+        (status = NOT_FOUND, description = "ApiToken not found"),
+        (status = FORBIDDEN, description = "Authenticated with an API token")
+    ),
+    params(
+        ("id" = i32, Path, description = "api token id"),
+    )
+)]
+pub async fn revoke<K: ApiTokenRepositoryTrait>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    State(state): State<ApiTokenState<K>>,
+) -> Result<impl IntoResponse, ApiTokenError> {
+    user.require_session()?;
+    state.token_service.revoke(user.id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
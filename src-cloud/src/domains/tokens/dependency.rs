@@ -0,0 +1,9 @@
+use shared::tokens::service::ApiTokenServiceTrait;
+
+#[derive(Clone)]
+pub struct ApiTokenDependency<KS>
+where
+    KS: ApiTokenServiceTrait,
+{
+    pub token_service: KS,
+}
@@ -0,0 +1,23 @@
+use axum::{
+    routing::{delete, get},
+    Router,
+};
+
+use shared::tokens::repository::ApiTokenRepositoryTrait;
+use shared::tokens::service::ApiTokenService;
+
+use super::controller;
+use super::dependency::ApiTokenDependency;
+
+pub fn routes<K: ApiTokenRepositoryTrait>(token_repository: K) -> Router {
+    let dependency = ApiTokenDependency {
+        token_service: ApiTokenService::new(token_repository),
+    };
+    Router::new()
+        .route(
+            "/tokens",
+            get(controller::find_all::<K>).post(controller::create::<K>),
+        )
+        .route("/tokens/:id", delete(controller::revoke::<K>))
+        .with_state(dependency)
+}
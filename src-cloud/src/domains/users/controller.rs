@@ -0,0 +1,139 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Extension, Json};
+use utoipa;
+
+use shared::users::error::UserError;
+use shared::users::model::{ChangePassword, Credentials};
+use shared::users::repository::UserRepositoryTrait;
+use shared::users::service::{UserService, UserServiceTrait};
+
+use super::dependency::UserDependency;
+use super::dto::RefreshToken;
+use crate::auth::{AuthKeys, AuthUser, TokenKind};
+use crate::domains::todos::controller::ValidatedJson;
+
+type UserState<U> = UserDependency<UserService<U>>;
+
+#[utoipa::path(
+    post,
+    path = "/users",
+    request_body = Credentials,
+    responses(
+        (status = CREATED, description = "Registered User successfully", body = User),
+        (status = BAD_REQUEST, description = "Email or password is invalid"),
+        (status = CONFLICT, description = "Email is already taken")
+    ),
+    security(())
+)]
+pub async fn register<U: UserRepositoryTrait>(
+    State(state): State<UserState<U>>,
+    ValidatedJson(payload): ValidatedJson<Credentials>,
+) -> Result<impl IntoResponse, UserError> {
+    let user = state.user_service.register(payload).await?;
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/me",
+    responses(
+        (status = 200, description = "The authenticated User", body = User),
+        (status = UNAUTHORIZED, description = "Access token is missing or invalid")
+    )
+)]
+pub async fn me<U: UserRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<UserState<U>>,
+) -> Result<impl IntoResponse, UserError> {
+    let user = state.user_service.find(user.id).await?;
+    Ok((StatusCode::OK, Json(user)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    request_body = Credentials,
+    responses(
+        (status = 200, description = "Logged in", body = TokenPair),
+        (status = UNAUTHORIZED, description = "Email or password is wrong")
+    ),
+    security(())
+)]
+pub async fn login<U: UserRepositoryTrait>(
+    State(state): State<UserState<U>>,
+    Extension(keys): Extension<AuthKeys>,
+    Json(payload): Json<Credentials>,
+) -> Result<impl IntoResponse, UserError> {
+    let user = state.user_service.login(payload).await?;
+    let version = state.user_service.token_version(user.id).await?;
+    Ok((StatusCode::OK, Json(keys.issue(user.id, version)?)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    request_body = RefreshToken,
+    responses(
+        (status = 200, description = "New tokens", body = TokenPair),
+        (status = UNAUTHORIZED, description = "Refresh token is invalid, expired or revoked")
+    ),
+    security(())
+)]
+pub async fn refresh<U: UserRepositoryTrait>(
+    State(state): State<UserState<U>>,
+    Extension(keys): Extension<AuthKeys>,
+    Json(payload): Json<RefreshToken>,
+) -> Result<impl IntoResponse, UserError> {
+    let (id, version) = keys.verify(&payload.refresh_token, TokenKind::Refresh)?;
+    // the user may be gone since the token was issued
+    let current = state
+        .user_service
+        .token_version(id)
+        .await
+        .map_err(|e| match e {
+            UserError::NotFound(_) => UserError::Unauthorized("the user doesn't exist".to_string()),
+            e => e,
+        })?;
+    if version != current {
+        return Err(UserError::Unauthorized(
+            "the refresh token was revoked".to_string(),
+        ));
+    }
+    Ok((StatusCode::OK, Json(keys.issue(id, current)?)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    responses(
+        (status = NO_CONTENT, description = "Revoked every refresh token of the User"),
+        (status = UNAUTHORIZED, description = "Access token is missing or invalid")
+    )
+)]
+pub async fn logout<U: UserRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<UserState<U>>,
+) -> Result<impl IntoResponse, UserError> {
+    state.user_service.logout(user.id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/me/password",
+    request_body = ChangePassword,
+    responses(
+        (status = NO_CONTENT, description = "Changed the password and revoked every refresh token"),
+        (status = BAD_REQUEST, description = "New password is invalid"),
+        (status = UNAUTHORIZED, description = "Current password is wrong, or not authenticated with a session access token")
+    )
+)]
+pub async fn change_password<U: UserRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<UserState<U>>,
+    ValidatedJson(payload): ValidatedJson<ChangePassword>,
+) -> Result<impl IntoResponse, UserError> {
+    user.require_session()
+        .map_err(|e| UserError::Unauthorized(e.to_string()))?;
+    state.user_service.change_password(user.id, payload).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,9 @@
+use shared::users::service::UserServiceTrait;
+
+#[derive(Clone)]
+pub struct UserDependency<US>
+where
+    US: UserServiceTrait,
+{
+    pub user_service: US,
+}
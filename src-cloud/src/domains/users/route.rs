@@ -0,0 +1,24 @@
+use axum::{
+    routing::{get, post, put},
+    Router,
+};
+
+use shared::users::repository::UserRepositoryTrait;
+use shared::users::service::UserService;
+
+use super::controller;
+use super::dependency::UserDependency;
+
+pub fn routes<U: UserRepositoryTrait>(user_repository: U) -> Router {
+    let dependency = UserDependency {
+        user_service: UserService::new(user_repository),
+    };
+    Router::new()
+        .route("/users", post(controller::register::<U>))
+        .route("/users/me", get(controller::me::<U>))
+        .route("/users/me/password", put(controller::change_password::<U>))
+        .route("/auth/login", post(controller::login::<U>))
+        .route("/auth/refresh", post(controller::refresh::<U>))
+        .route("/auth/logout", post(controller::logout::<U>))
+        .with_state(dependency)
+}
@@ -0,0 +1,93 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use utoipa;
+
+use shared::members::model::AcceptInvitation;
+use shared::workspaces::error::WorkspaceError;
+use shared::workspaces::model::{CreateWorkspace, CreateWorkspaceInvitation};
+use shared::workspaces::repository::WorkspaceRepositoryTrait;
+use shared::workspaces::service::{WorkspaceService, WorkspaceServiceTrait};
+
+use super::dependency::WorkspaceDependency;
+use crate::auth::AuthUser;
+use crate::domains::todos::controller::ValidatedJson;
+
+type WorkspaceState<W> = WorkspaceDependency<WorkspaceService<W>>;
+
+#[utoipa::path(
+    post,
+    path = "/workspaces",
+    request_body = CreateWorkspace,
+    responses(
+        (status = CREATED, description = "Created Workspace successfully, the user is its first owner", body = Workspace),
+        (status = BAD_REQUEST, description = "Workspace is invalid")
+    )
+)]
+pub async fn create<W: WorkspaceRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<WorkspaceState<W>>,
+    ValidatedJson(payload): ValidatedJson<CreateWorkspace>,
+) -> Result<impl IntoResponse, WorkspaceError> {
+    let workspace = state.workspace_service.create(user.id, payload).await?;
+    Ok((StatusCode::CREATED, Json(workspace)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/workspaces",
+    responses(
+        (status = 200, description = "Workspaces of the user, their personal one first, then in the order they joined them", body = Vec<Workspace>)
+    )
+)]
+pub async fn find_all<W: WorkspaceRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<WorkspaceState<W>>,
+) -> Result<impl IntoResponse, WorkspaceError> {
+    let workspaces = state.workspace_service.all(user.id).await?;
+    Ok((StatusCode::OK, Json(workspaces)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/workspaces/{id}/invitations",
+    request_body = CreateWorkspaceInvitation,
+    responses(
+        (status = CREATED, description = "Created Invitation successfully, with its token", body = NewWorkspaceInvitation),
+        (status = FORBIDDEN, description = "Not an admin of the workspace, or inviting a higher role"),
+        (status = NOT_FOUND, description = "Workspace not found")
+    ),
+    params(
+        ("id" = i32, Path, description = "workspace id"),
+    )
+)]
+pub async fn invite<W: WorkspaceRepositoryTrait>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    State(state): State<WorkspaceState<W>>,
+    Json(payload): Json<CreateWorkspaceInvitation>,
+) -> Result<impl IntoResponse, WorkspaceError> {
+    let invitation = state.workspace_service.invite(user.id, id, payload).await?;
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/workspaces/invitations/accept",
+    request_body = AcceptInvitation,
+    responses(
+        (status = 200, description = "Joined the workspace of the invitation", body = WorkspaceMember),
+        (status = BAD_REQUEST, description = "Invitation is unknown, expired or already accepted")
+    )
+)]
+pub async fn accept<W: WorkspaceRepositoryTrait>(
+    user: AuthUser,
+    State(state): State<WorkspaceState<W>>,
+    Json(payload): Json<AcceptInvitation>,
+) -> Result<impl IntoResponse, WorkspaceError> {
+    let member = state.workspace_service.accept(user.id, payload).await?;
+    Ok((StatusCode::OK, Json(member)))
+}
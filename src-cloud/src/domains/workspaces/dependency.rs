@@ -0,0 +1,9 @@
+use shared::workspaces::service::WorkspaceServiceTrait;
+
+#[derive(Clone)]
+pub struct WorkspaceDependency<WS>
+where
+    WS: WorkspaceServiceTrait,
+{
+    pub workspace_service: WS,
+}
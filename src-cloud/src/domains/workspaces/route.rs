@@ -0,0 +1,27 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+use shared::workspaces::repository::WorkspaceRepositoryTrait;
+use shared::workspaces::service::WorkspaceService;
+
+use super::controller;
+use super::dependency::WorkspaceDependency;
+
+pub fn routes<W: WorkspaceRepositoryTrait>(workspace_repository: W) -> Router {
+    let dependency = WorkspaceDependency {
+        workspace_service: WorkspaceService::new(workspace_repository),
+    };
+    Router::new()
+        .route(
+            "/workspaces",
+            get(controller::find_all::<W>).post(controller::create::<W>),
+        )
+        .route("/workspaces/:id/invitations", post(controller::invite::<W>))
+        .route(
+            "/workspaces/invitations/accept",
+            post(controller::accept::<W>),
+        )
+        .with_state(dependency)
+}
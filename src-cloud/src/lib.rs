@@ -1 +1,4 @@
+pub mod auth;
+pub mod cors;
 pub mod domains;
+pub mod routes;
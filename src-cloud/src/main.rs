@@ -1,11 +1,11 @@
-mod domains;
-mod routes;
-
 use dotenv::dotenv;
 use sqlx::PgPool;
 
-use crate::routes::create_app;
+use my_todo::auth::AuthKeys;
+use my_todo::cors::CorsConfig;
+use my_todo::routes::create_app;
 
+/// Entry point on Shuttle, see `bin/server.rs` to host the app elsewhere.
 #[shuttle_runtime::main]
 async fn main(
     #[shuttle_shared_db::Postgres] pool: PgPool,
@@ -20,159 +20,781 @@ async fn main(
 
     dotenv().ok();
 
-    let app = create_app(pool, secrets);
+    let mut cors = CorsConfig::from_vars(|key| secrets.get(key));
+    // the deployed web app, configured before `CORS_ORIGINS` existed
+    if let Some(remote) = secrets.get("REMOTE") {
+        cors.origins.push(remote);
+    }
+    let cors = cors.layer().map_err(anyhow::Error::from)?;
+    let keys = AuthKeys::from_secret(secrets.get("JWT_SECRET")).map_err(anyhow::Error::from)?;
+    let app = create_app(pool, cors, keys);
 
     Ok(app.into())
 }
 
 // TODO: move to todos domain
-//#[cfg(test)]
-//mod test {
-//    use super::*;
-//    use crate::repositories::{test_utils::TodoRepositoryForMemory, CreateTodo, Todo};
-//    use axum::{
-//        body::{to_bytes, Body},
-//        http::{header, Method, Request, StatusCode},
-//        response::Response,
-//    };
-//    use tower::ServiceExt;
-//
-//    fn build_todo_req_with_json(path: &str, method: Method, json_body: String) -> Request<Body> {
-//        Request::builder()
-//            .uri(path)
-//            .method(method)
-//            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
-//            .body(Body::from(json_body))
-//            .unwrap()
-//    }
-//
-//    fn build_todo_req_with_empty(method: Method, path: &str) -> Request<Body> {
-//        Request::builder()
-//            .uri(path)
-//            .method(method)
-//            .body(Body::empty())
-//            .unwrap()
-//    }
-//
-//    async fn res_to_todo(res: Response) -> Todo {
-//        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
-//        let body: String = String::from_utf8(bytes.to_vec()).unwrap();
-//        let todo: Todo = serde_json::from_str(&body)
-//            .expect(&format!("cannot convert Todo instance. Body: {}", body));
-//        todo
-//    }
-//
-//    #[tokio::test]
-//    async fn should_return_hello_world() {
-//        let repository = TodoRepositoryForMemory::new();
-//        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
-//
-//        let res = create_app(repository).oneshot(req).await.unwrap();
-//
-//        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
-//
-//        let body: String = String::from_utf8(bytes.to_vec()).unwrap();
-//
-//        assert_eq!(body, "Hello, world!");
-//    }
-//
-//    #[tokio::test]
-//    async fn should_create_todo() {
-//        let expected = Todo::new(1, "should_return_created_todo".to_string());
-//
-//        let repository = TodoRepositoryForMemory::new();
-//        let req = build_todo_req_with_json(
-//            "/todos",
-//            Method::POST,
-//            r#"{ "text": "should_return_created_todo" }"#.to_string(),
-//        );
-//
-//        let res = create_app(repository).oneshot(req).await.unwrap();
-//
-//        let todo = res_to_todo(res).await;
-//        assert_eq!(expected, todo);
-//    }
-//
-//    #[tokio::test]
-//    async fn should_find_todo() {
-//        let expected = Todo::new(1, "should_find_todo".to_string());
-//
-//        let repository = TodoRepositoryForMemory::new();
-//
-//        repository
-//            .create(CreateTodo::new("should_find_todo".to_string()))
-//            .await
-//            .expect("failed to create a todo");
-//
-//        let req = build_todo_req_with_empty(Method::GET, "/todos/1");
-//
-//        let res = create_app(repository).oneshot(req).await.unwrap();
-//
-//        let todo = res_to_todo(res).await;
-//        assert_eq!(expected, todo);
-//    }
-//
-//    #[tokio::test]
-//    async fn should_get_all_todos() {
-//        let expected = Todo::new(1, "should_get_all_todos".to_string());
-//
-//        let repository = TodoRepositoryForMemory::new();
-//        repository
-//            .create(CreateTodo::new("should_get_all_todos".to_string()))
-//            .await
-//            .expect("failed to create a todo");
-//
-//        let req = build_todo_req_with_empty(Method::GET, "/todos");
-//
-//        let res = create_app(repository).oneshot(req).await.unwrap();
-//
-//        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
-//
-//        let body: String = String::from_utf8(bytes.to_vec()).unwrap();
-//
-//        let todo: Vec<Todo> = serde_json::from_str(&body)
-//            .expect(&format!("cannot convert Todo instance. body: {}", body));
-//
-//        assert_eq!(vec![expected], todo);
-//    }
-//
-//    #[tokio::test]
-//    async fn should_update_todo() {
-//        let expected = Todo::new(1, "should_update_todo".to_string());
-//
-//        let repository = TodoRepositoryForMemory::new();
-//
-//        repository
-//            .create(CreateTodo::new("before_update_todo".to_string()))
-//            .await
-//            .expect("failed to create a todo");
-//
-//        let req = build_todo_req_with_json(
-//            "/todos/1",
-//            Method::PATCH,
-//            r#"{
-//            "id": 1,
-//            "text": "should_update_todo",
-//            "completed": false
-//            }"#
-//            .to_string(),
-//        );
-//
-//        let res = create_app(repository).oneshot(req).await.unwrap();
-//        let todo = res_to_todo(res).await;
-//        assert_eq!(expected, todo);
-//    }
-//
-//    #[tokio::test]
-//    async fn should_delete_todo() {
-//        let repository = TodoRepositoryForMemory::new();
-//
-//        repository
-//            .create(CreateTodo::new("should_delete_todo".to_string()))
-//            .await
-//            .expect("failed to create a todo");
-//        let req = build_todo_req_with_empty(Method::DELETE, "/todos/1");
-//        let res = create_app(repository).oneshot(req).await.unwrap();
-//        assert_eq!(StatusCode::NO_CONTENT, res.status());
-//    }
-//}
+#[cfg(test)]
+mod test {
+    use axum::{
+        body::{to_bytes, Body},
+        http::{header, Method, Request, StatusCode},
+        response::Response,
+        Router,
+    };
+    use my_todo::auth::{AuthKeys, WORKSPACE_HEADER};
+    use my_todo::routes::app;
+    use shared::labels::repository::memory::LabelRepositoryForMemory;
+    use shared::lists::model::{CreateList, List};
+    use shared::lists::repository::{memory::ListRepositoryForMemory, ListRepositoryTrait};
+    use shared::members::repository::memory::MemberRepositoryForMemory;
+    use shared::progress::NoProgress;
+    use shared::sync::engine::SyncEngine;
+    use shared::sync::error::SyncError;
+    use shared::sync::model::{
+        ChangePage, DocumentRequest, DocumentResponse, PushRequest, PushResponse,
+    };
+    use shared::sync::outbox::{Outbox, TodoRepositoryWithOutbox};
+    use shared::sync::repository::memory::SyncStateRepositoryForMemory;
+    use shared::sync::transport::SyncTransportTrait;
+    use shared::todos::document::memory::TodoDocumentRepositoryForMemory;
+    use shared::todos::document::TodoRepositoryWithDocuments;
+    use shared::todos::model::{CreateTodo, Todo, TodoQuery, UpdateTodo};
+    use shared::todos::repository::{memory::TodoRepositoryForMemory, TodoRepositoryTrait};
+    use shared::tokens::repository::memory::ApiTokenRepositoryForMemory;
+    use shared::users::repository::memory::UserRepositoryForMemory;
+    use shared::workspaces::repository::memory::WorkspaceRepositoryForMemory;
+    use tower::ServiceExt;
+
+    /// Owner of the todos the requests are authorized for.
+    const USER_ID: i32 = 1;
+
+    /// The personal workspace of `USER_ID`, the first one made by the memory store of the app.
+    const WORKSPACE_ID: i32 = 1;
+
+    fn keys() -> AuthKeys {
+        AuthKeys::new(b"a secret long enough for the tests")
+    }
+
+    fn bearer(user_id: i32) -> String {
+        format!("Bearer {}", keys().issue(user_id, 0).unwrap().access_token)
+    }
+
+    fn create_app(repository: TodoRepositoryForMemory) -> Router {
+        app(
+            repository,
+            TodoDocumentRepositoryForMemory::new(),
+            LabelRepositoryForMemory::new(),
+            ListRepositoryForMemory::new(),
+            MemberRepositoryForMemory::new(),
+            UserRepositoryForMemory::new(),
+            ApiTokenRepositoryForMemory::new(),
+            WorkspaceRepositoryForMemory::new(),
+            keys(),
+        )
+    }
+
+    fn build_todo_req_with_json(path: &str, method: Method, json_body: String) -> Request<Body> {
+        Request::builder()
+            .uri(path)
+            .method(method)
+            .header(header::AUTHORIZATION, bearer(USER_ID))
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(json_body))
+            .unwrap()
+    }
+
+    fn build_todo_req_with_empty(method: Method, path: &str) -> Request<Body> {
+        Request::builder()
+            .uri(path)
+            .method(method)
+            .header(header::AUTHORIZATION, bearer(USER_ID))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    async fn res_to_body(res: Response) -> String {
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    async fn res_to_todo(res: Response) -> Todo {
+        let body = res_to_body(res).await;
+        serde_json::from_str(&body)
+            .unwrap_or_else(|_| panic!("cannot convert Todo instance. Body: {}", body))
+    }
+
+    /// `Todo::new` as created just now, which the server decides.
+    fn created(expected: Todo, todo: &Todo) -> Todo {
+        Todo {
+            created_at: todo.created_at,
+            ..expected
+        }
+    }
+
+    #[tokio::test]
+    async fn should_return_hello_world() {
+        let repository = TodoRepositoryForMemory::new();
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+
+        let res = create_app(repository).oneshot(req).await.unwrap();
+
+        assert_eq!(res_to_body(res).await, "Hello, world!");
+    }
+
+    #[tokio::test]
+    async fn should_create_todo() {
+        let expected = Todo::new(1, "should_return_created_todo".to_string());
+
+        let repository = TodoRepositoryForMemory::new();
+        let req = build_todo_req_with_json(
+            "/todos",
+            Method::POST,
+            r#"{ "text": "should_return_created_todo" }"#.to_string(),
+        );
+
+        let res = create_app(repository).oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        assert_eq!(res.headers()[header::ETAG], "\"1\"");
+
+        let todo = res_to_todo(res).await;
+        assert_eq!(created(expected, &todo), todo);
+    }
+
+    #[tokio::test]
+    async fn should_find_todo() {
+        let expected = Todo::new(1, "should_find_todo".to_string());
+
+        let repository = TodoRepositoryForMemory::new();
+
+        repository
+            .owned_by(USER_ID)
+            .in_workspace(WORKSPACE_ID)
+            .create(CreateTodo::new("should_find_todo".to_string()))
+            .await
+            .expect("failed to create a todo");
+
+        let req = build_todo_req_with_empty(Method::GET, "/todos/1");
+
+        let res = create_app(repository).oneshot(req).await.unwrap();
+
+        let todo = res_to_todo(res).await;
+        assert_eq!(created(expected, &todo), todo);
+    }
+
+    #[tokio::test]
+    async fn should_get_all_todos() {
+        let expected = Todo::new(1, "should_get_all_todos".to_string());
+
+        let repository = TodoRepositoryForMemory::new();
+        repository
+            .owned_by(USER_ID)
+            .in_workspace(WORKSPACE_ID)
+            .create(CreateTodo::new("should_get_all_todos".to_string()))
+            .await
+            .expect("failed to create a todo");
+
+        let req = build_todo_req_with_empty(Method::GET, "/todos");
+
+        let res = create_app(repository).oneshot(req).await.unwrap();
+
+        let body = res_to_body(res).await;
+        let mut page: serde_json::Value = serde_json::from_str(&body)
+            .unwrap_or_else(|_| panic!("cannot convert page. body: {}", body));
+        assert_eq!(page["meta"]["total"], 1);
+        let todos: Vec<Todo> = serde_json::from_value(page["data"].take())
+            .unwrap_or_else(|_| panic!("cannot convert Todo instance. body: {}", body));
+
+        assert_eq!(vec![created(expected, &todos[0])], todos);
+    }
+
+    #[tokio::test]
+    async fn should_update_todo() {
+        let expected = Todo {
+            version: 2,
+            ..Todo::new(1, "should_update_todo".to_string())
+        };
+
+        let repository = TodoRepositoryForMemory::new();
+
+        repository
+            .owned_by(USER_ID)
+            .in_workspace(WORKSPACE_ID)
+            .create(CreateTodo::new("before_update_todo".to_string()))
+            .await
+            .expect("failed to create a todo");
+
+        let req = build_todo_req_with_json(
+            "/todos/1",
+            Method::PATCH,
+            r#"{
+            "text": "should_update_todo",
+            "completed": false
+            }"#
+            .to_string(),
+        );
+
+        let res = create_app(repository).oneshot(req).await.unwrap();
+        let todo = res_to_todo(res).await;
+        assert_eq!(created(expected, &todo), todo);
+    }
+
+    #[tokio::test]
+    async fn should_reject_stale_if_match() {
+        let repository = TodoRepositoryForMemory::new();
+
+        repository
+            .owned_by(USER_ID)
+            .in_workspace(WORKSPACE_ID)
+            .create(CreateTodo::new("should_reject_stale_if_match".to_string()))
+            .await
+            .expect("failed to create a todo");
+        let mut req = build_todo_req_with_json(
+            "/todos/1",
+            Method::PATCH,
+            r#"{ "completed": true }"#.to_string(),
+        );
+        req.headers_mut()
+            .insert(header::IF_MATCH, "\"2\"".parse().unwrap());
+
+        let res = create_app(repository).oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::PRECONDITION_FAILED, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_delete_todo() {
+        let repository = TodoRepositoryForMemory::new();
+
+        repository
+            .owned_by(USER_ID)
+            .in_workspace(WORKSPACE_ID)
+            .create(CreateTodo::new("should_delete_todo".to_string()))
+            .await
+            .expect("failed to create a todo");
+        let req = build_todo_req_with_empty(Method::DELETE, "/todos/1");
+        let res = create_app(repository.clone()).oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+
+        let req = build_todo_req_with_empty(Method::GET, "/todos/1");
+        let res = create_app(repository).oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_require_a_token() {
+        let repository = TodoRepositoryForMemory::new();
+
+        let mut req = build_todo_req_with_empty(Method::GET, "/todos");
+        req.headers_mut().remove(header::AUTHORIZATION);
+        let res = create_app(repository.clone()).oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+
+        let mut req = build_todo_req_with_empty(Method::GET, "/todos");
+        req.headers_mut()
+            .insert(header::AUTHORIZATION, "Bearer nonsense".parse().unwrap());
+        let res = create_app(repository).oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_hide_todos_of_other_users() {
+        let repository = TodoRepositoryForMemory::new();
+        repository
+            .owned_by(USER_ID + 1)
+            .in_workspace(WORKSPACE_ID)
+            .create(CreateTodo::new(
+                "should_hide_todos_of_other_users".to_string(),
+            ))
+            .await
+            .expect("failed to create a todo");
+
+        let req = build_todo_req_with_empty(Method::GET, "/todos/1");
+        let res = create_app(repository).oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_register_login_and_refresh() {
+        let app = create_app(TodoRepositoryForMemory::new());
+        let credentials =
+            r#"{ "email": "alice@example.com", "password": "correct horse" }"#.to_string();
+
+        let req = build_todo_req_with_json("/users", Method::POST, credentials.clone());
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+
+        let mut req = build_todo_req_with_json("/auth/login", Method::POST, credentials);
+        req.headers_mut().remove(header::AUTHORIZATION);
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+        let tokens: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+
+        let req = build_todo_req_with_json(
+            "/auth/refresh",
+            Method::POST,
+            serde_json::json!({ "refresh_token": tokens["refresh_token"] }).to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+        let tokens: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+
+        let mut req = build_todo_req_with_empty(Method::GET, "/users/me");
+        let token = format!("Bearer {}", tokens["access_token"].as_str().unwrap());
+        req.headers_mut()
+            .insert(header::AUTHORIZATION, token.parse().unwrap());
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+        let user: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+        assert_eq!(user["email"], "alice@example.com");
+
+        // changing the password, like logging out, revokes the refresh tokens issued before
+        let refresh = |tokens: &serde_json::Value| {
+            build_todo_req_with_json(
+                "/auth/refresh",
+                Method::POST,
+                serde_json::json!({ "refresh_token": tokens["refresh_token"] }).to_string(),
+            )
+        };
+        let req = build_todo_req_with_json(
+            "/users/me/password",
+            Method::PUT,
+            r#"{ "current_password": "correct horse", "new_password": "battery staple" }"#
+                .to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+        let res = app.clone().oneshot(refresh(&tokens)).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+
+        let credentials =
+            r#"{ "email": "alice@example.com", "password": "battery staple" }"#.to_string();
+        let req = build_todo_req_with_json("/auth/login", Method::POST, credentials);
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+        let tokens: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+
+        let req = build_todo_req_with_empty(Method::POST, "/auth/logout");
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+        let res = app.oneshot(refresh(&tokens)).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_authenticate_with_api_tokens() {
+        let app = create_app(TodoRepositoryForMemory::new());
+        let with_token = |mut req: Request<Body>, secret: &str| {
+            let token = format!("Bearer {secret}");
+            req.headers_mut()
+                .insert(header::AUTHORIZATION, token.parse().unwrap());
+            req
+        };
+
+        let req = build_todo_req_with_json(
+            "/tokens",
+            Method::POST,
+            r#"{ "name": "backup", "scope": "read" }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        let token: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+        let secret = token["secret"].as_str().unwrap();
+
+        for (req, status) in [
+            (
+                build_todo_req_with_empty(Method::GET, "/todos"),
+                StatusCode::OK,
+            ),
+            (
+                build_todo_req_with_json(
+                    "/todos",
+                    Method::POST,
+                    r#"{ "text": "read only" }"#.to_string(),
+                ),
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                build_todo_req_with_empty(Method::GET, "/tokens"),
+                StatusCode::FORBIDDEN,
+            ),
+        ] {
+            let res = app.clone().oneshot(with_token(req, secret)).await.unwrap();
+            assert_eq!(status, res.status());
+        }
+
+        let path = format!("/tokens/{}", token["id"]);
+        let req = build_todo_req_with_empty(Method::DELETE, &path);
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+
+        let req = build_todo_req_with_empty(Method::GET, "/todos");
+        let res = app.oneshot(with_token(req, secret)).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_share_lists_through_invitations() {
+        const BOB: i32 = USER_ID + 1;
+        let app = create_app(TodoRepositoryForMemory::new());
+        let as_bob = |mut req: Request<Body>| {
+            req.headers_mut()
+                .insert(header::AUTHORIZATION, bearer(BOB).parse().unwrap());
+            req
+        };
+        // shared lists are seen from the workspace they were shared from
+        let in_workspace = |mut req: Request<Body>| {
+            req.headers_mut()
+                .insert(WORKSPACE_HEADER, WORKSPACE_ID.to_string().parse().unwrap());
+            as_bob(req)
+        };
+
+        let req = build_todo_req_with_json(
+            "/lists",
+            Method::POST,
+            r#"{ "name": "groceries" }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        let list: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+        let path = format!("/lists/{}", list["id"]);
+
+        // only members invite, nobody invites themselves
+        let req = build_todo_req_with_json(
+            &format!("{path}/invitations"),
+            Method::POST,
+            r#"{ "role": "owner" }"#.to_string(),
+        );
+        let res = app.clone().oneshot(as_bob(req)).await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+
+        let req = build_todo_req_with_json(
+            &format!("{path}/invitations"),
+            Method::POST,
+            r#"{ "role": "editor" }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        let invitation: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+        let accept = format!(r#"{{ "token": {} }}"#, invitation["token"]);
+
+        let req = build_todo_req_with_empty(Method::GET, &path);
+        let res = app.clone().oneshot(as_bob(req)).await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+
+        for status in [StatusCode::OK, StatusCode::BAD_REQUEST] {
+            let req = build_todo_req_with_json("/invitations/accept", Method::POST, accept.clone());
+            let res = app.clone().oneshot(as_bob(req)).await.unwrap();
+            assert_eq!(status, res.status());
+        }
+
+        let req = build_todo_req_with_empty(Method::GET, &path);
+        let res = app.clone().oneshot(as_bob(req)).await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+        let req = build_todo_req_with_empty(Method::GET, &path);
+        let res = app.clone().oneshot(in_workspace(req)).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+        let req = build_todo_req_with_empty(Method::GET, &format!("{path}/members"));
+        let res = app.clone().oneshot(in_workspace(req)).await.unwrap();
+        let members: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+        assert_eq!(members.as_array().unwrap().len(), 2);
+
+        // editors change todos, not the list
+        let req =
+            build_todo_req_with_json(&path, Method::PATCH, r#"{ "name": "mine" }"#.to_string());
+        let res = app.clone().oneshot(in_workspace(req)).await.unwrap();
+        assert_eq!(StatusCode::FORBIDDEN, res.status());
+
+        let req = build_todo_req_with_empty(Method::DELETE, &format!("{path}/members/{BOB}"));
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+        let req = build_todo_req_with_empty(Method::GET, &path);
+        let res = app.oneshot(in_workspace(req)).await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_isolate_workspaces() {
+        const BOB: i32 = USER_ID + 1;
+        let app = create_app(TodoRepositoryForMemory::new());
+        let with = |mut req: Request<Body>, user_id: i32, workspace: Option<&str>| {
+            req.headers_mut()
+                .insert(header::AUTHORIZATION, bearer(user_id).parse().unwrap());
+            if let Some(workspace) = workspace {
+                req.headers_mut()
+                    .insert(WORKSPACE_HEADER, workspace.parse().unwrap());
+            }
+            req
+        };
+        let todos = |res: Response| async {
+            let page: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+            page["data"].as_array().unwrap().len()
+        };
+
+        let req = build_todo_req_with_json(
+            "/workspaces",
+            Method::POST,
+            r#"{ "name": "acme" }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        let acme: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+        let acme = acme["id"].to_string();
+
+        // the personal workspace is the one used without the header
+        let req = build_todo_req_with_json(
+            "/todos",
+            Method::POST,
+            r#"{ "text": "personal only" }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        let todo = res_to_todo(res).await;
+        let path = format!("/todos/{}", todo.id);
+
+        for (workspace, status, count) in [
+            (None, StatusCode::OK, 1),
+            (Some(WORKSPACE_ID.to_string()), StatusCode::OK, 1),
+            (Some(acme.clone()), StatusCode::NOT_FOUND, 0),
+        ] {
+            let req = build_todo_req_with_empty(Method::GET, &path);
+            let res = app
+                .clone()
+                .oneshot(with(req, USER_ID, workspace.as_deref()))
+                .await
+                .unwrap();
+            assert_eq!(status, res.status());
+            let req = build_todo_req_with_empty(Method::GET, "/todos");
+            let res = app
+                .clone()
+                .oneshot(with(req, USER_ID, workspace.as_deref()))
+                .await
+                .unwrap();
+            assert_eq!(count, todos(res).await);
+        }
+
+        // labels and lists are those of the workspace too
+        let req = build_todo_req_with_json(
+            "/labels",
+            Method::POST,
+            r#"{ "name": "urgent" }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        for (workspace, count) in [(None, 1), (Some(acme.as_str()), 0)] {
+            let req = build_todo_req_with_empty(Method::GET, "/labels");
+            let res = app
+                .clone()
+                .oneshot(with(req, USER_ID, workspace))
+                .await
+                .unwrap();
+            let labels: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+            assert_eq!(count, labels.as_array().unwrap().len());
+        }
+
+        // only members work in a workspace, and they join it by invitation
+        for (workspace, status) in [
+            (acme.as_str(), StatusCode::NOT_FOUND),
+            ("acme", StatusCode::BAD_REQUEST),
+        ] {
+            let req = build_todo_req_with_empty(Method::GET, "/todos");
+            let res = app
+                .clone()
+                .oneshot(with(req, BOB, Some(workspace)))
+                .await
+                .unwrap();
+            assert_eq!(status, res.status());
+        }
+        let invite = build_todo_req_with_json(
+            &format!("/workspaces/{acme}/invitations"),
+            Method::POST,
+            "{}".to_string(),
+        );
+        let res = app.clone().oneshot(with(invite, BOB, None)).await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+        let req = build_todo_req_with_json(
+            &format!("/workspaces/{acme}/invitations"),
+            Method::POST,
+            "{}".to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        let invitation: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+        let req = build_todo_req_with_json(
+            "/workspaces/invitations/accept",
+            Method::POST,
+            format!(r#"{{ "token": {} }}"#, invitation["token"]),
+        );
+        let res = app.clone().oneshot(with(req, BOB, None)).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+        let req = build_todo_req_with_empty(Method::GET, "/todos");
+        let res = app.oneshot(with(req, BOB, Some(&acme))).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+    }
+
+    /// The sync and list routes of `app`, as a client reaches them.
+    #[derive(Clone)]
+    struct RouterTransport {
+        app: Router,
+    }
+
+    impl RouterTransport {
+        /// The body of the response to `req`, unless it failed.
+        async fn send_ok(&self, req: Request<Body>) -> Result<String, SyncError> {
+            let res = self.app.clone().oneshot(req).await.unwrap();
+            let status = res.status();
+            let body = res_to_body(res).await;
+            if !status.is_success() {
+                return Err(SyncError::Remote(format!("{status}: {body}")));
+            }
+            Ok(body)
+        }
+
+        async fn send<R: serde::de::DeserializeOwned>(
+            &self,
+            req: Request<Body>,
+        ) -> Result<R, SyncError> {
+            let body = self.send_ok(req).await?;
+            Ok(serde_json::from_str(&body).unwrap())
+        }
+    }
+
+    #[axum::async_trait]
+    impl SyncTransportTrait for RouterTransport {
+        async fn pull(&self, cursor: i64, limit: u32) -> Result<ChangePage, SyncError> {
+            let path = format!("/sync?cursor={cursor}&limit={limit}");
+            self.send(build_todo_req_with_empty(Method::GET, &path))
+                .await
+        }
+
+        async fn push(&self, request: &PushRequest) -> Result<PushResponse, SyncError> {
+            let body = serde_json::to_string(request).unwrap();
+            self.send(build_todo_req_with_json("/sync", Method::POST, body))
+                .await
+        }
+
+        async fn exchange(&self, request: &DocumentRequest) -> Result<DocumentResponse, SyncError> {
+            let body = serde_json::to_string(request).unwrap();
+            self.send(build_todo_req_with_json(
+                "/sync/documents",
+                Method::POST,
+                body,
+            ))
+            .await
+        }
+
+        async fn lists(&self) -> Result<Vec<List>, SyncError> {
+            self.send(build_todo_req_with_empty(
+                Method::GET,
+                "/lists?archived=true",
+            ))
+            .await
+        }
+
+        async fn create_list(&self, payload: &CreateList) -> Result<List, SyncError> {
+            let body = serde_json::to_string(payload).unwrap();
+            self.send(build_todo_req_with_json("/lists", Method::POST, body))
+                .await
+        }
+
+        async fn delete_list(&self, id: i32) -> Result<(), SyncError> {
+            let path = format!("/lists/{id}?todos=move_to_inbox");
+            self.send_ok(build_todo_req_with_empty(Method::DELETE, &path))
+                .await
+                .map(drop)
+        }
+    }
+
+    #[tokio::test]
+    async fn should_sync_a_local_store() {
+        let app = create_app(TodoRepositoryForMemory::new());
+        let store = TodoRepositoryForMemory::new();
+        let lists = ListRepositoryForMemory::new();
+        let documents = TodoDocumentRepositoryForMemory::new();
+        let outbox = Outbox::new(SyncStateRepositoryForMemory::new());
+        let local = TodoRepositoryWithDocuments::new(
+            TodoRepositoryWithOutbox::new(store.clone(), outbox.clone()),
+            documents.clone(),
+        );
+        let engine = SyncEngine::new(
+            store,
+            lists.clone(),
+            documents,
+            outbox,
+            RouterTransport { app: app.clone() },
+        );
+
+        let offline = local
+            .create(CreateTodo::new("offline".to_string()))
+            .await
+            .unwrap();
+        let req = build_todo_req_with_json(
+            "/lists",
+            Method::POST,
+            r#"{ "name": "Groceries" }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        let groceries: List = serde_json::from_str(&res_to_body(res).await).unwrap();
+        let req = build_todo_req_with_json(
+            "/todos",
+            Method::POST,
+            format!(r#"{{ "text": "online", "list_id": {} }}"#, groceries.id),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+
+        let report = engine.sync(&NoProgress).await.unwrap();
+        assert_eq!((report.pushed, report.pulled), (1, 1));
+        // the list is made locally along its todo
+        let online = local
+            .all(&TodoQuery::default())
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|todo| todo.text == "online")
+            .unwrap();
+        assert_eq!(lists.find(online.list_id).await.unwrap().name, "Groceries");
+        let mut texts = Vec::from_iter(
+            local
+                .all(&TodoQuery::default())
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|todo| todo.text),
+        );
+        texts.sort();
+        assert_eq!(texts, ["offline", "online"]);
+
+        let req = build_todo_req_with_empty(Method::GET, "/todos");
+        let res = app.clone().oneshot(req).await.unwrap();
+        let page: serde_json::Value = serde_json::from_str(&res_to_body(res).await).unwrap();
+        let todos = page["data"].as_array().unwrap();
+        assert_eq!(todos.len(), 2);
+
+        // the text edited on both sides keeps both edits
+        let id = todos.iter().find(|todo| todo["text"] == "offline").unwrap()["id"].clone();
+        let req = build_todo_req_with_json(
+            &format!("/todos/{id}"),
+            Method::PATCH,
+            r#"{ "text": "the offline todo" }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+        local
+            .update(
+                offline.id,
+                UpdateTodo {
+                    text: Some("offline, edited".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        engine.sync(&NoProgress).await.unwrap();
+        let merged = "the offline todo, edited";
+        assert_eq!(local.find(offline.id).await.unwrap().text, merged);
+        let req = build_todo_req_with_empty(Method::GET, &format!("/todos/{id}"));
+        let res = app.oneshot(req).await.unwrap();
+        let todo: Todo = serde_json::from_str(&res_to_body(res).await).unwrap();
+        assert_eq!(todo.text, merged);
+    }
+}
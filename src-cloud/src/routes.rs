@@ -1,19 +1,67 @@
-use axum::{http::HeaderValue, routing::get, Json, Router};
-use hyper::header::CONTENT_TYPE;
+use axum::{middleware, routing::get, Extension, Json, Router};
 use sqlx::PgPool;
-use tower_http::cors::{Any, CorsLayer};
-use utoipa::OpenApi;
+use tower_http::cors::CorsLayer;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
 use utoipa_swagger_ui::SwaggerUi;
 
+use crate::auth::{authenticate, AuthKeys, Authenticator};
 use crate::domains;
-use shared::todos::model::{CreateTodo, Todo, UpdateTodo};
+use crate::domains::todos::dto::TodoExpand;
+use crate::domains::users::dto::{RefreshToken, TokenPair};
+use shared::labels::model::{CreateLabel, Label, UpdateLabel};
+use shared::labels::repository::{
+    memory::LabelRepositoryForMemory, LabelRepositoryForDb, LabelRepositoryTrait,
+};
+use shared::lists::model::{CreateList, List, ListCascade, UpdateList};
+use shared::lists::repository::{
+    memory::ListRepositoryForMemory, ListRepositoryForDb, ListRepositoryTrait,
+};
+use shared::members::model::{
+    AcceptInvitation, CreateInvitation, Invitation, Member, NewInvitation, Role, UpdateMember,
+};
+use shared::members::repository::{
+    memory::MemberRepositoryForMemory, MemberRepositoryForDb, MemberRepositoryTrait,
+};
+use shared::sync::model::{
+    ChangePage, DocumentRequest, DocumentResponse, DocumentResult, DocumentSync, OrderResult,
+    OrderSync, PushOp, PushRequest, PushResponse, PushResult,
+};
+use shared::todos::document::{
+    memory::TodoDocumentRepositoryForMemory, TodoDocumentRepositoryForDb,
+    TodoDocumentRepositoryTrait, TodoRepositoryWithDocuments,
+};
+use shared::todos::model::{
+    CreateTodo, EditScope, MoveTodo, Priority, SeriesOccurrences, Todo, TodoChange, TodoSearchHit,
+    TodoSeries, TodoSort, TodoTree, UpdateTodo,
+};
+use shared::todos::repository::{
+    memory::TodoRepositoryForMemory, TodoRepositoryForDb, TodoRepositoryTrait,
+};
+use shared::tokens::model::{ApiToken, CreateApiToken, NewApiToken, TokenScope};
+use shared::tokens::repository::{
+    memory::ApiTokenRepositoryForMemory, ApiTokenRepositoryForDb, ApiTokenRepositoryTrait,
+};
+use shared::users::model::{ChangePassword, Credentials, User};
+use shared::users::repository::{
+    memory::UserRepositoryForMemory, UserRepositoryForDb, UserRepositoryTrait,
+};
+use shared::workspaces::model::{
+    CreateWorkspace, CreateWorkspaceInvitation, NewWorkspaceInvitation, Workspace,
+    WorkspaceInvitation, WorkspaceMember, WorkspaceRole,
+};
+use shared::workspaces::repository::{
+    memory::WorkspaceRepositoryForMemory, WorkspaceRepositoryForDb, WorkspaceRepositoryTrait,
+};
+use shared::{Meta, TodoPageData};
 
 #[utoipa::path(
     get,
     path = "/docs/openapi.json",
     responses(
         (status = 200, description = "JSON file", body = ())
-    )
+    ),
+    security(())
 )]
 async fn openapi() -> Json<utoipa::openapi::OpenApi> {
     Json(ApiDoc::openapi())
@@ -27,43 +75,239 @@ async fn openapi() -> Json<utoipa::openapi::OpenApi> {
         domains::todos::controller::create,
         domains::todos::controller::delete,
         domains::todos::controller::find,
-        domains::todos::controller::update
+        domains::todos::controller::update,
+        domains::todos::controller::reorder,
+        domains::todos::controller::find_series,
+        domains::todos::controller::search,
+        domains::labels::controller::find_all,
+        domains::labels::controller::create,
+        domains::labels::controller::delete,
+        domains::labels::controller::find,
+        domains::labels::controller::update,
+        domains::labels::controller::find_by_todo,
+        domains::labels::controller::attach,
+        domains::labels::controller::detach,
+        domains::lists::controller::find_all,
+        domains::lists::controller::create,
+        domains::lists::controller::delete,
+        domains::lists::controller::find,
+        domains::lists::controller::update,
+        domains::lists::controller::archive,
+        domains::lists::controller::find_todos,
+        domains::lists::controller::create_todo,
+        domains::members::controller::find_all,
+        domains::members::controller::invite,
+        domains::members::controller::accept,
+        domains::members::controller::update,
+        domains::members::controller::remove,
+        domains::sync::controller::pull,
+        domains::sync::controller::push,
+        domains::sync::controller::exchange,
+        domains::users::controller::register,
+        domains::users::controller::me,
+        domains::users::controller::login,
+        domains::users::controller::refresh,
+        domains::users::controller::logout,
+        domains::users::controller::change_password,
+        domains::tokens::controller::find_all,
+        domains::tokens::controller::create,
+        domains::tokens::controller::revoke,
+        domains::workspaces::controller::find_all,
+        domains::workspaces::controller::create,
+        domains::workspaces::controller::invite,
+        domains::workspaces::controller::accept
     ),
-    components(schemas(Todo, CreateTodo, UpdateTodo))
+    components(schemas(
+        Todo,
+        CreateTodo,
+        UpdateTodo,
+        MoveTodo,
+        TodoTree,
+        TodoExpand,
+        EditScope,
+        TodoSeries,
+        SeriesOccurrences,
+        TodoSearchHit,
+        Priority,
+        TodoSort,
+        Label,
+        CreateLabel,
+        UpdateLabel,
+        List,
+        CreateList,
+        UpdateList,
+        ListCascade,
+        Role,
+        Member,
+        UpdateMember,
+        CreateInvitation,
+        Invitation,
+        NewInvitation,
+        AcceptInvitation,
+        ChangePage,
+        TodoChange,
+        PushRequest,
+        PushOp,
+        PushResponse,
+        PushResult,
+        DocumentSync,
+        DocumentRequest,
+        DocumentResponse,
+        DocumentResult,
+        OrderSync,
+        OrderResult,
+        Meta,
+        TodoPageData,
+        User,
+        Credentials,
+        ChangePassword,
+        TokenPair,
+        RefreshToken,
+        ApiToken,
+        CreateApiToken,
+        NewApiToken,
+        TokenScope,
+        Workspace,
+        CreateWorkspace,
+        WorkspaceRole,
+        WorkspaceMember,
+        CreateWorkspaceInvitation,
+        WorkspaceInvitation,
+        NewWorkspaceInvitation
+    )),
+    modifiers(&SecurityAddon),
+    security(("bearer_auth" = []))
 )]
 struct ApiDoc;
 
+/// Every path takes the access token from `/auth/login` or a personal API token from `/tokens`
+/// unless it says otherwise.
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT or API token")
+                    .build(),
+            ),
+        );
+    }
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub postgres: PgPool,
 }
 
-pub fn create_app(pool: PgPool, secrets: shuttle_runtime::SecretStore) -> Router {
+/// `cors` comes from `cors::CorsConfig`.
+pub fn create_app(pool: PgPool, cors: CorsLayer, keys: AuthKeys) -> Router {
     let doc = ApiDoc::openapi().to_pretty_json().unwrap();
     std::fs::write("openapi.json", doc.to_string()).unwrap_or(());
+    app(
+        TodoRepositoryForDb::new(pool.clone()),
+        TodoDocumentRepositoryForDb::new(pool.clone()),
+        LabelRepositoryForDb::new(pool.clone()),
+        ListRepositoryForDb::new(pool.clone()),
+        MemberRepositoryForDb::new(pool.clone()),
+        UserRepositoryForDb::new(pool.clone()),
+        ApiTokenRepositoryForDb::new(pool.clone()),
+        WorkspaceRepositoryForDb::new(pool),
+        keys,
+    )
+    .layer(cors)
+}
+
+/// `create_app` on the memory repositories, which lose everything with the process.
+pub fn create_memory_app(cors: CorsLayer, keys: AuthKeys) -> Router {
+    app(
+        TodoRepositoryForMemory::new(),
+        TodoDocumentRepositoryForMemory::new(),
+        LabelRepositoryForMemory::new(),
+        ListRepositoryForMemory::new(),
+        MemberRepositoryForMemory::new(),
+        UserRepositoryForMemory::new(),
+        ApiTokenRepositoryForMemory::new(),
+        WorkspaceRepositoryForMemory::new(),
+        keys,
+    )
+    .layer(cors)
+}
+
+/// Every route, served from the given repositories. The labels, lists and sync domains share the todo
+/// repository with the todos domain, which each request scopes to its user, and the roles of the
+/// members of shared lists are checked against `member_repository`. Every todo belongs to the
+/// workspace of the request it was created by, see `auth::WORKSPACE_HEADER`. The text
+/// documents of the todos, which sync clients merge their edits into, are kept in
+/// `document_repository` and follow every change of the text.
+#[allow(clippy::too_many_arguments)]
+pub fn app<T, D, L, LR, M, U, K, W>(
+    todo_repository: T,
+    document_repository: D,
+    label_repository: L,
+    list_repository: LR,
+    member_repository: M,
+    user_repository: U,
+    token_repository: K,
+    workspace_repository: W,
+    keys: AuthKeys,
+) -> Router
+where
+    T: TodoRepositoryTrait,
+    D: TodoDocumentRepositoryTrait,
+    L: LabelRepositoryTrait,
+    LR: ListRepositoryTrait,
+    M: MemberRepositoryTrait,
+    U: UserRepositoryTrait,
+    K: ApiTokenRepositoryTrait,
+    W: WorkspaceRepositoryTrait,
+{
+    let authenticator = Authenticator::new(
+        keys.clone(),
+        token_repository.clone(),
+        workspace_repository.clone(),
+    );
+    let todo_repository =
+        TodoRepositoryWithDocuments::new(todo_repository, document_repository.clone());
     Router::new()
         .merge(SwaggerUi::new("/docs").url("/docs/openapi.json", ApiDoc::openapi()))
         .route("/openapi.json", get(openapi))
         .route("/", get(root))
-        .merge(domains::todos::route::routes(pool))
-        .layer(
-            CorsLayer::new()
-                .allow_origin("http://localhost:3000".parse::<HeaderValue>().unwrap())
-                .allow_origin(
-                    secrets
-                        .get("REMOTE")
-                        .unwrap()
-                        .parse::<HeaderValue>()
-                        .unwrap(),
-                )
-                .allow_origin(
-                    "https://rust-todo-two.vercel.app"
-                        .parse::<HeaderValue>()
-                        .unwrap(),
-                )
-                .allow_methods(Any)
-                .allow_headers(vec![CONTENT_TYPE]),
-        )
+        .merge(domains::todos::route::routes(
+            todo_repository.clone(),
+            member_repository.clone(),
+        ))
+        .merge(domains::labels::route::routes(
+            label_repository,
+            todo_repository.clone(),
+        ))
+        .merge(domains::lists::route::routes(
+            list_repository.clone(),
+            todo_repository.clone(),
+            member_repository.clone(),
+        ))
+        .merge(domains::members::route::routes(
+            member_repository.clone(),
+            list_repository,
+            workspace_repository.clone(),
+        ))
+        .merge(domains::sync::route::routes(
+            todo_repository,
+            member_repository,
+            document_repository,
+        ))
+        .merge(domains::users::route::routes(user_repository))
+        .merge(domains::tokens::route::routes(token_repository))
+        .merge(domains::workspaces::route::routes(workspace_repository))
+        .layer(middleware::from_fn_with_state(
+            authenticator,
+            authenticate::<K, W>,
+        ))
+        .layer(Extension(keys))
 }
 
 async fn root() -> &'static str {
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use axum::body::{Body, HttpBody};
+use axum::http::{header, HeaderName, HeaderValue, Method, Request};
+use axum::response::Response;
+use http_body_util::BodyExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The `RequestInit` handed to `fetch`, only the parts the routes read.
+#[derive(Debug, Default, Deserialize)]
+pub struct RequestParams {
+    /// `GET` when missing, case insensitive like `fetch`.
+    pub method: Option<String>,
+    pub headers: Option<RequestHeaders>,
+    /// Sent as is when a string, as JSON otherwise.
+    pub body: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RequestHeaders {
+    Record(HashMap<String, String>),
+    Pairs(Vec<(String, String)>),
+}
+
+impl RequestParams {
+    /// A request to `url`, which is a path of the cloud API like `/todos?completed=false`.
+    pub fn into_request(self, url: &str) -> shared::Result<Request<Body>> {
+        let method = match self.method {
+            Some(method) => Method::from_bytes(method.to_uppercase().as_bytes())?,
+            None => Method::GET,
+        };
+        let mut req = Request::builder().method(method).uri(url);
+        let headers = req.headers_mut().expect("the builder has no error yet");
+        let pairs = match self.headers {
+            Some(RequestHeaders::Record(headers)) => headers.into_iter().collect(),
+            Some(RequestHeaders::Pairs(headers)) => headers,
+            None => Vec::new(),
+        };
+        for (name, value) in pairs {
+            headers.append(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(&value)?,
+            );
+        }
+
+        let body = match self.body {
+            None | Some(Value::Null) => return Ok(req.body(Body::empty())?),
+            Some(Value::String(body)) => body.into_bytes(),
+            Some(body) => serde_json::to_vec(&body)?,
+        };
+        // the routes only read JSON, `fetch` would send a string as `text/plain`
+        if !headers.contains_key(header::CONTENT_TYPE) {
+            headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            );
+        }
+        Ok(req.body(Body::from(body))?)
+    }
+}
+
+/// What the frontend builds a `Response` from.
+#[derive(Debug, Serialize)]
+pub struct CallResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+/// A piece of a response body as it is read.
+#[derive(Debug, Clone, Copy)]
+pub struct Chunk<'a> {
+    pub data: &'a [u8],
+    /// bytes read so far, `data` included
+    pub received: u64,
+    /// `None` when the body is streamed
+    pub total: Option<u64>,
+}
+
+impl CallResponse {
+    /// Reads `res`, handing the chunks of the body to `on_chunk` as they arrive.
+    pub async fn from_response(
+        res: Response,
+        mut on_chunk: impl FnMut(Chunk<'_>),
+    ) -> shared::Result<Self> {
+        let status = res.status().as_u16();
+        let headers = res
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let mut body = res.into_body();
+        let total = body.size_hint().exact();
+        let mut data = Vec::new();
+        while let Some(frame) = body.frame().await {
+            let Ok(chunk) = frame?.into_data() else {
+                continue;
+            };
+            data.extend_from_slice(&chunk);
+            on_chunk(Chunk {
+                data: &chunk,
+                received: data.len() as u64,
+                total,
+            });
+        }
+        Ok(Self {
+            status,
+            headers,
+            body: String::from_utf8(data)?,
+        })
+    }
+}
@@ -1,47 +1,89 @@
+pub mod dto;
+pub mod progress;
+pub mod router;
+pub mod sync;
 pub mod todos;
 
-use serde_json::{self, json, Value};
-use tauri::{command, Window};
+use serde_json::Value;
+use tauri::{command, State, Window};
 use log;
 
 use shared;
 
+use dto::{CallResponse, RequestParams};
+use progress::{Calls, ProgressEvent, Reporter};
+use router::LocalRouter;
+
 // https://tauri.app/v1/guides/features/command/#complete-example
 
+/// `fetch` of the frontend, `params` is its `RequestInit`. With a `progress` correlation id, the
+/// call emits `progress::PROGRESS_EVENT` events as the response is read and can be cancelled,
+/// like the long commands `sync`, `import` and `export`.
 #[command]
 pub async fn call(
     window: Window,
+    router: State<'_, LocalRouter>,
+    calls: State<'_, Calls>,
     url: String,
-    params: Value,
-    progress: Option<String>, // originally we use on_progress_id, but it seems that tauri doesn't support this format, always None
-) -> std::result::Result<Value, String> {
-    //shared::log::debug!("{url} {params} {:?}", progress);
-    Ok(json!(shared::resp_data({
-        _call(window, url, params, progress)
-            .await
-            .map_err(|x| x.to_string())?
-    })))
+    params: Option<Value>,
+    progress: Option<String>,
+) -> std::result::Result<CallResponse, String> {
+    log::debug!("call {url} {progress:?}");
+    _call(window, &router, &calls, url, params, progress)
+        .await
+        .map_err(|x| x.to_string())
 }
 
 async fn _call(
-    _window: Window,
+    window: Window,
+    router: &LocalRouter,
+    calls: &Calls,
     url: String,
-    _params: Value,
-    _progress: Option<String>,
-) -> shared::Result<Value> {
-    //let func = if let Some(id) = progress {
-    //    Some(move |data: ChatMessage| {
-    //        let data = serde_json::to_string(&data).unwrap_or_default();
-    //        let val = format!("window['on_progress']['{id}']({{event:{data}}})");
-    //        window.eval(&val).ok();
-    //    })
-    //} else {
-    //    None
-    //};
-    Ok(match url.as_ref() {
-        "/todos" => json!({"mock": "test"}),
-        _ => json!({"mock": "other"})
-    })
+    params: Option<Value>,
+    progress: Option<String>,
+) -> shared::Result<CallResponse> {
+    let params: RequestParams = match params {
+        Some(params) => serde_json::from_value(params)?,
+        None => RequestParams::default(),
+    };
+    let Some(id) = progress else {
+        return router.call(&url, params, |_| ()).await;
+    };
+
+    let mut cancellation = calls.start(&id);
+    let reporter = Reporter::new(window, id.clone(), cancellation.clone());
+    // dropping the request cancels the handler, like a client going away
+    let result = tokio::select! {
+        result = router.call(&url, params, |chunk| reporter.chunk(chunk)) => Some(result),
+        () = cancellation.cancelled() => None,
+    };
+    calls.finish(&id);
+
+    match result {
+        Some(Ok(res)) => {
+            reporter.emit(ProgressEvent::Finished {
+                status: Some(res.status),
+            });
+            Ok(res)
+        }
+        Some(Err(e)) => {
+            reporter.emit(ProgressEvent::Failed {
+                message: e.to_string(),
+            });
+            Err(e)
+        }
+        None => {
+            reporter.emit(ProgressEvent::Cancelled);
+            Err(anyhow::anyhow!("call {id} was cancelled"))
+        }
+    }
+}
+
+/// Cancels the call or long command with correlation id `id`, returns whether it was still in
+/// flight.
+#[command]
+pub fn cancel(calls: State<'_, Calls>, id: String) -> bool {
+    calls.cancel(&id)
 }
 
 #[command]
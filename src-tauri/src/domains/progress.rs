@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use shared::progress::{ProgressSink, Step};
+use tauri::{Manager, Window};
+use tokio::sync::watch;
+
+use super::dto::Chunk;
+
+/// Name of the window events `Progress` is emitted as.
+pub const PROGRESS_EVENT: &str = "progress";
+
+/// What happened to the call `id`, the correlation id the frontend passed to `call` or to a long
+/// command like `sync`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Progress {
+    pub id: String,
+    pub event: ProgressEvent,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// A piece of the response body was read. Long operations stream their progress as lines
+    /// of the body.
+    Chunk {
+        data: String,
+        /// bytes read so far, `data` included
+        received: u64,
+        /// `None` when the body is streamed
+        total: Option<u64>,
+    },
+    /// A long command got a step further.
+    Step(Step),
+    /// The whole response was read, or the command is done.
+    Finished {
+        /// `None` for commands other than `call`
+        status: Option<u16>,
+    },
+    Failed {
+        message: String,
+    },
+    Cancelled,
+}
+
+/// Emits the progress of call `id` to `window`, and tells long commands when it's cancelled.
+pub struct Reporter {
+    window: Window,
+    id: String,
+    cancellation: Cancellation,
+}
+
+impl Reporter {
+    pub fn new(window: Window, id: String, cancellation: Cancellation) -> Self {
+        Self {
+            window,
+            id,
+            cancellation,
+        }
+    }
+
+    pub fn emit(&self, event: ProgressEvent) {
+        let progress = Progress {
+            id: self.id.clone(),
+            event,
+        };
+        if let Err(e) = self.window.emit(PROGRESS_EVENT, progress) {
+            log::warn!("failed to emit the progress of {}: {e}", self.id);
+        }
+    }
+
+    pub fn chunk(&self, chunk: Chunk<'_>) {
+        self.emit(ProgressEvent::Chunk {
+            data: String::from_utf8_lossy(chunk.data).into_owned(),
+            received: chunk.received,
+            total: chunk.total,
+        });
+    }
+
+    /// Emits how a long command ended, `Cancelled` when it failed with `cancelled`.
+    pub fn end<T, E: PartialEq + Display>(&self, res: &Result<T, E>, cancelled: E) {
+        self.emit(match res {
+            Ok(_) => ProgressEvent::Finished { status: None },
+            Err(e) if *e == cancelled => ProgressEvent::Cancelled,
+            Err(e) => ProgressEvent::Failed {
+                message: e.to_string(),
+            },
+        });
+    }
+}
+
+impl ProgressSink for Reporter {
+    fn report(&self, step: Step) {
+        self.emit(ProgressEvent::Step(step));
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+}
+
+/// Whether `Calls::cancel` was called for a call.
+#[derive(Debug, Clone)]
+pub struct Cancellation(watch::Receiver<bool>);
+
+impl Cancellation {
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once cancelled, never when the call finished first.
+    pub async fn cancelled(&mut self) {
+        if self.0.wait_for(|cancelled| *cancelled).await.is_err() {
+            std::future::pending().await
+        }
+    }
+}
+
+/// Managed state of the in-flight calls that can be cancelled, by correlation id.
+#[derive(Debug, Default)]
+pub struct Calls {
+    cancels: Mutex<HashMap<String, watch::Sender<bool>>>,
+}
+
+impl Calls {
+    /// Cancelled when `cancel(id)` is called, dropping it doesn't cancel anything.
+    pub fn start(&self, id: &str) -> Cancellation {
+        let (cancel, cancelled) = watch::channel(false);
+        self.cancels.lock().unwrap().insert(id.to_string(), cancel);
+        Cancellation(cancelled)
+    }
+
+    pub fn finish(&self, id: &str) {
+        self.cancels.lock().unwrap().remove(id);
+    }
+
+    /// Whether `id` was still in flight.
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.cancels.lock().unwrap().remove(id) {
+            Some(cancel) => cancel.send(true).is_ok(),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use axum::http::header::AUTHORIZATION;
+use axum::Router;
+use tower::ServiceExt;
+
+use my_todo::auth::{AuthKeys, AuthUser};
+use my_todo::routes::app;
+use shared::labels::repository::sled::LabelRepositoryForSled;
+use shared::lists::repository::sled::ListRepositoryForSled;
+use shared::members::repository::sled::MemberRepositoryForSled;
+use shared::todos::document::sled::TodoDocumentRepositoryForSled;
+use shared::tokens::repository::sled::ApiTokenRepositoryForSled;
+use shared::users::repository::sled::UserRepositoryForSled;
+use shared::workspaces::repository::sled::WorkspaceRepositoryForSled;
+use shared::workspaces::repository::WorkspaceRepositoryTrait;
+use sled::Db;
+
+use super::dto::{CallResponse, Chunk, RequestParams};
+use super::todos::dependency::LocalTodoRepository;
+
+/// The only user of the desktop app.
+pub const LOCAL_USER_ID: i32 = 1;
+
+/// Key of the secret the router signs tokens with in the store, made once per install.
+const LOCAL_SECRET_KEY: &str = "local_router_secret";
+
+/// The routes of the cloud API served in-process, so the client code of the web app runs
+/// unchanged on the desktop. Everything is kept in the sled store, todos and their text
+/// documents being synced by `SyncDependency`.
+#[derive(Clone)]
+pub struct LocalRouter {
+    router: Router,
+    workspace_repository: WorkspaceRepositoryForSled,
+}
+
+impl LocalRouter {
+    pub fn open(
+        db: &Db,
+        todo_repository: LocalTodoRepository,
+        document_repository: TodoDocumentRepositoryForSled,
+    ) -> shared::Result<Self> {
+        let workspace_repository = WorkspaceRepositoryForSled::open(db)?;
+        Ok(Self {
+            router: app(
+                todo_repository,
+                document_repository,
+                LabelRepositoryForSled::open(db)?,
+                ListRepositoryForSled::open(db)?,
+                MemberRepositoryForSled::open(db)?,
+                UserRepositoryForSled::open(db)?,
+                ApiTokenRepositoryForSled::open(db)?,
+                workspace_repository.clone(),
+                AuthKeys::new(&shared::store::secret(db, LOCAL_SECRET_KEY)?),
+            ),
+            workspace_repository,
+        })
+    }
+
+    /// The router over the database of `shared::store`.
+    pub fn from_store(
+        todo_repository: LocalTodoRepository,
+        document_repository: TodoDocumentRepositoryForSled,
+    ) -> shared::Result<Self> {
+        let db = shared::store::db().ok_or_else(|| anyhow::anyhow!("the store has no database"))?;
+        Self::open(&db, todo_repository, document_repository)
+    }
+
+    /// Sends `params` to `url` as the local user in their personal workspace, bearer tokens of the
+    /// web app are dropped. The response body is handed to `on_chunk` as it is read.
+    pub async fn call(
+        &self,
+        url: &str,
+        params: RequestParams,
+        on_chunk: impl FnMut(Chunk<'_>),
+    ) -> shared::Result<CallResponse> {
+        let workspace = self.workspace_repository.personal(LOCAL_USER_ID).await?;
+        let mut req = params.into_request(url)?;
+        req.headers_mut().remove(AUTHORIZATION);
+        req.extensions_mut().insert(AuthUser {
+            id: LOCAL_USER_ID,
+            scope: None,
+            workspace_id: workspace.id,
+        });
+        let res = match self.router.clone().oneshot(req).await {
+            Ok(res) => res,
+            Err(never) => match never {},
+        };
+        CallResponse::from_response(res, on_chunk).await
+    }
+}
@@ -0,0 +1,45 @@
+use shared::progress::NoProgress;
+use shared::sync::error::SyncError;
+use shared::sync::model::{SyncReport, SyncStatus};
+use tauri::{State, Window};
+
+use super::dependency::SyncDependency;
+use crate::domains::progress::{Calls, Reporter};
+
+/// Pushes the local changes and pulls the remote ones, waiting for a sync already running.
+/// Fails with `NotConfigured` until `configure_sync` is called, or `SYNC_URL` and `SYNC_TOKEN`
+/// are set. With a `progress`
+/// correlation id, each round and page is emitted and `cancel` stops it between pages.
+#[tauri::command]
+pub async fn sync(
+    window: Window,
+    state: State<'_, SyncDependency>,
+    calls: State<'_, Calls>,
+    progress: Option<String>,
+) -> Result<SyncReport, SyncError> {
+    let Some(id) = progress else {
+        return state.engine.sync(&NoProgress).await;
+    };
+    let reporter = Reporter::new(window, id.clone(), calls.start(&id));
+    let res = state.engine.sync(&reporter).await;
+    calls.finish(&id);
+    reporter.end(&res, SyncError::Cancelled);
+    res
+}
+
+#[tauri::command]
+pub async fn status(state: State<'_, SyncDependency>) -> Result<SyncStatus, SyncError> {
+    state.engine.status().await
+}
+
+/// Points `sync` at the server at `url`, with `token`, a token of the `read_write` scope, and
+/// `workspace`, or the personal workspace without one. Kept until the app exits.
+#[tauri::command]
+pub fn configure_sync(url: String, token: String, workspace: Option<i32>) {
+    std::env::set_var("SYNC_URL", url);
+    std::env::set_var("SYNC_TOKEN", token);
+    match workspace {
+        Some(workspace) => std::env::set_var("SYNC_WORKSPACE", workspace.to_string()),
+        None => std::env::remove_var("SYNC_WORKSPACE"),
+    }
+}
@@ -0,0 +1,81 @@
+use axum::async_trait;
+
+use shared::lists::model::{CreateList, List};
+use shared::lists::repository::sled::ListRepositoryForSled;
+use shared::sync::engine::SyncEngine;
+use shared::sync::error::SyncError;
+use shared::sync::model::{
+    ChangePage, DocumentRequest, DocumentResponse, PushRequest, PushResponse,
+};
+use shared::sync::outbox::Outbox;
+use shared::sync::repository::SyncStateRepositoryForSled;
+use shared::sync::transport::{SyncTransportForHttp, SyncTransportTrait};
+use shared::todos::document::sled::TodoDocumentRepositoryForSled;
+use shared::todos::repository::sled::TodoRepositoryForSled;
+
+/// The server configured by the environment at the time of each request, so that the frontend
+/// can set it up with the `configure_sync` command without restarting the app.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SyncTransportFromEnv;
+
+#[async_trait]
+impl SyncTransportTrait for SyncTransportFromEnv {
+    async fn pull(&self, cursor: i64, limit: u32) -> Result<ChangePage, SyncError> {
+        SyncTransportForHttp::from_env()?.pull(cursor, limit).await
+    }
+
+    async fn push(&self, request: &PushRequest) -> Result<PushResponse, SyncError> {
+        SyncTransportForHttp::from_env()?.push(request).await
+    }
+
+    async fn exchange(&self, request: &DocumentRequest) -> Result<DocumentResponse, SyncError> {
+        SyncTransportForHttp::from_env()?.exchange(request).await
+    }
+
+    async fn lists(&self) -> Result<Vec<List>, SyncError> {
+        SyncTransportForHttp::from_env()?.lists().await
+    }
+
+    async fn create_list(&self, payload: &CreateList) -> Result<List, SyncError> {
+        SyncTransportForHttp::from_env()?.create_list(payload).await
+    }
+
+    async fn delete_list(&self, id: i32) -> Result<(), SyncError> {
+        SyncTransportForHttp::from_env()?.delete_list(id).await
+    }
+}
+
+pub type LocalSyncEngine = SyncEngine<
+    TodoRepositoryForSled,
+    ListRepositoryForSled,
+    SyncStateRepositoryForSled,
+    TodoDocumentRepositoryForSled,
+    SyncTransportFromEnv,
+>;
+
+/// Managed state of the sync commands.
+pub struct SyncDependency {
+    pub engine: LocalSyncEngine,
+}
+
+impl SyncDependency {
+    /// `todo_repository` is the store under the `LocalTodoRepository` recording into `outbox`,
+    /// the changes pulled are written to it directly. So are the lists to `list_repository` and
+    /// the ops of the text documents to `document_repository`.
+    pub fn new(
+        todo_repository: TodoRepositoryForSled,
+        list_repository: ListRepositoryForSled,
+        document_repository: TodoDocumentRepositoryForSled,
+        outbox: Outbox<SyncStateRepositoryForSled>,
+    ) -> Self {
+        Self {
+            engine: SyncEngine::new(
+                todo_repository,
+                list_repository,
+                document_repository,
+                outbox,
+                SyncTransportFromEnv,
+            ),
+        }
+    }
+}
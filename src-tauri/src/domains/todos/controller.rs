@@ -1,41 +1,102 @@
-use serde::{Deserialize, Serialize};
+use shared::progress::NoProgress;
+use shared::todos::error::TodoError;
+use shared::todos::model::{CreateTodo, EditScope, Todo, TodoTree, UpdateTodo};
+use shared::todos::service::TodoServiceTrait;
+use shared::todos::transfer;
+use shared::RespData;
+use tauri::{State, Window};
 
-#[derive(Serialize, Deserialize)]
-pub struct MockResponse {
-    message: String,
+use super::dependency::TodoDependency;
+use super::dto::FindAllArgs;
+use crate::domains::progress::{Calls, Reporter};
+
+// Errors are serialized as `{kind, message}`, the same payload as the HTTP API.
+
+#[tauri::command]
+pub async fn create(
+    state: State<'_, TodoDependency>,
+    payload: CreateTodo,
+) -> Result<Todo, TodoError> {
+    state.todo_service.create(payload).await
 }
 
-#[tauri::command(rename_all = "snake_case")]
-pub async fn create(invoke_message: String) -> MockResponse {
-    MockResponse {
-        message: format!("create called: {}", invoke_message),
-    }
+#[tauri::command]
+pub async fn find(state: State<'_, TodoDependency>, id: i32) -> Result<Todo, TodoError> {
+    state.todo_service.find(id).await
 }
 
 #[tauri::command]
-pub async fn find(invoke_message: String) -> MockResponse {
-    MockResponse {
-        message: format!("find called: {}", invoke_message),
-    }
+pub async fn find_tree(state: State<'_, TodoDependency>, id: i32) -> Result<TodoTree, TodoError> {
+    state.todo_service.find_tree(id).await
 }
 
 #[tauri::command]
-pub async fn find_all(invoke_message: String) -> MockResponse {
-    MockResponse {
-        message: format!("find_all called: {}", invoke_message),
-    }
+pub async fn find_all(
+    state: State<'_, TodoDependency>,
+    query: Option<FindAllArgs>,
+) -> Result<RespData<Vec<Todo>>, TodoError> {
+    let query = query.unwrap_or_default().try_into()?;
+    Ok(state.todo_service.find_all(query).await?.into())
 }
 
+/// `scope` picks the occurrences of a recurring todo to update, `this` by default.
 #[tauri::command]
-pub async fn update(invoke_message: String) -> MockResponse {
-    MockResponse {
-        message: format!("update called: {}", invoke_message),
+pub async fn update(
+    state: State<'_, TodoDependency>,
+    id: i32,
+    payload: UpdateTodo,
+    scope: Option<EditScope>,
+) -> Result<Todo, TodoError> {
+    match scope.unwrap_or_default() {
+        EditScope::This => state.todo_service.update(id, payload).await,
+        EditScope::AllFuture => state.todo_service.update_all_future(id, payload).await,
     }
 }
 
 #[tauri::command]
-pub async fn delete(invoke_message: String) -> MockResponse {
-    MockResponse {
-        message: String::from(format!("delete called: {}", invoke_message)),
-    }
+pub async fn delete(
+    state: State<'_, TodoDependency>,
+    id: i32,
+    version: Option<i32>,
+) -> Result<(), TodoError> {
+    state.todo_service.delete(id, version).await
+}
+
+/// Every todo, to be imported elsewhere. With a `progress` correlation id, each page is emitted
+/// and `cancel` stops it between pages.
+#[tauri::command]
+pub async fn export(
+    window: Window,
+    state: State<'_, TodoDependency>,
+    calls: State<'_, Calls>,
+    progress: Option<String>,
+) -> Result<Vec<Todo>, TodoError> {
+    let Some(id) = progress else {
+        return transfer::export(&state.todo_service, &NoProgress).await;
+    };
+    let reporter = Reporter::new(window, id.clone(), calls.start(&id));
+    let res = transfer::export(&state.todo_service, &reporter).await;
+    calls.finish(&id);
+    reporter.end(&res, TodoError::Cancelled);
+    res
+}
+
+/// Creates the exported `todos` in the inbox. With a `progress` correlation id, each page is
+/// emitted and `cancel` stops it between pages, keeping the todos created so far.
+#[tauri::command]
+pub async fn import(
+    window: Window,
+    state: State<'_, TodoDependency>,
+    calls: State<'_, Calls>,
+    todos: Vec<Todo>,
+    progress: Option<String>,
+) -> Result<Vec<Todo>, TodoError> {
+    let Some(id) = progress else {
+        return transfer::import(&state.todo_service, todos, &NoProgress).await;
+    };
+    let reporter = Reporter::new(window, id.clone(), calls.start(&id));
+    let res = transfer::import(&state.todo_service, todos, &reporter).await;
+    calls.finish(&id);
+    reporter.end(&res, TodoError::Cancelled);
+    res
 }
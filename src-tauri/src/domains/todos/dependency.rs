@@ -0,0 +1,39 @@
+use shared::members::repository::memory::MemberRepositoryForMemory;
+use shared::sync::outbox::TodoRepositoryWithOutbox;
+use shared::sync::repository::SyncStateRepositoryForSled;
+use shared::todos::document::sled::TodoDocumentRepositoryForSled;
+use shared::todos::document::TodoRepositoryWithDocuments;
+use shared::todos::repository::sled::TodoRepositoryForSled;
+use shared::todos::service::TodoService;
+
+/// The sled store, recording the changes to push into the outbox of `SyncDependency`.
+pub type LocalTodoRepository =
+    TodoRepositoryWithOutbox<TodoRepositoryForSled, SyncStateRepositoryForSled>;
+
+/// Todos of the desktop app live in the local sled store, their text documents next to them.
+/// There is a single user, so nothing is shared and the member repository is never consulted.
+pub type LocalTodoService = TodoService<
+    TodoRepositoryWithDocuments<LocalTodoRepository, TodoDocumentRepositoryForSled>,
+    MemberRepositoryForMemory,
+>;
+
+/// Managed state of the todo commands.
+pub struct TodoDependency {
+    pub todo_service: LocalTodoService,
+}
+
+impl TodoDependency {
+    /// `todo_repository` is shared with `LocalRouter`, a single one has to be opened over the
+    /// store since each keeps its own search index.
+    pub fn new(
+        todo_repository: LocalTodoRepository,
+        document_repository: TodoDocumentRepositoryForSled,
+    ) -> Self {
+        Self {
+            todo_service: TodoService::new(
+                TodoRepositoryWithDocuments::new(todo_repository, document_repository),
+                MemberRepositoryForMemory::new(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use shared::todos::error::TodoError;
+use shared::todos::model::{TodoCursor, TodoQuery, TodoSort};
+
+/// Arguments of `find_all`, the typed counterpart of the cloud query string.
+#[derive(Debug, Default, Deserialize)]
+pub struct FindAllArgs {
+    /// defaults to `created_at`
+    pub sort: Option<TodoSort>,
+    /// list id
+    pub list: Option<i32>,
+    /// only todos carrying all of these labels are returned
+    #[serde(default)]
+    pub labels: Vec<i32>,
+    pub completed: Option<bool>,
+    /// case insensitive substring of the text
+    pub text: Option<String>,
+    /// due at or after
+    pub due_from: Option<DateTime<Utc>>,
+    /// due before
+    pub due_to: Option<DateTime<Utc>>,
+    /// created at or after
+    pub created_from: Option<DateTime<Utc>>,
+    /// created before
+    pub created_to: Option<DateTime<Utc>>,
+    /// page size, 50 by default and 200 at most
+    pub limit: Option<u32>,
+    /// `meta.next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+impl TryFrom<FindAllArgs> for TodoQuery {
+    type Error = TodoError;
+
+    fn try_from(args: FindAllArgs) -> Result<Self, Self::Error> {
+        let cursor = args
+            .cursor
+            .map(|cursor| cursor.parse::<TodoCursor>())
+            .transpose()
+            .map_err(TodoError::Validation)?;
+
+        Ok(TodoQuery {
+            sort: args.sort.unwrap_or_default(),
+            list_id: args.list,
+            labels: args.labels,
+            completed: args.completed,
+            text: args.text.filter(|text| !text.is_empty()),
+            due_from: args.due_from,
+            due_to: args.due_to,
+            created_from: args.created_from,
+            created_to: args.created_to,
+            cursor,
+            limit: args.limit,
+        })
+    }
+}
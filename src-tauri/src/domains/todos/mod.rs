@@ -1 +1,3 @@
 pub mod controller;
+pub mod dependency;
+pub mod dto;
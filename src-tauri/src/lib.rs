@@ -1,14 +1,54 @@
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub mod domains;
 
+use tauri::Manager;
+
+use shared::lists::repository::sled::ListRepositoryForSled;
+use shared::sync::outbox::{Outbox, TodoRepositoryWithOutbox};
+use shared::sync::repository::SyncStateRepositoryForSled;
+use shared::todos::document::sled::TodoDocumentRepositoryForSled;
+use shared::todos::repository::sled::TodoRepositoryForSled;
+
+use domains::progress::Calls;
+use domains::router::LocalRouter;
+use domains::sync::dependency::SyncDependency;
+use domains::todos::dependency::TodoDependency;
+
 pub fn run() {
     tauri::Builder::default()
+        .setup(|app| {
+            let store = TodoRepositoryForSled::from_store()?;
+            let documents = TodoDocumentRepositoryForSled::from_store()?;
+            let outbox = Outbox::new(SyncStateRepositoryForSled::from_store()?);
+            let todo_repository = TodoRepositoryWithOutbox::new(store.clone(), outbox.clone());
+            app.manage(TodoDependency::new(
+                todo_repository.clone(),
+                documents.clone(),
+            ));
+            app.manage(LocalRouter::from_store(todo_repository, documents.clone())?);
+            app.manage(SyncDependency::new(
+                store,
+                ListRepositoryForSled::from_store()?,
+                documents,
+                outbox,
+            ));
+            app.manage(Calls::default());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
+            domains::call,
+            domains::cancel,
             domains::todos::controller::create,
             domains::todos::controller::find,
+            domains::todos::controller::find_tree,
             domains::todos::controller::find_all,
             domains::todos::controller::update,
             domains::todos::controller::delete,
+            domains::todos::controller::export,
+            domains::todos::controller::import,
+            domains::sync::controller::sync,
+            domains::sync::controller::status,
+            domains::sync::controller::configure_sync,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,15 +1,423 @@
 /* eslint-disable */
+export type AcceptInvitation = {
+  token: string
+}
+
+/** A personal API token. Its secret is only known when it's created, then only its hash is kept. */
+export type ApiToken = {
+  created_at: string
+  id: number
+  last_used_at?: string | null | undefined
+  name: string
+  /** Set once revoked, the token isn't accepted anymore. */
+  revoked_at?: string | null | undefined
+  scope: TokenScope
+  user_id: number
+}
+
+/** Changes after a cursor, what `GET /sync` returns. */
+export type ChangePage = {
+  changes: TodoChange[]
+  /** To pull the changes made after these ones. */
+  cursor: number
+  /** Set when there are more changes after this page. */
+  has_more: boolean
+}
+
+/** Changes the password of the authenticated user, who proves they know the current one. */
+export type ChangePassword = {
+  current_password: string
+  new_password: string
+}
+
+export type CreateApiToken = {
+  /** What the token is for, like the script using it. */
+  name: string
+  scope?: TokenScope | undefined
+}
+
+export type CreateInvitation = {
+  role?: Role | undefined
+}
+
+export type CreateLabel = {
+  color?: string | null | undefined
+  name: string
+}
+
+export type CreateList = {
+  name: string
+}
+
 export type CreateTodo = {
+  due_at?: string | null | undefined
+  /** The parent's list, or the inbox, when omitted. */
+  list_id?: number | null | undefined
+  /** Makes the todo a subtask. */
+  parent_id?: number | null | undefined
+  priority?: Priority | undefined
+  /** Needs `due_at`, the next occurrence is created when this one is completed. */
+  rrule?: string | null | undefined
+  starts_at?: string | null | undefined
   text: string
 }
 
+export type CreateWorkspace = {
+  name: string
+}
+
+export type CreateWorkspaceInvitation = {
+  role?: WorkspaceRole | undefined
+}
+
+/** What a user signs up and logs in with. */
+export type Credentials = {
+  email: string
+  password: string
+}
+
+/** The documents a client sends with `POST /sync/documents`, with the ops the server hasn't
+acknowledged yet. */
+export type DocumentRequest = {
+  documents: DocumentSync[]
+  /** The orders of the lists, merged as the documents are. */
+  orders?: OrderSync[] | undefined
+}
+
+export type DocumentResponse = {
+  /** One for each order. */
+  orders?: OrderResult[] | undefined
+  /** One for each document, in the same order. */
+  results: DocumentResult[]
+}
+
+export type DocumentResult = {
+  document: DocumentSync
+  status: 'merged'
+} | {
+  document: DocumentSync
+  kind: string
+  message: string
+  status: 'rejected'
+} | {
+  status: 'missing'
+} | {
+  message: string
+  status: 'retry'
+}
+
+/** The text document of a todo as one side has it: its version and the ops the other side
+lacks, by the id of the todo on the server. */
+export type DocumentSync = {
+  /** Ops inserting and deleting characters. */
+  delta: { [key: string]: any }
+  id: number
+  /** The latest counter of each replica, as `[replica, counter]` pairs. */
+  version: { [key: string]: any }
+}
+
+/** Which occurrences of a recurring todo an update applies to. */
+export type EditScope = 'this' | 'all_future'
+
+/** Joins a list once accepted with its token. Its token is only known when it's created, then
+only its hash is kept. */
+export type Invitation = {
+  accepted_at?: string | null | undefined
+  accepted_by?: number | null | undefined
+  created_at: string
+  created_by: number
+  expires_at: string
+  id: number
+  list_id: number
+  role: Role
+  /** Joined along with the list, the workspace it was shared from. */
+  workspace_id?: number | null | undefined
+}
+
+export type Label = {
+  /** `#rrggbb` */
+  color?: string | null | undefined
+  id: number
+  name: string
+}
+
+export type List = {
+  archived: boolean
+  id: number
+  /** The list todos go to when no list is given, it can't be archived nor deleted. */
+  inbox: boolean
+  name: string
+}
+
+/** What happens to the todos of a list that is archived or deleted. */
+export type ListCascade = 'move_to_inbox' | 'delete'
+
+export type Member = {
+  created_at: string
+  list_id: number
+  role: Role
+  user_id: number
+}
+
+/** Paging metadata of a listing. */
+export type Meta = {
+  /** Pass it as `cursor` to get the next page, absent on the last one. */
+  next_cursor?: string | null | undefined
+  /** Items matching the listing on every page. */
+  total: number
+}
+
+/** Moves a todo right before `before`, or to the end when `before` is `None`. */
+export type MoveTodo = {
+  before?: number | null | undefined
+}
+
+/** A token as created, along with the secret to send as `Authorization: Bearer <secret>`. */
+export type NewApiToken = ApiToken & {
+  /** Shown this once, store it somewhere safe. */
+  secret: string
+}
+
+/** An invitation as created, along with the token to hand to the invited user. */
+export type NewInvitation = Invitation & {
+  /** Shown this once, accepting the invitation takes it. */
+  token: string
+}
+
+/** A workspace invitation as created, along with the token to hand to the invited user. */
+export type NewWorkspaceInvitation = WorkspaceInvitation & {
+  /** Shown this once, accepting the invitation takes it. */
+  token: string
+}
+
+export type OrderResult = {
+  order: OrderSync
+  status: 'merged'
+} | {
+  kind: string
+  message: string
+  order: OrderSync
+  status: 'rejected'
+} | {
+  status: 'missing'
+} | {
+  message: string
+  status: 'retry'
+}
+
+/** The order of the todos of a list as one side has it, like `DocumentSync`, by the ids of the
+list and its todos on the server. */
+export type OrderSync = {
+  /** Ops inserting and deleting the ids of todos. */
+  delta: { [key: string]: any }
+  list_id: number
+  /** The latest counter of each replica, as `[replica, counter]` pairs. */
+  version: { [key: string]: any }
+}
+
+export type Priority = 'none' | 'low' | 'medium' | 'high' | 'urgent'
+
+export type PushOp = {
+  op: 'create'
+  series_id?: number | null | undefined
+  todo: CreateTodo
+} | {
+  id: number
+  op: 'update'
+  patch: UpdateTodo
+  version: number
+} | {
+  id: number
+  op: 'delete'
+}
+
+/** Local changes sent with `POST /sync`, applied in order. */
+export type PushRequest = {
+  ops: PushOp[]
+}
+
+export type PushResponse = {
+  /** One for each op, in the same order. */
+  results: PushResult[]
+}
+
+export type PushResult = {
+  status: 'applied'
+  todo: Todo
+} | {
+  status: 'deleted'
+} | {
+  status: 'conflict'
+  version: number
+} | {
+  status: 'missing'
+} | {
+  kind: string
+  message: string
+  status: 'rejected'
+} | {
+  message: string
+  status: 'retry'
+}
+
+export type RefreshToken = {
+  refresh_token: string
+}
+
+/** What a member may do with a list and its todos, each role being allowed what the ones before
+it are. */
+export type Role = 'viewer' | 'editor' | 'owner'
+
+/** A series with its occurrences, earliest due first. */
+export type SeriesOccurrences = TodoSeries & {
+  occurrences: Todo[]
+}
+
 export type Todo = {
   completed: boolean
+  created_at: string
+  due_at?: string | null | undefined
   id: number
+  list_id: number
+  parent_id?: number | null | undefined
+  /** Manual ordering key, only meaningful relative to other todos. */
+  position: number
+  priority: Priority
+  /** RFC 5545 `RRULE` the todo recurs by, see `Recurrence` for the supported subset. */
+  rrule?: string | null | undefined
+  /** Occurrences of the same recurring todo share their series. */
+  series_id?: number | null | undefined
+  starts_at?: string | null | undefined
   text: string
+  /** Bumped by every change to the todo. */
+  version: number
+}
+
+/** A change to a todo in the change log of the server, which sync clients pull. */
+export type TodoChange = {
+  seq: number
+  todo: Todo
+  type: 'upserted'
+  updated_at: string
+} | {
+  deleted_at: string
+  id: number
+  seq: number
+  type: 'deleted'
+}
+
+export type TodoExpand = 'children'
+
+export type TodoPageData = {
+  data: Todo[]
+  meta?: Meta | null | undefined
+  status: string
 }
 
+/** A todo matching a full-text search. */
+export type TodoSearchHit = Todo & {
+  /** relevance, only comparable between hits of the same search */
+  rank: number
+  /** the text with matched words wrapped in `<mark>`, it isn't HTML escaped */
+  snippet: string
+}
+
+/** What new occurrences of a recurring todo are created from. */
+export type TodoSeries = {
+  id: number
+  priority: Priority
+  rrule: string
+  text: string
+}
+
+export type TodoSort = 'position' | 'priority' | 'due_date' | 'created_at'
+
+/** A todo with its subtasks, in manual order. */
+export type TodoTree = Todo & {
+  children: TodoTree[]
+}
+
+/** What login and refresh return. Send `access_token` as `Authorization: Bearer <access_token>`. */
+export type TokenPair = {
+  access_token: string
+  /** Seconds the access token is valid for. */
+  expires_in: number
+  /** Trades for a new pair at `/auth/refresh` once the access token expired. */
+  refresh_token: string
+  /** Always `Bearer`. */
+  token_type: string
+}
+
+/** What a request authenticated with an API token may do. */
+export type TokenScope = 'read' | 'read_write'
+
+/** `color: null` removes the color, a missing `color` keeps it. */
+export type UpdateLabel = {
+  color?: string | null | undefined
+  name?: string | null | undefined
+}
+
+export type UpdateList = {
+  name?: string | null | undefined
+}
+
+export type UpdateMember = {
+  role: Role
+}
+
+/** `starts_at` and `due_at` distinguish a missing field (keep the current value)
+from an explicit `null` (clear it). */
 export type UpdateTodo = {
   completed?: boolean | null | undefined
+  due_at?: string | null | undefined
+  /** Moves the todo to another list. */
+  list_id?: number | null | undefined
+  /** Moves the todo, with its subtasks, under another todo. `null` makes it a root todo. */
+  parent_id?: number | null | undefined
+  priority?: Priority | null | undefined
+  /** Only editable for all future occurrences, `null` ends the series. */
+  rrule?: string | null | undefined
+  starts_at?: string | null | undefined
   text?: string | null | undefined
+  /** The version the update is based on, it fails with a conflict when the todo has changed
+since. Unconditional when missing. */
+  version?: number | null | undefined
+}
+
+export type User = {
+  created_at: string
+  email: string
+  id: number
+}
+
+/** A tenant, its todos, lists and labels are never seen from another workspace. Every user has a
+personal one, made along with them. */
+export type Workspace = {
+  created_at: string
+  id: number
+  name: string
+  /** The personal workspace of the user, used when a request doesn't name one. */
+  personal: boolean
 }
+
+/** Joins a workspace once accepted with its token, like `Invitation` does a list. */
+export type WorkspaceInvitation = {
+  accepted_at?: string | null | undefined
+  accepted_by?: number | null | undefined
+  created_at: string
+  created_by: number
+  expires_at: string
+  id: number
+  role: WorkspaceRole
+  workspace_id: number
+}
+
+export type WorkspaceMember = {
+  created_at: string
+  role: WorkspaceRole
+  user_id: number
+  workspace_id: number
+}
+
+/** What a member may do with a workspace, each role being allowed what the ones before it are. */
+export type WorkspaceRole = 'member' | 'admin' | 'owner'
@@ -1,7 +1,30 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 export type Fetch = typeof fetch;
 
+type CallResponse = {
+  status: number;
+  headers: Record<string, string>;
+  body: string;
+};
+
+export type Stage = "round" | "push" | "pull" | "exchange" | "export" | "import";
+
+export type ProgressEvent =
+  | { type: "chunk"; data: string; received: number; total: number | null }
+  | { type: "step"; stage: Stage; done: number; total: number | null }
+  | { type: "finished"; status: number | null }
+  | { type: "failed"; message: string }
+  | { type: "cancelled" };
+
+type Progress = { id: string; event: ProgressEvent };
+
+type Tracking = {
+  on_progress?: (event: ProgressEvent) => void;
+  signal?: AbortSignal | null;
+};
+
 export const tauriFetch: Fetch = async (
   input: string | Request | URL,
   init?: RequestInit,
@@ -13,14 +36,89 @@ export const tauriFetch: Fetch = async (
   if (input instanceof Request) {
     throw Error("type Request is not allowed for tauri");
   }
-  return call(input, init);
+  const res = await call(input, init);
+  // a null body status can't have a body
+  const body = [204, 205, 304].includes(res.status) ? null : res.body;
+  return new Response(body, { status: res.status, headers: res.headers });
 };
 
+/**
+ * Sends a request through the in-process router. With `on_progress` or an
+ * `init.signal`, the call gets a correlation id: `on_progress` receives the
+ * response body as it is read, and aborting the signal cancels the call.
+ */
 export async function call(
   url: string,
   init?: RequestInit,
-  on_progress_id?: string,
-): Promise<any> {
-  const params = init?.body;
-  return invoke("call", { url, params, progress: on_progress_id });
+  on_progress?: (event: ProgressEvent) => void,
+): Promise<CallResponse> {
+  const signal = init?.signal;
+  signal?.throwIfAborted();
+
+  const params = init && {
+    method: init.method,
+    headers:
+      init.headers instanceof Headers
+        ? Array.from(init.headers.entries())
+        : init.headers,
+    body: init.body,
+  };
+  if (!on_progress && !signal) {
+    return invoke("call", { url, params });
+  }
+  return track("call", { url, params }, { on_progress, signal });
+}
+
+/**
+ * Pushes the local changes and pulls the remote ones. `on_progress` receives
+ * each round and page, and aborting the signal stops it between pages.
+ */
+export function sync<T>(tracking: Tracking = {}): Promise<T> {
+  return track("sync", {}, tracking);
+}
+
+/** Every todo, read page by page, to be passed to `importTodos` elsewhere. */
+export function exportTodos<T>(tracking: Tracking = {}): Promise<T[]> {
+  return track("export", {}, tracking);
+}
+
+/**
+ * Creates exported `todos` in the inbox page by page. Aborting keeps the todos
+ * created so far.
+ */
+export function importTodos<T>(
+  todos: T[],
+  tracking: Tracking = {},
+): Promise<T[]> {
+  return track("import", { todos }, tracking);
+}
+
+/** Invokes `command` under a correlation id its progress and cancel go by. */
+async function track<T>(
+  command: string,
+  args: Record<string, unknown>,
+  { on_progress, signal }: Tracking,
+): Promise<T> {
+  signal?.throwIfAborted();
+  const id = crypto.randomUUID();
+  const unlisten = on_progress
+    ? await listen<Progress>("progress", ({ payload }) => {
+        if (payload.id === id) {
+          on_progress(payload.event);
+        }
+      })
+    : undefined;
+  const abort = () => invoke("cancel", { id });
+  signal?.addEventListener("abort", abort);
+  try {
+    return await invoke(command, { ...args, progress: id });
+  } catch (e) {
+    if (signal?.aborted) {
+      throw signal.reason;
+    }
+    throw e;
+  } finally {
+    signal?.removeEventListener("abort", abort);
+    unlisten?.();
+  }
 }